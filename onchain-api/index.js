@@ -14,10 +14,9 @@ const { Buffer } = require("buffer");
 const fs = require("fs");
 const BN = require("bn.js");
 
-const { PositionAccountData, InitializePositionData, ClosePositionData } = require('./schema');
-const PROGRAM_ID        = new PublicKey("URAa3qGD1qVKKqyQrF8iBVZRTwa4Q8RkMd6Gx7u2KL1");
-const DEX_PUBKEY        = new PublicKey("URAbknhQPhFiY92S5iM9nhzoZC5Vkch7S5VERa4PmuV");
-const DEX_FEES_PUBKEY   = new PublicKey("URAfeAaGMoavvTe8vqPwMX6cUvTjq8WMG5c9nFo7Q8j");
+const { PositionAccountData, PositionIndexData, NonceCounterData, InitializePositionData, ClosePositionData, CancelPositionData } = require('./schema');
+// Test network builds (`devnet` / `localnet` features) deploy under other ids.
+const PROGRAM_ID        = new PublicKey(process.env.URANUS_PROGRAM_ID || "URAa3qGD1qVKKqyQrF8iBVZRTwa4Q8RkMd6Gx7u2KL1");
 
 function lamportsToSOL(lamports) {
     return lamports / LAMPORTS_PER_SOL;
@@ -77,6 +76,128 @@ function getMarketAccount(mint) {
   return marketPDA;
 }
 
+function getConfigAccount() {
+  const [configPDA] = PublicKey.findProgramAddressSync(
+    [new TextEncoder().encode("uranus_config")],
+    PROGRAM_ID
+  );
+  return configPDA;
+}
+
+function getAuthorityAccount() {
+  const [authorityPDA] = PublicKey.findProgramAddressSync(
+    [new TextEncoder().encode("uranus_authority")],
+    PROGRAM_ID
+  );
+  return authorityPDA;
+}
+
+// Operator keys of the deployment: admin, DEX authority and fee wallet, 32 bytes each.
+async function getAuthorityKeys(connection) {
+  const accountInfo = await connection.getAccountInfo(getAuthorityAccount());
+  if (accountInfo === null) {
+    throw new Error('Authority account not found');
+  }
+
+  return {
+    admin: new PublicKey(accountInfo.data.subarray(0, 32)),
+    dexAuthority: new PublicKey(accountInfo.data.subarray(32, 64)),
+    feesWallet: new PublicKey(accountInfo.data.subarray(64, 96)),
+  };
+}
+
+function getFeeEscrowAccount() {
+  const [feeEscrowPDA] = PublicKey.findProgramAddressSync(
+    [new TextEncoder().encode("uranus_fee_escrow")],
+    PROGRAM_ID
+  );
+  return feeEscrowPDA;
+}
+
+function getSymbolRegistryAccount(mint) {
+  const [registryPDA] = PublicKey.findProgramAddressSync(
+    [
+      new TextEncoder().encode("uranus_symbol"),
+      mint.toBytes(),
+    ],
+    PROGRAM_ID
+  );
+  return registryPDA;
+}
+
+function getPriceFeedAccount(mint) {
+  const [priceFeedPDA] = PublicKey.findProgramAddressSync(
+    [
+      new TextEncoder().encode("uranus_oracle"),
+      mint.toBytes(),
+    ],
+    PROGRAM_ID
+  );
+  return priceFeedPDA;
+}
+
+function getPositionIndexAccount(owner) {
+  const [positionIndexPDA] = PublicKey.findProgramAddressSync(
+    [
+      new TextEncoder().encode("uranus_position_index"),
+      owner.toBytes(),
+    ],
+    PROGRAM_ID
+  );
+  return positionIndexPDA;
+}
+
+function getNonceCounterAccount(owner) {
+  const [nonceCounterPDA] = PublicKey.findProgramAddressSync(
+    [
+      new TextEncoder().encode("uranus_nonce"),
+      owner.toBytes(),
+    ],
+    PROGRAM_ID
+  );
+  return nonceCounterPDA;
+}
+
+function getUserStatsAccount(owner) {
+  const [userStatsPDA] = PublicKey.findProgramAddressSync(
+    [
+      new TextEncoder().encode("uranus_user_stats"),
+      owner.toBytes(),
+    ],
+    PROGRAM_ID
+  );
+  return userStatsPDA;
+}
+
+function getBlacklistAccount() {
+  const [blacklistPDA] = PublicKey.findProgramAddressSync(
+    [new TextEncoder().encode("uranus_blacklist")],
+    PROGRAM_ID
+  );
+  return blacklistPDA;
+}
+
+function getFreezeAccount(target) {
+  const [freezePDA] = PublicKey.findProgramAddressSync(
+    [
+      new TextEncoder().encode("uranus_freeze"),
+      target.toBytes(),
+    ],
+    PROGRAM_ID
+  );
+  return freezePDA;
+}
+
+async function getNextPositionNonce(connection, owner) {
+  const accountInfo = await connection.getAccountInfo(getNonceCounterAccount(owner), "confirmed");
+  if (accountInfo === null) {
+    return new BN(0);
+  }
+
+  const counter = deserialize(NonceCounterData.schema, accountInfo.data);
+  return new BN(counter.next_nonce.toString());
+}
+
 async function getMarketLiquidity(connection, mint){
     const marketAccount = getMarketAccount(mint);
     const accountInfo = await connection.getAccountInfo(marketAccount);
@@ -102,7 +223,7 @@ async function calculateFees(solAmount, leverage, connection) {
   return { basePaidAmount, percentageFee, accountFee };
 }
 
-async function createUranusPositionTransaction(connection, owner, mint, solAmount, leverage, direction) {
+async function createUranusPositionTransaction(connection, owner, mint, solAmount, leverage, direction, clientTag = null) {
   if (!owner || !mint || !solAmount || !leverage || !direction) {
     throw new Error("Missing required parameters");
   }
@@ -114,10 +235,12 @@ async function createUranusPositionTransaction(connection, owner, mint, solAmoun
     throw new Error("Market metadata symbol not found");
 
   const { basePaidAmount, percentageFee, accountFee } = await calculateFees(solAmount, leverage, connection);
-  const paidAmount = basePaidAmount.add(percentageFee).add(accountFee);
-  const positionSize = basePaidAmount.sub(percentageFee).sub(accountFee).mul(new BN(leverage));
+  // The rent-exempt reserve is funded by the payer on top of paid_amount and refunded on close.
+  const paidAmount = basePaidAmount.add(percentageFee);
+  const positionSize = basePaidAmount.sub(percentageFee).mul(new BN(leverage));
 
-  const positionNonce = new BN(Date.now());
+  const positionNonce = await getNextPositionNonce(connection, owner);
+  const { dexAuthority, feesWallet } = await getAuthorityKeys(connection);
   const [positionPda] = PublicKey.findProgramAddressSync(
     [
       new TextEncoder().encode("uranus_position"),
@@ -137,6 +260,9 @@ async function createUranusPositionTransaction(connection, owner, mint, solAmoun
       leverage,
       position_nonce: positionNonce,
       direction: direction.toLowerCase() === "long" ? 1 : -1,
+      margin_mode: 0,
+      client_tag: clientTag ?? new Uint8Array(16),
+      platform_fee_bps: 0,
     })
   );
 
@@ -151,9 +277,18 @@ async function createUranusPositionTransaction(connection, owner, mint, solAmoun
       { pubkey: owner, isSigner: false, isWritable: false },
       { pubkey: positionPda, isSigner: false, isWritable: true },
       { pubkey: getMarketAccount(mint), isSigner: false, isWritable: true },
-      { pubkey: DEX_PUBKEY, isSigner: false, isWritable: true },
-      { pubkey: DEX_FEES_PUBKEY, isSigner: false, isWritable: true },
+      { pubkey: dexAuthority, isSigner: false, isWritable: true },
+      { pubkey: feesWallet, isSigner: false, isWritable: true },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: getSymbolRegistryAccount(mint), isSigner: false, isWritable: false },
+      { pubkey: getConfigAccount(), isSigner: false, isWritable: false },
+      { pubkey: getFeeEscrowAccount(), isSigner: false, isWritable: true },
+      { pubkey: getPositionIndexAccount(owner), isSigner: false, isWritable: true },
+      { pubkey: getNonceCounterAccount(owner), isSigner: false, isWritable: true },
+      { pubkey: getFreezeAccount(owner), isSigner: false, isWritable: false },
+      { pubkey: getBlacklistAccount(), isSigner: false, isWritable: false },
+      { pubkey: getUserStatsAccount(owner), isSigner: false, isWritable: true },
+      { pubkey: getAuthorityAccount(), isSigner: false, isWritable: false },
     ],
     data: instructionData,
   });
@@ -169,12 +304,12 @@ async function createUranusPositionTransaction(connection, owner, mint, solAmoun
     positionNonce: positionNonce.toNumber(),
     positionPda,
     fees: percentageFee.add(accountFee).toNumber() / LAMPORTS_PER_SOL,
-    totalCost: paidAmount.toNumber() / LAMPORTS_PER_SOL,
+    totalCost: paidAmount.add(accountFee).toNumber() / LAMPORTS_PER_SOL,
   };
 }
 
-async function closeUranusPosition(connection, positionNonce, owner, positionPda) {
-  if (!connection || !positionNonce || !owner) {
+async function closeUranusPosition(connection, positionNonce, owner, marketMint, positionPda) {
+  if (!connection || !positionNonce || !owner || !marketMint) {
     throw new Error("Missing required parameters");
   }
 
@@ -208,6 +343,58 @@ async function closeUranusPosition(connection, positionNonce, owner, positionPda
     keys: [
       { pubkey: positionPda, isSigner: false, isWritable: true },
       { pubkey: owner, isSigner: true, isWritable: true },
+      { pubkey: getConfigAccount(), isSigner: false, isWritable: false },
+      { pubkey: getMarketAccount(marketMint), isSigner: false, isWritable: false },
+      { pubkey: getPriceFeedAccount(marketMint), isSigner: false, isWritable: false },
+      { pubkey: getFreezeAccount(owner), isSigner: false, isWritable: false },
+      { pubkey: getFreezeAccount(positionPda), isSigner: false, isWritable: false },
+    ],
+    data: instructionData,
+  });
+
+  const transaction = new Transaction().add(instruction);
+  transaction.feePayer = owner;
+  transaction.recentBlockhash = (await connection.getLatestBlockhash()).blockhash;
+
+  return transaction;
+}
+
+async function cancelUranusPosition(connection, positionNonce, owner, marketMint, positionPda) {
+  if (!connection || !positionNonce || !owner || !marketMint) {
+    throw new Error("Missing required parameters");
+  }
+
+  const positionNonceBN = new BN(positionNonce);
+
+  if (!positionPda) {
+    [positionPda] = PublicKey.findProgramAddressSync(
+      [
+        new TextEncoder().encode("uranus_position"),
+        owner.toBytes(),
+        positionNonceBN.toArray("le", 8),
+      ],
+      PROGRAM_ID
+    );
+  }
+
+  const serializedData = serialize(
+    CancelPositionData.schema,
+    new CancelPositionData({
+      position_nonce: positionNonceBN,
+    })
+  );
+
+  const instructionData = new Uint8Array(1 + serializedData.length);
+  instructionData[0] = 16;
+  instructionData.set(serializedData, 1);
+
+  const instruction = new TransactionInstruction({
+    programId: PROGRAM_ID,
+    keys: [
+      { pubkey: positionPda, isSigner: false, isWritable: true },
+      { pubkey: owner, isSigner: true, isWritable: true },
+      { pubkey: getFeeEscrowAccount(), isSigner: false, isWritable: true },
+      { pubkey: getMarketAccount(marketMint), isSigner: false, isWritable: true },
     ],
     data: instructionData,
   });
@@ -234,6 +421,14 @@ function deserializePositionAccount(data) {
         closed: deserialized.closed,
         position_nonce: Number(deserialized.position_nonce),
         direction: deserialized.direction === 1 ? "LONG" : "SHORT",
+        residual_profit: Number(deserialized.residual_profit) / LAMPORTS_PER_SOL,
+        guaranteed_stop_price: Number(deserialized.guaranteed_stop_price) / LAMPORTS_PER_SOL,
+        client_tag: Uint8Array.from(deserialized.client_tag),
+        margin_call_slot: Number(deserialized.margin_call_slot),
+        contract_type: deserialized.contract_type,
+        close_request_slot: Number(deserialized.close_request_slot),
+        open_base_fee_bps: deserialized.open_base_fee_bps,
+        open_leverage_fee_bps: deserialized.open_leverage_fee_bps,
     };
 
     return positionAccount;
@@ -244,7 +439,7 @@ async function getOpenPositions(connection, owner = null, marketMint = null, tic
     commitment: "confirmed",
   });
 
-  const validAccounts = accounts.filter(({ account }) => account.data.byteLength !== 0);
+  const validAccounts = accounts.filter(({ account }) => account.data.byteLength === PositionAccountData.size);
 
   let deserializedAccounts = validAccounts.map(({ account }) => {
     return deserializePositionAccount(account.data);
@@ -262,6 +457,16 @@ async function getOpenPositions(connection, owner = null, marketMint = null, tic
   return deserializedAccounts;
 }
 
+async function getPositionNonces(connection, owner){
+  const accountInfo = await connection.getAccountInfo(getPositionIndexAccount(owner), "confirmed");
+  if (accountInfo === null) {
+    return [];
+  }
+
+  const index = deserialize(PositionIndexData.schema, accountInfo.data);
+  return index.nonces.map((nonce) => Number(nonce));
+}
+
 async function getAllMarkets(connection){
   const accounts = await connection.getProgramAccounts(PROGRAM_ID, {
     commitment: "confirmed",
@@ -410,11 +615,22 @@ async function getTickerPrice(ticker){
 
 module.exports = {
     getMarketAccount,
+    getSymbolRegistryAccount,
+    getConfigAccount,
+    getAuthorityAccount,
+    getAuthorityKeys,
+    getFeeEscrowAccount,
+    getPriceFeedAccount,
+    getPositionIndexAccount,
+    getNonceCounterAccount,
+    getNextPositionNonce,
     getMarketLiquidity,
     calculateFees,
     createUranusPositionTransaction,
     closeUranusPosition,
+    cancelUranusPosition,
     getOpenPositions,
+    getPositionNonces,
     getAllMarkets,
     getAllSignaturesForMarket,
     getParsedTransactionsForMarket,
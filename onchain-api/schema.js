@@ -1,6 +1,7 @@
 /* Define Schemas for Borsh Serialization */
 class PositionAccountData {
   constructor(props) {
+    this.version = props.version;
     this.owner = props.owner;
     this.market_mint = props.market_mint;
     this.market_symbol = props.market_symbol;
@@ -13,10 +14,28 @@ class PositionAccountData {
     this.position_nonce = props.position_nonce;
     this.pnl = props.pnl;
     this.direction = props.direction;
+    this.margin_mode = props.margin_mode;
+    this.confirmed = props.confirmed;
+    this.open_fee = props.open_fee;
+    this.open_slot = props.open_slot;
+    this.liquidation_auction_slot = props.liquidation_auction_slot;
+    this.funding_index_snapshot = props.funding_index_snapshot;
+    this.borrow_index_snapshot = props.borrow_index_snapshot;
+    this.residual_profit = props.residual_profit;
+    this.residual_fee = props.residual_fee;
+    this.guaranteed_stop_price = props.guaranteed_stop_price;
+    this.client_tag = props.client_tag;
+    this.margin_call_slot = props.margin_call_slot;
+    this.contract_type = props.contract_type;
+    this.close_request_slot = props.close_request_slot;
+    this.open_base_fee_bps = props.open_base_fee_bps;
+    this.open_leverage_fee_bps = props.open_leverage_fee_bps;
+    this.checksum = props.checksum;
   }
 
   static schema = {
     struct: {
+      version: "u8",
       owner: { array: { type: "u8", len: 32 } },
       market_mint: { array: { type: "u8", len: 32 } },
       market_symbol: { array: { type: "u8", len: 32 } },
@@ -29,10 +48,27 @@ class PositionAccountData {
       position_nonce: "u64",
       pnl: "i64",
       direction: "i8",
+      margin_mode: "u8",
+      confirmed: "u8",
+      open_fee: "u64",
+      open_slot: "u64",
+      liquidation_auction_slot: "u64",
+      funding_index_snapshot: "i128",
+      borrow_index_snapshot: "i128",
+      residual_profit: "u64",
+      residual_fee: "u64",
+      guaranteed_stop_price: "u64",
+      client_tag: { array: { type: "u8", len: 16 } },
+      margin_call_slot: "u64",
+      contract_type: "u8",
+      close_request_slot: "u64",
+      open_base_fee_bps: "u16",
+      open_leverage_fee_bps: "u16",
+      checksum: "u32",
     },
   };
 
-  static size = 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 1;
+  static size = 1 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 1 + 1 + 1 + 8 + 8 + 8 + 16 + 16 + 8 + 8 + 8 + 16 + 8 + 1 + 8 + 2 + 2 + 4;
 }
 
 class InitializePositionData {
@@ -44,6 +80,9 @@ class InitializePositionData {
         this.leverage = props.leverage;
         this.position_nonce = props.position_nonce;
         this.direction = props.direction;
+        this.margin_mode = props.margin_mode;
+        this.client_tag = props.client_tag;
+        this.platform_fee_bps = props.platform_fee_bps;
     }
 
     static schema = {
@@ -55,6 +94,9 @@ class InitializePositionData {
             leverage: 'u8',
             position_nonce: 'u64',
             direction: 'i8',
+            margin_mode: 'u8',
+            client_tag: { array: { type: 'u8', len: 16 } },
+            platform_fee_bps: 'u16',
         }
     };
 }
@@ -73,8 +115,51 @@ class ClosePositionData {
     };
 }
 
+class CancelPositionData {
+    constructor(props) {
+        this.position_nonce = props.position_nonce;
+    }
+
+    static schema = {
+        struct: {
+            position_nonce: 'u64',
+        }
+    };
+}
+
+class PositionIndexData {
+    constructor(props) {
+        this.owner = props.owner;
+        this.nonces = props.nonces;
+    }
+
+    static schema = {
+        struct: {
+            owner: { array: { type: 'u8', len: 32 } },
+            nonces: { array: { type: 'u64' } },
+        }
+    };
+}
+
+class NonceCounterData {
+    constructor(props) {
+        this.owner = props.owner;
+        this.next_nonce = props.next_nonce;
+    }
+
+    static schema = {
+        struct: {
+            owner: { array: { type: 'u8', len: 32 } },
+            next_nonce: 'u64',
+        }
+    };
+}
+
 module.exports = {
     PositionAccountData,
+    PositionIndexData,
+    NonceCounterData,
     InitializePositionData,
-    ClosePositionData
+    ClosePositionData,
+    CancelPositionData
 };
\ No newline at end of file
@@ -0,0 +1,120 @@
+//! Anchor-compatible views of the program's accounts.
+//!
+//! The program stores its accounts without a discriminator, so Anchor's generated
+//! deserializers cannot read them directly. `AnchorAccount` gives every account type the
+//! 8-byte discriminator Anchor would assign it (`sha256("account:<Name>")[..8]`) and the same
+//! `try_serialize` / `try_deserialize` / `try_deserialize_unchecked` shape as Anchor's
+//! `AccountSerialize` and `AccountDeserialize`, over the discriminator-prefixed encoding.
+//! `from_program_data` reads the raw on-chain bytes, so an Anchor program can take one of our
+//! accounts as an `UncheckedAccount` and decode it without a custom deserializer.
+
+use std::io::Write;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{hash::hashv, program_error::ProgramError};
+
+use crate::processor::deserialize_padded;
+use crate::{
+    AttestationAccount, AuthorityAccount, BlacklistAccount, CloseOrderAccount, CorruptionMarkAccount, DcaScheduleAccount, EpochSnapshotAccount,
+    FollowerAccount, FreezeAccount, GlobalConfig, HedgeModeAccount, IntegratorAccount, LeadTraderAccount, LiquidationQueueAccount, LpPositionAccount,
+    ManagedVaultAccount, MarketState, NonceCounterAccount, PositionAccount, PositionHistoryAccount, PositionIndexAccount, PositionTreeAccount,
+    PriceFeedAccount, ScheduledOrderAccount, TwapScheduleAccount, UserStatsAccount, VaultDepositorAccount,
+};
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// Anchor's account discriminator for a type named `name`.
+pub fn account_discriminator(name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    discriminator("account", name)
+}
+
+/// Anchor's instruction discriminator for a handler named `name` (snake case).
+pub fn instruction_discriminator(name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    discriminator("global", name)
+}
+
+fn discriminator(namespace: &str, name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    let hash = hashv(&[namespace.as_bytes(), b":", name.as_bytes()]);
+    
+    hash.to_bytes()[..DISCRIMINATOR_LEN].try_into().unwrap()
+}
+
+pub trait AnchorAccount: BorshSerialize + BorshDeserialize + Default {
+    /// Type name the discriminator is derived from, as Anchor's IDL would list it.
+    const NAME: &'static str;
+    
+    fn discriminator() -> [u8; DISCRIMINATOR_LEN] {
+        account_discriminator(Self::NAME)
+    }
+    
+    /// Decodes the account as the program stores it, without a discriminator.
+    fn from_program_data(data: &[u8]) -> Result<Self, ProgramError> {
+        deserialize_padded::<Self>(data)
+    }
+    
+    /// Writes the discriminator followed by the Borsh encoding.
+    fn try_serialize<W: Write>(&self, writer: &mut W) -> Result<(), ProgramError> {
+        writer
+            .write_all(&Self::discriminator())
+            .and_then(|_| self.serialize(writer))
+            .map_err(|_| ProgramError::AccountDataTooSmall)
+    }
+    
+    /// Reads a discriminator-prefixed buffer, rejecting any other discriminator.
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        if buf.len() < DISCRIMINATOR_LEN || buf[..DISCRIMINATOR_LEN] != Self::discriminator() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        Self::try_deserialize_unchecked(buf)
+    }
+    
+    /// Reads a discriminator-prefixed buffer without checking the discriminator.
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self, ProgramError> {
+        let data = buf.get(DISCRIMINATOR_LEN..).ok_or(ProgramError::InvalidAccountData)?;
+        let account = deserialize_padded::<Self>(data)?;
+        *buf = &[];
+        
+        Ok(account)
+    }
+}
+
+macro_rules! anchor_accounts {
+    ($($account:ident),* $(,)?) => {
+        $(
+            impl AnchorAccount for $account {
+                const NAME: &'static str = stringify!($account);
+            }
+        )*
+    };
+}
+
+anchor_accounts!(
+    PositionAccount,
+    GlobalConfig,
+    MarketState,
+    AuthorityAccount,
+    AttestationAccount,
+    FreezeAccount,
+    CorruptionMarkAccount,
+    PositionHistoryAccount,
+    BlacklistAccount,
+    IntegratorAccount,
+    PriceFeedAccount,
+    CloseOrderAccount,
+    ScheduledOrderAccount,
+    DcaScheduleAccount,
+    TwapScheduleAccount,
+    EpochSnapshotAccount,
+    PositionTreeAccount,
+    LeadTraderAccount,
+    FollowerAccount,
+    ManagedVaultAccount,
+    VaultDepositorAccount,
+    HedgeModeAccount,
+    LpPositionAccount,
+    PositionIndexAccount,
+    LiquidationQueueAccount,
+    UserStatsAccount,
+    NonceCounterAccount,
+);
@@ -0,0 +1,19 @@
+//! Writes the program's Borsh schema as JSON to the given path, or to stdout without one.
+//!
+//! `cargo run --features schema --bin export-schema -- schema.json`
+
+use std::{env, fs, process};
+
+fn main() {
+    let json = uranus_position::schema::program_schema().to_json();
+    
+    match env::args().nth(1) {
+        Some(path) => {
+            if let Err(err) = fs::write(&path, json) {
+                eprintln!("Could not write {}: {}", path, err);
+                process::exit(1);
+            }
+        },
+        None => print!("{}", json),
+    }
+}
@@ -0,0 +1,893 @@
+//! Off-chain helpers for reading program accounts over RPC.
+//!
+//! Decoding and derived values go through the same functions the program uses, so a
+//! `PositionView` matches what `VIEW_POSITION` would return for the same accounts.
+
+use solana_account_decoder::UiAccountEncoding;
+use solana_program::{
+    address_lookup_table::{
+        instruction::{create_lookup_table, extend_lookup_table},
+        state::AddressLookupTable,
+        AddressLookupTableAccount,
+    },
+    clock::Slot,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    message::{v0, CompileError, VersionedMessage},
+    program_error::ProgramError,
+    pubkey,
+    pubkey::Pubkey,
+    system_program,
+};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_rpc_client_api::{
+    client_error::{Error as RpcError, ErrorKind as RpcErrorKind},
+    config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    filter::{Memcmp, RpcFilterType},
+    request::{RpcError as RpcRequestError, RpcResponseErrorData},
+    response::RpcSimulateTransactionResult,
+};
+use solana_sdk::transaction::TransactionError;
+use borsh::BorshSerialize;
+use std::collections::{hash_map::Entry, HashMap};
+use std::fmt;
+use thiserror::Error;
+
+use crate::processor::deserialize_padded;
+use crate::{
+    accrued_borrow_fee, accrued_funding, DexError, AttestationAccount, BlacklistAccount, find_attestation_address, find_authority_address, find_blacklist_address, find_close_order_address, find_config_address, find_dca_schedule_address, find_epoch_snapshot_address, find_fee_escrow_address, find_fee_vault_address,
+    find_follower_address, find_hedge_mode_address, find_insurance_fund_address, find_integrator_address, find_lead_trader_address, find_liquidation_queue_address, find_lp_position_address, find_managed_vault_address,
+    find_market_address, find_market_insurance_address, find_nonce_counter_address, find_order_book_address, find_position_history_address, find_position_index_address, find_position_tree_address, find_price_feed_address, find_program_vault_address,
+    find_scheduled_order_address, find_settlement_vault_address, find_twap_schedule_address, find_user_stats_address, find_vault_depositor_address, CloseOrderAccount, DcaScheduleAccount, EpochSnapshotAccount,
+    FollowerAccount, GlobalConfig, HedgeModeAccount, IntegratorAccount, LeadTraderAccount, LiquidationQueueAccount, LpPositionAccount, ManagedVaultAccount, MarketState,
+    NonceCounterAccount, PositionAccount, PositionHealth, PositionHistoryAccount, PositionIndexAccount, PositionTreeAccount, PriceFeedAccount,
+    ScheduledOrderAccount, TwapScheduleAccount, INSTRUCTION_EXECUTE_CLOSE_ORDER, INSTRUCTION_EXPIRE_POSITION, INSTRUCTION_FORCE_CLOSE,
+    INSTRUCTION_FORCE_CLOSE_UNRECOVERABLE, INSTRUCTION_LIQUIDATE, INSTRUCTION_MARGIN_CALL, INSTRUCTION_PROCESS_PNL, INSTRUCTION_QUEUE_LIQUIDATION,
+    INSTRUCTION_SETTLE_DELISTED_POSITION, INSTRUCTION_STOP_OUT, INSTRUCTION_TRIGGER_GUARANTEED_STOP, UserStatsAccount, VaultDepositorAccount, LEGACY_POSITION_ACCOUNT_LEN, MARKET_STATUS_MIGRATED, POSITION_LONG, POSITION_SHORT, SPL_TOKEN_PROGRAM_ID,
+};
+
+/// Byte offset of `PositionAccount::owner`, right after the version byte.
+const POSITION_OWNER_OFFSET: usize = 1;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("RPC request failed: {0}")]
+    Rpc(Box<RpcError>),
+    #[error("Account could not be decoded: {0}")]
+    Decode(#[from] ProgramError),
+    #[error("Account {0} does not exist")]
+    AccountNotFound(Pubkey),
+    #[error("Account {0} is not an address lookup table")]
+    InvalidLookupTable(Pubkey),
+    #[error("Message could not be compiled: {0}")]
+    Compile(#[from] CompileError),
+    #[error("Instruction data could not be encoded")]
+    Encode,
+    #[error("Instruction {0} does not fit in a transaction on its own")]
+    InstructionTooLarge(usize),
+    #[error("{0}")]
+    Program(ProgramFailure),
+}
+
+impl From<RpcError> for ClientError {
+    fn from(e: RpcError) -> Self {
+        match rpc_program_failure(&e) {
+            Some(failure) => ClientError::Program(failure),
+            None => ClientError::Rpc(Box::new(e)),
+        }
+    }
+}
+
+/// An instruction of this program that failed with a custom error code. `error` is `None` for
+/// codes this build does not know, e.g. from a newer program version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramFailure {
+    pub instruction_index: u8,
+    pub code: u32,
+    pub error: Option<DexError>,
+    /// Program logs of the failed simulation, empty when the RPC node returned none.
+    pub logs: Vec<String>,
+}
+
+impl fmt::Display for ProgramFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.error {
+            Some(error) => write!(f, "Instruction {} failed: {} ({:?}, code {})", self.instruction_index, error, error, self.code),
+            None => write!(f, "Instruction {} failed with unknown program error code {}", self.instruction_index, self.code),
+        }
+    }
+}
+
+/// The program failure inside a transaction error, if the failing instruction belongs to this
+/// program. With logs the failing program is read from them; without, a custom code is assumed
+/// to be ours.
+pub fn program_failure(err: &TransactionError, logs: &[String]) -> Option<ProgramFailure> {
+    let TransactionError::InstructionError(instruction_index, InstructionError::Custom(code)) = err else {
+        return None;
+    };
+    
+    let failed_program = logs
+        .iter()
+        .rev()
+        .find_map(|line| line.strip_prefix("Program ")?.split_once(" failed: ").map(|(program, _)| program));
+    
+    if failed_program.is_some_and(|program| program != crate::id().to_string()) {
+        return None;
+    }
+    
+    Some(ProgramFailure {
+        instruction_index: *instruction_index,
+        code: *code,
+        error: DexError::try_from(*code).ok(),
+        logs: logs.to_vec(),
+    })
+}
+
+/// Decodes the failure of a `simulate_transaction` result.
+pub fn simulation_failure(result: &RpcSimulateTransactionResult) -> Option<ProgramFailure> {
+    program_failure(result.err.as_ref()?, result.logs.as_deref().unwrap_or_default())
+}
+
+/// Decodes a program failure from a send or preflight error, using the simulation logs when the
+/// node included them.
+pub fn rpc_program_failure(err: &RpcError) -> Option<ProgramFailure> {
+    match err.kind() {
+        RpcErrorKind::RpcError(RpcRequestError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => simulation_failure(result),
+        RpcErrorKind::TransactionError(err) => program_failure(err, &[]),
+        _ => None,
+    }
+}
+
+pub struct MarketView {
+    pub address: Pubkey,
+    pub liquidity: u64,
+    pub state: MarketState,
+}
+
+pub struct PositionView {
+    pub address: Pubkey,
+    pub position: PositionAccount,
+    pub mark_price: u64,
+    pub unrealized_pnl: i128,
+    pub accrued_funding: i128,
+    pub accrued_borrow_fee: i128,
+    pub health: PositionHealth,
+}
+
+/// An owner's positions valued together. Totals cover the confirmed, open positions in
+/// `positions`; `pending_collateral` is what unconfirmed opens would refund on cancel or expiry.
+pub struct PortfolioReport {
+    pub owner: Pubkey,
+    pub positions: Vec<PositionView>,
+    pub collateral: u64,
+    pub pending_collateral: u64,
+    pub notional: u128,
+    pub unrealized_pnl: i128,
+    pub accrued_funding: i128,
+    pub accrued_borrow_fee: i128,
+    /// Collateral plus unrealized PnL net of funding and borrow, plus pending collateral.
+    pub net_value: i128,
+}
+
+/// All open and pending positions of `owner`, skipping accounts still on the legacy layout.
+pub fn fetch_positions_by_owner(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+) -> Result<Vec<(Pubkey, PositionAccount)>, ClientError> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            POSITION_OWNER_OFFSET,
+            owner.as_ref(),
+        ))]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+    
+    rpc.get_program_accounts_with_config(&crate::id(), config)?
+        .into_iter()
+        .filter(|(_, account)| account.data.len() != LEGACY_POSITION_ACCOUNT_LEN)
+        .map(|(address, account)| Ok((address, deserialize_padded::<PositionAccount>(&account.data)?)))
+        .collect()
+}
+
+/// Nonces from the owner's position index, empty if the owner never opened a position through it.
+pub fn fetch_position_nonces(rpc: &RpcClient, owner: &Pubkey) -> Result<Vec<u64>, ClientError> {
+    let (address, _bump) = find_position_index_address(owner, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(deserialize_padded::<PositionIndexAccount>(&account.data)?.nonces),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Nonce the next `INITIALIZE` from `owner` must use.
+pub fn fetch_next_position_nonce(rpc: &RpcClient, owner: &Pubkey) -> Result<u64, ClientError> {
+    let (address, _bump) = find_nonce_counter_address(owner, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(deserialize_padded::<NonceCounterAccount>(&account.data)?.next_nonce),
+        None => Ok(0),
+    }
+}
+
+/// State and liquidity of a market, following the redirect left by `MIGRATE_MARKET`; markets
+/// created before they had data read as all-default.
+pub fn fetch_market_state(rpc: &RpcClient, market_mint: &Pubkey) -> Result<MarketView, ClientError> {
+    let (mut address, _bump) = find_market_address(market_mint, &crate::id());
+    
+    loop {
+        let account = rpc
+            .get_account_with_commitment(&address, rpc.commitment())?
+            .value
+            .ok_or(ClientError::AccountNotFound(address))?;
+        
+        let state = if account.data.is_empty() {
+            MarketState::default()
+        } else {
+            deserialize_padded::<MarketState>(&account.data)?
+        };
+        
+        if state.status == MARKET_STATUS_MIGRATED && state.migrated_to != address {
+            address = state.migrated_to;
+            continue;
+        }
+        
+        return Ok(MarketView {
+            address,
+            liquidity: account.lamports,
+            state,
+        });
+    }
+}
+
+pub fn fetch_config(rpc: &RpcClient) -> Result<GlobalConfig, ClientError> {
+    let (address, _bump) = find_config_address(&crate::id());
+    let data = rpc.get_account_data(&address)?;
+    
+    Ok(deserialize_padded::<GlobalConfig>(&data)?)
+}
+
+pub fn fetch_price_feed(rpc: &RpcClient, market_mint: &Pubkey) -> Result<PriceFeedAccount, ClientError> {
+    let (address, _bump) = find_price_feed_address(market_mint, &crate::id());
+    let data = rpc.get_account_data(&address)?;
+    
+    Ok(deserialize_padded::<PriceFeedAccount>(&data)?)
+}
+
+/// Derived values for a confirmed position at `mark_price`, using the on-chain formulas.
+pub fn position_view(
+    address: Pubkey,
+    position: PositionAccount,
+    mark_price: u64,
+    market_state: &MarketState,
+    config: &GlobalConfig,
+) -> Result<PositionView, ClientError> {
+    let health = position.health(mark_price, config.maintenance_margin_bps, 0)?;
+    
+    Ok(PositionView {
+        address,
+        mark_price,
+        unrealized_pnl: position.unrealized_pnl(mark_price)?,
+        accrued_funding: accrued_funding(&position, market_state),
+        accrued_borrow_fee: accrued_borrow_fee(&position, market_state),
+        health,
+        position,
+    })
+}
+
+/// Every confirmed, open position of `owner` valued at its market's oracle price.
+pub fn fetch_position_views_by_owner(rpc: &RpcClient, owner: &Pubkey) -> Result<Vec<PositionView>, ClientError> {
+    let config = fetch_config(rpc)?;
+    
+    fetch_positions_by_owner(rpc, owner)?
+        .into_iter()
+        .filter(|(_, position)| position.is_open() && position.entry_price != 0)
+        .map(|(address, position)| {
+            let mark_price = fetch_price_feed(rpc, &position.market_mint)?.price;
+            let market = fetch_market_state(rpc, &position.market_mint)?;
+            
+            position_view(address, position, mark_price, &market.state, &config)
+        })
+        .collect()
+}
+
+/// Values every position of `owner` at `prices` (mark price by market mint), falling back to the
+/// market's oracle price for mints missing from it. Each market and price feed is fetched once.
+pub fn portfolio_value(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    prices: &HashMap<Pubkey, u64>,
+) -> Result<PortfolioReport, ClientError> {
+    let config = fetch_config(rpc)?;
+    let mut markets: HashMap<Pubkey, (MarketState, u64)> = HashMap::new();
+    let mut report = PortfolioReport {
+        owner: *owner,
+        positions: Vec::new(),
+        collateral: 0,
+        pending_collateral: 0,
+        notional: 0,
+        unrealized_pnl: 0,
+        accrued_funding: 0,
+        accrued_borrow_fee: 0,
+        net_value: 0,
+    };
+    
+    for (address, position) in fetch_positions_by_owner(rpc, owner)? {
+        if !position.is_open() {
+            continue;
+        }
+        
+        if position.entry_price == 0 {
+            report.pending_collateral = report.pending_collateral.saturating_add(position.paid_amount);
+            continue;
+        }
+        
+        let (market_state, mark_price) = match markets.entry(position.market_mint) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let state = fetch_market_state(rpc, &position.market_mint)?.state;
+                let mark_price = match prices.get(&position.market_mint) {
+                    Some(price) => *price,
+                    None => fetch_price_feed(rpc, &position.market_mint)?.price,
+                };
+                entry.insert((state, mark_price))
+            },
+        };
+        
+        let view = position_view(address, position, *mark_price, market_state, &config)?;
+        
+        report.collateral = report.collateral.saturating_add(view.position.paid_amount);
+        report.notional = report.notional.saturating_add(view.health.notional);
+        report.unrealized_pnl = report.unrealized_pnl.saturating_add(view.unrealized_pnl);
+        report.accrued_funding = report.accrued_funding.saturating_add(view.accrued_funding);
+        report.accrued_borrow_fee = report.accrued_borrow_fee.saturating_add(view.accrued_borrow_fee);
+        report.positions.push(view);
+    }
+    
+    report.net_value = (report.collateral as i128 + report.pending_collateral as i128)
+        .saturating_add(report.unrealized_pnl)
+        .saturating_sub(report.accrued_funding)
+        .saturating_sub(report.accrued_borrow_fee);
+    
+    Ok(report)
+}
+
+/// An owner's LP position in a market, `None` if the owner never deposited.
+pub fn fetch_lp_position(
+    rpc: &RpcClient,
+    market_mint: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Option<LpPositionAccount>, ClientError> {
+    let (address, _bump) = find_lp_position_address(market_mint, owner, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<LpPositionAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// The close order resting against an owner's position, `None` if there is none.
+pub fn fetch_close_order(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    position_nonce: u64,
+) -> Result<Option<CloseOrderAccount>, ClientError> {
+    let (address, _bump) = find_close_order_address(owner, position_nonce, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<CloseOrderAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// The scheduled order for an owner's position nonce, `None` if there is none.
+pub fn fetch_scheduled_order(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    position_nonce: u64,
+) -> Result<Option<ScheduledOrderAccount>, ClientError> {
+    let (address, _bump) = find_scheduled_order_address(owner, position_nonce, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<ScheduledOrderAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// An owner's DCA schedule, `None` once it has been cancelled or has run out of budget.
+pub fn fetch_dca_schedule(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    schedule_id: u64,
+) -> Result<Option<DcaScheduleAccount>, ClientError> {
+    let (address, _bump) = find_dca_schedule_address(owner, schedule_id, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<DcaScheduleAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// The TWAP filling an owner's position, `None` once it has completed or been cancelled.
+pub fn fetch_twap_schedule(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    position_nonce: u64,
+) -> Result<Option<TwapScheduleAccount>, ClientError> {
+    let (address, _bump) = find_twap_schedule_address(owner, position_nonce, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<TwapScheduleAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_lead_trader(rpc: &RpcClient, lead: &Pubkey) -> Result<Option<LeadTraderAccount>, ClientError> {
+    let (address, _bump) = find_lead_trader_address(lead, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<LeadTraderAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_follower(
+    rpc: &RpcClient,
+    lead: &Pubkey,
+    follower: &Pubkey,
+) -> Result<Option<FollowerAccount>, ClientError> {
+    let (address, _bump) = find_follower_address(lead, follower, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<FollowerAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_managed_vault(
+    rpc: &RpcClient,
+    manager: &Pubkey,
+    vault_id: u64,
+) -> Result<Option<ManagedVaultAccount>, ClientError> {
+    let (address, _bump) = find_managed_vault_address(manager, vault_id, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<ManagedVaultAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_vault_depositor(
+    rpc: &RpcClient,
+    vault: &Pubkey,
+    depositor: &Pubkey,
+) -> Result<Option<VaultDepositorAccount>, ClientError> {
+    let (address, _bump) = find_vault_depositor_address(vault, depositor, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<VaultDepositorAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_hedge_mode(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    market_mint: &Pubkey,
+) -> Result<Option<HedgeModeAccount>, ClientError> {
+    let (address, _bump) = find_hedge_mode_address(owner, market_mint, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<HedgeModeAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_integrator(rpc: &RpcClient, integrator: &Pubkey) -> Result<Option<IntegratorAccount>, ClientError> {
+    let (address, _bump) = find_integrator_address(integrator, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<IntegratorAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_attestation(rpc: &RpcClient, owner: &Pubkey) -> Result<Option<AttestationAccount>, ClientError> {
+    let (address, _bump) = find_attestation_address(owner, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<AttestationAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_blacklist(rpc: &RpcClient) -> Result<Option<BlacklistAccount>, ClientError> {
+    let (address, _bump) = find_blacklist_address(&crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<BlacklistAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// An owner's settlement history, `None` if they never initialized it.
+pub fn fetch_position_history(rpc: &RpcClient, owner: &Pubkey) -> Result<Option<PositionHistoryAccount>, ClientError> {
+    let (address, _bump) = find_position_history_address(owner, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<PositionHistoryAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_user_stats(rpc: &RpcClient, owner: &Pubkey) -> Result<Option<UserStatsAccount>, ClientError> {
+    let (address, _bump) = find_user_stats_address(owner, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<UserStatsAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn fetch_liquidation_queue(rpc: &RpcClient, market_mint: &Pubkey) -> Result<Option<LiquidationQueueAccount>, ClientError> {
+    let (address, _bump) = find_liquidation_queue_address(market_mint, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<LiquidationQueueAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// A market's statistics snapshot for `epoch`, `None` if that epoch was never rolled over.
+pub fn fetch_epoch_snapshot(rpc: &RpcClient, market_mint: &Pubkey, epoch: u64) -> Result<Option<EpochSnapshotAccount>, ClientError> {
+    let (address, _bump) = find_epoch_snapshot_address(market_mint, epoch, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<EpochSnapshotAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// A market's compressed position tree; the leaves themselves are rebuilt from events.
+pub fn fetch_position_tree(rpc: &RpcClient, market_mint: &Pubkey) -> Result<Option<PositionTreeAccount>, ClientError> {
+    let (address, _bump) = find_position_tree_address(market_mint, &crate::id());
+    
+    match rpc.get_account_with_commitment(&address, rpc.commitment())?.value {
+        Some(account) => Ok(Some(deserialize_padded::<PositionTreeAccount>(&account.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// Addresses an `EXTEND_LOOKUP_TABLE` instruction adds at most, keeping it within a legacy
+/// transaction alongside the create instruction.
+pub const LOOKUP_TABLE_EXTEND_CHUNK: usize = 20;
+
+/// Accounts that appear in most instructions touching `market_mint` and never change: the
+/// program and its global PDAs, the market's own PDAs, the fee wallet and the system and token
+/// programs. Putting them in an address lookup table keeps large instructions under the
+/// transaction size limit.
+pub fn market_static_accounts(market_mint: &Pubkey) -> Vec<Pubkey> {
+    let program_id = crate::id();
+    
+    vec![
+        program_id,
+        find_config_address(&program_id).0,
+        find_blacklist_address(&program_id).0,
+        find_fee_escrow_address(&program_id).0,
+        find_fee_vault_address(&program_id).0,
+        find_insurance_fund_address(&program_id).0,
+        find_program_vault_address(&program_id).0,
+        find_market_address(market_mint, &program_id).0,
+        find_price_feed_address(market_mint, &program_id).0,
+        find_market_insurance_address(market_mint, &program_id).0,
+        find_settlement_vault_address(market_mint, &program_id).0,
+        find_order_book_address(market_mint, POSITION_LONG, &program_id).0,
+        find_order_book_address(market_mint, POSITION_SHORT, &program_id).0,
+        find_liquidation_queue_address(market_mint, &program_id).0,
+        find_position_tree_address(market_mint, &program_id).0,
+        find_authority_address(&program_id).0,
+        system_program::id(),
+        SPL_TOKEN_PROGRAM_ID,
+    ]
+}
+
+/// Instructions extending `lookup_table` with `addresses`, split into chunks of
+/// `LOOKUP_TABLE_EXTEND_CHUNK`; `payer` funds the table's growing rent.
+pub fn extend_lookup_table_instructions(
+    lookup_table: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    addresses: &[Pubkey],
+) -> Vec<Instruction> {
+    addresses
+        .chunks(LOOKUP_TABLE_EXTEND_CHUNK)
+        .map(|chunk| extend_lookup_table(*lookup_table, *authority, Some(*payer), chunk.to_vec()))
+        .collect()
+}
+
+/// Instructions creating a lookup table for `market_mint` and filling it with
+/// `market_static_accounts`, with the table's address. `recent_slot` must be a recent finalized
+/// slot; the table becomes usable one slot after the transaction that extends it.
+pub fn create_market_lookup_table_instructions(
+    authority: &Pubkey,
+    payer: &Pubkey,
+    recent_slot: Slot,
+    market_mint: &Pubkey,
+) -> (Pubkey, Vec<Instruction>) {
+    let (create, lookup_table) = create_lookup_table(*authority, *payer, recent_slot);
+    let mut instructions = vec![create];
+    
+    instructions.extend(extend_lookup_table_instructions(
+        &lookup_table,
+        authority,
+        payer,
+        &market_static_accounts(market_mint),
+    ));
+    
+    (lookup_table, instructions)
+}
+
+/// Decoded lookup table, ready to pass to a v0 message.
+pub fn fetch_lookup_table(rpc: &RpcClient, lookup_table: &Pubkey) -> Result<AddressLookupTableAccount, ClientError> {
+    let account = rpc
+        .get_account_with_commitment(lookup_table, rpc.commitment())?
+        .value
+        .ok_or(ClientError::AccountNotFound(*lookup_table))?;
+    let table = AddressLookupTable::deserialize(&account.data)
+        .map_err(|_| ClientError::InvalidLookupTable(*lookup_table))?;
+    
+    Ok(AddressLookupTableAccount {
+        key: *lookup_table,
+        addresses: table.addresses.to_vec(),
+    })
+}
+
+/// Instructions adding whatever `market_static_accounts` of `market_mint` the existing table
+/// lacks, e.g. PDAs introduced by a program upgrade; empty when it is already complete.
+pub fn update_market_lookup_table_instructions(
+    table: &AddressLookupTableAccount,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    market_mint: &Pubkey,
+) -> Vec<Instruction> {
+    let missing: Vec<Pubkey> = market_static_accounts(market_mint)
+        .into_iter()
+        .filter(|address| !table.addresses.contains(address))
+        .collect();
+    
+    extend_lookup_table_instructions(&table.key, authority, payer, &missing)
+}
+
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+
+const COMPUTE_BUDGET_SET_UNIT_LIMIT: u8 = 2;
+const COMPUTE_BUDGET_SET_UNIT_PRICE: u8 = 3;
+
+/// Compute budget prepended to a transaction; a zero field leaves the runtime default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+    pub unit_limit: u32,
+    pub unit_price_micro_lamports: u64,
+}
+
+impl ComputeBudget {
+    /// `SetComputeUnitLimit` and `SetComputeUnitPrice` instructions for the nonzero fields.
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let mut instructions = Vec::with_capacity(2);
+        
+        if self.unit_limit > 0 {
+            let mut data = vec![COMPUTE_BUDGET_SET_UNIT_LIMIT];
+            data.extend_from_slice(&self.unit_limit.to_le_bytes());
+            instructions.push(Instruction::new_with_bytes(COMPUTE_BUDGET_PROGRAM_ID, &data, Vec::new()));
+        }
+        
+        if self.unit_price_micro_lamports > 0 {
+            let mut data = vec![COMPUTE_BUDGET_SET_UNIT_PRICE];
+            data.extend_from_slice(&self.unit_price_micro_lamports.to_le_bytes());
+            instructions.push(Instruction::new_with_bytes(COMPUTE_BUDGET_PROGRAM_ID, &data, Vec::new()));
+        }
+        
+        instructions
+    }
+}
+
+/// An instruction for this program: the `tag` byte (`INSTRUCTION_*`) followed by the Borsh
+/// payload, as `process_instruction` decodes it.
+pub fn dex_instruction<T: BorshSerialize>(
+    tag: u8,
+    data: &T,
+    accounts: Vec<AccountMeta>,
+) -> Result<Instruction, ClientError> {
+    let mut instruction_data = vec![tag];
+    data.serialize(&mut instruction_data).map_err(|_| ClientError::Encode)?;
+    
+    Ok(Instruction::new_with_bytes(crate::id(), &instruction_data, accounts))
+}
+
+/// A v0 message running `instructions` after the `budget` instructions, resolving every
+/// account found in `lookup_tables` through them. Sign it with `VersionedTransaction::try_new`.
+pub fn build_v0_message(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    budget: ComputeBudget,
+    recent_blockhash: Hash,
+) -> Result<VersionedMessage, ClientError> {
+    let mut all_instructions = budget.instructions();
+    all_instructions.extend_from_slice(instructions);
+    
+    let message = v0::Message::try_compile(payer, &all_instructions, lookup_tables, recent_blockhash)?;
+    
+    Ok(VersionedMessage::V0(message))
+}
+
+/// `build_v0_message` with the cluster's latest blockhash and the given lookup tables fetched.
+pub fn fetch_v0_message(
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[Pubkey],
+    budget: ComputeBudget,
+) -> Result<VersionedMessage, ClientError> {
+    let tables = lookup_tables
+        .iter()
+        .map(|table| fetch_lookup_table(rpc, table))
+        .collect::<Result<Vec<_>, _>>()?;
+    
+    build_v0_message(payer, instructions, &tables, budget, rpc.get_latest_blockhash()?)
+}
+
+/// Serialized transaction size limit (`PACKET_DATA_SIZE`).
+pub const TRANSACTION_SIZE_LIMIT: usize = 1232;
+/// Compute units a single transaction may request.
+pub const MAX_TRANSACTION_COMPUTE_UNITS: u32 = 1_400_000;
+/// Runtime default charged per instruction when no limit is requested.
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNITS: u32 = 200_000;
+/// Headroom added on top of the summed estimates of a batch, in basis points.
+pub const COMPUTE_UNIT_MARGIN_BPS: u32 = 1_000;
+
+const COMPUTE_BUDGET_INSTRUCTION_UNITS: u32 = 150;
+
+/// Estimated compute units consumed by `instruction`. Settlement and liquidation paths carry
+/// per-tag estimates; anything else is charged the runtime default.
+pub fn estimate_compute_units(instruction: &Instruction) -> u32 {
+    if instruction.program_id == COMPUTE_BUDGET_PROGRAM_ID {
+        return COMPUTE_BUDGET_INSTRUCTION_UNITS;
+    }
+    
+    if instruction.program_id != crate::id() {
+        return DEFAULT_INSTRUCTION_COMPUTE_UNITS;
+    }
+    
+    match instruction.data.first().copied() {
+        Some(INSTRUCTION_PROCESS_PNL) => 60_000,
+        Some(INSTRUCTION_FORCE_CLOSE) => 50_000,
+        Some(INSTRUCTION_FORCE_CLOSE_UNRECOVERABLE) => 30_000,
+        Some(INSTRUCTION_LIQUIDATE) => 80_000,
+        Some(INSTRUCTION_EXPIRE_POSITION) => 40_000,
+        Some(INSTRUCTION_SETTLE_DELISTED_POSITION) => 50_000,
+        Some(INSTRUCTION_STOP_OUT) => 70_000,
+        Some(INSTRUCTION_TRIGGER_GUARANTEED_STOP) => 70_000,
+        Some(INSTRUCTION_EXECUTE_CLOSE_ORDER) => 70_000,
+        Some(INSTRUCTION_MARGIN_CALL) => 30_000,
+        Some(INSTRUCTION_QUEUE_LIQUIDATION) => 30_000,
+        _ => DEFAULT_INSTRUCTION_COMPUTE_UNITS,
+    }
+}
+
+/// One transaction of a batch plan: its instructions and the budget covering them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedTransaction {
+    pub budget: ComputeBudget,
+    pub instructions: Vec<Instruction>,
+}
+
+fn planned_budget(units: u32, unit_price_micro_lamports: u64) -> ComputeBudget {
+    let margin = (units as u64 * COMPUTE_UNIT_MARGIN_BPS as u64 / 10_000) as u32;
+    
+    ComputeBudget {
+        unit_limit: units.saturating_add(margin).min(MAX_TRANSACTION_COMPUTE_UNITS),
+        unit_price_micro_lamports,
+    }
+}
+
+fn fits_transaction(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    budget: ComputeBudget,
+) -> Result<bool, ClientError> {
+    let message = build_v0_message(payer, instructions, lookup_tables, budget, Hash::default())?;
+    let signatures = message.header().num_required_signatures as usize;
+    
+    // Compact-u16 signature count plus 64 bytes per signature.
+    Ok(1 + signatures * 64 + message.serialize().len() <= TRANSACTION_SIZE_LIMIT)
+}
+
+/// Packs `instructions` in order into as few v0 transactions as fit both the compute limit
+/// (summed estimates plus `COMPUTE_UNIT_MARGIN_BPS`) and the packet size with the given lookup
+/// tables. Each planned transaction requests exactly the units it is estimated to need.
+pub fn plan_batches(
+    payer: &Pubkey,
+    instructions: Vec<Instruction>,
+    lookup_tables: &[AddressLookupTableAccount],
+    unit_price_micro_lamports: u64,
+) -> Result<Vec<PlannedTransaction>, ClientError> {
+    let mut plans = Vec::new();
+    let mut current: Vec<Instruction> = Vec::new();
+    let mut current_units = 0u32;
+    
+    for (index, instruction) in instructions.into_iter().enumerate() {
+        let units = current_units.saturating_add(estimate_compute_units(&instruction));
+        let budget = planned_budget(units, unit_price_micro_lamports);
+        
+        current.push(instruction);
+        
+        if budget.unit_limit < MAX_TRANSACTION_COMPUTE_UNITS
+            && fits_transaction(payer, &current, lookup_tables, budget)?
+        {
+            current_units = units;
+            continue;
+        }
+        
+        let instruction = current.pop().expect("instruction was just pushed");
+        
+        if current.is_empty() {
+            return Err(ClientError::InstructionTooLarge(index));
+        }
+        
+        plans.push(PlannedTransaction {
+            budget: planned_budget(current_units, unit_price_micro_lamports),
+            instructions: std::mem::take(&mut current),
+        });
+        
+        current_units = estimate_compute_units(&instruction);
+        let budget = planned_budget(current_units, unit_price_micro_lamports);
+        current.push(instruction);
+        
+        if !fits_transaction(payer, &current, lookup_tables, budget)? {
+            return Err(ClientError::InstructionTooLarge(index));
+        }
+    }
+    
+    if !current.is_empty() {
+        plans.push(PlannedTransaction {
+            budget: planned_budget(current_units, unit_price_micro_lamports),
+            instructions: current,
+        });
+    }
+    
+    Ok(plans)
+}
+
+/// Compiles each planned transaction into a v0 message against `recent_blockhash`.
+pub fn build_batch_messages(
+    payer: &Pubkey,
+    plans: &[PlannedTransaction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<Vec<VersionedMessage>, ClientError> {
+    plans
+        .iter()
+        .map(|plan| build_v0_message(payer, &plan.instructions, lookup_tables, plan.budget, recent_blockhash))
+        .collect()
+}
+
+/// Keypair of `TEST_AUTHORITY`, which can sign `INITIALIZE_AUTHORITY` on test network builds.
+#[cfg(feature = "devnet")]
+pub fn test_authority_keypair() -> solana_sdk::signer::keypair::Keypair {
+    solana_sdk::signer::keypair::keypair_from_seed(crate::TEST_AUTHORITY_SEED).unwrap()
+}
+
+/// Keypair whose address is this test network build's program id, for `solana program deploy
+/// --program-id`.
+#[cfg(feature = "devnet")]
+pub fn test_program_keypair() -> solana_sdk::signer::keypair::Keypair {
+    solana_sdk::signer::keypair::keypair_from_seed(crate::TEST_PROGRAM_SEED).unwrap()
+}
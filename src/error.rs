@@ -0,0 +1,278 @@
+//! Program error codes returned as `ProgramError::Custom`.
+
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DexError {
+    #[error("Invalid market symbol")]
+    InvalidSymbol,
+    #[error("Market is not registered")]
+    MarketNotRegistered,
+    #[error("Account layout requires migration")]
+    AccountNeedsMigration,
+    #[error("Position is above maintenance margin")]
+    PositionNotLiquidatable,
+    #[error("Position entry price not set")]
+    PositionNotConfirmed,
+    #[error("Invalid config parameter")]
+    InvalidConfig,
+    #[error("Position is below the initial margin")]
+    InsufficientInitialMargin,
+    #[error("Invalid margin mode")]
+    InvalidMarginMode,
+    #[error("Cross margin account required")]
+    MarginAccountRequired,
+    #[error("Price outside the allowed oracle band")]
+    PriceOutsideBand,
+    #[error("Oracle price is stale")]
+    StalePrice,
+    #[error("Settlement PnL does not match the oracle")]
+    PnlMismatch,
+    #[error("Confirmation timeout has not elapsed")]
+    ConfirmationPending,
+    #[error("Liquidation price inconsistent with entry, direction or leverage")]
+    InvalidLiquidationPrice,
+    #[error("Order book side is full")]
+    OrderBookFull,
+    #[error("Order not found")]
+    OrderNotFound,
+    #[error("Leverage exceeds the tier for this position size")]
+    LeverageTierExceeded,
+    #[error("Position cannot be closed this soon after opening")]
+    CloseTooSoon,
+    #[error("Profitable close before the market's minimum holding period")]
+    MinHoldingPeriod,
+    #[error("Position nonce is not the owner's next nonce")]
+    NonceMismatch,
+    #[error("Market has not been initialized")]
+    MarketNotInitialized,
+    #[error("Market is not active")]
+    MarketNotActive,
+    #[error("Market listing is still in its veto window")]
+    ListingTimelockPending,
+    #[error("Market listing veto window has passed")]
+    ListingVetoWindowClosed,
+    #[error("Delisted market is still in its grace period")]
+    MarketWindDownPending,
+    #[error("Market still has open interest")]
+    MarketHasOpenInterest,
+    #[error("Market has migrated to a new address")]
+    MarketMigrated,
+    #[error("Market liquidity cap exceeded")]
+    MarketLiquidityCapExceeded,
+    #[error("Realized profit below the configured minimum")]
+    ProfitBelowRealizeThreshold,
+    #[error("Market liquidity cannot cover the payout")]
+    InsufficientMarketLiquidity,
+    #[error("Position is above the stop-out margin")]
+    PositionNotStoppedOut,
+    #[error("Stop-out is disabled")]
+    StopOutDisabled,
+    #[error("Guaranteed stops are disabled")]
+    GuaranteedStopDisabled,
+    #[error("Invalid guaranteed stop price")]
+    InvalidStopPrice,
+    #[error("Guaranteed stop price has not been reached")]
+    StopNotTriggered,
+    #[error("Close order limit price has not been reached")]
+    CloseOrderNotTriggered,
+    #[error("Scheduled order is not executable yet")]
+    ScheduledOrderNotReady,
+    #[error("DCA schedule is not due yet")]
+    DcaNotDue,
+    #[error("Lead position cannot be copied")]
+    LeadPositionNotCopyable,
+    #[error("Lead position is still open")]
+    LeadPositionStillOpen,
+    #[error("Follower still has open copied positions")]
+    FollowerHasOpenCopies,
+    #[error("Managed vault risk limit exceeded")]
+    VaultRiskLimitExceeded,
+    #[error("Managed vault funds are deployed in open positions")]
+    VaultFundsDeployed,
+    #[error("Managed vault epoch has not ended")]
+    VaultEpochNotEnded,
+    #[error("Managed vault request already pending")]
+    VaultRequestPending,
+    #[error("Portfolio health below the configured floor")]
+    PortfolioHealthTooLow,
+    #[error("Positions cannot be netted")]
+    PositionsNotNettable,
+    #[error("Platform fee share above the configured maximum")]
+    PlatformFeeTooHigh,
+    #[error("Integrator is not registered or inactive")]
+    IntegratorNotRegistered,
+    #[error("Owner has no valid attestation")]
+    AttestationRequired,
+    #[error("Account is frozen")]
+    AccountFrozen,
+    #[error("Address is blacklisted")]
+    AddressBlacklisted,
+    #[error("Blacklist is full")]
+    BlacklistFull,
+    #[error("Owner is opening positions too quickly")]
+    OpenRateLimited,
+    #[error("Owner notional cap exceeded")]
+    OwnerNotionalCapExceeded,
+    #[error("Position is not in the margin call zone")]
+    MarginCallNotDue,
+    #[error("Liquidation queue is full")]
+    LiquidationQueueFull,
+    #[error("A riskier queued position must be liquidated first")]
+    LiquidationOutOfOrder,
+    #[error("Price source account is invalid or not trading")]
+    InvalidPriceSource,
+    #[error("Funding interval has not elapsed")]
+    FundingIntervalNotElapsed,
+    #[error("Instruction does not support this market's settlement asset")]
+    SettlementAssetUnsupported,
+    #[error("Swap output is below the requested minimum")]
+    SwapSlippageExceeded,
+    #[error("Open exceeds the market's TWAP threshold")]
+    TwapRequired,
+    #[error("Next TWAP slice is not due yet")]
+    TwapSliceNotDue,
+    #[error("Trade is too large for the market's liquidity")]
+    PriceImpactTooHigh,
+    #[error("Market statistics already rolled over this epoch")]
+    EpochNotElapsed,
+    #[error("Market accounting invariant violated")]
+    InvariantViolated,
+    #[error("Account must be writable")]
+    AccountNotWritable,
+    #[error("Market still holds liquidity or LP shares")]
+    MarketNotEmpty,
+    #[error("Account data is not zeroed")]
+    AccountNotZeroed,
+    #[error("Merkle proof does not match the position tree")]
+    InvalidMerkleProof,
+    #[error("Position tree is full")]
+    PositionTreeFull,
+    #[error("PnL is out of range for the position")]
+    PnlOutOfRange,
+    #[error("Position is not in a state that allows this transition")]
+    InvalidPositionTransition,
+    #[error("Position account fails its checksum")]
+    PositionCorrupted,
+    #[error("Position account passes its checksum")]
+    PositionNotCorrupted,
+    #[error("Force close is still in its contest window")]
+    ForceCloseDelayPending,
+    #[error("Authority account is missing or not initialized")]
+    AuthorityNotInitialized,
+    #[error("Operator set is full")]
+    OperatorSetFull,
+    #[error("Position size does not match the paid amount and leverage")]
+    PositionSizeMismatch,
+    #[error("Program is shut down; only closes, settlements, withdrawals and rent reclamation are allowed")]
+    ProgramShutdown,
+}
+
+impl From<DexError> for ProgramError {
+    fn from(e: DexError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl TryFrom<u32> for DexError {
+    type Error = ProgramError;
+    
+    /// Decodes a `ProgramError::Custom` code; codes past the last variant come back unchanged.
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        DEX_ERRORS.get(code as usize).copied().ok_or(ProgramError::Custom(code))
+    }
+}
+
+/// Every variant in code order. New variants are appended here as well as to the enum.
+const DEX_ERRORS: [DexError; 81] = [
+    DexError::InvalidSymbol,
+    DexError::MarketNotRegistered,
+    DexError::AccountNeedsMigration,
+    DexError::PositionNotLiquidatable,
+    DexError::PositionNotConfirmed,
+    DexError::InvalidConfig,
+    DexError::InsufficientInitialMargin,
+    DexError::InvalidMarginMode,
+    DexError::MarginAccountRequired,
+    DexError::PriceOutsideBand,
+    DexError::StalePrice,
+    DexError::PnlMismatch,
+    DexError::ConfirmationPending,
+    DexError::InvalidLiquidationPrice,
+    DexError::OrderBookFull,
+    DexError::OrderNotFound,
+    DexError::LeverageTierExceeded,
+    DexError::CloseTooSoon,
+    DexError::MinHoldingPeriod,
+    DexError::NonceMismatch,
+    DexError::MarketNotInitialized,
+    DexError::MarketNotActive,
+    DexError::ListingTimelockPending,
+    DexError::ListingVetoWindowClosed,
+    DexError::MarketWindDownPending,
+    DexError::MarketHasOpenInterest,
+    DexError::MarketMigrated,
+    DexError::MarketLiquidityCapExceeded,
+    DexError::ProfitBelowRealizeThreshold,
+    DexError::InsufficientMarketLiquidity,
+    DexError::PositionNotStoppedOut,
+    DexError::StopOutDisabled,
+    DexError::GuaranteedStopDisabled,
+    DexError::InvalidStopPrice,
+    DexError::StopNotTriggered,
+    DexError::CloseOrderNotTriggered,
+    DexError::ScheduledOrderNotReady,
+    DexError::DcaNotDue,
+    DexError::LeadPositionNotCopyable,
+    DexError::LeadPositionStillOpen,
+    DexError::FollowerHasOpenCopies,
+    DexError::VaultRiskLimitExceeded,
+    DexError::VaultFundsDeployed,
+    DexError::VaultEpochNotEnded,
+    DexError::VaultRequestPending,
+    DexError::PortfolioHealthTooLow,
+    DexError::PositionsNotNettable,
+    DexError::PlatformFeeTooHigh,
+    DexError::IntegratorNotRegistered,
+    DexError::AttestationRequired,
+    DexError::AccountFrozen,
+    DexError::AddressBlacklisted,
+    DexError::BlacklistFull,
+    DexError::OpenRateLimited,
+    DexError::OwnerNotionalCapExceeded,
+    DexError::MarginCallNotDue,
+    DexError::LiquidationQueueFull,
+    DexError::LiquidationOutOfOrder,
+    DexError::InvalidPriceSource,
+    DexError::FundingIntervalNotElapsed,
+    DexError::SettlementAssetUnsupported,
+    DexError::SwapSlippageExceeded,
+    DexError::TwapRequired,
+    DexError::TwapSliceNotDue,
+    DexError::PriceImpactTooHigh,
+    DexError::EpochNotElapsed,
+    DexError::InvariantViolated,
+    DexError::AccountNotWritable,
+    DexError::MarketNotEmpty,
+    DexError::AccountNotZeroed,
+    DexError::InvalidMerkleProof,
+    DexError::PositionTreeFull,
+    DexError::PnlOutOfRange,
+    DexError::InvalidPositionTransition,
+    DexError::PositionCorrupted,
+    DexError::PositionNotCorrupted,
+    DexError::ForceCloseDelayPending,
+    DexError::AuthorityNotInitialized,
+    DexError::OperatorSetFull,
+    DexError::PositionSizeMismatch,
+    DexError::ProgramShutdown,
+];
+
+const _: () = {
+    let mut code = 0;
+    while code < DEX_ERRORS.len() {
+        assert!(DEX_ERRORS[code] as usize == code, "DEX_ERRORS out of order");
+        code += 1;
+    }
+};
@@ -0,0 +1,657 @@
+//! Events the program writes with `sol_log_data`, and with the `events` feature an off-chain
+//! decoder for them.
+//!
+//! Indexers should feed whole transaction logs to `decode_logs`, which tracks the invocation
+//! stack so that `Program data:` lines written by other programs in the same transaction are
+//! never mistaken for ours.
+
+#[cfg(feature = "events")]
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, log::sol_log_data, program_error::ProgramError, pubkey::Pubkey};
+#[cfg(feature = "events")]
+use thiserror::Error;
+
+use crate::state::CompressedPosition;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferReason {
+    CollateralReturn,
+    Profit,
+    ProfitFee,
+    Loss,
+    DustSweep,
+    MarginDraw,
+    MarginWithdrawal,
+    OpenFee,
+    Liquidation,
+    ForceClose,
+    MarketTransfer,
+    OrderRefund,
+    OrderFill,
+    LiquidationReward,
+    Takeover,
+    CloseFee,
+    LiquidationFee,
+    InsuranceFee,
+    SkewSurcharge,
+    SkewRebate,
+    RentRefund,
+    ListingBondRefund,
+    ListingBondSlash,
+    DelistSettlement,
+    MarketClosure,
+    MarketMigration,
+    VaultBackstop,
+    VaultWithdrawal,
+    InsuranceDraw,
+    LpFeeShare,
+    LiquidityWithdrawal,
+    LpFeeClaim,
+    StopOutSettlement,
+    GuaranteedStopGap,
+    DcaFill,
+    KeeperReimbursement,
+    CopyFill,
+    CopyProfitShare,
+    ManagedVaultFill,
+    ManagedVaultWithdrawal,
+    PerformanceFee,
+    SpotSwap,
+    GarbageCollection,
+    BorrowFee,
+    FeeWithdrawal,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRole {
+    Maker,
+    Taker,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeeChargedEvent {
+    pub market_mint: Pubkey,
+    pub payer: Pubkey,
+    pub position_nonce: u64,
+    pub role: FillRole,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OrderEvent {
+    pub market_mint: Pubkey,
+    pub side: i8,
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub price: u64,
+    pub paid_amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FillEvent {
+    pub market_mint: Pubkey,
+    pub bid_order_id: u64,
+    pub ask_order_id: u64,
+    pub bid_owner: Pubkey,
+    pub ask_owner: Pubkey,
+    pub maker_side: i8,
+    pub price: u64,
+    pub notional: u64,
+    pub bid_fee: u64,
+    pub ask_fee: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LiquidationEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub liquidator: Pubkey,
+    pub mark_price: u64,
+    pub closed_notional: u64,
+    pub auction_reward: u64,
+    pub keeper_fee: u64,
+    pub insurance_fee: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarketInitializedEvent {
+    pub market_mint: Pubkey,
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub slot: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarketListingEvent {
+    pub market_mint: Pubkey,
+    pub market: Pubkey,
+    pub lister: Pubkey,
+    pub bond: u64,
+    pub oracle_feed: Pubkey,
+    pub slot: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarketDelistedEvent {
+    pub market_mint: Pubkey,
+    pub market: Pubkey,
+    pub wind_down_slot: u64,
+}
+
+/// Rent reclaimed from a zeroed, program-owned account by `COLLECT_GARBAGE`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountReclaimedEvent {
+    pub account: Pubkey,
+    pub caller: Pubkey,
+    pub caller_share: u64,
+    pub treasury_share: u64,
+}
+
+/// A compressed position leaf as written to `tree`, with the tree's new `root`. Indexers keep
+/// the leaves to build proofs; on close `position` is the leaf removed, with its realized PnL
+/// and what was paid out to the owner.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompressedPositionEvent {
+    pub tree: Pubkey,
+    pub position: CompressedPosition,
+    pub root: [u8; 32],
+    pub realized_pnl: i64,
+    pub payout: u64,
+}
+
+/// An accounting invariant (`INVARIANT_*`) that failed for a market: `available` fell short of
+/// the `required` amount. `position_nonce` is set for per-position checks.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InvariantViolationEvent {
+    pub market_mint: Pubkey,
+    pub check: u8,
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub required: u64,
+    pub available: u64,
+}
+
+/// Fees a market collected over `epoch`, split as `split_fee` routed them.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EpochSummaryEvent {
+    pub market_mint: Pubkey,
+    pub epoch: u64,
+    pub protocol_fees: u64,
+    pub lp_fees: u64,
+    pub cumulative_volume: u64,
+    pub cumulative_fees: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarginCallEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub mark_price: u64,
+    pub equity: i64,
+    pub maintenance_requirement: u64,
+    pub health_bps: u64,
+    pub slot: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarketClosedEvent {
+    pub market_mint: Pubkey,
+    pub market: Pubkey,
+    pub returned_liquidity: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarketMigratedEvent {
+    pub market_mint: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub liquidity: u64,
+}
+
+/// A position moved from `from_market_mint` to `to_market_mint` by `MIGRATE_POSITION_MARKET`,
+/// taking `liquidity` of the old market's liquidity with it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PositionMarketMigratedEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub from_market_mint: Pubkey,
+    pub to_market_mint: Pubkey,
+    pub liquidity: u64,
+}
+
+/// A corrupted position marked for, or closed by, `FORCE_CLOSE`; `close_slot` is when the
+/// close becomes possible.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CorruptionEvent {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub marked_slot: u64,
+    pub close_slot: u64,
+}
+
+/// Fees pulled from the fee vault by `WITHDRAW_FEES`; `remaining` is what the vault still holds
+/// above its rent reserve.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeesWithdrawnEvent {
+    pub fees_wallet: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+}
+
+/// `SCHEDULE_SHUTDOWN` set or cancelled the shutdown; `shutdown_slot` is zero once cancelled.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownEvent {
+    pub admin: Pubkey,
+    pub shutdown_slot: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LiquidationQueueEvent {
+    pub market_mint: Pubkey,
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub keeper: Pubkey,
+    pub health_bps: u64,
+    pub queued: bool,
+    pub queue_length: u32,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LiquidityEvent {
+    pub market_mint: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub total_shares: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ResidualProfitEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub paid: u64,
+    pub fee: u64,
+    pub remaining: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProfitRealizedEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub mark_price: u64,
+    pub amount: u64,
+    pub fee: u64,
+    pub new_entry_price: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StopOutEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub keeper: Pubkey,
+    pub mark_price: u64,
+    pub pnl: i64,
+    pub health_bps: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AttestationEvent {
+    pub owner: Pubkey,
+    pub attestor: Pubkey,
+    pub expiry_slot: u64,
+    pub revoked: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BadDebtEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub shortfall: u64,
+    pub insured: u64,
+    pub market_bad_debt: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FreezeEvent {
+    pub target: Pubkey,
+    pub authority: Pubkey,
+    pub reason_code: u16,
+    pub frozen: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GuaranteedStopEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub stop_price: u64,
+    pub mark_price: u64,
+    pub premium: u64,
+    pub insured_gap: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlacklistEvent {
+    pub address: Pubkey,
+    pub authority: Pubkey,
+    pub reason_code: u16,
+    pub blocked: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CloseOrderEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub limit_price: u64,
+    pub stop_price: u64,
+    pub mark_price: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledOrderEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub kind: u8,
+    pub execute_after_slot: u64,
+    pub execute_after_timestamp: i64,
+    pub mark_price: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DcaEvent {
+    pub owner: Pubkey,
+    pub schedule_id: u64,
+    pub market_mint: Pubkey,
+    pub position_nonce: u64,
+    pub price: u64,
+    pub amount: u64,
+    pub remaining_budget: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TwapEvent {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub market_mint: Pubkey,
+    pub price: u64,
+    pub amount: u64,
+    pub slices_filled: u16,
+    pub remaining_budget: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CopyTradeEvent {
+    pub lead: Pubkey,
+    pub follower: Pubkey,
+    pub lead_position_nonce: u64,
+    pub follower_position_nonce: u64,
+    pub price: u64,
+    pub collateral: u64,
+    pub pnl: i64,
+    pub profit_share: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManagedVaultEvent {
+    pub vault: Pubkey,
+    pub manager: Pubkey,
+    pub account: Pubkey,
+    pub position_nonce: u64,
+    pub amount: u64,
+    pub shares: u64,
+    pub pnl: i64,
+    pub net_asset_value: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NettingEvent {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub long_position_nonce: u64,
+    pub short_position_nonce: u64,
+    pub mark_price: u64,
+    pub netted_notional: u64,
+    pub pnl: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HedgeModeEvent {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub enabled: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LpFeeClaimEvent {
+    pub market_mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub remaining: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FeeSplitEvent {
+    pub market_mint: Pubkey,
+    pub reason: TransferReason,
+    pub fee: u64,
+    pub protocol_fee: u64,
+    pub lp_fee: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IntegratorEvent {
+    pub integrator: Pubkey,
+    pub fee_share_bps: u16,
+    pub active: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PlatformFeeEvent {
+    pub platform: Pubkey,
+    pub market_mint: Pubkey,
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub fee: u64,
+    pub platform_fee: u64,
+}
+
+/// Which oracle source (`PRICE_SOURCE_*`) priced a settlement.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SettlementPriceEvent {
+    pub market_mint: Pubkey,
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub price: u64,
+    pub source: u8,
+    pub publish_slot: u64,
+}
+
+/// `fee` is in the input asset and stays in the input market's pool for its LPs.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SwapEvent {
+    pub user: Pubkey,
+    pub input_market_mint: Pubkey,
+    pub output_market_mint: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LamportTransferEvent {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub reason: TransferReason,
+}
+
+/// Emitted through `sol_log_data` as a Borsh-encoded enum.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum DexEvent {
+    LamportTransfer(LamportTransferEvent),
+    FeeCharged(FeeChargedEvent),
+    OrderPlaced(OrderEvent),
+    OrderCancelled(OrderEvent),
+    OrderFilled(FillEvent),
+    Liquidation(LiquidationEvent),
+    MarketInitialized(MarketInitializedEvent),
+    MarketListed(MarketListingEvent),
+    MarketActivated(MarketListingEvent),
+    MarketVetoed(MarketListingEvent),
+    MarketDelisted(MarketDelistedEvent),
+    MarketClosed(MarketClosedEvent),
+    MarketMigrated(MarketMigratedEvent),
+    LiquidityBootstrapped(LiquidityEvent),
+    FeeSplit(FeeSplitEvent),
+    LiquidityDeposited(LiquidityEvent),
+    LiquidityWithdrawn(LiquidityEvent),
+    LpFeesClaimed(LpFeeClaimEvent),
+    ResidualProfit(ResidualProfitEvent),
+    ProfitRealized(ProfitRealizedEvent),
+    StoppedOut(StopOutEvent),
+    BadDebt(BadDebtEvent),
+    GuaranteedStopSet(GuaranteedStopEvent),
+    GuaranteedStopTriggered(GuaranteedStopEvent),
+    CloseOrderPlaced(CloseOrderEvent),
+    CloseOrderCancelled(CloseOrderEvent),
+    CloseOrderExecuted(CloseOrderEvent),
+    ScheduledOrderPlaced(ScheduledOrderEvent),
+    ScheduledOrderCancelled(ScheduledOrderEvent),
+    ScheduledOrderExecuted(ScheduledOrderEvent),
+    DcaScheduleCreated(DcaEvent),
+    DcaScheduleCancelled(DcaEvent),
+    DcaExecuted(DcaEvent),
+    CopyOpened(CopyTradeEvent),
+    CopyClosed(CopyTradeEvent),
+    ManagedVaultCreated(ManagedVaultEvent),
+    ManagedVaultDeposited(ManagedVaultEvent),
+    ManagedVaultWithdrawn(ManagedVaultEvent),
+    ManagedVaultPositionOpened(ManagedVaultEvent),
+    ManagedVaultPositionClosed(ManagedVaultEvent),
+    PerformanceFeePaid(ManagedVaultEvent),
+    ManagedVaultDepositRequested(ManagedVaultEvent),
+    ManagedVaultWithdrawalRequested(ManagedVaultEvent),
+    ManagedVaultEpochProcessed(ManagedVaultEvent),
+    PositionsNetted(NettingEvent),
+    HedgeModeSet(HedgeModeEvent),
+    PlatformFeePaid(PlatformFeeEvent),
+    IntegratorRegistered(IntegratorEvent),
+    AttestationIssued(AttestationEvent),
+    AttestationRevoked(AttestationEvent),
+    AccountFrozen(FreezeEvent),
+    AccountUnfrozen(FreezeEvent),
+    AddressBlacklisted(BlacklistEvent),
+    AddressUnblacklisted(BlacklistEvent),
+    MarginCall(MarginCallEvent),
+    LiquidationQueued(LiquidationQueueEvent),
+    LiquidationDequeued(LiquidationQueueEvent),
+    SettlementPriced(SettlementPriceEvent),
+    Swapped(SwapEvent),
+    TwapCreated(TwapEvent),
+    TwapCancelled(TwapEvent),
+    TwapSliceFilled(TwapEvent),
+    EpochSummary(EpochSummaryEvent),
+    InvariantViolated(InvariantViolationEvent),
+    AccountReclaimed(AccountReclaimedEvent),
+    CompressedPositionOpened(CompressedPositionEvent),
+    CompressedCollateralAdded(CompressedPositionEvent),
+    CompressedPositionClosed(CompressedPositionEvent),
+    PositionMarketMigrated(PositionMarketMigratedEvent),
+    PositionMarkedCorrupted(CorruptionEvent),
+    PositionForceClosed(CorruptionEvent),
+    FeesWithdrawn(FeesWithdrawnEvent),
+    ShutdownScheduled(ShutdownEvent),
+}
+
+pub(crate) fn emit_event(event: &DexEvent) -> ProgramResult {
+    let data = event.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    sol_log_data(&[&data]);
+    Ok(())
+}
+
+#[cfg(feature = "events")]
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+#[cfg(feature = "events")]
+const PROGRAM_LOG_PREFIX: &str = "Program ";
+
+#[cfg(feature = "events")]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EventDecodeError {
+    #[error("Event payload is not valid base64")]
+    InvalidBase64,
+    #[error("Event payload is not a DexEvent")]
+    InvalidEvent,
+}
+
+/// Decodes one raw `sol_log_data` payload.
+#[cfg(feature = "events")]
+pub fn decode_event(data: &[u8]) -> Result<DexEvent, EventDecodeError> {
+    DexEvent::try_from_slice(data).map_err(|_| EventDecodeError::InvalidEvent)
+}
+
+/// Decodes a single `Program data: <base64>` log line; any other line yields `None`.
+#[cfg(feature = "events")]
+pub fn decode_log_line(line: &str) -> Option<Result<DexEvent, EventDecodeError>> {
+    let payload = line.strip_prefix(PROGRAM_DATA_PREFIX)?;
+    
+    Some(
+        STANDARD
+            .decode(payload.trim())
+            .map_err(|_| EventDecodeError::InvalidBase64)
+            .and_then(|data| decode_event(&data)),
+    )
+}
+
+/// Every event emitted by this program in a transaction's logs, in emission order.
+#[cfg(feature = "events")]
+pub fn decode_logs<S: AsRef<str>>(logs: &[S]) -> Result<Vec<DexEvent>, EventDecodeError> {
+    decode_program_logs(logs, &crate::id())
+}
+
+/// Same as `decode_logs` for a deployment of the program at `program_id`.
+#[cfg(feature = "events")]
+pub fn decode_program_logs<S: AsRef<str>>(
+    logs: &[S],
+    program_id: &Pubkey,
+) -> Result<Vec<DexEvent>, EventDecodeError> {
+    let program_id = program_id.to_string();
+    let mut invocations: Vec<String> = Vec::new();
+    let mut events = Vec::new();
+    
+    for line in logs.iter().map(AsRef::as_ref) {
+        if let Some(result) = decode_log_line(line) {
+            if invocations.last() == Some(&program_id) {
+                events.push(result?);
+            }
+            continue;
+        }
+        
+        let Some(rest) = line.strip_prefix(PROGRAM_LOG_PREFIX) else {
+            continue;
+        };
+        let mut words = rest.split_whitespace();
+        let Some(invoked) = words.next().filter(|word| word.parse::<Pubkey>().is_ok()) else {
+            continue;
+        };
+        
+        match words.next() {
+            Some("invoke") => invocations.push(invoked.to_string()),
+            Some("success") | Some("failed:") => {
+                invocations.pop();
+            },
+            _ => {},
+        }
+    }
+    
+    Ok(events)
+}
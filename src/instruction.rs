@@ -0,0 +1,1258 @@
+//! Instruction tags and the Borsh payloads that follow them.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::*;
+
+pub const INSTRUCTION_INITIALIZE: u8 = 0;
+pub const INSTRUCTION_DEX_MODIFY: u8 = 1;
+pub const INSTRUCTION_USER_MODIFY: u8 = 2;
+pub const INSTRUCTION_PROCESS_PNL: u8 = 3;
+pub const INSTRUCTION_FORCE_CLOSE: u8 = 4;
+pub const INSTRUCTION_MARKET_TRANSFER: u8 = 5;
+pub const INSTRUCTION_INITIALIZE_CONFIG: u8 = 6;
+pub const INSTRUCTION_UPDATE_CONFIG: u8 = 7;
+pub const INSTRUCTION_REGISTER_SYMBOL: u8 = 8;
+pub const INSTRUCTION_MIGRATE_ACCOUNT: u8 = 9;
+pub const INSTRUCTION_RESIZE_POSITION: u8 = 10;
+pub const INSTRUCTION_FORCE_CLOSE_UNRECOVERABLE: u8 = 11;
+pub const INSTRUCTION_LIQUIDATE: u8 = 12;
+pub const INSTRUCTION_DEPOSIT_MARGIN: u8 = 13;
+pub const INSTRUCTION_WITHDRAW_MARGIN: u8 = 14;
+pub const INSTRUCTION_UPDATE_PRICE_FEED: u8 = 15;
+pub const INSTRUCTION_CANCEL_POSITION: u8 = 16;
+pub const INSTRUCTION_EXPIRE_POSITION: u8 = 17;
+pub const INSTRUCTION_VIEW_POSITION: u8 = 18;
+pub const INSTRUCTION_QUOTE: u8 = 19;
+pub const INSTRUCTION_SET_FEATURE_FLAGS: u8 = 20;
+pub const INSTRUCTION_SET_MARKET_FEES: u8 = 21;
+pub const INSTRUCTION_PLACE_ORDER: u8 = 22;
+pub const INSTRUCTION_CANCEL_ORDER: u8 = 23;
+pub const INSTRUCTION_MATCH_ORDERS: u8 = 24;
+pub const INSTRUCTION_TAKEOVER_POSITION: u8 = 25;
+pub const INSTRUCTION_ACCRUE_FUNDING: u8 = 26;
+pub const INSTRUCTION_SET_BORROW_CURVE: u8 = 27;
+pub const INSTRUCTION_SET_MARKET_SPREAD: u8 = 28;
+pub const INSTRUCTION_SET_LEVERAGE_TIERS: u8 = 29;
+pub const INSTRUCTION_INITIALIZE_INSURANCE_FUND: u8 = 30;
+pub const INSTRUCTION_SET_MIN_HOLDING: u8 = 31;
+pub const INSTRUCTION_INIT_MARKET: u8 = 32;
+pub const INSTRUCTION_LIST_MARKET: u8 = 33;
+pub const INSTRUCTION_ACTIVATE_MARKET: u8 = 34;
+pub const INSTRUCTION_VETO_MARKET: u8 = 35;
+pub const INSTRUCTION_DELIST_MARKET: u8 = 36;
+pub const INSTRUCTION_SETTLE_DELISTED_POSITION: u8 = 37;
+pub const INSTRUCTION_CLOSE_MARKET: u8 = 38;
+pub const INSTRUCTION_MIGRATE_MARKET: u8 = 39;
+pub const INSTRUCTION_DEPOSIT_PROGRAM_VAULT: u8 = 40;
+pub const INSTRUCTION_WITHDRAW_PROGRAM_VAULT: u8 = 41;
+pub const INSTRUCTION_REBALANCE_MARKETS: u8 = 42;
+pub const INSTRUCTION_SET_REBALANCE_TARGETS: u8 = 43;
+pub const INSTRUCTION_CRANK_REBALANCE: u8 = 44;
+pub const INSTRUCTION_INITIALIZE_MARKET_INSURANCE_FUND: u8 = 45;
+pub const INSTRUCTION_SET_MARKET_LIQUIDITY_CAP: u8 = 46;
+pub const INSTRUCTION_BOOTSTRAP_LIQUIDITY: u8 = 47;
+pub const INSTRUCTION_DEPOSIT_LIQUIDITY: u8 = 48;
+pub const INSTRUCTION_WITHDRAW_LIQUIDITY: u8 = 49;
+pub const INSTRUCTION_CLAIM_LP_FEES: u8 = 50;
+pub const INSTRUCTION_VIEW_LP_SHARE_PRICE: u8 = 51;
+pub const INSTRUCTION_CLAIM_RESIDUAL_PROFIT: u8 = 52;
+pub const INSTRUCTION_REALIZE_PROFIT: u8 = 53;
+pub const INSTRUCTION_STOP_OUT: u8 = 54;
+pub const INSTRUCTION_SET_GUARANTEED_STOP: u8 = 55;
+pub const INSTRUCTION_TRIGGER_GUARANTEED_STOP: u8 = 56;
+pub const INSTRUCTION_PLACE_CLOSE_ORDER: u8 = 57;
+pub const INSTRUCTION_CANCEL_CLOSE_ORDER: u8 = 58;
+pub const INSTRUCTION_EXECUTE_CLOSE_ORDER: u8 = 59;
+pub const INSTRUCTION_PLACE_SCHEDULED_ORDER: u8 = 60;
+pub const INSTRUCTION_CANCEL_SCHEDULED_ORDER: u8 = 61;
+pub const INSTRUCTION_EXECUTE_SCHEDULED_ORDER: u8 = 62;
+pub const INSTRUCTION_CREATE_DCA_SCHEDULE: u8 = 63;
+pub const INSTRUCTION_CANCEL_DCA_SCHEDULE: u8 = 64;
+pub const INSTRUCTION_CRANK_DCA: u8 = 65;
+pub const INSTRUCTION_REGISTER_LEAD_TRADER: u8 = 66;
+pub const INSTRUCTION_FOLLOW_LEAD_TRADER: u8 = 67;
+pub const INSTRUCTION_UNFOLLOW_LEAD_TRADER: u8 = 68;
+pub const INSTRUCTION_COPY_OPEN: u8 = 69;
+pub const INSTRUCTION_COPY_CLOSE: u8 = 70;
+pub const INSTRUCTION_CREATE_MANAGED_VAULT: u8 = 71;
+pub const INSTRUCTION_DEPOSIT_MANAGED_VAULT: u8 = 72;
+pub const INSTRUCTION_WITHDRAW_MANAGED_VAULT: u8 = 73;
+pub const INSTRUCTION_MANAGED_VAULT_OPEN: u8 = 74;
+pub const INSTRUCTION_MANAGED_VAULT_CLOSE: u8 = 75;
+pub const INSTRUCTION_CRANK_VAULT_EPOCH: u8 = 76;
+pub const INSTRUCTION_CLAIM_VAULT_REQUEST: u8 = 77;
+pub const INSTRUCTION_VIEW_PORTFOLIO_HEALTH: u8 = 78;
+pub const INSTRUCTION_NET_POSITIONS: u8 = 79;
+pub const INSTRUCTION_SET_HEDGE_MODE: u8 = 80;
+pub const INSTRUCTION_REGISTER_INTEGRATOR: u8 = 81;
+pub const INSTRUCTION_ISSUE_ATTESTATION: u8 = 82;
+pub const INSTRUCTION_REVOKE_ATTESTATION: u8 = 83;
+pub const INSTRUCTION_FREEZE_ACCOUNT: u8 = 84;
+pub const INSTRUCTION_UNFREEZE_ACCOUNT: u8 = 85;
+pub const INSTRUCTION_BLACKLIST_ADD: u8 = 86;
+pub const INSTRUCTION_BLACKLIST_REMOVE: u8 = 87;
+pub const INSTRUCTION_SET_MARKET_OWNER_NOTIONAL_CAP: u8 = 88;
+pub const INSTRUCTION_MARGIN_CALL: u8 = 89;
+pub const INSTRUCTION_VIEW_HEALTH_FACTOR: u8 = 90;
+pub const INSTRUCTION_QUEUE_LIQUIDATION: u8 = 91;
+pub const INSTRUCTION_SET_FALLBACK_ORACLE: u8 = 92;
+pub const INSTRUCTION_SET_PRICE_SOURCE: u8 = 93;
+pub const INSTRUCTION_SYNC_PRICE_FEED: u8 = 94;
+pub const INSTRUCTION_SET_FUNDING_PARAMS: u8 = 95;
+pub const INSTRUCTION_SET_SETTLEMENT_ASSET: u8 = 96;
+pub const INSTRUCTION_SET_CONTRACT_TYPE: u8 = 97;
+pub const INSTRUCTION_SWAP: u8 = 98;
+pub const INSTRUCTION_SET_TWAP_THRESHOLD: u8 = 99;
+pub const INSTRUCTION_CREATE_TWAP_OPEN: u8 = 100;
+pub const INSTRUCTION_CANCEL_TWAP_OPEN: u8 = 101;
+pub const INSTRUCTION_CRANK_TWAP: u8 = 102;
+pub const INSTRUCTION_ROLLOVER_EPOCH: u8 = 103;
+pub const INSTRUCTION_VERIFY_INVARIANTS: u8 = 104;
+pub const INSTRUCTION_RECLAIM_MARKET: u8 = 105;
+pub const INSTRUCTION_COLLECT_GARBAGE: u8 = 106;
+pub const INSTRUCTION_INITIALIZE_POSITION_TREE: u8 = 107;
+pub const INSTRUCTION_OPEN_COMPRESSED_POSITION: u8 = 108;
+pub const INSTRUCTION_ADD_COMPRESSED_COLLATERAL: u8 = 109;
+pub const INSTRUCTION_CLOSE_COMPRESSED_POSITION: u8 = 110;
+pub const INSTRUCTION_MIGRATE_POSITION_MARKET: u8 = 111;
+pub const INSTRUCTION_MARK_CORRUPTED: u8 = 112;
+pub const INSTRUCTION_INITIALIZE_AUTHORITY: u8 = 113;
+pub const INSTRUCTION_UPDATE_AUTHORITY: u8 = 114;
+pub const INSTRUCTION_SET_OPERATOR: u8 = 115;
+pub const INSTRUCTION_OPERATOR_HEARTBEAT: u8 = 116;
+pub const INSTRUCTION_INIT_POSITION_HISTORY: u8 = 117;
+pub const INSTRUCTION_ADD_POSITION_MARGIN: u8 = 118;
+pub const INSTRUCTION_REMOVE_POSITION_MARGIN: u8 = 119;
+pub const INSTRUCTION_SET_FEE_ROUTES: u8 = 120;
+pub const INSTRUCTION_INIT_FEE_VAULT: u8 = 121;
+pub const INSTRUCTION_WITHDRAW_FEES: u8 = 122;
+pub const INSTRUCTION_VIEW_PROTOCOL_REVENUE: u8 = 123;
+pub const INSTRUCTION_SCHEDULE_SHUTDOWN: u8 = 124;
+
+/// Instructions still accepted once the program is shut down: closes and settlements, the
+/// oracle, funding and operator upkeep they rely on, withdrawals, rent reclamation and views.
+pub const SHUTDOWN_ALLOWED_INSTRUCTIONS: [u8; 52] = [
+    INSTRUCTION_DEX_MODIFY,
+    INSTRUCTION_USER_MODIFY,
+    INSTRUCTION_PROCESS_PNL,
+    INSTRUCTION_FORCE_CLOSE,
+    INSTRUCTION_MIGRATE_ACCOUNT,
+    INSTRUCTION_RESIZE_POSITION,
+    INSTRUCTION_FORCE_CLOSE_UNRECOVERABLE,
+    INSTRUCTION_LIQUIDATE,
+    INSTRUCTION_WITHDRAW_MARGIN,
+    INSTRUCTION_UPDATE_PRICE_FEED,
+    INSTRUCTION_CANCEL_POSITION,
+    INSTRUCTION_EXPIRE_POSITION,
+    INSTRUCTION_VIEW_POSITION,
+    INSTRUCTION_QUOTE,
+    INSTRUCTION_CANCEL_ORDER,
+    INSTRUCTION_ACCRUE_FUNDING,
+    INSTRUCTION_DELIST_MARKET,
+    INSTRUCTION_SETTLE_DELISTED_POSITION,
+    INSTRUCTION_CLOSE_MARKET,
+    INSTRUCTION_WITHDRAW_PROGRAM_VAULT,
+    INSTRUCTION_WITHDRAW_LIQUIDITY,
+    INSTRUCTION_CLAIM_LP_FEES,
+    INSTRUCTION_VIEW_LP_SHARE_PRICE,
+    INSTRUCTION_CLAIM_RESIDUAL_PROFIT,
+    INSTRUCTION_STOP_OUT,
+    INSTRUCTION_TRIGGER_GUARANTEED_STOP,
+    INSTRUCTION_CANCEL_CLOSE_ORDER,
+    INSTRUCTION_EXECUTE_CLOSE_ORDER,
+    INSTRUCTION_CANCEL_SCHEDULED_ORDER,
+    INSTRUCTION_CANCEL_DCA_SCHEDULE,
+    INSTRUCTION_UNFOLLOW_LEAD_TRADER,
+    INSTRUCTION_COPY_CLOSE,
+    INSTRUCTION_WITHDRAW_MANAGED_VAULT,
+    INSTRUCTION_MANAGED_VAULT_CLOSE,
+    INSTRUCTION_CRANK_VAULT_EPOCH,
+    INSTRUCTION_CLAIM_VAULT_REQUEST,
+    INSTRUCTION_VIEW_PORTFOLIO_HEALTH,
+    INSTRUCTION_NET_POSITIONS,
+    INSTRUCTION_MARGIN_CALL,
+    INSTRUCTION_VIEW_HEALTH_FACTOR,
+    INSTRUCTION_QUEUE_LIQUIDATION,
+    INSTRUCTION_SYNC_PRICE_FEED,
+    INSTRUCTION_CANCEL_TWAP_OPEN,
+    INSTRUCTION_VERIFY_INVARIANTS,
+    INSTRUCTION_RECLAIM_MARKET,
+    INSTRUCTION_COLLECT_GARBAGE,
+    INSTRUCTION_CLOSE_COMPRESSED_POSITION,
+    INSTRUCTION_MARK_CORRUPTED,
+    INSTRUCTION_OPERATOR_HEARTBEAT,
+    INSTRUCTION_REMOVE_POSITION_MARGIN,
+    INSTRUCTION_WITHDRAW_FEES,
+    INSTRUCTION_VIEW_PROTOCOL_REVENUE,
+];
+
+pub fn allowed_during_shutdown(instruction: u8) -> bool {
+    SHUTDOWN_ALLOWED_INSTRUCTIONS.contains(&instruction)
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InitializePositionData {
+    pub market_mint: Pubkey,
+    pub market_symbol: [u8; MAX_SYMBOL_LENGTH],
+    pub paid_amount: u64,
+    pub position_size: u64,
+    pub leverage: u8,
+    pub position_nonce: u64,
+    pub direction: i8,
+    pub margin_mode: u8,
+    pub client_tag: [u8; CLIENT_TAG_LENGTH],
+    pub platform_fee_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DexModifyData {
+    pub new_entry_price: u64,
+    pub new_liquidation_price: u64,
+    pub position_nonce: u64,
+    pub new_close_state: u8,
+    pub new_pnl: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserModifyData {
+    pub close_position: bool,
+    pub position_nonce: u64,
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+impl DexModifyData {
+    pub const LEN: usize = 8 + 8 + 8 + 1 + 8;
+    
+    /// Fixed-offset decode of the Borsh layout, skipping the generic deserializer on the
+    /// price-update path. Rejects any length other than `LEN`, as `try_from_slice` does.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        
+        Ok(Self {
+            new_entry_price: read_u64(data, 0),
+            new_liquidation_price: read_u64(data, 8),
+            position_nonce: read_u64(data, 16),
+            new_close_state: data[24],
+            new_pnl: read_u64(data, 25) as i64,
+        })
+    }
+}
+
+impl UserModifyData {
+    pub const LEN: usize = 1 + 8;
+    
+    /// Fixed-offset decode of the Borsh layout; like Borsh, a flag byte other than 0 or 1 is
+    /// rejected.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        
+        let close_position = match data[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+        
+        Ok(Self {
+            close_position,
+            position_nonce: read_u64(data, 1),
+        })
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessPnlData {
+    pub position_nonce: u64,
+    pub final_pnl: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarketTransferData {
+    pub amount: u64,
+    pub from_market_mint: Pubkey,
+    pub to_market_mint: Pubkey,
+    pub from_market_pda: Pubkey,
+    pub to_market_pda: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RebalanceLeg {
+    pub market_mint: Pubkey,
+    pub weight_bps: u16,
+}
+
+/// One leg per market account passed after the DEX signer, in the same order; weights must
+/// sum to 100%.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RebalanceData {
+    pub legs: Vec<RebalanceLeg>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RebalanceTargetsData {
+    pub targets: [RebalanceTarget; MAX_REBALANCE_MARKETS],
+    pub max_per_call: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FeeRoutesData {
+    pub routes: [FeeRoute; MAX_FEE_ROUTES],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WithdrawFeesData {
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScheduleShutdownData {
+    pub cancel: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConfigData {
+    pub dust_threshold: u64,
+    pub maintenance_margin_bps: u16,
+    pub initial_margin_bps: u16,
+    pub oracle_authority: Pubkey,
+    pub price_band_bps: u16,
+    pub max_price_staleness_slots: u64,
+    pub pnl_tolerance_bps: u16,
+    pub confirmation_timeout_slots: u64,
+    pub liquidation_price_tolerance_bps: u16,
+    pub min_market_base_fee_bps: u16,
+    pub max_market_base_fee_bps: u16,
+    pub max_market_leverage_fee_bps: u16,
+    pub auction_start_discount_bps: u16,
+    pub auction_max_discount_bps: u16,
+    pub auction_duration_slots: u64,
+    pub close_fee_bps: u16,
+    pub liquidation_fee_bps: u16,
+    pub liquidation_fee_keeper_share_bps: u16,
+    pub skew_surcharge_bps: u16,
+    pub skew_rebate_bps: u16,
+    pub min_close_slots: u64,
+    pub listing_bond_lamports: u64,
+    pub listing_timelock_slots: u64,
+    pub lp_fee_share_bps: u16,
+    pub min_realized_profit: u64,
+    pub stop_out_margin_bps: u16,
+    pub guaranteed_stop_premium_bps: u16,
+    pub portfolio_health_floor_bps: u16,
+    pub max_platform_fee_bps: u16,
+    pub attestor: Pubkey,
+    pub freeze_close_timelock_slots: u64,
+    pub min_open_slot_gap: u64,
+    pub max_opens_per_slot: u16,
+    pub max_owner_notional: u64,
+    pub margin_call_threshold_bps: u16,
+    pub swap_fee_bps: u16,
+    pub max_trade_liquidity_bps: u16,
+    pub close_settlement_deadline_slots: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FeatureFlagsData {
+    pub enable: u64,
+    pub disable: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarketFeesData {
+    pub market_mint: Pubkey,
+    pub base_fee_bps: u16,
+    pub leverage_fee_bps: u16,
+    pub maker_fee_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaceOrderData {
+    pub market_mint: Pubkey,
+    pub side: i8,
+    pub price: u64,
+    pub paid_amount: u64,
+    pub leverage: u8,
+    pub position_nonce: u64,
+    pub display_amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CancelOrderData {
+    pub market_mint: Pubkey,
+    pub side: i8,
+    pub order_id: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchOrdersData {
+    pub market_mint: Pubkey,
+    pub max_fills: u8,
+}
+
+/// Index increments per unit of notional; positive means that side pays funding.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FundingData {
+    pub market_mint: Pubkey,
+    pub long_index_delta: i64,
+    pub short_index_delta: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BorrowCurveData {
+    pub market_mint: Pubkey,
+    pub base_rate_bps: u16,
+    pub slope1_bps: u16,
+    pub slope2_bps: u16,
+    pub kink_utilization_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarketSpreadData {
+    pub market_mint: Pubkey,
+    pub base_bps: u16,
+    pub utilization_factor_bps: u16,
+    pub skew_factor_bps: u16,
+    pub max_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LeverageTiersData {
+    pub market_mint: Pubkey,
+    pub tiers: [LeverageTier; MAX_LEVERAGE_TIERS],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinHoldingData {
+    pub market_mint: Pubkey,
+    pub min_holding_slots: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarketLiquidityCapData {
+    pub market_mint: Pubkey,
+    pub max_liquidity: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarketOwnerNotionalCapData {
+    pub market_mint: Pubkey,
+    pub max_owner_notional: u64,
+}
+
+/// `Pubkey::default()` as `settlement_mint` settles the market in native SOL.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SettlementAssetData {
+    pub market_mint: Pubkey,
+    pub settlement_mint: Pubkey,
+}
+
+/// Swaps `amount_in` of the input market's settlement asset for the output market's, failing
+/// below `min_amount_out`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SwapData {
+    pub input_market_mint: Pubkey,
+    pub output_market_mint: Pubkey,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+}
+
+/// `contract_type` is `CONTRACT_TYPE_LINEAR` or `CONTRACT_TYPE_INVERSE`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContractTypeData {
+    pub market_mint: Pubkey,
+    pub contract_type: u8,
+}
+
+/// Zero for either field leaves it unenforced.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FundingParamsData {
+    pub market_mint: Pubkey,
+    pub funding_interval_slots: u64,
+    pub max_funding_rate_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BootstrapLiquidityData {
+    pub market_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LiquidityDepositData {
+    pub market_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LiquidityWithdrawData {
+    pub market_mint: Pubkey,
+    pub shares: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClaimLpFeesData {
+    pub market_mint: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ViewLpSharePriceData {
+    pub market_mint: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClaimResidualProfitData {
+    pub position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RealizeProfitData {
+    pub position_nonce: u64,
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionMarginData {
+    pub position_nonce: u64,
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StopOutData {
+    pub position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GuaranteedStopData {
+    pub position_nonce: u64,
+    pub stop_price: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TriggerGuaranteedStopData {
+    pub position_nonce: u64,
+}
+
+/// Placing again over an existing close order replaces both prices; a zero price leaves that leg
+/// unset.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaceCloseOrderData {
+    pub position_nonce: u64,
+    pub limit_price: u64,
+    pub stop_price: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CloseOrderData {
+    pub position_nonce: u64,
+}
+
+/// `direction`, `leverage` and `paid_amount` only apply to `SCHEDULED_ORDER_OPEN`, which reserves
+/// `position_nonce` from the owner's counter; a scheduled close targets an existing position.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaceScheduledOrderData {
+    pub market_mint: Pubkey,
+    pub position_nonce: u64,
+    pub kind: u8,
+    pub direction: i8,
+    pub leverage: u8,
+    pub paid_amount: u64,
+    pub execute_after_slot: u64,
+    pub execute_after_timestamp: i64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScheduledOrderData {
+    pub position_nonce: u64,
+}
+
+/// `paid_amount` is escrowed up front and split evenly over `slices` fills into the position
+/// under `position_nonce`, claimed from the owner's counter like `PLACE_ORDER`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CreateTwapOpenData {
+    pub market_mint: Pubkey,
+    pub position_nonce: u64,
+    pub direction: i8,
+    pub leverage: u8,
+    pub paid_amount: u64,
+    pub slices: u16,
+    pub interval_slots: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TwapScheduleData {
+    pub position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EpochRolloverData {
+    pub market_mint: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VerifyInvariantsData {
+    pub market_mint: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionTreeData {
+    pub market_mint: Pubkey,
+}
+
+/// `proof` is the path of the empty leaf at the tree's `next_index`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpenCompressedPositionData {
+    pub market_mint: Pubkey,
+    pub direction: i8,
+    pub leverage: u8,
+    pub amount: u64,
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressedCollateralData {
+    pub position: CompressedPosition,
+    pub amount: u64,
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CloseCompressedPositionData {
+    pub position: CompressedPosition,
+    pub proof: Vec<[u8; 32]>,
+}
+
+/// Zero lets opens of any size go through `INITIALIZE`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TwapThresholdData {
+    pub market_mint: Pubkey,
+    pub twap_threshold_notional: u64,
+}
+
+/// `budget` is escrowed up front and also pays each new position's rent.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CreateDcaScheduleData {
+    pub schedule_id: u64,
+    pub market_mint: Pubkey,
+    pub direction: i8,
+    pub leverage: u8,
+    pub amount_per_interval: u64,
+    pub interval_slots: u64,
+    pub budget: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DcaScheduleData {
+    pub schedule_id: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterLeadTraderData {
+    pub profit_share_bps: u16,
+}
+
+/// Following again tops up the copy margin by `deposit` and replaces the ratio and cap;
+/// `max_collateral` of 0 leaves copies uncapped.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FollowLeadTraderData {
+    pub lead: Pubkey,
+    pub deposit: u64,
+    pub copy_ratio_bps: u16,
+    pub max_collateral: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnfollowLeadTraderData {
+    pub lead: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CopyTradeData {
+    pub lead: Pubkey,
+    pub lead_position_nonce: u64,
+    pub follower: Pubkey,
+}
+
+/// Risk limits the manager trades under: `max_position_collateral` of 0 leaves single positions
+/// uncapped, and `max_deployed_bps` bounds the share of net asset value held in positions.
+/// `performance_fee_bps` of gains above the high-water mark goes to the manager, and queued
+/// deposits and withdrawals settle every `epoch_slots`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CreateManagedVaultData {
+    pub vault_id: u64,
+    pub max_leverage: u8,
+    pub max_position_collateral: u64,
+    pub max_deployed_bps: u16,
+    pub performance_fee_bps: u16,
+    pub epoch_slots: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManagedVaultDepositData {
+    pub manager: Pubkey,
+    pub vault_id: u64,
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManagedVaultWithdrawData {
+    pub manager: Pubkey,
+    pub vault_id: u64,
+    pub shares: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManagedVaultEpochData {
+    pub manager: Pubkey,
+    pub vault_id: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ViewPortfolioHealthData {
+    pub owner: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManagedVaultOpenData {
+    pub vault_id: u64,
+    pub market_mint: Pubkey,
+    pub direction: i8,
+    pub leverage: u8,
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ManagedVaultCloseData {
+    pub vault_id: u64,
+    pub position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InitMarketData {
+    pub market_mint: Pubkey,
+}
+
+/// Symbol and decimals only register the mint if no admin entry exists yet.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ListMarketData {
+    pub market_mint: Pubkey,
+    pub market_symbol: [u8; MAX_SYMBOL_LENGTH],
+    pub decimals: u8,
+    pub oracle_feed: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarketListingData {
+    pub market_mint: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DelistMarketData {
+    pub market_mint: Pubkey,
+    pub grace_slots: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SettleDelistedData {
+    pub position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CloseMarketData {
+    pub market_mint: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MigrateMarketData {
+    pub market_mint: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuthorityData {
+    pub admin: Pubkey,
+    pub dex_authority: Pubkey,
+    pub fees_wallet: Pubkey,
+}
+
+/// Zero `roles` removes the operator.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetOperatorData {
+    pub operator: Pubkey,
+    pub roles: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MigratePositionMarketData {
+    pub position_nonce: u64,
+    pub new_market_mint: Pubkey,
+    pub new_market_symbol: [u8; MAX_SYMBOL_LENGTH],
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarketInsuranceFundData {
+    pub market_mint: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarginTransferData {
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VaultTransferData {
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PriceFeedData {
+    pub market_mint: Pubkey,
+    pub price: u64,
+    pub confidence: u64,
+}
+
+/// `Pubkey::default()` as `fallback_authority` removes the market's fallback oracle.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetFallbackOracleData {
+    pub market_mint: Pubkey,
+    pub fallback_authority: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetPriceSourceData {
+    pub market_mint: Pubkey,
+    pub source: PriceSource,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SyncPriceFeedData {
+    pub market_mint: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CancelPositionData {
+    pub position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExpirePositionData {
+    pub position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ViewPositionData {
+    pub mark_price: u64,
+}
+
+/// Returned by `VIEW_POSITION` through `set_return_data`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct PositionSummary {
+    pub position_nonce: u64,
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub direction: i8,
+    pub leverage: u8,
+    pub closed: u8,
+    pub confirmed: u8,
+    pub entry_price: u64,
+    pub liquidation_price: u64,
+    pub mark_price: u64,
+    pub collateral: u64,
+    pub notional: u64,
+    pub unrealized_pnl: i64,
+    pub equity: i64,
+    pub maintenance_requirement: u64,
+    pub health_bps: u64,
+    pub profit_fee: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ViewHealthFactorData {
+    pub position_nonce: u64,
+}
+
+/// Returned by `VIEW_HEALTH_FACTOR` through `set_return_data`, computed exactly as `LIQUIDATE`
+/// does at the oracle price. `liquidation_price` counts cross margin as collateral and is 0 when
+/// no price can liquidate the position; `distance_bps` is how far the oracle price must move to
+/// reach it, `u64::MAX` when it never can.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct HealthFactor {
+    pub position_nonce: u64,
+    pub mark_price: u64,
+    pub equity: i64,
+    pub maintenance_requirement: u64,
+    pub health_bps: u64,
+    pub liquidatable: bool,
+    pub liquidation_price: u64,
+    pub distance_bps: u64,
+}
+
+/// Returned by `QUOTE` through `set_return_data`, computed exactly as `INITIALIZE` would.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct PositionQuote {
+    pub leverage: u8,
+    pub open_fee: u64,
+    pub collateral: u64,
+    pub position_size: u64,
+    pub estimated_entry_price: u64,
+    pub estimated_liquidation_price: u64,
+}
+
+/// Returned by `VIEW_LP_SHARE_PRICE` through `set_return_data`; `share_price` is lamports per
+/// share scaled by `LP_FEE_PER_SHARE_PRECISION`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct LpSharePrice {
+    pub market_mint: Pubkey,
+    pub pool_value: u64,
+    pub total_shares: u64,
+    pub protocol_shares: u64,
+    pub share_price: u128,
+    pub unclaimed_fees: u64,
+    pub compounding: bool,
+}
+
+/// Returned by `VIEW_PROTOCOL_REVENUE` through `set_return_data`. Balances are lamports above
+/// rent (the treasury's in full); the cumulative fields sum the markets passed.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct ProtocolRevenue {
+    pub treasury_balance: u64,
+    pub insurance_balance: u64,
+    pub fee_vault_balance: u64,
+    pub program_vault_balance: u64,
+    pub markets: u32,
+    pub market_insurance_balance: u64,
+    pub cumulative_volume: u64,
+    pub cumulative_fees: u64,
+    pub epoch_protocol_fees: u64,
+    pub epoch_lp_fees: u64,
+    pub unclaimed_lp_fees: u64,
+    pub protocol_lp_shares: u64,
+    pub bad_debt: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LiquidateData {
+    pub position_nonce: u64,
+    pub mark_price: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueueLiquidationData {
+    pub position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarginCallData {
+    pub position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TakeoverData {
+    pub position_nonce: u64,
+    pub new_position_nonce: u64,
+    pub collateral_top_up: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterSymbolData {
+    pub market_mint: Pubkey,
+    pub market_symbol: [u8; MAX_SYMBOL_LENGTH],
+    pub decimals: u8,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NetPositionsData {
+    pub long_position_nonce: u64,
+    pub short_position_nonce: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetHedgeModeData {
+    pub market_mint: Pubkey,
+    pub enabled: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterIntegratorData {
+    pub integrator: Pubkey,
+    pub fee_share_bps: u16,
+    pub active: bool,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IssueAttestationData {
+    pub owner: Pubkey,
+    pub expiry_slot: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RevokeAttestationData {
+    pub owner: Pubkey,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FreezeData {
+    pub target: Pubkey,
+    pub reason_code: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlacklistData {
+    pub address: Pubkey,
+    pub reason_code: u16,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn dex_modify_unpack_matches_borsh() {
+        let data = DexModifyData {
+            new_entry_price: 123_456_789,
+            new_liquidation_price: u64::MAX - 7,
+            position_nonce: 42,
+            new_close_state: 2,
+            new_pnl: -987_654_321,
+        };
+        let bytes = data.try_to_vec().unwrap();
+        
+        assert_eq!(bytes.len(), DexModifyData::LEN);
+        
+        let unpacked = DexModifyData::unpack(&bytes).unwrap();
+        let decoded = DexModifyData::try_from_slice(&bytes).unwrap();
+        
+        assert_eq!(unpacked.try_to_vec().unwrap(), decoded.try_to_vec().unwrap());
+        assert_eq!(unpacked.new_pnl, data.new_pnl);
+        assert!(DexModifyData::unpack(&bytes[..DexModifyData::LEN - 1]).is_err());
+        assert!(DexModifyData::unpack(&[bytes.clone(), vec![0]].concat()).is_err());
+    }
+    
+    #[test]
+    fn user_modify_unpack_matches_borsh() {
+        for close_position in [false, true] {
+            let data = UserModifyData { close_position, position_nonce: u64::MAX - 1 };
+            let bytes = data.try_to_vec().unwrap();
+            
+            assert_eq!(bytes.len(), UserModifyData::LEN);
+            
+            let unpacked = UserModifyData::unpack(&bytes).unwrap();
+            
+            assert_eq!(unpacked.close_position, close_position);
+            assert_eq!(unpacked.position_nonce, data.position_nonce);
+        }
+        
+        let mut bytes = UserModifyData { close_position: true, position_nonce: 1 }.try_to_vec().unwrap();
+        bytes[0] = 2;
+        
+        assert!(UserModifyData::try_from_slice(&bytes).is_err());
+        assert!(UserModifyData::unpack(&bytes).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Fee formulas. Intermediates are `u128` so no product of a lamport amount and a rate can
+//! overflow. Fees the trader pays round up and shares paid out of a fee (to LPs, platforms or
+//! keepers) round down, so rounding never works against the program; results saturate at
+//! `u64::MAX`.
+
+use crate::*;
+
+/// `bps` of `amount`, rounded up.
+pub fn fee_bps(amount: u64, bps: u64) -> u64 {
+    (amount as u128)
+        .saturating_mul(bps as u128)
+        .div_ceil(BASIS_POINTS_DIVISOR as u128)
+        .min(u64::MAX as u128) as u64
+}
+
+/// `bps` of `amount`, rounded down.
+pub fn payout_bps(amount: u64, bps: u64) -> u64 {
+    ((amount as u128).saturating_mul(bps as u128) / BASIS_POINTS_DIVISOR as u128).min(u64::MAX as u128) as u64
+}
+
+/// Base plus per-leverage fee, charged on the paid amount at open and on profit at settlement.
+pub fn trading_fee(amount: u64, leverage: u8, fees: &FeeSchedule) -> u64 {
+    let rate_bps = (fees.leverage_fee_bps as u128)
+        .saturating_mul(leverage as u128)
+        .saturating_add(fees.base_fee_bps as u128)
+        .min(u64::MAX as u128) as u64;
+    
+    fee_bps(amount, rate_bps)
+}
+
+/// Flat fee on notional charged at settlement whatever the sign of the PnL.
+pub fn close_fee(notional: u64, close_fee_bps: u16) -> u64 {
+    fee_bps(notional, close_fee_bps as u64)
+}
+
+/// Fee for one fill: takers pay `trading_fee`, makers the flat maker rate.
+pub fn fill_fee(amount: u64, leverage: u8, role: FillRole, fees: &FeeSchedule) -> u64 {
+    match role {
+        FillRole::Taker => trading_fee(amount, leverage, fees),
+        FillRole::Maker => fee_bps(amount, fees.maker_fee_bps),
+    }
+}
+
+/// Fee on the notional a liquidation closes, capped at what the position has `available`.
+pub fn liquidation_fee(closed_notional: u64, liquidation_fee_bps: u16, available: u64) -> u64 {
+    fee_bps(closed_notional, liquidation_fee_bps as u64).min(available)
+}
+
+/// Splits a liquidation fee into `(keeper_fee, insurance_fee)`, the keeper leg rounded down.
+pub fn split_liquidation_fee(fee: u64, keeper_share_bps: u16) -> (u64, u64) {
+    let keeper_fee = payout_bps(fee, keeper_share_bps as u64).min(fee);
+    
+    (keeper_fee, fee - keeper_fee)
+}
+
+/// Premium for a guaranteed stop, charged on notional.
+pub fn guaranteed_stop_premium(notional: u64, premium_bps: u16) -> u64 {
+    fee_bps(notional, premium_bps as u64)
+}
+
+/// Splits a collected fee into `(protocol_fee, lp_fee)`, the LP leg being `lp_fee_share_bps`
+/// of it rounded down.
+pub fn split_lp_fee(fee: u64, lp_fee_share_bps: u16) -> (u64, u64) {
+    let lp_fee = payout_bps(fee, lp_fee_share_bps as u64).min(fee);
+    
+    (fee - lp_fee, lp_fee)
+}
+
+/// The routing platform's `platform_fee_bps` cut of an open fee, rounded down.
+pub fn platform_fee_share(fee: u64, platform_fee_bps: u16) -> u64 {
+    payout_bps(fee, platform_fee_bps as u64).min(fee)
+}
+
+/// A fee route's `share_bps` cut of a fee, rounded down; the remainder stays with the default sink.
+pub fn fee_route_share(fee: u64, share_bps: u16) -> u64 {
+    payout_bps(fee, share_bps as u64).min(fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SCHEDULE: FeeSchedule = FeeSchedule {
+        base_fee_bps: BASE_FEE_BASIS_POINTS,
+        leverage_fee_bps: LEVERAGE_FEE_BASIS_POINTS,
+        maker_fee_bps: MAKER_FEE_BASIS_POINTS,
+    };
+    
+    #[test]
+    fn fees_round_up_and_payouts_round_down() {
+        assert_eq!(fee_bps(1, 1), 1);
+        assert_eq!(payout_bps(1, 9_999), 0);
+        assert_eq!(fee_bps(10_000, 1), 1);
+        assert_eq!(fee_bps(10_001, 1), 2);
+        assert_eq!(payout_bps(10_001, 1), 1);
+        assert_eq!(fee_bps(0, u64::MAX), 0);
+        assert_eq!(fee_bps(u64::MAX, 0), 0);
+    }
+    
+    #[test]
+    fn one_lamport_positions_pay_one_lamport() {
+        assert_eq!(trading_fee(1, 1, &SCHEDULE), 1);
+        assert_eq!(trading_fee(1, MAXIMUM_LEVERAGE, &SCHEDULE), 1);
+        assert_eq!(fill_fee(1, 1, FillRole::Maker, &SCHEDULE), 1);
+        assert_eq!(close_fee(1, 1), 1);
+        assert_eq!(close_fee(1, 0), 0);
+        assert_eq!(liquidation_fee(1, 500, 0), 0);
+        assert_eq!(guaranteed_stop_premium(1, 1), 1);
+        assert_eq!(split_lp_fee(1, 5_000), (1, 0));
+        assert_eq!(split_liquidation_fee(1, 9_999), (0, 1));
+        assert_eq!(platform_fee_share(1, 10_000), 1);
+    }
+    
+    #[test]
+    fn max_amounts_do_not_overflow() {
+        let max = u64::MAX;
+        
+        assert_eq!(trading_fee(max, 1, &SCHEDULE), (max as u128 * 210).div_ceil(10_000) as u64);
+        assert_eq!(trading_fee(max, u8::MAX, &FeeSchedule { base_fee_bps: max, ..SCHEDULE }), max);
+        assert_eq!(close_fee(max, u16::MAX), max);
+        assert_eq!(close_fee(max, 10_000), max);
+        assert_eq!(close_fee(max, 1), (max as u128).div_ceil(10_000) as u64);
+        assert_eq!(liquidation_fee(max, 100, max), (max as u128 * 100).div_ceil(10_000) as u64);
+        assert_eq!(guaranteed_stop_premium(max, 9_999), (max as u128 * 9_999).div_ceil(10_000) as u64);
+        assert_eq!(platform_fee_share(max, u16::MAX), max);
+        assert_eq!(split_lp_fee(max, u16::MAX), (0, max));
+        assert_eq!(split_liquidation_fee(max, u16::MAX), (max, 0));
+    }
+    
+    #[test]
+    fn splits_account_for_the_whole_fee() {
+        for fee in [0, 1, 2, 3, 9_999, 10_000, 10_001, 123_456_789, u64::MAX - 1, u64::MAX] {
+            for bps in [0, 1, 3_333, 5_000, 9_999, 10_000] {
+                let (protocol, lp) = split_lp_fee(fee, bps);
+                let (keeper, insurance) = split_liquidation_fee(fee, bps);
+                
+                assert_eq!(protocol as u128 + lp as u128, fee as u128);
+                assert_eq!(keeper as u128 + insurance as u128, fee as u128);
+                assert!(lp <= payout_bps(fee, bps as u64));
+                assert!(platform_fee_share(fee, bps) <= fee);
+            }
+        }
+    }
+}
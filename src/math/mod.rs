@@ -0,0 +1,430 @@
+//! Pricing, fee and margin formulas shared by the processor and off-chain clients.
+
+use solana_program::{
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+};
+
+use crate::*;
+
+pub mod fees;
+
+pub use self::fees::*;
+
+/// Linear PnL is `size * (exit / entry - 1)`; inverse PnL, `size * entry * (1 / entry - 1 / exit)`,
+/// keeps `size` in collateral units at entry and works out to `size * (exit - entry) / exit`.
+pub fn unrealized_pnl(position: &PositionAccount, mark_price: u64) -> Result<i128, ProgramError> {
+    if position.entry_price == 0 {
+        return Err(DexError::PositionNotConfirmed.into());
+    }
+    
+    let price_delta = mark_price as i128 - position.entry_price as i128;
+    let reference_price = if position.contract_type == CONTRACT_TYPE_INVERSE {
+        mark_price.max(1) as i128
+    } else {
+        position.entry_price as i128
+    };
+    
+    Ok((position.position_size as i128)
+        .saturating_mul(price_delta)
+        / reference_price
+        * position.direction as i128)
+}
+
+/// Lowest and highest PnL the position could settle at anywhere in the `band_bps` price band
+/// around `price`. PnL is monotonic in the exit price, so the band's edges bound it; a loss is
+/// never larger than the position's size.
+pub fn pnl_bounds(position: &PositionAccount, price: u64, band_bps: u16) -> Result<(i128, i128), ProgramError> {
+    let band = (price as u128).saturating_mul(band_bps as u128) / BASIS_POINTS_DIVISOR as u128;
+    let low = (price as u128).saturating_sub(band) as u64;
+    let high = (price as u128).saturating_add(band).min(u64::MAX as u128) as u64;
+    
+    let (at_low, at_high) = (unrealized_pnl(position, low)?, unrealized_pnl(position, high)?);
+    let floor = -(position.position_size as i128);
+    
+    Ok((at_low.min(at_high).max(floor), at_low.max(at_high)))
+}
+
+/// Price at which equity falls to the maintenance requirement on the current notional.
+/// Returns 0 when the position cannot reach that point (e.g. an unleveraged long, or an
+/// unleveraged inverse short).
+pub fn compute_liquidation_price(
+    entry_price: u64,
+    position_size: u64,
+    collateral: u64,
+    direction: i8,
+    contract_type: u8,
+    maintenance_margin_bps: u16,
+) -> u64 {
+    if entry_price == 0 || position_size == 0 {
+        return 0;
+    }
+    
+    let entry = entry_price as u128;
+    let size = position_size as u128;
+    let collateral = collateral as u128;
+    let divisor = BASIS_POINTS_DIVISOR as u128;
+    let margin = maintenance_margin_bps as u128;
+    
+    if contract_type == CONTRACT_TYPE_INVERSE {
+        let price = if direction == POSITION_LONG {
+            entry.saturating_mul(size).saturating_mul(divisor.saturating_add(margin))
+                / size.saturating_add(collateral).saturating_mul(divisor)
+        } else {
+            if size <= collateral {
+                return 0;
+            }
+            entry.saturating_mul(size).saturating_mul(divisor.saturating_sub(margin))
+                / (size - collateral).saturating_mul(divisor)
+        };
+        
+        return price.min(u64::MAX as u128) as u64;
+    }
+    
+    let price = if direction == POSITION_LONG {
+        if size <= collateral {
+            return 0;
+        }
+        entry.saturating_mul(size - collateral).saturating_mul(divisor)
+            / size.saturating_mul(divisor.saturating_sub(margin))
+    } else {
+        entry.saturating_mul(size.saturating_add(collateral)).saturating_mul(divisor)
+            / size.saturating_mul(divisor.saturating_add(margin))
+    };
+    
+    price.min(u64::MAX as u128) as u64
+}
+
+pub enum SkewAdjustment {
+    Surcharge(u64),
+    Rebate(u64),
+}
+
+/// Imbalance-increasing trades pay `skew_surcharge_bps` into the market's rebate pool;
+/// imbalance-reducing trades get up to `skew_rebate_bps` back out of it. Call before the
+/// trade's open interest is applied.
+pub fn skew_adjustment(
+    market_state: &mut MarketState,
+    direction: i8,
+    size: u64,
+    opening: bool,
+    config: &GlobalConfig,
+) -> SkewAdjustment {
+    let bps = |rate: u16| ((size as u128).saturating_mul(rate as u128) / BASIS_POINTS_DIVISOR as u128) as u64;
+    
+    if market_state.reduces_skew(direction, size, opening) {
+        let rebate = bps(config.skew_rebate_bps).min(market_state.skew_rebate_pool);
+        market_state.skew_rebate_pool = market_state.skew_rebate_pool.saturating_sub(rebate);
+        SkewAdjustment::Rebate(rebate)
+    } else {
+        let surcharge = bps(config.skew_surcharge_bps);
+        market_state.skew_rebate_pool = market_state.skew_rebate_pool.saturating_add(surcharge);
+        SkewAdjustment::Surcharge(surcharge)
+    }
+}
+
+/// Clamped leverage, open fee, post-fee collateral and notional size for a paid amount.
+pub fn open_terms(
+    paid_amount: u64,
+    requested_leverage: u8,
+    role: FillRole,
+    fees: &FeeSchedule,
+) -> (u8, u64, u64, u64) {
+    let leverage = requested_leverage.clamp(1, MAXIMUM_LEVERAGE);
+    let fee = fill_fee(paid_amount, leverage, role, fees);
+    let collateral = paid_amount.saturating_sub(fee);
+    let position_size = collateral.saturating_mul(leverage as u64);
+    
+    (leverage, fee, collateral, position_size)
+}
+
+pub struct PositionHealth {
+    pub pnl: i128,
+    pub notional: u128,
+    pub equity: i128,
+    pub maintenance: u128,
+}
+
+impl PositionHealth {
+    /// Equity as a share of the maintenance requirement; below 10_000 the position is liquidatable.
+    pub fn health_bps(&self) -> u64 {
+        if self.equity <= 0 {
+            return 0;
+        }
+        if self.maintenance == 0 {
+            return u64::MAX;
+        }
+        
+        ((self.equity as u128).saturating_mul(BASIS_POINTS_DIVISOR as u128) / self.maintenance)
+            .min(u64::MAX as u128) as u64
+    }
+    
+    /// The same health with accrued but unsettled funding and borrow (`accrued_charges`) taken
+    /// out of equity, as the position would settle.
+    pub fn net_of(self, charges: i128) -> Self {
+        PositionHealth {
+            equity: self.equity.saturating_sub(charges),
+            ..self
+        }
+    }
+}
+
+/// Single source of the equity and maintenance math used by liquidation and the view instructions.
+pub fn position_health(
+    position: &PositionAccount,
+    mark_price: u64,
+    maintenance_margin_bps: u16,
+    shared_margin: u64,
+) -> Result<PositionHealth, ProgramError> {
+    let pnl = unrealized_pnl(position, mark_price)?;
+    let notional = position.notional(mark_price);
+    let equity = position.paid_amount as i128 + shared_margin as i128 + pnl;
+    let maintenance = notional
+        .saturating_mul(maintenance_margin_bps as u128)
+        / BASIS_POINTS_DIVISOR as u128;
+    
+    Ok(PositionHealth {
+        pnl,
+        notional,
+        equity,
+        maintenance,
+    })
+}
+
+/// Share of `mark_price` the price must move to reach `liquidation_price`; `u64::MAX` when the
+/// position has no liquidation price and 0 once it is past it.
+pub fn liquidation_distance_bps(mark_price: u64, liquidation_price: u64, direction: i8) -> u64 {
+    if liquidation_price == 0 {
+        return u64::MAX;
+    }
+    if mark_price == 0 {
+        return 0;
+    }
+    
+    let gap = if direction == POSITION_LONG {
+        mark_price.saturating_sub(liquidation_price)
+    } else {
+        liquidation_price.saturating_sub(mark_price)
+    };
+    
+    ((gap as u128).saturating_mul(BASIS_POINTS_DIVISOR as u128) / mark_price as u128).min(u64::MAX as u128) as u64
+}
+
+/// Liquidator discount after `elapsed` slots of auction, ramping linearly from the start to the
+/// maximum discount over `auction_duration_slots`.
+pub fn auction_discount_bps(config: &GlobalConfig, elapsed: u64) -> u64 {
+    let start = config.auction_start_discount_bps as u64;
+    let max = config.auction_max_discount_bps as u64;
+    
+    if config.auction_duration_slots == 0 || elapsed >= config.auction_duration_slots {
+        return max;
+    }
+    
+    start.saturating_add(
+        max.saturating_sub(start).saturating_mul(elapsed) / config.auction_duration_slots
+    )
+}
+
+/// Borrow interest owed by a position on its notional since its snapshot.
+pub fn accrued_borrow_fee(position: &PositionAccount, market_state: &MarketState) -> i128 {
+    let index_delta = market_state
+        .cumulative_borrow_index
+        .saturating_sub(position.borrow_index_snapshot)
+        .max(0);
+    
+    (position.position_size as i128).saturating_mul(index_delta) / FUNDING_INDEX_PRECISION
+}
+
+/// Price a trade actually gets once the spread is applied: buying (opening a long or closing a
+/// short) pays above the oracle, selling receives below it.
+pub fn apply_spread(price: u64, direction: i8, spread_bps: u64, opening: bool) -> u64 {
+    let buying = (direction == POSITION_LONG) == opening;
+    let adjustment = (price as u128).saturating_mul(spread_bps as u128) / BASIS_POINTS_DIVISOR as u128;
+    
+    if buying {
+        (price as u128).saturating_add(adjustment).min(u64::MAX as u128) as u64
+    } else {
+        (price as u128).saturating_sub(adjustment) as u64
+    }
+}
+
+pub(crate) fn check_leverage_tier(market_state: &MarketState, notional: u64, leverage: u8) -> ProgramResult {
+    match market_state.max_leverage_for(notional) {
+        Some(max_leverage) if leverage <= max_leverage => Ok(()),
+        Some(max_leverage) => {
+            msg!("Leverage {}x above the {}x tier limit for notional {}", leverage, max_leverage, notional);
+            Err(DexError::LeverageTierExceeded.into())
+        },
+        None => {
+            msg!("No leverage tier covers notional {}", notional);
+            Err(DexError::LeverageTierExceeded.into())
+        },
+    }
+}
+
+/// Funding and borrow a position owes since its snapshots; negative when funding owed to it
+/// outweighs its borrow fee.
+pub fn accrued_charges(position: &PositionAccount, market_state: &MarketState) -> i128 {
+    accrued_funding(position, market_state).saturating_add(accrued_borrow_fee(position, market_state))
+}
+
+/// Funding owed by a position since its snapshot; negative when the position is owed funding.
+pub fn accrued_funding(position: &PositionAccount, market_state: &MarketState) -> i128 {
+    let index_delta = market_state
+        .funding_index(position.direction)
+        .saturating_sub(position.funding_index_snapshot);
+    
+    (position.position_size as i128).saturating_mul(index_delta) / FUNDING_INDEX_PRECISION
+}
+
+/// Share of a market's `available` liquidity backing `position_size` out of its total
+/// `open_interest`; the whole amount when the position is the only open interest left.
+pub fn migration_liquidity(available: u64, position_size: u64, open_interest: u64) -> u64 {
+    if open_interest <= position_size {
+        return available;
+    }
+    
+    ((available as u128).saturating_mul(position_size as u128) / open_interest as u128) as u64
+}
+
+/// Liquidity each market should hold once `total` is split by `weights_bps`; the rounding
+/// remainder goes to the last market so the targets always sum to `total`.
+pub fn rebalance_targets(total: u64, weights_bps: &[u16]) -> Vec<u64> {
+    let weight_sum: u64 = weights_bps.iter().map(|weight| *weight as u64).sum();
+    
+    if weight_sum == 0 {
+        return vec![0; weights_bps.len()];
+    }
+    
+    let mut targets: Vec<u64> = weights_bps
+        .iter()
+        .map(|weight| ((total as u128).saturating_mul(*weight as u128) / weight_sum as u128) as u64)
+        .collect();
+    
+    let assigned: u64 = targets.iter().sum();
+    if let Some(last) = targets.last_mut() {
+        *last = last.saturating_add(total.saturating_sub(assigned));
+    }
+    
+    targets
+}
+
+/// LP shares minted for `amount` lamports added to a pool worth `pool_value` before the
+/// deposit; the first deposit into an empty pool mints one share per lamport.
+pub fn lp_shares_for_deposit(amount: u64, pool_value: u64, total_shares: u64) -> u64 {
+    if total_shares == 0 || pool_value == 0 {
+        return amount;
+    }
+    
+    ((amount as u128).saturating_mul(total_shares as u128) / pool_value as u128).min(u64::MAX as u128) as u64
+}
+
+/// Lamports `shares` are worth in a pool worth `pool_value`.
+pub fn lp_share_value(shares: u64, pool_value: u64, total_shares: u64) -> u64 {
+    if total_shares == 0 {
+        return 0;
+    }
+    
+    ((shares as u128).saturating_mul(pool_value as u128) / total_shares as u128).min(u64::MAX as u128) as u64
+}
+
+/// Constant-product swap of `amount_in` against reserves `reserve_in` and `reserve_out`, with
+/// `fee_bps` of the input withheld before pricing. Returns `(amount_out, fee)`.
+pub fn constant_product_output(amount_in: u64, reserve_in: u64, reserve_out: u64, fee_bps: u16) -> (u64, u64) {
+    let fee = ((amount_in as u128).saturating_mul(fee_bps as u128) / BASIS_POINTS_DIVISOR as u128) as u64;
+    let net_in = amount_in.saturating_sub(fee) as u128;
+    let denominator = (reserve_in as u128).saturating_add(net_in);
+    
+    if denominator == 0 {
+        return (0, fee);
+    }
+    
+    ((net_in.saturating_mul(reserve_out as u128) / denominator) as u64, fee)
+}
+
+/// Fees `shares` earned while the accumulator moved from `fee_per_share_paid` to `fee_per_share`.
+pub fn lp_pending_fees(shares: u64, fee_per_share: u128, fee_per_share_paid: u128) -> u64 {
+    ((shares as u128).saturating_mul(fee_per_share.saturating_sub(fee_per_share_paid)) / LP_FEE_PER_SHARE_PRECISION)
+        .min(u64::MAX as u128) as u64
+}
+
+/// Lamports per share scaled by `LP_FEE_PER_SHARE_PRECISION`; an empty pool prices shares at
+/// the one lamport a first deposit mints them for.
+pub fn lp_share_price(pool_value: u64, total_shares: u64) -> u128 {
+    if total_shares == 0 {
+        return LP_FEE_PER_SHARE_PRECISION;
+    }
+    
+    (pool_value as u128).saturating_mul(LP_FEE_PER_SHARE_PRECISION) / total_shares as u128
+}
+
+/// Performance fee on the gain of `total_shares` from `high_water_mark` up to `share_price`,
+/// both scaled by `LP_FEE_PER_SHARE_PRECISION`; nothing is owed at or below the mark.
+pub fn performance_fee(share_price: u128, high_water_mark: u128, total_shares: u64, fee_bps: u16) -> u64 {
+    let gain = share_price.saturating_sub(high_water_mark).saturating_mul(total_shares as u128)
+        / LP_FEE_PER_SHARE_PRECISION;
+    
+    (gain.saturating_mul(fee_bps as u128) / BASIS_POINTS_DIVISOR as u128).min(u64::MAX as u128) as u64
+}
+
+/// Entry price at which a position of `position_size` shows `pnl` at `mark_price`, the inverse
+/// of `unrealized_pnl`. Returns 0 when no positive entry price gives that PnL.
+pub fn entry_price_for_pnl(mark_price: u64, position_size: u64, direction: i8, contract_type: u8, pnl: i128) -> u64 {
+    if contract_type == CONTRACT_TYPE_INVERSE {
+        if position_size == 0 {
+            return 0;
+        }
+        
+        let numerator = (position_size as i128).saturating_sub(pnl.saturating_mul(direction as i128));
+        
+        return ((mark_price as i128).saturating_mul(numerator) / position_size as i128).clamp(0, u64::MAX as i128) as u64;
+    }
+    
+    let denominator = (position_size as i128).saturating_add(pnl.saturating_mul(direction as i128));
+    
+    if denominator <= 0 {
+        return 0;
+    }
+    
+    ((mark_price as i128).saturating_mul(position_size as i128) / denominator).clamp(0, u64::MAX as i128) as u64
+}
+
+/// Sources of a realized loss. The owner's wallet is deliberately not one of them: whatever the
+/// position's collateral and the owner's cross margin cannot cover is `uncovered` and falls to
+/// insurance and bad-debt accounting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LossCoverage {
+    pub from_collateral: u64,
+    pub from_margin: u64,
+    pub uncovered: u64,
+}
+
+/// Splits `loss` between `collateral` and then `margin`, leaving the rest uncovered.
+pub fn cover_loss(loss: u64, collateral: u64, margin: u64) -> LossCoverage {
+    let from_collateral = loss.min(collateral);
+    let from_margin = loss.saturating_sub(from_collateral).min(margin);
+    
+    LossCoverage {
+        from_collateral,
+        from_margin,
+        uncovered: loss.saturating_sub(from_collateral).saturating_sub(from_margin),
+    }
+}
+
+/// Converts `mantissa * 10^exponent` to a `PRICE_DECIMALS` price, rounding down. `None` for a
+/// non-positive value or one that overflows `u64`.
+pub fn scale_price(mantissa: i128, exponent: i32) -> Option<u64> {
+    if mantissa <= 0 {
+        return None;
+    }
+    
+    let shift = exponent.checked_add(PRICE_DECIMALS)?;
+    let scaled = if shift >= 0 {
+        mantissa.checked_mul(10i128.checked_pow(shift as u32)?)?
+    } else {
+        mantissa / 10i128.checked_pow(shift.unsigned_abs()).unwrap_or(i128::MAX)
+    };
+    
+    u64::try_from(scaled).ok().filter(|price| *price > 0)
+}
@@ -0,0 +1,511 @@
+//! Program-derived address seeds for every account the program owns.
+
+use solana_program::pubkey::Pubkey;
+
+use crate::*;
+
+#[inline(always)]
+pub fn find_position_address(
+    owner: &Pubkey,
+    position_nonce: u64,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_position",
+            owner.as_ref(),
+            &position_nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Market PDA under `MARKET_SEED_VERSION`, where new markets are created.
+#[inline(always)]
+pub fn find_market_address(
+    market_mint: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    find_market_address_with_version(market_mint, MARKET_SEED_VERSION, program_id)
+}
+
+#[inline(always)]
+pub fn find_market_address_with_version(
+    market_mint: &Pubkey,
+    seed_version: &[u8],
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_market",
+            market_mint.as_ref(),
+            seed_version,
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_config",
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_symbol_registry_address(
+    market_mint: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_symbol",
+            market_mint.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_margin_address(owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_margin",
+            owner.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_position_index_address(owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_position_index",
+            owner.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_nonce_counter_address(owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_nonce",
+            owner.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_price_feed_address(market_mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_oracle",
+            market_mint.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_fee_escrow_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_fee_escrow",
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn order_book_side_seed(side: i8) -> &'static [u8] {
+    if side == POSITION_LONG {
+        b"bids"
+    } else {
+        b"asks"
+    }
+}
+
+#[inline(always)]
+pub fn find_order_book_address(market_mint: &Pubkey, side: i8, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_book",
+            market_mint.as_ref(),
+            order_book_side_seed(side),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_insurance_fund_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_insurance",
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_market_insurance_address(
+    market_mint: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_market_insurance",
+            market_mint.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_program_vault_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_program_vault",
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_lp_position_address(market_mint: &Pubkey, owner: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_lp",
+            market_mint.as_ref(),
+            owner.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_close_order_address(owner: &Pubkey, position_nonce: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_close_order",
+            owner.as_ref(),
+            &position_nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_scheduled_order_address(owner: &Pubkey, position_nonce: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_scheduled",
+            owner.as_ref(),
+            &position_nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_dca_schedule_address(owner: &Pubkey, schedule_id: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_dca",
+            owner.as_ref(),
+            &schedule_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_twap_schedule_address(owner: &Pubkey, position_nonce: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_twap",
+            owner.as_ref(),
+            &position_nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_epoch_snapshot_address(market_mint: &Pubkey, epoch: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_epoch",
+            market_mint.as_ref(),
+            &epoch.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_position_tree_address(market_mint: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_position_tree",
+            market_mint.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_lead_trader_address(lead: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_lead",
+            lead.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_follower_address(lead: &Pubkey, follower: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_follow",
+            lead.as_ref(),
+            follower.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_copy_link_address(
+    follower: &Pubkey,
+    lead: &Pubkey,
+    lead_position_nonce: u64,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_copy",
+            follower.as_ref(),
+            lead.as_ref(),
+            &lead_position_nonce.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_managed_vault_address(
+    manager: &Pubkey,
+    vault_id: u64,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_managed_vault",
+            manager.as_ref(),
+            &vault_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_vault_depositor_address(
+    vault: &Pubkey,
+    depositor: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_vault_depositor",
+            vault.as_ref(),
+            depositor.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_liquidation_queue_address(
+    market_mint: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_liquidation_queue",
+            market_mint.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_position_history_address(
+    owner: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_history",
+            owner.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_user_stats_address(
+    owner: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_user_stats",
+            owner.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_authority_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"uranus_authority"], program_id)
+}
+
+#[inline(always)]
+pub fn find_fee_vault_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"uranus_fee_vault"], program_id)
+}
+
+#[inline(always)]
+pub fn find_blacklist_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"uranus_blacklist"], program_id)
+}
+
+#[inline(always)]
+pub fn find_freeze_address(
+    target: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_freeze",
+            target.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_corruption_mark_address(
+    position: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_corruption",
+            position.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_attestation_address(
+    owner: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_attestation",
+            owner.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_integrator_address(
+    integrator: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_integrator",
+            integrator.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_hedge_mode_address(
+    owner: &Pubkey,
+    market_mint: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_hedge",
+            owner.as_ref(),
+            market_mint.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_vault_request_address(
+    vault: &Pubkey,
+    depositor: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_vault_request",
+            vault.as_ref(),
+            depositor.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+#[inline(always)]
+pub fn find_vault_epoch_address(
+    vault: &Pubkey,
+    epoch: u64,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_vault_epoch",
+            vault.as_ref(),
+            &epoch.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// SPL token account holding an SPL-settled market's funds; the PDA is its own token authority.
+#[inline(always)]
+pub fn find_settlement_vault_address(
+    market_mint: &Pubkey,
+    program_id: &Pubkey
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"uranus_settlement_vault",
+            market_mint.as_ref(),
+        ],
+        program_id,
+    )
+}
@@ -0,0 +1,174 @@
+//! Read-only accounting audits: recompute what a market owes from its own state and the
+//! positions supplied, and flag any shortfall against the balances actually held.
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Emits an `InvariantViolated` event when `available` falls short of `required`.
+fn check_invariant(
+    violations: &mut u32,
+    market_mint: &Pubkey,
+    check: u8,
+    position: Option<&PositionAccount>,
+    required: u64,
+    available: u64,
+) -> ProgramResult {
+    if available >= required {
+        return Ok(());
+    }
+    
+    *violations = violations.saturating_add(1);
+    
+    msg!("Invariant {} violated: required {}, available {}", check, required, available);
+    
+    emit_event(&DexEvent::InvariantViolated(InvariantViolationEvent {
+        market_mint: *market_mint,
+        check,
+        owner: position.map(|position| position.owner).unwrap_or_default(),
+        position_nonce: position.map(|position| position.position_nonce).unwrap_or_default(),
+        required,
+        available,
+    }))
+}
+
+/// Permissionless conservation check for one market. Accounts: market, then for SPL-settled
+/// markets the settlement vault and token program, then any number of position accounts.
+/// Native markets must hold rent, unclaimed LP fees and the skew rebate pool, and each open
+/// native position must hold rent plus its collateral; an SPL vault must cover unclaimed LP fees
+/// plus the supplied positions' collateral. The supplied positions' size per side may not exceed
+/// the market's recorded open interest. Every shortfall is emitted before the call fails.
+pub(crate) fn process_verify_invariants(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    verify_data: VerifyInvariantsData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let (market_pda, _market_bump) = find_market_address(&verify_data.market_mint, program_id);
+    
+    assert_key_eq(market_account, &market_pda)?;
+    
+    if market_account.owner != program_id {
+        msg!("Market has not been initialized");
+        return Err(DexError::MarketNotInitialized.into());
+    }
+    
+    let market_state = load_market_state(market_account, program_id)?;
+    let vault_account = if market_state.is_native_settlement() {
+        None
+    } else {
+        let vault_account = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        
+        verify_settlement_vault(vault_account, token_program, &market_state, program_id)?;
+        
+        Some(vault_account)
+    };
+    
+    let rent = Rent::get()?;
+    let mut violations = 0u32;
+    let mut vault_collateral = 0u64;
+    let mut size_long = 0u64;
+    let mut size_short = 0u64;
+    
+    for position_account in accounts_iter {
+        assert_owned_by(position_account, program_id)?;
+        
+        let position = try_load_position_account(position_account)?;
+        position.validate_address(position_account.key, program_id)?;
+        
+        if position.market_mint != verify_data.market_mint || !position.is_open() {
+            continue;
+        }
+        
+        if position.direction > 0 {
+            size_long = size_long.saturating_add(position.position_size);
+        } else {
+            size_short = size_short.saturating_add(position.position_size);
+        }
+        
+        if vault_account.is_some() {
+            vault_collateral = vault_collateral.saturating_add(position.paid_amount);
+            continue;
+        }
+        
+        check_invariant(
+            &mut violations,
+            &verify_data.market_mint,
+            INVARIANT_POSITION_COLLATERAL,
+            Some(&position),
+            rent.minimum_balance(position_account.data_len())
+                .saturating_add(position.paid_amount),
+            position_account.lamports(),
+        )?;
+    }
+    
+    let (required, available) = match vault_account {
+        Some(vault_account) => (
+            market_state.unclaimed_lp_fees.saturating_add(vault_collateral),
+            token_account_amount(vault_account)?,
+        ),
+        None => (
+            rent.minimum_balance(market_account.data_len())
+                .saturating_add(market_state.unclaimed_lp_fees)
+                .saturating_add(market_state.skew_rebate_pool),
+            market_account.lamports(),
+        ),
+    };
+    
+    check_invariant(
+        &mut violations,
+        &verify_data.market_mint,
+        INVARIANT_MARKET_SOLVENCY,
+        None,
+        required,
+        available,
+    )?;
+    check_invariant(
+        &mut violations,
+        &verify_data.market_mint,
+        INVARIANT_OPEN_INTEREST_LONG,
+        None,
+        size_long,
+        market_state.open_interest_long,
+    )?;
+    check_invariant(
+        &mut violations,
+        &verify_data.market_mint,
+        INVARIANT_OPEN_INTEREST_SHORT,
+        None,
+        size_short,
+        market_state.open_interest_short,
+    )?;
+    
+    if violations > 0 {
+        msg!("{} invariant violations for market {}", violations, verify_data.market_mint);
+        return Err(DexError::InvariantViolated.into());
+    }
+    
+    msg!(
+        "Market {} invariants hold: balance {} covers {}, open interest {}/{}",
+        verify_data.market_mint,
+        available,
+        required,
+        market_state.open_interest_long,
+        market_state.open_interest_short
+    );
+    msg!(
+        "Ledger: deposited {}, paid out {}, fees {}, bad debt {}",
+        market_state.total_deposited,
+        market_state.total_paid_out,
+        market_state.cumulative_fees,
+        market_state.bad_debt
+    );
+    
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! The deployment's operator keys: the DEX authority that confirms and settles positions and the
+//! fee wallet, held in the `["uranus_authority"]` PDA instead of being compiled in.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    bpf_loader_upgradeable,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// `UpgradeableLoaderState::ProgramData` tag, followed by the deployment slot and the optional
+/// upgrade authority.
+const PROGRAM_DATA_TAG: u32 = 3;
+const PROGRAM_DATA_AUTHORITY_OFFSET: usize = 4 + 8;
+
+/// The authority PDA among `accounts`, if it was passed and initialized.
+pub(crate) fn find_authority(accounts: &[AccountInfo], program_id: &Pubkey) -> Result<Option<AuthorityAccount>, ProgramError> {
+    let (authority_pda, _authority_bump) = find_authority_address(program_id);
+    
+    let Some(authority_account) = accounts.iter().find(|account| account.key == &authority_pda) else {
+        return Ok(None);
+    };
+    
+    if authority_account.owner != program_id || authority_account.data_is_empty() {
+        return Ok(None);
+    }
+    
+    Ok(Some(deserialize_padded::<AuthorityAccount>(&authority_account.data.borrow())?))
+}
+
+/// The authority PDA, which every instruction checking the DEX authority or fee wallet must
+/// pass among its accounts.
+pub(crate) fn load_authority(accounts: &[AccountInfo], program_id: &Pubkey) -> Result<AuthorityAccount, ProgramError> {
+    find_authority(accounts, program_id)?.ok_or_else(|| {
+        msg!("Authority account missing or not initialized");
+        DexError::AuthorityNotInitialized.into()
+    })
+}
+
+/// Whether users may settle and liquidate without the operator: either the config enables
+/// `FEATURE_TRUSTLESS_CLOSE` outright or the operator has missed its heartbeat.
+pub(crate) fn trustless_paths_enabled(config: &GlobalConfig, accounts: &[AccountInfo], program_id: &Pubkey, slot: u64) -> Result<bool, ProgramError> {
+    if config.feature_enabled(FEATURE_TRUSTLESS_CLOSE) {
+        return Ok(true);
+    }
+    
+    Ok(find_authority(accounts, program_id)?.is_some_and(|authority| authority.operator_offline(slot)))
+}
+
+/// Upgrade authority recorded in the program's `ProgramData` account, or `None` once the
+/// program has been made immutable.
+fn program_upgrade_authority(program_data_account: &AccountInfo, program_id: &Pubkey) -> Result<Option<Pubkey>, ProgramError> {
+    let (program_data_address, _program_data_bump) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    
+    assert_key_eq(program_data_account, &program_data_address)?;
+    
+    let data = program_data_account.data.borrow();
+    
+    if data.len() < PROGRAM_DATA_AUTHORITY_OFFSET + 1 + 32
+        || u32::from_le_bytes(data[..4].try_into().unwrap()) != PROGRAM_DATA_TAG
+    {
+        msg!("Invalid program data account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if data[PROGRAM_DATA_AUTHORITY_OFFSET] == 0 {
+        return Ok(None);
+    }
+    
+    let start = PROGRAM_DATA_AUTHORITY_OFFSET + 1;
+    
+    Ok(Some(Pubkey::new_from_array(data[start..start + 32].try_into().unwrap())))
+}
+
+#[cfg(feature = "devnet")]
+fn is_test_authority(key: &Pubkey) -> bool {
+    key == &TEST_AUTHORITY
+}
+
+#[cfg(not(feature = "devnet"))]
+fn is_test_authority(_key: &Pubkey) -> bool {
+    false
+}
+
+/// Creates the authority PDA at deployment. Only the program's upgrade authority, or
+/// `TEST_AUTHORITY` on test network builds, may sign, and it pays the rent.
+pub(crate) fn process_initialize_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    authority_data: AuthorityData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let authority_account = next_account_info(accounts_iter)?;
+    let upgrade_authority_account = next_account_info(accounts_iter)?;
+    let program_data_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(upgrade_authority_account)?;
+    
+    if !is_test_authority(upgrade_authority_account.key)
+        && program_upgrade_authority(program_data_account, program_id)? != Some(*upgrade_authority_account.key)
+    {
+        msg!("Signer is not the program upgrade authority");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (authority_pda, authority_bump) = find_authority_address(program_id);
+    
+    assert_key_eq(authority_account, &authority_pda)?;
+    
+    if !authority_account.data_is_empty() {
+        msg!("Authority already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            upgrade_authority_account.key,
+            authority_account.key,
+            Rent::get()?.minimum_balance(AuthorityAccount::LEN),
+            AuthorityAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            upgrade_authority_account.clone(),
+            authority_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[b"uranus_authority", &[authority_bump]]],
+    )?;
+    
+    let authority = AuthorityAccount {
+        admin: authority_data.admin,
+        dex_authority: authority_data.dex_authority,
+        fees_wallet: authority_data.fees_wallet,
+        ..AuthorityAccount::default()
+    };
+    authority.serialize(&mut *authority_account.data.borrow_mut())?;
+    
+    msg!("Authority initialized: DEX {}, fees {}", authority.dex_authority, authority.fees_wallet);
+    
+    Ok(())
+}
+
+/// Rotates the operator keys (authority admin only).
+pub(crate) fn process_update_authority(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    authority_data: AuthorityData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let authority_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    
+    let (authority_pda, _authority_bump) = find_authority_address(program_id);
+    
+    assert_key_eq(authority_account, &authority_pda)?;
+    
+    let mut authority = load_authority(accounts, program_id)?;
+    
+    if !admin_account.is_signer || admin_account.key != &authority.admin {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    authority.admin = authority_data.admin;
+    authority.dex_authority = authority_data.dex_authority;
+    authority.fees_wallet = authority_data.fees_wallet;
+    authority.serialize(&mut &mut authority_account.data.borrow_mut()[..])?;
+    
+    msg!("Authority updated: admin {}, DEX {}, fees {}", authority.admin, authority.dex_authority, authority.fees_wallet);
+    
+    Ok(())
+}
+
+/// Adds an operator, changes its roles, or removes it with zero roles (authority admin only). Authority accounts created
+/// before the operator set existed are grown first, with the admin paying the rent.
+pub(crate) fn process_set_operator(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    operator_data: SetOperatorData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let authority_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let (authority_pda, _authority_bump) = find_authority_address(program_id);
+    
+    assert_key_eq(authority_account, &authority_pda)?;
+    
+    let mut authority = load_authority(accounts, program_id)?;
+    
+    if !admin_account.is_signer || admin_account.key != &authority.admin {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    if operator_data.operator == Pubkey::default() || operator_data.roles & !OPERATOR_ROLES_ALL != 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let existing = authority.operators.iter().position(|operator| operator == &operator_data.operator);
+    
+    match (operator_data.roles, existing) {
+        (0, Some(index)) => {
+            authority.operators[index] = Pubkey::default();
+            authority.operator_roles[index] = 0;
+        },
+        (0, None) => {},
+        (roles, Some(index)) => authority.operator_roles[index] = roles,
+        (roles, None) => {
+            let free = authority
+                .operators
+                .iter()
+                .position(|operator| operator == &Pubkey::default())
+                .ok_or(DexError::OperatorSetFull)?;
+            authority.operators[free] = operator_data.operator;
+            authority.operator_roles[free] = roles;
+        },
+    }
+    
+    if authority_account.data_len() < AuthorityAccount::LEN {
+        resize_account(authority_account, admin_account, system_program, AuthorityAccount::LEN)?;
+    }
+    
+    authority.serialize(&mut &mut authority_account.data.borrow_mut()[..])?;
+    
+    msg!("Operator {} roles set to {:#04b}", operator_data.operator, operator_data.roles);
+    
+    Ok(())
+}
+
+/// Records that the operator is alive. Any key holding a role may sign; authority accounts
+/// created before heartbeats existed are grown first, with the signer paying the rent.
+pub(crate) fn process_operator_heartbeat(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let authority_account = next_account_info(accounts_iter)?;
+    let operator_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let (authority_pda, _authority_bump) = find_authority_address(program_id);
+    
+    assert_key_eq(authority_account, &authority_pda)?;
+    
+    let mut authority = load_authority(accounts, program_id)?;
+    
+    authority.check_role(operator_account, OPERATOR_ROLES_ALL)?;
+    
+    if authority_account.data_len() < AuthorityAccount::LEN {
+        resize_account(authority_account, operator_account, system_program, AuthorityAccount::LEN)?;
+    }
+    
+    authority.last_heartbeat_slot = Clock::get()?.slot;
+    authority.serialize(&mut &mut authority_account.data.borrow_mut()[..])?;
+    
+    msg!("Operator heartbeat from {} at slot {}", operator_account.key, authority.last_heartbeat_slot);
+    
+    Ok(())
+}
@@ -0,0 +1,417 @@
+//! Compliance controls: owner attestations checked at open in permissioned deployments, admin
+//! freezes of owners or positions for incident response, and the address blacklist.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// With `FEATURE_PERMISSIONED_OPENS` enabled, requires `owner`'s attestation account among
+/// `accounts`, issued by the config's current attestor and neither revoked nor expired. A no-op
+/// in the default permissionless mode.
+pub(crate) fn check_attestation(
+    accounts: &[AccountInfo],
+    owner: &Pubkey,
+    config: &GlobalConfig,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if !config.feature_enabled(FEATURE_PERMISSIONED_OPENS) {
+        return Ok(());
+    }
+    
+    let (attestation_pda, _attestation_bump) = find_attestation_address(owner, program_id);
+    
+    let attestation_account = accounts
+        .iter()
+        .find(|account| account.key == &attestation_pda && account.owner == program_id)
+        .ok_or_else(|| {
+            msg!("Attestation account for {} missing", owner);
+            ProgramError::from(DexError::AttestationRequired)
+        })?;
+    
+    let attestation = deserialize_padded::<AttestationAccount>(&attestation_account.data.borrow())?;
+    
+    if !attestation.is_valid(&config.attestor, Clock::get()?.slot) {
+        msg!("Attestation for {} is revoked, expired or from a replaced attestor", owner);
+        return Err(DexError::AttestationRequired.into());
+    }
+    
+    Ok(())
+}
+
+/// The config's attestor clears `owner` to open positions, creating or re-issuing the
+/// attestation PDA.
+pub(crate) fn process_issue_attestation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    attestation_data: IssueAttestationData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let attestation_account = next_account_info(accounts_iter)?;
+    let attestor_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if !attestor_account.is_signer || attestor_account.key != &config.attestor {
+        msg!("Only the configured attestor can issue attestations");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let slot = Clock::get()?.slot;
+    
+    if attestation_data.expiry_slot != 0 && attestation_data.expiry_slot <= slot {
+        msg!("Attestation would already be expired");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (attestation_pda, attestation_bump) = find_attestation_address(&attestation_data.owner, program_id);
+    
+    assert_key_eq(attestation_account, &attestation_pda)?;
+    
+    if attestation_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                attestor_account.key,
+                attestation_account.key,
+                Rent::get()?.minimum_balance(AttestationAccount::LEN),
+                AttestationAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                attestor_account.clone(),
+                attestation_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"uranus_attestation",
+                attestation_data.owner.as_ref(),
+                &[attestation_bump],
+            ]],
+        )?;
+    } else if attestation_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    AttestationAccount {
+        owner: attestation_data.owner,
+        attestor: *attestor_account.key,
+        issued_slot: slot,
+        expiry_slot: attestation_data.expiry_slot,
+        revoked: 0,
+    }
+    .serialize(&mut *attestation_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::AttestationIssued(AttestationEvent {
+        owner: attestation_data.owner,
+        attestor: *attestor_account.key,
+        expiry_slot: attestation_data.expiry_slot,
+        revoked: false,
+    }))?;
+    
+    msg!("Attestation issued for {}", attestation_data.owner);
+    msg!("Expiry slot: {}", attestation_data.expiry_slot);
+    
+    Ok(())
+}
+
+/// The attestor or the config admin withdraws `owner`'s clearance; open positions are untouched.
+pub(crate) fn process_revoke_attestation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    revoke_data: RevokeAttestationData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let attestation_account = next_account_info(accounts_iter)?;
+    let authority_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if authority_account.key != &config.attestor {
+        config.check_admin(authority_account)?;
+    } else if !authority_account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let (attestation_pda, _attestation_bump) = find_attestation_address(&revoke_data.owner, program_id);
+    
+    if attestation_account.key != &attestation_pda || attestation_account.owner != program_id {
+        msg!("Invalid attestation account");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut attestation = deserialize_padded::<AttestationAccount>(&attestation_account.data.borrow())?;
+    attestation.revoked = 1;
+    attestation.serialize(&mut *attestation_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::AttestationRevoked(AttestationEvent {
+        owner: revoke_data.owner,
+        attestor: attestation.attestor,
+        expiry_slot: attestation.expiry_slot,
+        revoked: true,
+    }))?;
+    
+    msg!("Attestation revoked for {}", revoke_data.owner);
+    
+    Ok(())
+}
+
+/// Reads `target`'s freeze account, which must be among `accounts` whether or not it was ever
+/// created so a freeze cannot be dodged by leaving it out. `None` when not frozen.
+fn load_freeze(accounts: &[AccountInfo], target: &Pubkey, program_id: &Pubkey) -> Result<Option<FreezeAccount>, ProgramError> {
+    let (freeze_pda, _freeze_bump) = find_freeze_address(target, program_id);
+    
+    let freeze_account = accounts.iter().find(|account| account.key == &freeze_pda).ok_or_else(|| {
+        msg!("Freeze account for {} missing", target);
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    
+    if freeze_account.owner != program_id || freeze_account.data_is_empty() {
+        return Ok(None);
+    }
+    
+    let freeze = deserialize_padded::<FreezeAccount>(&freeze_account.data.borrow())?;
+    
+    Ok(freeze.is_frozen().then_some(freeze))
+}
+
+/// Rejects opens and withdrawals touching any frozen `targets`.
+pub(crate) fn check_not_frozen(accounts: &[AccountInfo], targets: &[&Pubkey], program_id: &Pubkey) -> ProgramResult {
+    for target in targets {
+        if let Some(freeze) = load_freeze(accounts, target, program_id)? {
+            msg!("{} is frozen (reason {})", target, freeze.reason_code);
+            return Err(DexError::AccountFrozen.into());
+        }
+    }
+    
+    Ok(())
+}
+
+/// Lets closes through once every frozen target has been frozen for the config's
+/// `freeze_close_timelock_slots`.
+pub(crate) fn check_close_not_frozen(
+    accounts: &[AccountInfo],
+    targets: &[&Pubkey],
+    config: &GlobalConfig,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let slot = Clock::get()?.slot;
+    
+    for target in targets {
+        if let Some(freeze) = load_freeze(accounts, target, program_id)? {
+            let unlock_slot = freeze.frozen_slot.saturating_add(config.freeze_close_timelock_slots);
+            
+            if slot < unlock_slot {
+                msg!("{} is frozen (reason {}), closes allowed from slot {}", target, freeze.reason_code, unlock_slot);
+                return Err(DexError::AccountFrozen.into());
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// The config admin freezes or unfreezes an owner or position, recording `reason_code`.
+pub(crate) fn process_set_freeze(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    freeze_data: FreezeData,
+    frozen: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let freeze_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let (freeze_pda, freeze_bump) = find_freeze_address(&freeze_data.target, program_id);
+    
+    assert_key_eq(freeze_account, &freeze_pda)?;
+    
+    if freeze_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                admin_account.key,
+                freeze_account.key,
+                Rent::get()?.minimum_balance(FreezeAccount::LEN),
+                FreezeAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                admin_account.clone(),
+                freeze_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"uranus_freeze",
+                freeze_data.target.as_ref(),
+                &[freeze_bump],
+            ]],
+        )?;
+    } else if freeze_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    FreezeAccount {
+        target: freeze_data.target,
+        frozen: frozen as u8,
+        reason_code: freeze_data.reason_code,
+        frozen_slot: Clock::get()?.slot,
+    }
+    .serialize(&mut *freeze_account.data.borrow_mut())?;
+    
+    let event = FreezeEvent {
+        target: freeze_data.target,
+        authority: *admin_account.key,
+        reason_code: freeze_data.reason_code,
+        frozen,
+    };
+    
+    emit_event(&if frozen { DexEvent::AccountFrozen(event) } else { DexEvent::AccountUnfrozen(event) })?;
+    
+    msg!(
+        "{} {} (reason {})",
+        if frozen { "Frozen" } else { "Unfrozen" },
+        freeze_data.target,
+        freeze_data.reason_code
+    );
+    
+    Ok(())
+}
+
+/// Rejects any of `addresses` on the blacklist. The blacklist PDA must be among `accounts`, even
+/// before the admin has created it, so the check cannot be skipped.
+pub(crate) fn check_not_blacklisted(accounts: &[AccountInfo], addresses: &[&Pubkey], program_id: &Pubkey) -> ProgramResult {
+    let (blacklist_pda, _blacklist_bump) = find_blacklist_address(program_id);
+    
+    let blacklist_account = accounts.iter().find(|account| account.key == &blacklist_pda).ok_or_else(|| {
+        msg!("Blacklist account missing");
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    
+    if blacklist_account.owner != program_id || blacklist_account.data_is_empty() {
+        return Ok(());
+    }
+    
+    let blacklist = deserialize_padded::<BlacklistAccount>(&blacklist_account.data.borrow())?;
+    
+    for address in addresses {
+        if blacklist.is_blocked(address) {
+            msg!("{} is blacklisted", address);
+            return Err(DexError::AddressBlacklisted.into());
+        }
+    }
+    
+    Ok(())
+}
+
+/// The config admin adds `address` to, or removes it from, the blacklist, creating the registry
+/// on first use.
+pub(crate) fn process_update_blacklist(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    blacklist_data: BlacklistData,
+    blocked: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let blacklist_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let (blacklist_pda, blacklist_bump) = find_blacklist_address(program_id);
+    
+    assert_key_eq(blacklist_account, &blacklist_pda)?;
+    
+    if blacklist_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                admin_account.key,
+                blacklist_account.key,
+                Rent::get()?.minimum_balance(BlacklistAccount::LEN),
+                BlacklistAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                admin_account.clone(),
+                blacklist_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[b"uranus_blacklist", &[blacklist_bump]]],
+        )?;
+    } else if blacklist_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let mut blacklist = deserialize_padded::<BlacklistAccount>(&blacklist_account.data.borrow())?;
+    
+    if blocked {
+        if blacklist.is_blocked(&blacklist_data.address) {
+            msg!("{} already blacklisted", blacklist_data.address);
+            return Err(ProgramError::InvalidArgument);
+        }
+        
+        if blacklist.entries.len() >= MAX_BLACKLIST_ENTRIES {
+            return Err(DexError::BlacklistFull.into());
+        }
+        
+        blacklist.entries.push(BlacklistEntry {
+            address: blacklist_data.address,
+            reason_code: blacklist_data.reason_code,
+            added_slot: Clock::get()?.slot,
+        });
+    } else {
+        let before = blacklist.entries.len();
+        blacklist.entries.retain(|entry| entry.address != blacklist_data.address);
+        
+        if blacklist.entries.len() == before {
+            msg!("{} is not blacklisted", blacklist_data.address);
+            return Err(ProgramError::InvalidArgument);
+        }
+    }
+    
+    zero_account_data(blacklist_account)?;
+    blacklist.serialize(&mut *blacklist_account.data.borrow_mut())?;
+    
+    let event = BlacklistEvent {
+        address: blacklist_data.address,
+        authority: *admin_account.key,
+        reason_code: blacklist_data.reason_code,
+        blocked,
+    };
+    
+    emit_event(&if blocked { DexEvent::AddressBlacklisted(event) } else { DexEvent::AddressUnblacklisted(event) })?;
+    
+    msg!(
+        "{} {} (reason {}), {} entries",
+        if blocked { "Blacklisted" } else { "Removed from blacklist" },
+        blacklist_data.address,
+        blacklist_data.reason_code,
+        blacklist.entries.len()
+    );
+    
+    Ok(())
+}
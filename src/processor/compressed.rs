@@ -0,0 +1,453 @@
+//! Compressed positions: small isolated positions kept as leaves of a per-market merkle tree
+//! instead of individual PDAs. The tree account holds their collateral and only the root is
+//! stored, so the leaf and its proof (indexed from `CompressedPosition*` events) are supplied on
+//! every change. Proofs must match the current root; a transaction that races another update
+//! to the same tree fails and is retried with a fresh proof.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+const EMPTY_LEAF: [u8; 32] = [0; 32];
+
+fn load_position_tree(
+    tree_account: &AccountInfo,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<PositionTreeAccount, ProgramError> {
+    let (tree_pda, _tree_bump) = find_position_tree_address(market_mint, program_id);
+    
+    assert_key_eq(tree_account, &tree_pda)?;
+    assert_owned_by(tree_account, program_id)?;
+    
+    deserialize_padded::<PositionTreeAccount>(&tree_account.data.borrow())
+}
+
+/// Creates a market's compressed position tree with every leaf empty; the payer funds its rent.
+pub(crate) fn process_initialize_position_tree(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    tree_data: PositionTreeData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let tree_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let payer_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(payer_account)?;
+    verify_market_address(market_account, &tree_data.market_mint, program_id)?;
+    assert_owned_by(market_account, program_id)?;
+    
+    let (tree_pda, tree_bump) = find_position_tree_address(&tree_data.market_mint, program_id);
+    
+    assert_key_eq(tree_account, &tree_pda)?;
+    
+    if !tree_account.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            payer_account.key,
+            tree_account.key,
+            Rent::get()?.minimum_balance(PositionTreeAccount::LEN),
+            PositionTreeAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            payer_account.clone(),
+            tree_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_position_tree",
+            tree_data.market_mint.as_ref(),
+            &[tree_bump],
+        ]],
+    )?;
+    
+    let tree = PositionTreeAccount {
+        market_mint: tree_data.market_mint,
+        depth: POSITION_TREE_DEPTH,
+        root: PositionTreeAccount::empty_root(POSITION_TREE_DEPTH),
+        ..PositionTreeAccount::default()
+    };
+    tree.serialize(&mut *tree_account.data.borrow_mut())?;
+    
+    msg!("Position tree for {} initialized with depth {}", tree_data.market_mint, tree.depth);
+    
+    Ok(())
+}
+
+/// Opens an isolated position of at most `MAX_COMPRESSED_POSITION_NOTIONAL` as the tree's next
+/// leaf (`FEATURE_COMPRESSED_POSITIONS`). Accounts: tree, market, owner (signer, pays),
+/// config, price feed, DEX fee wallet, system program.
+pub(crate) fn process_open_compressed_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    open_data: OpenCompressedPositionData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let tree_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    assert_fee_vault(dex_fees_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if !config.feature_enabled(FEATURE_COMPRESSED_POSITIONS) {
+        msg!("Compressed positions are disabled");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    check_not_blacklisted(accounts, &[owner_account.key], program_id)?;
+    
+    if open_data.direction != POSITION_LONG && open_data.direction != POSITION_SHORT {
+        msg!("Invalid direction");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut tree = load_position_tree(tree_account, &open_data.market_mint, program_id)?;
+    
+    if tree.next_index >> tree.depth != 0 {
+        msg!("Position tree holds {} leaves", tree.next_index);
+        return Err(DexError::PositionTreeFull.into());
+    }
+    
+    verify_market_address(market_account, &open_data.market_mint, program_id)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    
+    if !market_state.is_active() {
+        return Err(DexError::MarketNotActive.into());
+    }
+    
+    check_native_settlement(&market_state)?;
+    
+    let (leverage, fee, collateral, position_size) =
+        open_terms(open_data.amount, open_data.leverage, FillRole::Taker, &market_state.fee_schedule());
+    
+    if !(MIN_POSITION_SIZE_LAMPORTS..=MAX_COMPRESSED_POSITION_NOTIONAL).contains(&position_size) {
+        msg!(
+            "Compressed positions must be between {} and {} notional",
+            MIN_POSITION_SIZE_LAMPORTS,
+            MAX_COMPRESSED_POSITION_NOTIONAL
+        );
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if (collateral as u128).saturating_mul(BASIS_POINTS_DIVISOR as u128)
+        < (position_size as u128).saturating_mul(config.initial_margin_bps as u128)
+    {
+        msg!("Collateral below initial margin of {} bps", config.initial_margin_bps);
+        return Err(DexError::InsufficientInitialMargin.into());
+    }
+    
+    check_leverage_tier(&market_state, position_size, leverage)?;
+    check_price_impact(market_account, position_size, &config)?;
+    
+    let oracle_price = load_price_feed(price_feed_account, &open_data.market_mint, &config, program_id)?;
+    let price = apply_spread(
+        oracle_price.effective(open_data.direction, true),
+        open_data.direction,
+        market_state.spread_bps(market_account.lamports()),
+        true,
+    );
+    
+    invoke(
+        &system_instruction::transfer(
+            owner_account.key,
+            tree_account.key,
+            open_data.amount,
+        ),
+        &[
+            owner_account.clone(),
+            tree_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    split_fee(
+        accounts,
+        tree_account,
+        dex_fees_account,
+        market_account,
+        &open_data.market_mint,
+        &mut market_state,
+        fee,
+        TransferReason::OpenFee,
+        &config,
+    )?;
+    
+    let position = CompressedPosition {
+        owner: *owner_account.key,
+        market_mint: open_data.market_mint,
+        leaf_index: tree.next_index,
+        entry_price: price,
+        liquidation_price: compute_liquidation_price(
+            price,
+            position_size,
+            collateral,
+            open_data.direction,
+            market_state.contract_type,
+            config.maintenance_margin_bps,
+        ),
+        paid_amount: collateral,
+        position_size,
+        leverage,
+        direction: open_data.direction,
+        contract_type: market_state.contract_type,
+        open_slot: Clock::get()?.slot,
+        funding_index_snapshot: market_state.funding_index(open_data.direction),
+        borrow_index_snapshot: market_state.cumulative_borrow_index,
+    };
+    
+    tree.replace_leaf(EMPTY_LEAF, position.leaf_hash()?, position.leaf_index, &open_data.proof)?;
+    tree.next_index = tree.next_index.saturating_add(1);
+    tree.open_positions = tree.open_positions.saturating_add(1);
+    tree.collateral = tree.collateral.saturating_add(collateral);
+    tree.serialize(&mut *tree_account.data.borrow_mut())?;
+    
+    market_state.add_open_interest(open_data.direction, position_size);
+    save_market_state(market_account, &market_state)?;
+    
+    emit_event(&DexEvent::CompressedPositionOpened(CompressedPositionEvent {
+        tree: *tree_account.key,
+        position: position.clone(),
+        root: tree.root,
+        realized_pnl: 0,
+        payout: 0,
+    }))?;
+    
+    msg!(
+        "Compressed position {} opened: size {}, collateral {}, entry {}",
+        position.leaf_index,
+        position_size,
+        collateral,
+        price
+    );
+    
+    Ok(())
+}
+
+/// Adds collateral to a compressed position and moves its liquidation price accordingly.
+/// Accounts: tree, owner (signer), config, system program.
+pub(crate) fn process_add_compressed_collateral(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    collateral_data: CompressedCollateralData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let tree_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let current = collateral_data.position;
+    
+    assert_key_eq(owner_account, &current.owner)?;
+    
+    if collateral_data.amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let config = load_config(config_account, program_id)?;
+    let mut tree = load_position_tree(tree_account, &current.market_mint, program_id)?;
+    
+    invoke(
+        &system_instruction::transfer(
+            owner_account.key,
+            tree_account.key,
+            collateral_data.amount,
+        ),
+        &[
+            owner_account.clone(),
+            tree_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    let mut position = current.clone();
+    position.paid_amount = position.paid_amount.saturating_add(collateral_data.amount);
+    position.liquidation_price = compute_liquidation_price(
+        position.entry_price,
+        position.position_size,
+        position.paid_amount,
+        position.direction,
+        position.contract_type,
+        config.maintenance_margin_bps,
+    );
+    
+    tree.replace_leaf(current.leaf_hash()?, position.leaf_hash()?, position.leaf_index, &collateral_data.proof)?;
+    tree.collateral = tree.collateral.saturating_add(collateral_data.amount);
+    tree.serialize(&mut *tree_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::CompressedCollateralAdded(CompressedPositionEvent {
+        tree: *tree_account.key,
+        position: position.clone(),
+        root: tree.root,
+        realized_pnl: 0,
+        payout: 0,
+    }))?;
+    
+    msg!(
+        "Compressed position {} collateral now {}, liquidation price {}",
+        position.leaf_index,
+        position.paid_amount,
+        position.liquidation_price
+    );
+    
+    Ok(())
+}
+
+/// Settles a compressed position at the oracle price and empties its leaf. The owner may close
+/// at any time; anyone may once the price has crossed the liquidation price. Losses are capped
+/// at the collateral (any excess becomes the market's bad debt) and profit at the market's
+/// liquidity above rent. Accounts: tree, market, owner, caller (signer), config, price feed,
+/// DEX fee wallet.
+pub(crate) fn process_close_compressed_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    close_data: CloseCompressedPositionData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let tree_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let caller_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(caller_account)?;
+    assert_fee_vault(dex_fees_account, program_id)?;
+    
+    let position = close_data.position;
+    
+    assert_key_eq(owner_account, &position.owner)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut tree = load_position_tree(tree_account, &position.market_mint, program_id)?;
+    
+    tree.replace_leaf(position.leaf_hash()?, EMPTY_LEAF, position.leaf_index, &close_data.proof)?;
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let oracle_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?;
+    let exit_price = apply_spread(
+        oracle_price.effective(position.direction, false),
+        position.direction,
+        market_state.spread_bps(market_account.lamports()),
+        false,
+    );
+    let liquidatable = if position.direction == POSITION_LONG {
+        exit_price <= position.liquidation_price
+    } else {
+        exit_price >= position.liquidation_price
+    };
+    
+    if caller_account.key != owner_account.key && !liquidatable {
+        msg!("Only the owner can close a healthy compressed position");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let as_position = position.to_position();
+    let pnl = as_position
+        .unrealized_pnl(exit_price)?
+        .saturating_sub(accrued_funding(&as_position, &market_state))
+        .saturating_sub(accrued_borrow_fee(&as_position, &market_state));
+    
+    market_state.remove_open_interest(position.direction, position.position_size);
+    
+    let fee = close_fee(position.position_size, config.close_fee_bps).min(position.paid_amount);
+    split_fee(
+        accounts,
+        tree_account,
+        dex_fees_account,
+        market_account,
+        &position.market_mint,
+        &mut market_state,
+        fee,
+        TransferReason::CloseFee,
+        &config,
+    )?;
+    
+    let collateral = position.paid_amount.saturating_sub(fee);
+    let payout = if pnl >= 0 {
+        let profit = (pnl.min(u64::MAX as i128) as u64).min(
+            market_account
+                .lamports()
+                .saturating_sub(Rent::get()?.minimum_balance(market_account.data_len())),
+        );
+        
+        move_lamports(market_account, owner_account, profit, TransferReason::Profit)?;
+        market_state.record_payout(profit);
+        
+        collateral.saturating_add(profit)
+    } else {
+        let owed = pnl.unsigned_abs().min(u64::MAX as u128) as u64;
+        let loss = owed.min(collateral);
+        
+        move_lamports(tree_account, market_account, loss, TransferReason::Loss)?;
+        market_state.record_deposit(loss);
+        market_state.bad_debt = market_state.bad_debt.saturating_add(owed - loss);
+        
+        collateral - loss
+    };
+    
+    move_lamports(
+        tree_account,
+        owner_account,
+        payout.min(collateral),
+        TransferReason::CollateralReturn,
+    )?;
+    
+    tree.open_positions = tree.open_positions.saturating_sub(1);
+    tree.collateral = tree.collateral.saturating_sub(position.paid_amount);
+    tree.serialize(&mut *tree_account.data.borrow_mut())?;
+    save_market_state(market_account, &market_state)?;
+    
+    let realized_pnl = pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+    
+    emit_event(&DexEvent::CompressedPositionClosed(CompressedPositionEvent {
+        tree: *tree_account.key,
+        position: position.clone(),
+        root: tree.root,
+        realized_pnl,
+        payout,
+    }))?;
+    
+    msg!(
+        "Compressed position {} closed at {}: pnl {}, paid out {}",
+        position.leaf_index,
+        exit_price,
+        realized_pnl,
+        payout
+    );
+    
+    Ok(())
+}
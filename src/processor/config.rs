@@ -0,0 +1,742 @@
+//! Global config, feature flags, the market symbol registry and the integrator registry.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn load_config(config_account: &AccountInfo, program_id: &Pubkey) -> Result<GlobalConfig, ProgramError> {
+    let (config_pda, _config_bump) = find_config_address(program_id);
+    
+    assert_key_eq(config_account, &config_pda)?;
+    
+    if config_account.owner != program_id {
+        msg!("Config account not initialized");
+        return Err(ProgramError::UninitializedAccount);
+    }
+    
+    deserialize_padded::<GlobalConfig>(&config_account.data.borrow()).inspect_err(|_| {
+        msg!("Invalid config data");
+    })
+}
+
+pub(crate) fn apply_config_data(config: &mut GlobalConfig, config_data: &ConfigData) -> ProgramResult {
+    if config_data.maintenance_margin_bps as u64 >= BASIS_POINTS_DIVISOR {
+        msg!("Maintenance margin must be below 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.initial_margin_bps < config_data.maintenance_margin_bps
+        || config_data.initial_margin_bps as u64 > BASIS_POINTS_DIVISOR
+    {
+        msg!("Initial margin must be between maintenance margin and 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.stop_out_margin_bps != 0
+        && (config_data.stop_out_margin_bps <= config_data.maintenance_margin_bps
+            || config_data.stop_out_margin_bps as u64 >= BASIS_POINTS_DIVISOR)
+    {
+        msg!("Stop-out margin must be above maintenance margin and below 100%, or zero");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    config.dust_threshold = config_data.dust_threshold;
+    config.maintenance_margin_bps = config_data.maintenance_margin_bps;
+    config.initial_margin_bps = config_data.initial_margin_bps;
+    config.oracle_authority = config_data.oracle_authority;
+    config.price_band_bps = config_data.price_band_bps;
+    config.max_price_staleness_slots = config_data.max_price_staleness_slots;
+    config.pnl_tolerance_bps = config_data.pnl_tolerance_bps;
+    config.confirmation_timeout_slots = config_data.confirmation_timeout_slots;
+    if config_data.min_market_base_fee_bps > config_data.max_market_base_fee_bps
+        || config_data.max_market_base_fee_bps as u64 >= BASIS_POINTS_DIVISOR
+        || config_data.max_market_leverage_fee_bps as u64 >= BASIS_POINTS_DIVISOR
+    {
+        msg!("Market fee bounds must be ordered and below 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.close_fee_bps as u64 >= BASIS_POINTS_DIVISOR {
+        msg!("Close fee must be below 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.liquidation_fee_bps as u64 >= BASIS_POINTS_DIVISOR
+        || config_data.liquidation_fee_keeper_share_bps as u64 > BASIS_POINTS_DIVISOR
+    {
+        msg!("Liquidation fee must be below 100% and the keeper share at most 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.skew_surcharge_bps as u64 >= BASIS_POINTS_DIVISOR
+        || config_data.skew_rebate_bps as u64 >= BASIS_POINTS_DIVISOR
+    {
+        msg!("Skew surcharge and rebate must be below 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.auction_start_discount_bps > config_data.auction_max_discount_bps
+        || config_data.auction_max_discount_bps as u64 >= BASIS_POINTS_DIVISOR
+    {
+        msg!("Auction discount must ramp upwards and stay below 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.lp_fee_share_bps as u64 > BASIS_POINTS_DIVISOR {
+        msg!("LP fee share must be at most 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.guaranteed_stop_premium_bps as u64 >= BASIS_POINTS_DIVISOR {
+        msg!("Guaranteed stop premium must be below 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.margin_call_threshold_bps != 0 && config_data.margin_call_threshold_bps as u64 <= BASIS_POINTS_DIVISOR {
+        msg!("Margin call threshold must sit above the liquidation threshold of 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.max_trade_liquidity_bps as u64 > BASIS_POINTS_DIVISOR {
+        msg!("Max trade size must be at most 100% of market liquidity");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.swap_fee_bps as u64 >= BASIS_POINTS_DIVISOR {
+        msg!("Swap fee must be below 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if config_data.max_platform_fee_bps as u64 > BASIS_POINTS_DIVISOR {
+        msg!("Platform fee share must be at most 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    config.liquidation_price_tolerance_bps = config_data.liquidation_price_tolerance_bps;
+    config.min_market_base_fee_bps = config_data.min_market_base_fee_bps;
+    config.max_market_base_fee_bps = config_data.max_market_base_fee_bps;
+    config.max_market_leverage_fee_bps = config_data.max_market_leverage_fee_bps;
+    config.auction_start_discount_bps = config_data.auction_start_discount_bps;
+    config.auction_max_discount_bps = config_data.auction_max_discount_bps;
+    config.auction_duration_slots = config_data.auction_duration_slots;
+    config.close_fee_bps = config_data.close_fee_bps;
+    config.liquidation_fee_bps = config_data.liquidation_fee_bps;
+    config.liquidation_fee_keeper_share_bps = config_data.liquidation_fee_keeper_share_bps;
+    config.skew_surcharge_bps = config_data.skew_surcharge_bps;
+    config.skew_rebate_bps = config_data.skew_rebate_bps;
+    config.min_close_slots = config_data.min_close_slots;
+    config.listing_bond_lamports = config_data.listing_bond_lamports;
+    config.listing_timelock_slots = config_data.listing_timelock_slots;
+    config.lp_fee_share_bps = config_data.lp_fee_share_bps;
+    config.min_realized_profit = config_data.min_realized_profit;
+    config.stop_out_margin_bps = config_data.stop_out_margin_bps;
+    config.guaranteed_stop_premium_bps = config_data.guaranteed_stop_premium_bps;
+    config.portfolio_health_floor_bps = config_data.portfolio_health_floor_bps;
+    config.max_platform_fee_bps = config_data.max_platform_fee_bps;
+    config.attestor = config_data.attestor;
+    config.freeze_close_timelock_slots = config_data.freeze_close_timelock_slots;
+    config.min_open_slot_gap = config_data.min_open_slot_gap;
+    config.max_opens_per_slot = config_data.max_opens_per_slot;
+    config.max_owner_notional = config_data.max_owner_notional;
+    config.margin_call_threshold_bps = config_data.margin_call_threshold_bps;
+    config.swap_fee_bps = config_data.swap_fee_bps;
+    config.max_trade_liquidity_bps = config_data.max_trade_liquidity_bps;
+    config.close_settlement_deadline_slots = config_data.close_settlement_deadline_slots;
+    
+    Ok(())
+}
+
+pub(crate) fn log_config(config: &GlobalConfig) {
+    msg!("Dust threshold: {} lamports", config.dust_threshold);
+    msg!("Maintenance margin: {} bps", config.maintenance_margin_bps);
+    msg!("Initial margin: {} bps", config.initial_margin_bps);
+    msg!("Oracle authority: {}", config.oracle_authority);
+    msg!("Price band: {} bps", config.price_band_bps);
+    msg!("Max price staleness: {} slots", config.max_price_staleness_slots);
+    msg!("PnL tolerance: {} bps", config.pnl_tolerance_bps);
+    msg!("Confirmation timeout: {} slots", config.confirmation_timeout_slots);
+    msg!("Liquidation price tolerance: {} bps", config.liquidation_price_tolerance_bps);
+    msg!("Market base fee bounds: {}-{} bps", config.min_market_base_fee_bps, config.max_market_base_fee_bps);
+    msg!("Market leverage fee cap: {} bps", config.max_market_leverage_fee_bps);
+    msg!(
+        "Liquidation auction: {}-{} bps over {} slots",
+        config.auction_start_discount_bps,
+        config.auction_max_discount_bps,
+        config.auction_duration_slots
+    );
+    msg!("Close fee: {} bps", config.close_fee_bps);
+    msg!(
+        "Liquidation fee: {} bps, keeper share {} bps",
+        config.liquidation_fee_bps,
+        config.liquidation_fee_keeper_share_bps
+    );
+    msg!("Skew surcharge: {} bps, rebate: {} bps", config.skew_surcharge_bps, config.skew_rebate_bps);
+    msg!("Minimum close delay: {} slots", config.min_close_slots);
+    msg!(
+        "Market listing: {} lamports bond, {} slot veto window",
+        config.listing_bond_lamports,
+        config.listing_timelock_slots
+    );
+    msg!("LP fee share: {} bps", config.lp_fee_share_bps);
+    msg!("Minimum realized profit: {} lamports", config.min_realized_profit);
+    msg!("Stop-out margin: {} bps", config.stop_out_margin_bps);
+    msg!("Guaranteed stop premium: {} bps", config.guaranteed_stop_premium_bps);
+    msg!("Portfolio health floor: {} bps", config.portfolio_health_floor_bps);
+    msg!("Max platform fee share: {} bps", config.max_platform_fee_bps);
+    msg!("Attestor: {}", config.attestor);
+    msg!("Frozen close timelock: {} slots", config.freeze_close_timelock_slots);
+    msg!("Open rate limit: {} slot gap, {} per slot", config.min_open_slot_gap, config.max_opens_per_slot);
+    msg!("Max owner notional: {} lamports", config.max_owner_notional);
+    msg!("Margin call threshold: {} bps", config.margin_call_threshold_bps);
+    msg!("Swap fee: {} bps", config.swap_fee_bps);
+    msg!("Max trade size: {} bps of market liquidity", config.max_trade_liquidity_bps);
+    msg!("Close settlement deadline: {} slots", config.close_settlement_deadline_slots);
+}
+
+#[inline(always)]
+pub(crate) fn process_set_feature_flags(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    flags_data: FeatureFlagsData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let config_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let mut config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let previous_flags = config.feature_flags;
+    config.feature_flags = (config.feature_flags | flags_data.enable) & !flags_data.disable;
+    
+    let data_len = GlobalConfig::LEN;
+    if config_account.data_len() < data_len {
+        resize_account(config_account, admin_account, system_program, data_len)?;
+    }
+    
+    config.serialize(&mut *config_account.data.borrow_mut())?;
+    
+    msg!("Feature flags: {:#x} -> {:#x}", previous_flags, config.feature_flags);
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_rebalance_targets(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    targets_data: RebalanceTargetsData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let config_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let mut config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let active: Vec<&RebalanceTarget> = targets_data
+        .targets
+        .iter()
+        .filter(|target| target.weight_bps != 0)
+        .collect();
+    let weight_sum: u64 = active.iter().map(|target| target.weight_bps as u64).sum();
+    
+    if !active.is_empty() && weight_sum != BASIS_POINTS_DIVISOR {
+        msg!("Rebalance weights must sum to {} bps", BASIS_POINTS_DIVISOR);
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    for (index, target) in active.iter().enumerate() {
+        if active[..index].iter().any(|other| other.market_mint == target.market_mint) {
+            msg!("Market {} has two rebalance targets", target.market_mint);
+            return Err(DexError::InvalidConfig.into());
+        }
+    }
+    
+    config.rebalance_targets = targets_data.targets;
+    config.rebalance_max_per_call = targets_data.max_per_call;
+    
+    let data_len = GlobalConfig::LEN;
+    if config_account.data_len() < data_len {
+        resize_account(config_account, admin_account, system_program, data_len)?;
+    }
+    
+    config.serialize(&mut *config_account.data.borrow_mut())?;
+    
+    for target in config.active_rebalance_targets() {
+        msg!("Rebalance target: {} -> {} bps", target.market_mint, target.weight_bps);
+    }
+    msg!("Rebalance cap: {} lamports per call", config.rebalance_max_per_call);
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_fee_routes(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    routes_data: FeeRoutesData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let config_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let mut config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let active: Vec<&FeeRoute> = routes_data.routes.iter().filter(|route| route.share_bps != 0).collect();
+    
+    for (index, route) in active.iter().enumerate() {
+        if route.fee_type >= FEE_TYPE_COUNT || route.destination == Pubkey::default() {
+            msg!("Invalid fee route {}: type {} to {}", index, route.fee_type, route.destination);
+            return Err(DexError::InvalidConfig.into());
+        }
+        
+        if active[..index]
+            .iter()
+            .any(|other| other.fee_type == route.fee_type && other.destination == route.destination)
+        {
+            msg!("Fee type {} routed to {} twice", route.fee_type, route.destination);
+            return Err(DexError::InvalidConfig.into());
+        }
+    }
+    
+    for fee_type in 0..FEE_TYPE_COUNT {
+        let share_sum: u64 = active
+            .iter()
+            .filter(|route| route.fee_type == fee_type)
+            .map(|route| route.share_bps as u64)
+            .sum();
+        
+        if share_sum > BASIS_POINTS_DIVISOR {
+            msg!("Fee type {} routes exceed {} bps", fee_type, BASIS_POINTS_DIVISOR);
+            return Err(DexError::InvalidConfig.into());
+        }
+    }
+    
+    config.fee_routes = routes_data.routes;
+    
+    let data_len = GlobalConfig::LEN;
+    if config_account.data_len() < data_len {
+        resize_account(config_account, admin_account, system_program, data_len)?;
+    }
+    
+    config.serialize(&mut *config_account.data.borrow_mut())?;
+    
+    for route in config.fee_routes.iter().filter(|route| route.share_bps != 0) {
+        msg!("Fee route: type {} -> {} ({} bps)", route.fee_type, route.destination, route.share_bps);
+    }
+    
+    Ok(())
+}
+
+/// Rejects an instruction outside `SHUTDOWN_ALLOWED_INSTRUCTIONS` once the shutdown has taken
+/// effect, reading the config wherever it sits among the accounts. The config must be passed;
+/// instructions sent before it exists pass unchecked.
+pub(crate) fn check_not_shut_down(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let (config_pda, _config_bump) = find_config_address(program_id);
+    
+    let config_account = accounts.iter().find(|account| account.key == &config_pda).ok_or_else(|| {
+        msg!("Config account missing");
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    
+    if config_account.owner != program_id {
+        return Ok(());
+    }
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if config.shutdown_slot != 0 && config.is_shut_down(Clock::get()?.slot) {
+        msg!("Program shut down since slot {}", config.shutdown_slot);
+        return Err(DexError::ProgramShutdown.into());
+    }
+    
+    Ok(())
+}
+
+/// Schedules the terminal shutdown `SHUTDOWN_TIMELOCK_SLOTS` out, or cancels it while it is still
+/// pending (config admin only). Once it takes effect it can no longer be cancelled.
+pub(crate) fn process_schedule_shutdown(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    shutdown_data: ScheduleShutdownData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let config_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let mut config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let slot = Clock::get()?.slot;
+    
+    if shutdown_data.cancel {
+        if config.shutdown_slot == 0 {
+            msg!("No shutdown scheduled");
+            return Ok(());
+        }
+        
+        if config.is_shut_down(slot) {
+            msg!("Shutdown took effect at slot {} and can no longer be cancelled", config.shutdown_slot);
+            return Err(DexError::ProgramShutdown.into());
+        }
+        
+        config.shutdown_slot = 0;
+        msg!("Shutdown cancelled");
+    } else {
+        if config.shutdown_slot != 0 {
+            msg!("Shutdown already scheduled for slot {}", config.shutdown_slot);
+            return Ok(());
+        }
+        
+        config.shutdown_slot = slot.saturating_add(SHUTDOWN_TIMELOCK_SLOTS);
+        msg!("Shutdown scheduled for slot {}", config.shutdown_slot);
+    }
+    
+    let data_len = GlobalConfig::LEN;
+    if config_account.data_len() < data_len {
+        resize_account(config_account, admin_account, system_program, data_len)?;
+    }
+    
+    config.serialize(&mut *config_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::ShutdownScheduled(ShutdownEvent {
+        admin: *admin_account.key,
+        shutdown_slot: config.shutdown_slot,
+    }))?;
+    
+    Ok(())
+}
+
+pub(crate) fn process_initialize_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    config_data: ConfigData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let config_account = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    load_authority(accounts, program_id)?.check_dex(dex_account)?;
+    
+    let (config_pda, config_bump) = find_config_address(program_id);
+    
+    assert_key_eq(config_account, &config_pda)?;
+    
+    // A repeated initialization with the same parameters is a no-op, so racing bootstrap
+    // clients both succeed; different ones conflict with what is already stored.
+    if !config_account.data_is_empty() {
+        let existing = load_config(config_account, program_id)?;
+        let mut requested = existing.clone();
+        apply_config_data(&mut requested, &config_data)?;
+        
+        if requested.try_to_vec()? != existing.try_to_vec()? {
+            msg!("Config already initialized with different parameters");
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        
+        msg!("Config already initialized");
+        return Ok(());
+    }
+    
+    let mut config = GlobalConfig {
+        version: CONFIG_ACCOUNT_VERSION,
+        admin: *dex_account.key,
+        ..GlobalConfig::default()
+    };
+    apply_config_data(&mut config, &config_data)?;
+    
+    let serialized_data = config.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    let data_len = serialized_data.len();
+    
+    let rent = Rent::get()?;
+    let minimum_balance = rent.minimum_balance(data_len);
+    
+    let config_seeds = &[
+        b"uranus_config".as_ref(),
+        &[config_bump],
+    ];
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            dex_account.key,
+            config_account.key,
+            minimum_balance,
+            data_len as u64,
+            program_id,
+        ),
+        &[
+            dex_account.clone(),
+            config_account.clone(),
+            system_program.clone(),
+        ],
+        &[config_seeds],
+    )?;
+    
+    config.serialize(&mut *config_account.data.borrow_mut())?;
+    
+    msg!("Config initialized");
+    log_config(&config);
+    
+    Ok(())
+}
+
+pub(crate) fn process_update_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    config_data: ConfigData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let config_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let mut config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    apply_config_data(&mut config, &config_data)?;
+    config.version = CONFIG_ACCOUNT_VERSION;
+    
+    let data_len = GlobalConfig::LEN;
+    if config_account.data_len() < data_len {
+        resize_account(config_account, admin_account, system_program, data_len)?;
+    }
+    
+    config.serialize(&mut *config_account.data.borrow_mut())?;
+    
+    msg!("Config updated");
+    log_config(&config);
+    
+    Ok(())
+}
+
+pub(crate) fn load_symbol_registry(
+    symbol_registry_account: &AccountInfo,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<SymbolRegistryAccount, ProgramError> {
+    let (symbol_registry_pda, _registry_bump) = find_symbol_registry_address(market_mint, program_id);
+    
+    assert_key_eq(symbol_registry_account, &symbol_registry_pda)?;
+    
+    if symbol_registry_account.owner != program_id || symbol_registry_account.data_is_empty() {
+        msg!("Market {} is not registered", market_mint);
+        return Err(DexError::MarketNotRegistered.into());
+    }
+    
+    SymbolRegistryAccount::try_from_slice(&symbol_registry_account.data.borrow()).map_err(|_| {
+        msg!("Invalid symbol registry data");
+        ProgramError::InvalidAccountData
+    })
+}
+
+pub(crate) fn process_register_symbol(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    register_data: RegisterSymbolData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let entry = SymbolRegistryAccount {
+        market_mint: register_data.market_mint,
+        market_symbol: register_data.market_symbol,
+        decimals: register_data.decimals,
+    };
+    
+    write_symbol_registry(symbol_registry_account, admin_account, system_program, &entry, program_id)
+}
+
+/// Validates and stores a registry entry, creating the PDA at the payer's expense on first use.
+pub(crate) fn write_symbol_registry<'a>(
+    symbol_registry_account: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    entry: &SymbolRegistryAccount,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    validate_market_symbol(&entry.market_symbol)?;
+    
+    let (symbol_registry_pda, registry_bump) = find_symbol_registry_address(
+        &entry.market_mint,
+        program_id
+    );
+    
+    assert_key_eq(symbol_registry_account, &symbol_registry_pda)?;
+    
+    if symbol_registry_account.data_is_empty() {
+        let serialized_data = entry.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+        let data_len = serialized_data.len();
+        
+        let rent = Rent::get()?;
+        let minimum_balance = rent.minimum_balance(data_len);
+        
+        let registry_seeds = &[
+            b"uranus_symbol".as_ref(),
+            entry.market_mint.as_ref(),
+            &[registry_bump],
+        ];
+        
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                symbol_registry_account.key,
+                minimum_balance,
+                data_len as u64,
+                program_id,
+            ),
+            &[
+                payer_account.clone(),
+                symbol_registry_account.clone(),
+                system_program.clone(),
+            ],
+            &[registry_seeds],
+        )?;
+    } else if symbol_registry_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    entry.serialize(&mut *symbol_registry_account.data.borrow_mut())?;
+    
+    msg!("Symbol registered: {}", fixed_array_to_string(&entry.market_symbol)?);
+    msg!("Market mint: {}", entry.market_mint);
+    msg!("Decimals: {}", entry.decimals);
+    
+    Ok(())
+}
+
+/// Loads the registry entry of `integrator`, which must be registered and active.
+pub(crate) fn load_integrator(
+    integrator_account: &AccountInfo,
+    integrator: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<IntegratorAccount, ProgramError> {
+    let (integrator_pda, _integrator_bump) = find_integrator_address(integrator, program_id);
+    
+    assert_key_eq(integrator_account, &integrator_pda)?;
+    
+    if integrator_account.owner != program_id || integrator_account.data_is_empty() {
+        msg!("Integrator {} is not registered", integrator);
+        return Err(DexError::IntegratorNotRegistered.into());
+    }
+    
+    let entry = deserialize_padded::<IntegratorAccount>(&integrator_account.data.borrow())?;
+    
+    if !entry.is_active() {
+        msg!("Integrator {} is inactive", integrator);
+        return Err(DexError::IntegratorNotRegistered.into());
+    }
+    
+    Ok(entry)
+}
+
+/// Approves, re-rates or deactivates a frontend, keeping its volume stats across updates.
+pub(crate) fn process_register_integrator(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    integrator_data: RegisterIntegratorData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let integrator_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    if integrator_data.fee_share_bps > config.max_platform_fee_bps {
+        msg!("Platform fee share capped at {} bps", config.max_platform_fee_bps);
+        return Err(DexError::PlatformFeeTooHigh.into());
+    }
+    
+    let (integrator_pda, integrator_bump) = find_integrator_address(&integrator_data.integrator, program_id);
+    
+    assert_key_eq(integrator_account, &integrator_pda)?;
+    
+    let mut entry = if integrator_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                admin_account.key,
+                integrator_account.key,
+                Rent::get()?.minimum_balance(IntegratorAccount::LEN),
+                IntegratorAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                admin_account.clone(),
+                integrator_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"uranus_integrator",
+                integrator_data.integrator.as_ref(),
+                &[integrator_bump],
+            ]],
+        )?;
+        
+        IntegratorAccount {
+            integrator: integrator_data.integrator,
+            registered_slot: Clock::get()?.slot,
+            ..IntegratorAccount::default()
+        }
+    } else if integrator_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    } else {
+        deserialize_padded::<IntegratorAccount>(&integrator_account.data.borrow())?
+    };
+    
+    entry.fee_share_bps = integrator_data.fee_share_bps;
+    entry.active = integrator_data.active as u8;
+    entry.serialize(&mut *integrator_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::IntegratorRegistered(IntegratorEvent {
+        integrator: integrator_data.integrator,
+        fee_share_bps: integrator_data.fee_share_bps,
+        active: integrator_data.active,
+    }))?;
+    
+    msg!(
+        "Integrator {}: {} bps fee share, {}",
+        integrator_data.integrator,
+        integrator_data.fee_share_bps,
+        if integrator_data.active { "active" } else { "inactive" }
+    );
+    
+    Ok(())
+}
@@ -0,0 +1,550 @@
+//! Copy trading: lead trader registration, follower subscriptions and the cranks that mirror a
+//! lead's opens and closes into followers' positions.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::{invoke, invoke_signed},
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn load_lead_trader(
+    lead_trader_account: &AccountInfo,
+    lead: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<LeadTraderAccount, ProgramError> {
+    let (lead_trader_pda, _lead_trader_bump) = find_lead_trader_address(lead, program_id);
+    
+    assert_key_eq(lead_trader_account, &lead_trader_pda)?;
+    
+    if lead_trader_account.owner != program_id {
+        msg!("Lead trader not registered");
+        return Err(ProgramError::UninitializedAccount);
+    }
+    
+    deserialize_padded::<LeadTraderAccount>(&lead_trader_account.data.borrow())
+}
+
+pub(crate) fn load_follower(
+    follower_account: &AccountInfo,
+    lead: &Pubkey,
+    follower: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<FollowerAccount, ProgramError> {
+    let (follower_pda, _follower_bump) = find_follower_address(lead, follower, program_id);
+    
+    assert_key_eq(follower_account, &follower_pda)?;
+    
+    assert_owned_by(follower_account, program_id)?;
+    
+    deserialize_padded::<FollowerAccount>(&follower_account.data.borrow())
+}
+
+/// Copy margin left in a follower account above its rent.
+pub(crate) fn follower_margin(follower_account: &AccountInfo) -> Result<u64, ProgramError> {
+    Ok(follower_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(follower_account.data_len())))
+}
+
+/// True while `lead_position_account` still holds the lead's open position `position_nonce`.
+fn lead_position_open(
+    lead_position_account: &AccountInfo,
+    lead: &Pubkey,
+    position_nonce: u64,
+    program_id: &Pubkey,
+) -> Result<bool, ProgramError> {
+    let (position_pda, _position_bump) = find_position_address(lead, position_nonce, program_id);
+    
+    assert_key_eq(lead_position_account, &position_pda)?;
+    
+    if lead_position_account.owner != program_id || lead_position_account.data_is_empty() {
+        return Ok(false);
+    }
+    
+    Ok(try_load_position_account(lead_position_account)
+        .map(|position| position.validate(lead, position_nonce).is_ok() && position.is_open())
+        .unwrap_or(false))
+}
+
+/// Registers the signer as a lead trader, or updates the profit share of an existing
+/// registration.
+pub(crate) fn process_register_lead_trader(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    register_data: RegisterLeadTraderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let lead_trader_account = next_account_info(accounts_iter)?;
+    let lead_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(lead_account)?;
+    
+    if register_data.profit_share_bps > MAX_COPY_PROFIT_SHARE_BPS {
+        msg!("Profit share must be at most {} bps", MAX_COPY_PROFIT_SHARE_BPS);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (lead_trader_pda, lead_trader_bump) = find_lead_trader_address(lead_account.key, program_id);
+    
+    assert_key_eq(lead_trader_account, &lead_trader_pda)?;
+    
+    let mut lead_trader = if lead_trader_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                lead_account.key,
+                lead_trader_account.key,
+                Rent::get()?.minimum_balance(LeadTraderAccount::LEN),
+                LeadTraderAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                lead_account.clone(),
+                lead_trader_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[b"uranus_lead", lead_account.key.as_ref(), &[lead_trader_bump]]],
+        )?;
+        
+        LeadTraderAccount {
+            lead: *lead_account.key,
+            registered_slot: Clock::get()?.slot,
+            ..LeadTraderAccount::default()
+        }
+    } else {
+        load_lead_trader(lead_trader_account, lead_account.key, program_id)?
+    };
+    
+    lead_trader.profit_share_bps = register_data.profit_share_bps;
+    lead_trader.serialize(&mut *lead_trader_account.data.borrow_mut())?;
+    
+    msg!("Lead trader {} registered with a {} bps profit share", lead_account.key, lead_trader.profit_share_bps);
+    
+    Ok(())
+}
+
+/// Subscribes the signer to a lead trader, depositing copy margin; following again tops the
+/// margin up and replaces the copy ratio and cap.
+pub(crate) fn process_follow_lead_trader(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    follow_data: FollowLeadTraderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let follower_account = next_account_info(accounts_iter)?;
+    let lead_trader_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    if follow_data.copy_ratio_bps == 0 || &follow_data.lead == owner_account.key {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut lead_trader = load_lead_trader(lead_trader_account, &follow_data.lead, program_id)?;
+    let (follower_pda, follower_bump) = find_follower_address(&follow_data.lead, owner_account.key, program_id);
+    
+    assert_key_eq(follower_account, &follower_pda)?;
+    
+    let mut follower = if follower_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                owner_account.key,
+                follower_account.key,
+                Rent::get()?.minimum_balance(FollowerAccount::LEN),
+                FollowerAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                owner_account.clone(),
+                follower_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"uranus_follow",
+                follow_data.lead.as_ref(),
+                owner_account.key.as_ref(),
+                &[follower_bump],
+            ]],
+        )?;
+        
+        lead_trader.followers = lead_trader.followers.saturating_add(1);
+        lead_trader.serialize(&mut *lead_trader_account.data.borrow_mut())?;
+        
+        FollowerAccount {
+            follower: *owner_account.key,
+            lead: follow_data.lead,
+            subscribed_slot: Clock::get()?.slot,
+            ..FollowerAccount::default()
+        }
+    } else {
+        load_follower(follower_account, &follow_data.lead, owner_account.key, program_id)?
+    };
+    
+    if follow_data.deposit > 0 {
+        invoke(
+            &system_instruction::transfer(
+                owner_account.key,
+                follower_account.key,
+                follow_data.deposit,
+            ),
+            &[
+                owner_account.clone(),
+                follower_account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+    }
+    
+    follower.copy_ratio_bps = follow_data.copy_ratio_bps;
+    follower.max_collateral = follow_data.max_collateral;
+    follower.serialize(&mut *follower_account.data.borrow_mut())?;
+    
+    msg!(
+        "Following {} at {} bps, copy margin: {} lamports",
+        follow_data.lead,
+        follower.copy_ratio_bps,
+        follower_margin(follower_account)?
+    );
+    
+    Ok(())
+}
+
+/// Ends a subscription once no copied positions remain open, returning the copy margin and rent.
+pub(crate) fn process_unfollow_lead_trader(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    unfollow_data: UnfollowLeadTraderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let follower_account = next_account_info(accounts_iter)?;
+    let lead_trader_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let follower = load_follower(follower_account, &unfollow_data.lead, owner_account.key, program_id)?;
+    
+    if follower.open_copies > 0 {
+        msg!("{} copied positions still open", follower.open_copies);
+        return Err(DexError::FollowerHasOpenCopies.into());
+    }
+    
+    let mut lead_trader = load_lead_trader(lead_trader_account, &unfollow_data.lead, program_id)?;
+    lead_trader.followers = lead_trader.followers.saturating_sub(1);
+    lead_trader.serialize(&mut *lead_trader_account.data.borrow_mut())?;
+    
+    let returned = follower_account.lamports();
+    refund_order_account(follower_account, owner_account)?;
+    
+    msg!("Unfollowed {}, returned {} lamports", unfollow_data.lead, returned);
+    
+    Ok(())
+}
+
+/// Permissionless crank mirroring a lead's open position for one follower: the follower opens
+/// the same market, direction and leverage at the oracle price with `copy_ratio_bps` of the
+/// lead's collateral, funded from the follower's copy margin. Only positions the lead opened
+/// after the follower subscribed are copied, each at most once.
+pub(crate) fn process_copy_open(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    copy_data: CopyTradeData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let follower_account = next_account_info(accounts_iter)?;
+    let copy_link_account = next_account_info(accounts_iter)?;
+    let lead_position_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let nonce_counter_account = next_account_info(accounts_iter)?;
+    let position_index_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(keeper_account)?;
+    
+    if owner_account.key != &copy_data.follower {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let config = load_config(config_account, program_id)?;
+    let mut follower = load_follower(follower_account, &copy_data.lead, &copy_data.follower, program_id)?;
+    
+    if !lead_position_open(lead_position_account, &copy_data.lead, copy_data.lead_position_nonce, program_id)? {
+        return Err(DexError::LeadPositionNotCopyable.into());
+    }
+    
+    let lead_position = try_load_position_account(lead_position_account)?;
+    
+    if !lead_position.is_confirmed() || lead_position.open_slot < follower.subscribed_slot {
+        msg!("Lead position {} predates the subscription or is unconfirmed", lead_position.position_nonce);
+        return Err(DexError::LeadPositionNotCopyable.into());
+    }
+    
+    let (copy_link_pda, copy_link_bump) = find_copy_link_address(
+        &copy_data.follower,
+        &copy_data.lead,
+        copy_data.lead_position_nonce,
+        program_id,
+    );
+    
+    assert_key_eq(copy_link_account, &copy_link_pda)?;
+    
+    if !copy_link_account.data_is_empty() {
+        msg!("Lead position {} already copied", copy_data.lead_position_nonce);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let mut amount = ((lead_position.paid_amount as u128)
+        .saturating_mul(follower.copy_ratio_bps as u128)
+        / BASIS_POINTS_DIVISOR as u128)
+        .min(u64::MAX as u128) as u64;
+    if follower.max_collateral != 0 {
+        amount = amount.min(follower.max_collateral);
+    }
+    
+    let rent = Rent::get()?;
+    let required = amount
+        .saturating_add(rent.minimum_balance(PositionAccount::LEN))
+        .saturating_add(rent.minimum_balance(CopyLinkAccount::LEN));
+    
+    if follower_margin(follower_account)? < required {
+        msg!("Copy margin cannot fund {} lamports", required);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    let symbol_registry = load_symbol_registry(symbol_registry_account, &lead_position.market_mint, program_id)?;
+    verify_market_address(market_account, &lead_position.market_mint, program_id)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let mark_price = load_price_feed(price_feed_account, &lead_position.market_mint, &config, program_id)?.effective(lead_position.direction, true);
+    let keeper_lamports = keeper_account.lamports();
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            keeper_account.key,
+            copy_link_account.key,
+            rent.minimum_balance(CopyLinkAccount::LEN),
+            CopyLinkAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            keeper_account.clone(),
+            copy_link_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_copy",
+            copy_data.follower.as_ref(),
+            copy_data.lead.as_ref(),
+            &copy_data.lead_position_nonce.to_le_bytes(),
+            &[copy_link_bump],
+        ]],
+    )?;
+    
+    let position = open_funded_position(
+        accounts,
+        follower_account,
+        keeper_account,
+        owner_account,
+        position_account,
+        nonce_counter_account,
+        position_index_account,
+        market_account,
+        dex_fees_account,
+        system_program,
+        &mut market_state,
+        &config,
+        &lead_position.market_mint,
+        symbol_registry.market_symbol,
+        lead_position.direction,
+        lead_position.leverage,
+        amount,
+        mark_price,
+        TransferReason::CopyFill,
+        program_id,
+    )?;
+    save_market_state(market_account, &market_state)?;
+    
+    CopyLinkAccount {
+        follower: copy_data.follower,
+        lead: copy_data.lead,
+        lead_position_nonce: copy_data.lead_position_nonce,
+        follower_position_nonce: position.position_nonce,
+    }
+    .serialize(&mut *copy_link_account.data.borrow_mut())?;
+    
+    let reimbursement = keeper_lamports.saturating_sub(keeper_account.lamports());
+    move_lamports(follower_account, keeper_account, reimbursement, TransferReason::KeeperReimbursement)?;
+    
+    follower.open_copies = follower.open_copies.saturating_add(1);
+    follower.serialize(&mut *follower_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::CopyOpened(CopyTradeEvent {
+        lead: copy_data.lead,
+        follower: copy_data.follower,
+        lead_position_nonce: copy_data.lead_position_nonce,
+        follower_position_nonce: position.position_nonce,
+        price: position.entry_price,
+        collateral: position.paid_amount,
+        pnl: 0,
+        profit_share: 0,
+    }))?;
+    
+    msg!(
+        "Copied lead position {} into follower position {} at {}",
+        copy_data.lead_position_nonce,
+        position.position_nonce,
+        position.entry_price
+    );
+    
+    Ok(())
+}
+
+/// Permissionless crank mirroring a lead's close: once the lead position is gone, the follower's
+/// copy settles at the oracle price back into the copy margin and the lead takes its profit
+/// share of any gain. A copy the follower already closed just has its link removed.
+pub(crate) fn process_copy_close(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    copy_data: CopyTradeData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let follower_account = next_account_info(accounts_iter)?;
+    let copy_link_account = next_account_info(accounts_iter)?;
+    let lead_position_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let lead_trader_account = next_account_info(accounts_iter)?;
+    let lead_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(keeper_account)?;
+    
+    if lead_account.key != &copy_data.lead {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let config = load_config(config_account, program_id)?;
+    let lead_trader = load_lead_trader(lead_trader_account, &copy_data.lead, program_id)?;
+    let mut follower = load_follower(follower_account, &copy_data.lead, &copy_data.follower, program_id)?;
+    let (copy_link_pda, _copy_link_bump) = find_copy_link_address(
+        &copy_data.follower,
+        &copy_data.lead,
+        copy_data.lead_position_nonce,
+        program_id,
+    );
+    
+    if copy_link_account.key != &copy_link_pda || copy_link_account.owner != program_id {
+        msg!("Invalid copy link account");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let copy_link = deserialize_padded::<CopyLinkAccount>(&copy_link_account.data.borrow())?;
+    
+    if lead_position_open(lead_position_account, &copy_data.lead, copy_data.lead_position_nonce, program_id)? {
+        return Err(DexError::LeadPositionStillOpen.into());
+    }
+    
+    let (position_pda, _position_bump) =
+        find_position_address(&copy_data.follower, copy_link.follower_position_nonce, program_id);
+    
+    assert_key_eq(position_account, &position_pda)?;
+    
+    let copy = if position_account.owner == program_id && !position_account.data_is_empty() {
+        try_load_position_account(position_account)
+            .ok()
+            .filter(|position| position.is_open() && position.position_nonce == copy_link.follower_position_nonce)
+    } else {
+        None
+    };
+    
+    let mut final_pnl = 0;
+    let mut profit_share = 0;
+    let mut mark_price = 0;
+    
+    if let Some(position) = copy {
+        verify_market_address(market_account, &position.market_mint, program_id)?;
+        
+        let oracle_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?;
+        mark_price = oracle_price.effective(position.direction, false);
+        
+        let mut market_state = touch_market_state(market_account, program_id)?;
+        final_pnl = settle_at_mark_price(
+            &position,
+            position_account,
+            follower_account,
+            market_account,
+            &mut market_state,
+            None,
+            &config,
+            accounts,
+            mark_price,
+            TransferReason::Profit,
+            program_id,
+        )?;
+        
+        emit_settlement_price(&position, &oracle_price, mark_price)?;
+        
+        if final_pnl > 0 {
+            profit_share = ((final_pnl as u128)
+                .saturating_mul(lead_trader.profit_share_bps as u128)
+                / BASIS_POINTS_DIVISOR as u128)
+                .min(follower_margin(follower_account)? as u128) as u64;
+            
+            move_lamports(follower_account, lead_account, profit_share, TransferReason::CopyProfitShare)?;
+        }
+        
+        follower.realized_pnl = follower
+            .realized_pnl
+            .saturating_add(final_pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+            .saturating_sub(profit_share as i64);
+    }
+    
+    refund_order_account(copy_link_account, follower_account)?;
+    
+    follower.open_copies = follower.open_copies.saturating_sub(1);
+    follower.serialize(&mut *follower_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::CopyClosed(CopyTradeEvent {
+        lead: copy_data.lead,
+        follower: copy_data.follower,
+        lead_position_nonce: copy_data.lead_position_nonce,
+        follower_position_nonce: copy_link.follower_position_nonce,
+        price: mark_price,
+        collateral: 0,
+        pnl: final_pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        profit_share,
+    }))?;
+    
+    msg!(
+        "Closed follower copy of lead position {}, pnl: {}, profit share: {}",
+        copy_data.lead_position_nonce,
+        final_pnl,
+        profit_share
+    );
+    
+    Ok(())
+}
@@ -0,0 +1,295 @@
+//! Recurring DCA schedules that open a new position from an escrowed budget every interval.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn load_dca_schedule(
+    schedule_account: &AccountInfo,
+    owner: &Pubkey,
+    schedule_id: u64,
+    program_id: &Pubkey,
+) -> Result<DcaScheduleAccount, ProgramError> {
+    let (schedule_pda, _schedule_bump) = find_dca_schedule_address(owner, schedule_id, program_id);
+    
+    assert_key_eq(schedule_account, &schedule_pda)?;
+    
+    assert_owned_by(schedule_account, program_id)?;
+    
+    deserialize_padded::<DcaScheduleAccount>(&schedule_account.data.borrow())
+}
+
+/// Budget one execution needs: the interval amount plus the new position account's rent.
+fn dca_execution_cost(schedule: &DcaScheduleAccount) -> Result<u64, ProgramError> {
+    Ok(schedule
+        .amount_per_interval
+        .saturating_add(Rent::get()?.minimum_balance(PositionAccount::LEN)))
+}
+
+/// Creates a DCA schedule and escrows its whole budget; the first execution is due immediately.
+pub(crate) fn process_create_dca_schedule(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    create_data: CreateDcaScheduleData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let schedule_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    if create_data.direction != POSITION_LONG && create_data.direction != POSITION_SHORT {
+        msg!("Invalid direction");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if create_data.interval_slots == 0 {
+        msg!("DCA interval must be at least one slot");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let leverage = create_data.leverage.clamp(1, MAXIMUM_LEVERAGE);
+    if create_data.amount_per_interval.saturating_mul(leverage as u64) < MIN_POSITION_SIZE_LAMPORTS {
+        msg!("DCA amount too small");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    verify_market_address(market_account, &create_data.market_mint, program_id)?;
+    
+    check_leverage_tier(
+        &load_tradable_market_state(market_account, program_id)?,
+        create_data.amount_per_interval.saturating_mul(leverage as u64),
+        leverage,
+    )?;
+    
+    let (schedule_pda, schedule_bump) =
+        find_dca_schedule_address(owner_account.key, create_data.schedule_id, program_id);
+    
+    assert_key_eq(schedule_account, &schedule_pda)?;
+    
+    if !schedule_account.data_is_empty() {
+        msg!("DCA schedule {} already exists", create_data.schedule_id);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let schedule = DcaScheduleAccount {
+        owner: *owner_account.key,
+        market_mint: create_data.market_mint,
+        schedule_id: create_data.schedule_id,
+        direction: create_data.direction,
+        leverage,
+        amount_per_interval: create_data.amount_per_interval,
+        interval_slots: create_data.interval_slots,
+        remaining_budget: create_data.budget,
+        next_execution_slot: Clock::get()?.slot,
+        executions: 0,
+    };
+    
+    if create_data.budget < dca_execution_cost(&schedule)? {
+        msg!("Budget does not cover a single execution");
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            owner_account.key,
+            schedule_account.key,
+            Rent::get()?
+                .minimum_balance(DcaScheduleAccount::LEN)
+                .saturating_add(create_data.budget),
+            DcaScheduleAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            owner_account.clone(),
+            schedule_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_dca",
+            owner_account.key.as_ref(),
+            &create_data.schedule_id.to_le_bytes(),
+            &[schedule_bump],
+        ]],
+    )?;
+    
+    schedule.serialize(&mut *schedule_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::DcaScheduleCreated(DcaEvent {
+        owner: schedule.owner,
+        schedule_id: schedule.schedule_id,
+        market_mint: schedule.market_mint,
+        position_nonce: 0,
+        price: 0,
+        amount: 0,
+        remaining_budget: schedule.remaining_budget,
+    }))?;
+    
+    msg!(
+        "DCA schedule {}: {} lamports every {} slots, budget {}",
+        schedule.schedule_id,
+        schedule.amount_per_interval,
+        schedule.interval_slots,
+        schedule.remaining_budget
+    );
+    
+    Ok(())
+}
+
+/// Stops a DCA schedule, refunding the unspent budget and the account's rent.
+pub(crate) fn process_cancel_dca_schedule(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cancel_data: DcaScheduleData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let schedule_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let schedule = load_dca_schedule(schedule_account, owner_account.key, cancel_data.schedule_id, program_id)?;
+    
+    move_lamports(schedule_account, owner_account, schedule.remaining_budget, TransferReason::OrderRefund)?;
+    refund_order_account(schedule_account, owner_account)?;
+    
+    emit_event(&DexEvent::DcaScheduleCancelled(DcaEvent {
+        owner: schedule.owner,
+        schedule_id: schedule.schedule_id,
+        market_mint: schedule.market_mint,
+        position_nonce: 0,
+        price: 0,
+        amount: schedule.remaining_budget,
+        remaining_budget: 0,
+    }))?;
+    
+    msg!("DCA schedule {} cancelled after {} executions", schedule.schedule_id, schedule.executions);
+    
+    Ok(())
+}
+
+/// Permissionless crank that opens the schedule's next position at the spread-adjusted oracle
+/// price under a freshly claimed nonce. The keeper fronts the account rent and is reimbursed
+/// from the budget; once the budget cannot fund another execution the schedule closes itself
+/// and refunds the remainder to the owner.
+pub(crate) fn process_crank_dca(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    crank_data: DcaScheduleData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let schedule_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let nonce_counter_account = next_account_info(accounts_iter)?;
+    let position_index_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(keeper_account)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut schedule = load_dca_schedule(schedule_account, owner_account.key, crank_data.schedule_id, program_id)?;
+    let current_slot = Clock::get()?.slot;
+    
+    if current_slot < schedule.next_execution_slot {
+        msg!("Next DCA execution is due at slot {}", schedule.next_execution_slot);
+        return Err(DexError::DcaNotDue.into());
+    }
+    
+    if schedule.remaining_budget < dca_execution_cost(&schedule)? {
+        msg!("DCA budget exhausted, cancel the schedule to recover the remainder");
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    let symbol_registry = load_symbol_registry(symbol_registry_account, &schedule.market_mint, program_id)?;
+    verify_market_address(market_account, &schedule.market_mint, program_id)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let mark_price = load_price_feed(price_feed_account, &schedule.market_mint, &config, program_id)?.effective(schedule.direction, true);
+    let keeper_lamports = keeper_account.lamports();
+    
+    let position = open_funded_position(
+        accounts,
+        schedule_account,
+        keeper_account,
+        owner_account,
+        position_account,
+        nonce_counter_account,
+        position_index_account,
+        market_account,
+        dex_fees_account,
+        system_program,
+        &mut market_state,
+        &config,
+        &schedule.market_mint,
+        symbol_registry.market_symbol,
+        schedule.direction,
+        schedule.leverage,
+        schedule.amount_per_interval,
+        mark_price,
+        TransferReason::DcaFill,
+        program_id,
+    )?;
+    save_market_state(market_account, &market_state)?;
+    
+    let reimbursement = keeper_lamports.saturating_sub(keeper_account.lamports());
+    move_lamports(schedule_account, keeper_account, reimbursement, TransferReason::KeeperReimbursement)?;
+    
+    schedule.remaining_budget = schedule
+        .remaining_budget
+        .saturating_sub(schedule.amount_per_interval)
+        .saturating_sub(reimbursement);
+    schedule.next_execution_slot = current_slot.saturating_add(schedule.interval_slots);
+    schedule.executions = schedule.executions.saturating_add(1);
+    
+    emit_event(&DexEvent::DcaExecuted(DcaEvent {
+        owner: schedule.owner,
+        schedule_id: schedule.schedule_id,
+        market_mint: schedule.market_mint,
+        position_nonce: position.position_nonce,
+        price: position.entry_price,
+        amount: schedule.amount_per_interval,
+        remaining_budget: schedule.remaining_budget,
+    }))?;
+    
+    msg!(
+        "DCA schedule {} opened position {} at {}",
+        schedule.schedule_id,
+        position.position_nonce,
+        position.entry_price
+    );
+    
+    if schedule.remaining_budget < dca_execution_cost(&schedule)? {
+        move_lamports(schedule_account, owner_account, schedule.remaining_budget, TransferReason::OrderRefund)?;
+        refund_order_account(schedule_account, owner_account)?;
+        
+        msg!("DCA schedule {} finished after {} executions", schedule.schedule_id, schedule.executions);
+        
+        return Ok(());
+    }
+    
+    schedule.serialize(&mut *schedule_account.data.borrow_mut())?;
+    
+    Ok(())
+}
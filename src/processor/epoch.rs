@@ -0,0 +1,142 @@
+//! Epoch rollover: freezes a market's running statistics into a write-once snapshot PDA per
+//! epoch, so analytics and rewards read a stable record instead of replaying history.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Lamports the market's insurance fund holds above rent; zero until it is initialized.
+pub(crate) fn market_insurance_balance(
+    insurance_account: &AccountInfo,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let (insurance_pda, _insurance_bump) = find_market_insurance_address(market_mint, program_id);
+    
+    assert_key_eq(insurance_account, &insurance_pda)?;
+    
+    if insurance_account.owner != program_id {
+        return Ok(0);
+    }
+    
+    Ok(insurance_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(insurance_account.data_len())))
+}
+
+/// Permissionless crank, once per epoch per market, that snapshots the market's lifetime volume
+/// and fees, open interest, insurance balance and the epoch's fee legs into the PDA for the epoch
+/// just ended, then resets the epoch fee counters and emits an `EpochSummary`. A crank that skips
+/// epochs folds them into the next snapshot; the keeper pays the snapshot's rent.
+pub(crate) fn process_rollover_epoch(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    rollover_data: EpochRolloverData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let snapshot_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let insurance_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(keeper_account)?;
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        keeper_account,
+        system_program,
+        &rollover_data.market_mint,
+        program_id,
+    )?;
+    let clock = Clock::get()?;
+    
+    if clock.epoch <= market_state.stats_epoch {
+        msg!("Statistics already rolled over in epoch {}", market_state.stats_epoch);
+        return Err(DexError::EpochNotElapsed.into());
+    }
+    
+    let epoch = clock.epoch.saturating_sub(1);
+    let (snapshot_pda, snapshot_bump) = find_epoch_snapshot_address(&rollover_data.market_mint, epoch, program_id);
+    
+    assert_key_eq(snapshot_account, &snapshot_pda)?;
+    
+    if !snapshot_account.data_is_empty() {
+        msg!("Epoch {} already has a snapshot", epoch);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let snapshot = EpochSnapshotAccount {
+        market_mint: rollover_data.market_mint,
+        epoch,
+        snapshot_slot: clock.slot,
+        cumulative_volume: market_state.cumulative_volume,
+        cumulative_fees: market_state.cumulative_fees,
+        open_interest_long: market_state.open_interest_long,
+        open_interest_short: market_state.open_interest_short,
+        insurance_balance: market_insurance_balance(insurance_account, &rollover_data.market_mint, program_id)?,
+        epoch_protocol_fees: market_state.epoch_protocol_fees,
+        epoch_lp_fees: market_state.epoch_lp_fees,
+    };
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            keeper_account.key,
+            snapshot_account.key,
+            Rent::get()?.minimum_balance(EpochSnapshotAccount::LEN),
+            EpochSnapshotAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            keeper_account.clone(),
+            snapshot_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_epoch",
+            rollover_data.market_mint.as_ref(),
+            &epoch.to_le_bytes(),
+            &[snapshot_bump],
+        ]],
+    )?;
+    
+    snapshot.serialize(&mut *snapshot_account.data.borrow_mut())?;
+    
+    market_state.stats_epoch = clock.epoch;
+    market_state.epoch_protocol_fees = 0;
+    market_state.epoch_lp_fees = 0;
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::EpochSummary(EpochSummaryEvent {
+        market_mint: rollover_data.market_mint,
+        epoch,
+        protocol_fees: snapshot.epoch_protocol_fees,
+        lp_fees: snapshot.epoch_lp_fees,
+        cumulative_volume: snapshot.cumulative_volume,
+        cumulative_fees: snapshot.cumulative_fees,
+    }))?;
+    
+    msg!(
+        "Epoch {} snapshot for {}: volume {}, fees {}, open interest {}/{}, insurance {}",
+        epoch,
+        rollover_data.market_mint,
+        snapshot.cumulative_volume,
+        snapshot.cumulative_fees,
+        snapshot.open_interest_long,
+        snapshot.open_interest_short,
+        snapshot.insurance_balance
+    );
+    
+    Ok(())
+}
@@ -0,0 +1,109 @@
+//! Protocol fee vault: fees accrue in a program-owned PDA during user transactions and the
+//! treasurer pulls them out to the authority fees wallet separately.
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar::{rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Checks that `fee_vault_account` is the initialized fee vault PDA, the default sink for
+/// protocol fees.
+pub(crate) fn assert_fee_vault(fee_vault_account: &AccountInfo, program_id: &Pubkey) -> ProgramResult {
+    let (fee_vault_pda, _fee_vault_bump) = find_fee_vault_address(program_id);
+    
+    assert_key_eq(fee_vault_account, &fee_vault_pda)?;
+    
+    if fee_vault_account.owner != program_id {
+        msg!("Fee vault not initialized");
+        return Err(ProgramError::UninitializedAccount);
+    }
+    
+    Ok(())
+}
+
+/// Creates the fee vault with its rent-exempt reserve. Anyone may pay for it, and repeating it
+/// once the vault exists succeeds without changes.
+pub(crate) fn process_init_fee_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let fee_vault_account = next_account_info(accounts_iter)?;
+    let payer_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(payer_account)?;
+    
+    let (fee_vault_pda, fee_vault_bump) = find_fee_vault_address(program_id);
+    
+    assert_key_eq(fee_vault_account, &fee_vault_pda)?;
+    
+    if fee_vault_account.owner == program_id {
+        msg!("Fee vault already initialized");
+        return Ok(());
+    }
+    
+    create_pda_account(
+        fee_vault_account,
+        payer_account,
+        system_program,
+        Rent::get()?.minimum_balance(0),
+        0,
+        &[b"uranus_fee_vault", &[fee_vault_bump]],
+        program_id,
+    )?;
+    
+    msg!("Fee vault initialized: {}", fee_vault_account.key);
+    
+    Ok(())
+}
+
+/// Pays accrued fees above the vault's rent reserve to the authority fees wallet (treasurer
+/// role). A zero `amount` withdraws everything available.
+pub(crate) fn process_withdraw_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    withdraw_data: WithdrawFeesData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let fee_vault_account = next_account_info(accounts_iter)?;
+    let treasurer_account = next_account_info(accounts_iter)?;
+    let fees_wallet_account = next_account_info(accounts_iter)?;
+    
+    let authority = load_authority(accounts, program_id)?;
+    
+    authority.check_role(treasurer_account, OPERATOR_ROLE_TREASURER)?;
+    
+    assert_key_eq(fees_wallet_account, &authority.fees_wallet)?;
+    assert_fee_vault(fee_vault_account, program_id)?;
+    
+    let available = fee_vault_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(fee_vault_account.data_len()));
+    let amount = if withdraw_data.amount == 0 { available } else { withdraw_data.amount };
+    
+    if amount > available {
+        msg!("Fee vault holds {} lamports above rent, {} requested", available, amount);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    move_lamports(fee_vault_account, fees_wallet_account, amount, TransferReason::FeeWithdrawal)?;
+    
+    emit_event(&DexEvent::FeesWithdrawn(FeesWithdrawnEvent {
+        fees_wallet: *fees_wallet_account.key,
+        amount,
+        remaining: available - amount,
+    }))?;
+    
+    msg!("Fees withdrawn: {} lamports to {}", amount, fees_wallet_account.key);
+    
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! Account guards shared by every handler, so ownership, signer, writability and address checks
+//! fail the same way wherever they are made.
+
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use super::*;
+
+/// Rejects an account not owned by `owner`.
+pub(crate) fn assert_owned_by(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner != owner {
+        msg!("Account {} is not owned by {}", account.key, owner);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    Ok(())
+}
+
+/// Rejects an account that did not sign the transaction.
+pub(crate) fn assert_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer {
+        msg!("Account {} must sign", account.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    Ok(())
+}
+
+/// Rejects an account the handler is about to modify but that was passed read-only.
+pub(crate) fn assert_writable(account: &AccountInfo) -> ProgramResult {
+    if !account.is_writable {
+        msg!("Account {} must be writable", account.key);
+        return Err(DexError::AccountNotWritable.into());
+    }
+    
+    Ok(())
+}
+
+/// Rejects an account whose address is not `expected`, typically a derived PDA.
+pub(crate) fn assert_key_eq(account: &AccountInfo, expected: &Pubkey) -> ProgramResult {
+    if account.key != expected {
+        msg!("Account {} does not match expected {}", account.key, expected);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    Ok(())
+}
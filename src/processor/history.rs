@@ -0,0 +1,100 @@
+//! Opt-in per-owner settlement history, kept in a fixed-size ring buffer PDA.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Creates the owner's history ring buffer; settlements append to it from then on whenever it
+/// is passed among their accounts. The owner pays the rent.
+pub(crate) fn process_init_position_history(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let history_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let (history_pda, history_bump) = find_position_history_address(owner_account.key, program_id);
+    
+    assert_key_eq(history_account, &history_pda)?;
+    
+    if !history_account.data_is_empty() {
+        msg!("Position history already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            owner_account.key,
+            history_account.key,
+            Rent::get()?.minimum_balance(PositionHistoryAccount::LEN),
+            PositionHistoryAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            owner_account.clone(),
+            history_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_history",
+            owner_account.key.as_ref(),
+            &[history_bump],
+        ]],
+    )?;
+    
+    let history = PositionHistoryAccount {
+        owner: *owner_account.key,
+        ..PositionHistoryAccount::default()
+    };
+    history.serialize(&mut &mut history_account.data.borrow_mut()[..])?;
+    
+    msg!("Position history initialized for {}", owner_account.key);
+    
+    Ok(())
+}
+
+/// Appends a settlement of `position` to its owner's history when the history PDA is among
+/// `accounts`; owners who never opted in are skipped.
+pub(crate) fn record_settlement_history(
+    accounts: &[AccountInfo],
+    position: &PositionAccount,
+    pnl: i128,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (history_pda, _history_bump) = find_position_history_address(&position.owner, program_id);
+    
+    let Some(history_account) = accounts.iter().find(|account| account.key == &history_pda) else {
+        return Ok(());
+    };
+    
+    if history_account.owner != program_id || history_account.data_is_empty() {
+        return Ok(());
+    }
+    
+    let mut history = deserialize_padded::<PositionHistoryAccount>(&history_account.data.borrow())?;
+    
+    history.push(HistoryRecord {
+        market_mint: position.market_mint,
+        direction: position.direction,
+        pnl: pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    history.serialize(&mut &mut history_account.data.borrow_mut()[..])?;
+    
+    Ok(())
+}
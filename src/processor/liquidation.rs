@@ -0,0 +1,1110 @@
+//! Margin calls, the liquidation queue, liquidation auctions, position takeovers and the
+//! insurance fund.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::{invoke, invoke_signed},
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Permissionless crank warning an owner whose position's health, computed as `LIQUIDATE` does,
+/// is below the config `margin_call_threshold_bps` but still above liquidation. The warning slot
+/// is kept on the position so each episode is announced once; a later call after the position
+/// recovers clears it.
+pub(crate) fn process_margin_call(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    margin_call_data: MarginCallData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let margin_account = next_account_info(accounts_iter).ok();
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, margin_call_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if config.margin_call_threshold_bps == 0 {
+        msg!("Margin calls are disabled");
+        return Err(DexError::MarginCallNotDue.into());
+    }
+    
+    if !position.is_open() || !position.is_confirmed() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    // The crank pays no rent, so older layouts must first be grown with `RESIZE_POSITION`.
+    if position_account.data_len() < PositionAccount::LEN {
+        msg!("Position account predates margin calls, resize it first");
+        return Err(DexError::AccountNeedsMigration.into());
+    }
+    
+    position.upgrade_version();
+    
+    let mark_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?.effective(position.direction, false);
+    let shared_margin = match cross_margin_account(&position, margin_account, program_id)? {
+        Some(margin_account) => margin_available(margin_account)?,
+        None => 0,
+    };
+    let health = position
+        .health(mark_price, config.maintenance_margin_bps, shared_margin)?
+        .net_of(position_accrued_charges(accounts, &position, program_id)?);
+    let health_bps = health.health_bps();
+    
+    if health_bps >= config.margin_call_threshold_bps as u64 {
+        if position.margin_call_slot == 0 {
+            msg!("Position {} health {} bps, no margin call", position.position_nonce, health_bps);
+            return Err(DexError::MarginCallNotDue.into());
+        }
+        
+        position.margin_call_slot = 0;
+        save_position_account(position_account, &position)?;
+        
+        msg!("Position {} recovered, margin call cleared", position.position_nonce);
+        
+        return Ok(());
+    }
+    
+    if health.equity < health.maintenance as i128 {
+        msg!("Position {} is liquidatable", position.position_nonce);
+        return Err(DexError::MarginCallNotDue.into());
+    }
+    
+    if position.margin_call_slot != 0 {
+        msg!("Position {} already called at slot {}", position.position_nonce, position.margin_call_slot);
+        return Err(DexError::MarginCallNotDue.into());
+    }
+    
+    let slot = Clock::get()?.slot;
+    position.margin_call_slot = slot;
+    save_position_account(position_account, &position)?;
+    
+    emit_event(&DexEvent::MarginCall(MarginCallEvent {
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        market_mint: position.market_mint,
+        mark_price,
+        equity: health.equity.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        maintenance_requirement: health.maintenance.min(u64::MAX as u128) as u64,
+        health_bps,
+        slot,
+    }))?;
+    
+    msg!("Margin call: position {} at {} bps health", position.position_nonce, health_bps);
+    
+    Ok(())
+}
+
+/// `market_mint`'s liquidation queue PDA when it is among `accounts`, created or not.
+pub(crate) fn find_liquidation_queue_account<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Option<&'a AccountInfo<'b>> {
+    let (queue_pda, _queue_bump) = find_liquidation_queue_address(market_mint, program_id);
+    
+    accounts.iter().find(|account| account.key == &queue_pda)
+}
+
+/// An uncreated queue reads as empty.
+fn load_liquidation_queue(
+    queue_account: &AccountInfo,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<LiquidationQueueAccount, ProgramError> {
+    if queue_account.owner != program_id || queue_account.data_is_empty() {
+        return Ok(LiquidationQueueAccount {
+            market_mint: *market_mint,
+            entries: Vec::new(),
+        });
+    }
+    
+    deserialize_padded::<LiquidationQueueAccount>(&queue_account.data.borrow())
+}
+
+fn save_liquidation_queue(queue_account: &AccountInfo, queue: &LiquidationQueueAccount) -> ProgramResult {
+    zero_account_data(queue_account)?;
+    queue.serialize(&mut *queue_account.data.borrow_mut())?;
+    
+    Ok(())
+}
+
+/// Drops a liquidated or recovered position from its market's queue, if it is queued.
+fn dequeue_liquidation(
+    queue_account: Option<&AccountInfo>,
+    position: &PositionAccount,
+    keeper: &Pubkey,
+    health_bps: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let Some(queue_account) = queue_account.filter(|account| account.owner == program_id) else {
+        return Ok(());
+    };
+    
+    let mut queue = load_liquidation_queue(queue_account, &position.market_mint, program_id)?;
+    
+    if !queue.remove(&position.owner, position.position_nonce) {
+        return Ok(());
+    }
+    
+    save_liquidation_queue(queue_account, &queue)?;
+    
+    emit_event(&DexEvent::LiquidationDequeued(LiquidationQueueEvent {
+        market_mint: position.market_mint,
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        keeper: *keeper,
+        health_bps,
+        queued: false,
+        queue_length: queue.entries.len() as u32,
+    }))
+}
+
+/// Permissionless: a keeper registers an underwater position in its market's liquidation
+/// queue, or refreshes its health there. The same call drops a queued position that has since
+/// recovered or closed.
+pub(crate) fn process_queue_liquidation(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    queue_data: QueueLiquidationData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let queue_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let margin_account = next_account_info(accounts_iter).ok();
+    
+    assert_signer(keeper_account)?;
+    
+    let (position_pda, _position_bump) = find_position_address(owner_account.key, queue_data.position_nonce, program_id);
+    
+    assert_key_eq(position_account, &position_pda)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    // A closed position leaves no market to look up, so its entry is found by owner and nonce.
+    if position_account.owner != program_id || position_account.data_is_empty() {
+        if queue_account.owner != program_id || queue_account.data_is_empty() {
+            return Err(DexError::PositionNotLiquidatable.into());
+        }
+        
+        let mut queue = deserialize_padded::<LiquidationQueueAccount>(&queue_account.data.borrow())?;
+        let (queue_pda, _queue_bump) = find_liquidation_queue_address(&queue.market_mint, program_id);
+        
+        if queue_account.key != &queue_pda || !queue.remove(owner_account.key, queue_data.position_nonce) {
+            return Err(DexError::PositionNotLiquidatable.into());
+        }
+        
+        save_liquidation_queue(queue_account, &queue)?;
+        
+        emit_event(&DexEvent::LiquidationDequeued(LiquidationQueueEvent {
+            market_mint: queue.market_mint,
+            owner: *owner_account.key,
+            position_nonce: queue_data.position_nonce,
+            keeper: *keeper_account.key,
+            health_bps: 0,
+            queued: false,
+            queue_length: queue.entries.len() as u32,
+        }))?;
+        
+        msg!("Closed position {} dropped from the liquidation queue", queue_data.position_nonce);
+        
+        return Ok(());
+    }
+    
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, queue_data.position_nonce)?;
+    
+    let (queue_pda, queue_bump) = find_liquidation_queue_address(&position.market_mint, program_id);
+    
+    assert_key_eq(queue_account, &queue_pda)?;
+    
+    let mark_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?.effective(position.direction, false);
+    let shared_margin = match cross_margin_account(&position, margin_account, program_id)? {
+        Some(margin_account) => margin_available(margin_account)?,
+        None => 0,
+    };
+    let health = position
+        .health(mark_price, config.maintenance_margin_bps, shared_margin)?
+        .net_of(position_accrued_charges(accounts, &position, program_id)?);
+    let health_bps = health.health_bps();
+    
+    if !position.is_open() || health.equity >= health.maintenance as i128 {
+        if load_liquidation_queue(queue_account, &position.market_mint, program_id)?
+            .entry_index(&position.owner, position.position_nonce)
+            .is_none()
+        {
+            return Err(DexError::PositionNotLiquidatable.into());
+        }
+        
+        dequeue_liquidation(Some(queue_account), &position, keeper_account.key, health_bps, program_id)?;
+        
+        msg!("Position {} no longer liquidatable, dropped from the queue", position.position_nonce);
+        
+        return Ok(());
+    }
+    
+    if queue_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                keeper_account.key,
+                queue_account.key,
+                Rent::get()?.minimum_balance(LiquidationQueueAccount::LEN),
+                LiquidationQueueAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                keeper_account.clone(),
+                queue_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"uranus_liquidation_queue",
+                position.market_mint.as_ref(),
+                &[queue_bump],
+            ]],
+        )?;
+    } else if queue_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let mut queue = load_liquidation_queue(queue_account, &position.market_mint, program_id)?;
+    
+    if !queue.upsert(LiquidationQueueEntry {
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        health_bps,
+        notional: health.notional.min(u64::MAX as u128) as u64,
+        queued_slot: Clock::get()?.slot,
+    }) {
+        msg!("Liquidation queue full of riskier positions");
+        return Err(DexError::LiquidationQueueFull.into());
+    }
+    
+    save_liquidation_queue(queue_account, &queue)?;
+    
+    emit_event(&DexEvent::LiquidationQueued(LiquidationQueueEvent {
+        market_mint: position.market_mint,
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        keeper: *keeper_account.key,
+        health_bps,
+        queued: true,
+        queue_length: queue.entries.len() as u32,
+    }))?;
+    
+    msg!(
+        "Position {} queued for liquidation at {} bps health, {} queued",
+        position.position_nonce,
+        health_bps,
+        queue.entries.len()
+    );
+    
+    Ok(())
+}
+
+pub(crate) fn process_liquidate(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    liquidate_data: LiquidateData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let liquidator_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let insurance_fund_account = next_account_info(accounts_iter)?;
+    let margin_account = next_account_info(accounts_iter).ok();
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let (insurance_fund_pda, _insurance_bump) = find_insurance_fund_address(program_id);
+    
+    if insurance_fund_account.key != &insurance_fund_pda || insurance_fund_account.owner != program_id {
+        msg!("Invalid or uninitialized insurance fund account");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let config = load_config(config_account, program_id)?;
+    let mut position = try_load_position_account(position_account)?;
+    
+    let dex_liquidation = liquidator_account.is_signer
+        && find_authority(accounts, program_id)?
+            .is_some_and(|authority| authority.has_role(liquidator_account.key, OPERATOR_ROLE_LIQUIDATOR));
+    let mark_price = if dex_liquidation {
+        let price_feed = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?;
+        check_price_band(liquidate_data.mark_price, price_feed.price, config.price_band_bps)?;
+        
+        liquidate_data.mark_price
+    } else if config.feature_enabled(FEATURE_PERMISSIONLESS_LIQUIDATION)
+        || trustless_paths_enabled(&config, accounts, program_id, Clock::get()?.slot)?
+    {
+        load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?.effective(position.direction, false)
+    } else {
+        return Err(ProgramError::MissingRequiredSignature);
+    };
+    
+    position.validate(owner_account.key, liquidate_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    // A market's own sub-fund, when it exists, takes that market's insurance share.
+    let insurance_fund_account = find_market_insurance_account(accounts, &position.market_mint, program_id)
+        .unwrap_or(insurance_fund_account);
+    
+    if !position.is_open() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    assert_owned_by(market_account, program_id)?;
+    
+    // Keepers must pass the market's queue, created or not, so its order cannot be skipped.
+    let queue_account = find_liquidation_queue_account(accounts, &position.market_mint, program_id);
+    
+    if queue_account.is_none() && !dex_liquidation {
+        msg!("Liquidation queue account missing");
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    
+    let cross_margin = cross_margin_account(&position, margin_account, program_id)?;
+    let shared_margin = match cross_margin {
+        Some(margin_account) => margin_available(margin_account)?,
+        None => 0,
+    };
+    let health = position
+        .health(mark_price, config.maintenance_margin_bps, shared_margin)?
+        .net_of(position_accrued_charges(accounts, &position, program_id)?);
+    let pnl = health.pnl;
+    let equity = health.equity;
+    let maintenance = health.maintenance;
+    
+    msg!("Equity: {}, maintenance requirement: {}", equity, maintenance);
+    
+    let current_slot = Clock::get()?.slot;
+    
+    if equity >= maintenance as i128 {
+        if position.liquidation_auction_slot == 0 {
+            return Err(DexError::PositionNotLiquidatable.into());
+        }
+        
+        position.liquidation_auction_slot = 0;
+        save_position_account(position_account, &position)?;
+        dequeue_liquidation(queue_account, &position, liquidator_account.key, health.health_bps(), program_id)?;
+        
+        msg!("Position {} recovered, liquidation auction cancelled", position.position_nonce);
+        
+        return Ok(());
+    }
+    
+    // The first call only opens the auction; the discount then ramps with every slot until a
+    // liquidator decides it is worth taking.
+    if position.liquidation_auction_slot == 0 {
+        position.liquidation_auction_slot = current_slot;
+        save_position_account(position_account, &position)?;
+        
+        msg!("Liquidation auction started for position {} at slot {}", position.position_nonce, current_slot);
+        
+        return Ok(());
+    }
+    
+    if let Some(queue_account) = queue_account.filter(|account| account.owner == program_id) {
+        let queue = load_liquidation_queue(queue_account, &position.market_mint, program_id)?;
+        
+        // A position nobody queued waits behind every queued one; `QUEUE_LIQUIDATION` slots it in.
+        match queue.entry_index(&position.owner, position.position_nonce) {
+            Some(index) if index != 0 => {
+                msg!("Position {} is #{} in the liquidation queue", position.position_nonce, index + 1);
+                return Err(DexError::LiquidationOutOfOrder.into());
+            }
+            None if !queue.entries.is_empty() => {
+                msg!(
+                    "Position {} is not queued while {} queued positions wait, queue it first",
+                    position.position_nonce,
+                    queue.entries.len()
+                );
+                return Err(DexError::LiquidationOutOfOrder.into());
+            }
+            _ => {}
+        }
+    }
+    
+    dequeue_liquidation(queue_account, &position, liquidator_account.key, health.health_bps(), program_id)?;
+    
+    let discount_bps = auction_discount_bps(
+        &config,
+        current_slot.saturating_sub(position.liquidation_auction_slot),
+    );
+    
+    msg!("Auction discount: {} bps", discount_bps);
+    
+    // Closing a fraction f realizes f * pnl but leaves equity unchanged, so the
+    // remaining notional (1 - f) * N is healthy once f >= 1 - equity / maintenance.
+    let close_bps = if equity <= 0 || maintenance == 0 {
+        BASIS_POINTS_DIVISOR as u128
+    } else {
+        (BASIS_POINTS_DIVISOR as u128)
+            .saturating_sub((equity as u128).saturating_mul(BASIS_POINTS_DIVISOR as u128) / maintenance)
+    };
+    
+    let position_lamports = position_account.lamports();
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    
+    if close_bps >= BASIS_POINTS_DIVISOR as u128 {
+        market_state.remove_open_interest(position.direction, position.position_size);
+        
+        let loss = if pnl < 0 {
+            settle_loss(
+                &position,
+                position_account,
+                market_account,
+                cross_margin,
+                Some(insurance_fund_account),
+                &mut market_state,
+                pnl.unsigned_abs().min(u64::MAX as u128) as u64,
+                TransferReason::Liquidation,
+            )?
+            .from_collateral
+        } else {
+            0
+        };
+        let reward = ((position.position_size as u128)
+            .saturating_mul(discount_bps as u128)
+            / BASIS_POINTS_DIVISOR as u128)
+            .min(position_lamports.saturating_sub(loss) as u128) as u64;
+        
+        move_lamports(position_account, liquidator_account, reward, TransferReason::LiquidationReward)?;
+        
+        let (keeper_fee, insurance_fee) = charge_liquidation_fee(
+            accounts,
+            position_account,
+            liquidator_account,
+            insurance_fund_account,
+            market_account,
+            &position.market_mint,
+            &mut market_state,
+            position.position_size,
+            position_account.lamports(),
+            &config,
+        )?;
+        save_market_state(market_account, &market_state)?;
+        
+        let returned = position_account.lamports();
+        move_lamports(position_account, owner_account, returned, TransferReason::CollateralReturn)?;
+        
+        zero_account_data(position_account)?;
+        remove_from_position_index(
+            find_position_index_account(accounts, &position.owner, program_id),
+            &position.owner,
+            position.position_nonce,
+            program_id,
+        )?;
+        record_settlement_history(accounts, &position, pnl, program_id)?;
+        
+        emit_event(&DexEvent::Liquidation(LiquidationEvent {
+            owner: position.owner,
+            position_nonce: position.position_nonce,
+            market_mint: position.market_mint,
+            liquidator: *liquidator_account.key,
+            mark_price,
+            closed_notional: position.position_size,
+            auction_reward: reward,
+            keeper_fee,
+            insurance_fee,
+        }))?;
+        
+        msg!(
+            "Position {} fully liquidated, loss: {}, reward: {}, returned: {}",
+            position.position_nonce,
+            loss,
+            reward,
+            returned
+        );
+        
+        return Ok(());
+    }
+    
+    let realized = pnl.saturating_mul(close_bps as i128) / BASIS_POINTS_DIVISOR as i128;
+    let closed_size = (position.position_size as u128)
+        .saturating_mul(close_bps)
+        .div_ceil(BASIS_POINTS_DIVISOR as u128) as u64;
+    
+    if realized < 0 {
+        let loss = (realized.unsigned_abs().min(position_lamports as u128)) as u64;
+        
+        move_lamports(position_account, market_account, loss, TransferReason::Liquidation)?;
+        market_state.record_deposit(loss);
+        
+        position.paid_amount = position.paid_amount.saturating_sub(loss);
+    } else if realized > 0 {
+        let gain = (realized as u128).min(market_account.lamports() as u128) as u64;
+        
+        move_lamports(market_account, position_account, gain, TransferReason::Profit)?;
+        market_state.record_payout(gain);
+        
+        position.paid_amount = position.paid_amount.saturating_add(gain);
+    }
+    
+    let reward = ((closed_size as u128)
+        .saturating_mul(discount_bps as u128)
+        / BASIS_POINTS_DIVISOR as u128)
+        .min(position.paid_amount as u128) as u64;
+    
+    move_lamports(position_account, liquidator_account, reward, TransferReason::LiquidationReward)?;
+    position.paid_amount = position.paid_amount.saturating_sub(reward);
+    
+    let (keeper_fee, insurance_fee) = charge_liquidation_fee(
+        accounts,
+        position_account,
+        liquidator_account,
+        insurance_fund_account,
+        market_account,
+        &position.market_mint,
+        &mut market_state,
+        closed_size,
+        position.paid_amount,
+        &config,
+    )?;
+    position.paid_amount = position
+        .paid_amount
+        .saturating_sub(keeper_fee)
+        .saturating_sub(insurance_fee);
+    
+    market_state.remove_open_interest(position.direction, closed_size);
+    save_market_state(market_account, &market_state)?;
+    
+    emit_event(&DexEvent::Liquidation(LiquidationEvent {
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        market_mint: position.market_mint,
+        liquidator: *liquidator_account.key,
+        mark_price,
+        closed_notional: closed_size,
+        auction_reward: reward,
+        keeper_fee,
+        insurance_fee,
+    }))?;
+    
+    position.position_size = position.position_size.saturating_sub(closed_size);
+    position.liquidation_auction_slot = 0;
+    position.refresh_liquidation_price(config.maintenance_margin_bps);
+    save_position_account(position_account, &position)?;
+    
+    msg!("Position {} partially liquidated: {} bps closed", position.position_nonce, close_bps);
+    msg!("Realized pnl: {}, reward: {}, remaining size: {}", realized, reward, position.position_size);
+    
+    Ok(())
+}
+
+/// Charges the liquidation fee on `closed_notional`, capped at `available`, and splits it
+/// between the keeper and the insurance fund, the fund's part sharing `lp_fee_share_bps` with
+/// the market's LPs. Returns `(keeper_fee, insurance_fee)`, the latter including the LP leg.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn charge_liquidation_fee(
+    accounts: &[AccountInfo],
+    position_account: &AccountInfo,
+    liquidator_account: &AccountInfo,
+    insurance_fund_account: &AccountInfo,
+    market_account: &AccountInfo,
+    market_mint: &Pubkey,
+    market_state: &mut MarketState,
+    closed_notional: u64,
+    available: u64,
+    config: &GlobalConfig,
+) -> Result<(u64, u64), ProgramError> {
+    let fee = liquidation_fee(closed_notional, config.liquidation_fee_bps, available);
+    let (keeper_fee, insurance_fee) = split_liquidation_fee(fee, config.liquidation_fee_keeper_share_bps);
+    
+    move_lamports(position_account, liquidator_account, keeper_fee, TransferReason::LiquidationFee)?;
+    split_fee(
+        accounts,
+        position_account,
+        insurance_fund_account,
+        market_account,
+        market_mint,
+        market_state,
+        insurance_fee,
+        TransferReason::InsuranceFee,
+        config,
+    )?;
+    
+    Ok((keeper_fee, insurance_fee))
+}
+
+pub(crate) fn process_initialize_insurance_fund(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let insurance_fund_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let (insurance_fund_pda, insurance_bump) = find_insurance_fund_address(program_id);
+    
+    assert_key_eq(insurance_fund_account, &insurance_fund_pda)?;
+    
+    if insurance_fund_account.owner == program_id {
+        msg!("Insurance fund already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let rent = Rent::get()?;
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            admin_account.key,
+            insurance_fund_account.key,
+            rent.minimum_balance(0),
+            0,
+            program_id,
+        ),
+        &[
+            admin_account.clone(),
+            insurance_fund_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[b"uranus_insurance", &[insurance_bump]]],
+    )?;
+    
+    msg!("Insurance fund initialized: {}", insurance_fund_account.key);
+    
+    Ok(())
+}
+
+/// The market's insurance sub-fund if it was passed anywhere in `accounts` and has been created.
+pub(crate) fn find_market_insurance_account<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Option<&'a AccountInfo<'b>> {
+    let (insurance_pda, _insurance_bump) = find_market_insurance_address(market_mint, program_id);
+    
+    accounts
+        .iter()
+        .find(|account| account.key == &insurance_pda && account.owner == program_id)
+}
+
+/// Moves up to `amount` from a market's insurance sub-fund into the market, keeping the fund
+/// rent exempt; returns the amount moved.
+/// Accepts either the global insurance fund or `market_mint`'s insurance sub-fund.
+pub(crate) fn verify_insurance_account(
+    insurance_account: &AccountInfo,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (insurance_fund_pda, _insurance_bump) = find_insurance_fund_address(program_id);
+    let (market_insurance_pda, _market_insurance_bump) = find_market_insurance_address(market_mint, program_id);
+    
+    if (insurance_account.key != &insurance_fund_pda && insurance_account.key != &market_insurance_pda)
+        || insurance_account.owner != program_id
+    {
+        msg!("Invalid or uninitialized insurance fund account");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    Ok(())
+}
+
+pub(crate) fn draw_market_insurance(
+    insurance_account: Option<&AccountInfo>,
+    market_account: &AccountInfo,
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    let Some(insurance_account) = insurance_account else {
+        return Ok(0);
+    };
+    
+    let available = insurance_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(insurance_account.data_len()));
+    let drawn = amount.min(available);
+    
+    move_lamports(insurance_account, market_account, drawn, TransferReason::InsuranceDraw)?;
+    
+    Ok(drawn)
+}
+
+pub(crate) fn process_initialize_market_insurance_fund(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    insurance_data: MarketInsuranceFundData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let insurance_fund_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let (insurance_fund_pda, insurance_bump) = find_market_insurance_address(&insurance_data.market_mint, program_id);
+    
+    assert_key_eq(insurance_fund_account, &insurance_fund_pda)?;
+    
+    if insurance_fund_account.owner == program_id {
+        msg!("Market insurance fund already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let rent = Rent::get()?;
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            admin_account.key,
+            insurance_fund_account.key,
+            rent.minimum_balance(0),
+            0,
+            program_id,
+        ),
+        &[
+            admin_account.clone(),
+            insurance_fund_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[b"uranus_market_insurance", insurance_data.market_mint.as_ref(), &[insurance_bump]]],
+    )?;
+    
+    msg!("Insurance fund for {} initialized: {}", insurance_data.market_mint, insurance_fund_account.key);
+    
+    Ok(())
+}
+
+/// Lets a liquidator assume a position in auction instead of closing it against the market:
+/// the liquidator pays the owner what a full liquidation would have returned, tops up the
+/// collateral, and receives the position under a new PDA of their own.
+pub(crate) fn process_takeover_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    takeover_data: TakeoverData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let liquidator_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let new_position_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let nonce_counter_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(liquidator_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, takeover_data.position_nonce)?;
+    
+    if !position.is_open() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if position.margin_mode != MARGIN_MODE_ISOLATED {
+        msg!("Only isolated positions can be taken over");
+        return Err(DexError::InvalidMarginMode.into());
+    }
+    
+    if position.liquidation_auction_slot == 0 {
+        msg!("No liquidation auction running for position {}", position.position_nonce);
+        return Err(DexError::PositionNotLiquidatable.into());
+    }
+    
+    position.validate_address(position_account.key, program_id)?;
+    
+    let (new_position_pda, new_position_bump) = find_position_address(
+        liquidator_account.key,
+        takeover_data.new_position_nonce,
+        program_id
+    );
+    
+    assert_key_eq(new_position_account, &new_position_pda)?;
+    
+    let mark_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?.effective(position.direction, false);
+    let health = position
+        .health(mark_price, config.maintenance_margin_bps, 0)?
+        .net_of(position_accrued_charges(accounts, &position, program_id)?);
+    
+    if health.equity >= health.maintenance as i128 {
+        return Err(DexError::PositionNotLiquidatable.into());
+    }
+    
+    let claimed_nonce = claim_position_nonce(
+        nonce_counter_account,
+        liquidator_account.key,
+        liquidator_account,
+        system_program,
+        program_id,
+    )?;
+    
+    if claimed_nonce != takeover_data.new_position_nonce {
+        msg!("Expected position nonce {}", claimed_nonce);
+        return Err(DexError::NonceMismatch.into());
+    }
+    
+    let current_slot = Clock::get()?.slot;
+    let discount_bps = auction_discount_bps(
+        &config,
+        current_slot.saturating_sub(position.liquidation_auction_slot),
+    );
+    let reward = (position.position_size as u128)
+        .saturating_mul(discount_bps as u128)
+        / BASIS_POINTS_DIVISOR as u128;
+    let owner_payout = if health.equity > 0 {
+        (health.equity as u128).saturating_sub(reward).min(u64::MAX as u128) as u64
+    } else {
+        0
+    };
+    
+    let position_lamports = position_account.lamports();
+    let data_len = PositionAccount::LEN;
+    let minimum_balance = Rent::get()?.minimum_balance(data_len);
+    
+    let mut new_position = PositionAccount {
+        owner: *liquidator_account.key,
+        position_nonce: takeover_data.new_position_nonce,
+        paid_amount: position.paid_amount.saturating_add(takeover_data.collateral_top_up),
+        liquidation_auction_slot: 0,
+        ..position.clone()
+    };
+    
+    let new_health = new_position.health(mark_price, config.initial_margin_bps, 0)?;
+    
+    if new_health.equity < new_health.maintenance as i128 {
+        msg!("Top-up leaves the position below the initial margin");
+        return Err(DexError::InsufficientInitialMargin.into());
+    }
+    
+    invoke(
+        &system_instruction::transfer(
+            liquidator_account.key,
+            owner_account.key,
+            owner_payout,
+        ),
+        &[
+            liquidator_account.clone(),
+            owner_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    let seeds = &[
+        b"uranus_position",
+        liquidator_account.key.as_ref(),
+        &takeover_data.new_position_nonce.to_le_bytes(),
+        &[new_position_bump],
+    ];
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            liquidator_account.key,
+            new_position_account.key,
+            takeover_data.collateral_top_up.max(minimum_balance.saturating_sub(position_lamports)),
+            data_len as u64,
+            program_id,
+        ),
+        &[
+            liquidator_account.clone(),
+            new_position_account.clone(),
+            system_program.clone(),
+        ],
+        &[seeds],
+    )?;
+    
+    move_lamports(position_account, new_position_account, position_lamports, TransferReason::Takeover)?;
+    
+    new_position.paid_amount = new_position_account.lamports().saturating_sub(minimum_balance);
+    new_position.refresh_liquidation_price(config.maintenance_margin_bps);
+    save_position_account(new_position_account, &new_position)?;
+    
+    zero_account_data(position_account)?;
+    remove_from_position_index(
+        find_position_index_account(accounts, &position.owner, program_id),
+        &position.owner,
+        position.position_nonce,
+        program_id,
+    )?;
+    
+    if let Some(liquidator_index_account) = find_position_index_account(accounts, liquidator_account.key, program_id) {
+        append_to_position_index(
+            liquidator_index_account,
+            liquidator_account.key,
+            liquidator_account,
+            system_program,
+            takeover_data.new_position_nonce,
+            program_id,
+        )?;
+    }
+    
+    msg!(
+        "Position {} taken over by {} as {}",
+        position.position_nonce,
+        liquidator_account.key,
+        takeover_data.new_position_nonce
+    );
+    msg!("Owner paid: {}, discount: {} bps, collateral: {}", owner_payout, discount_bps, new_position.paid_amount);
+    
+    Ok(())
+}
+
+/// Takes a realized `loss` from the position's collateral and then the owner's cross margin.
+/// Any excess is never charged to the owner: the insurance account covers what it can and
+/// the rest is added to the market's bad debt, which the caller saves.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn settle_loss(
+    position: &PositionAccount,
+    position_account: &AccountInfo,
+    market_account: &AccountInfo,
+    cross_margin: Option<&AccountInfo>,
+    insurance_account: Option<&AccountInfo>,
+    market_state: &mut MarketState,
+    loss: u64,
+    reason: TransferReason,
+) -> Result<LossCoverage, ProgramError> {
+    let margin = match cross_margin {
+        Some(margin_account) => margin_available(margin_account)?,
+        None => 0,
+    };
+    let coverage = cover_loss(loss, position_account.lamports(), margin);
+    
+    move_lamports(position_account, market_account, coverage.from_collateral, reason)?;
+    market_state.record_deposit(coverage.from_collateral);
+    
+    if let Some(margin_account) = cross_margin {
+        let drawn = draw_from_margin(margin_account, market_account, coverage.from_margin)?;
+        market_state.record_deposit(drawn);
+    }
+    
+    if coverage.uncovered > 0 {
+        let insured = draw_market_insurance(insurance_account, market_account, coverage.uncovered)?;
+        market_state.record_deposit(insured);
+        market_state.bad_debt = market_state
+            .bad_debt
+            .saturating_add(coverage.uncovered.saturating_sub(insured));
+        
+        emit_event(&DexEvent::BadDebt(BadDebtEvent {
+            owner: position.owner,
+            position_nonce: position.position_nonce,
+            market_mint: position.market_mint,
+            shortfall: coverage.uncovered,
+            insured,
+            market_bad_debt: market_state.bad_debt,
+        }))?;
+        
+        msg!("Loss exceeded collateral by {}, insurance covered {}", coverage.uncovered, insured);
+    }
+    
+    Ok(coverage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn cover_loss_never_exceeds_its_sources() {
+        for loss in [0, 1, 500, 1_000, 1_500, 3_000, u64::MAX] {
+            for collateral in [0, 1_000] {
+                for margin in [0, 700] {
+                    let coverage = cover_loss(loss, collateral, margin);
+                    
+                    assert!(coverage.from_collateral <= collateral);
+                    assert!(coverage.from_margin <= margin);
+                    assert_eq!(
+                        coverage.from_collateral as u128 + coverage.from_margin as u128 + coverage.uncovered as u128,
+                        loss as u128
+                    );
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn settle_loss_caps_at_collateral_and_books_bad_debt() {
+        let owner = Pubkey::new_unique();
+        let program_id = crate::id();
+        let (position_key, market_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut position_lamports, mut market_lamports) = (1_000u64, 50_000u64);
+        let (mut position_data, mut market_data) = (vec![], vec![]);
+        
+        let position_account = AccountInfo::new(
+            &position_key,
+            false,
+            true,
+            &mut position_lamports,
+            &mut position_data,
+            &program_id,
+            false,
+            0,
+        );
+        let market_account = AccountInfo::new(
+            &market_key,
+            false,
+            true,
+            &mut market_lamports,
+            &mut market_data,
+            &program_id,
+            false,
+            0,
+        );
+        let position = PositionAccount {
+            owner,
+            ..PositionAccount::default()
+        };
+        let mut market_state = MarketState::default();
+        
+        let coverage = settle_loss(
+            &position,
+            &position_account,
+            &market_account,
+            None,
+            None,
+            &mut market_state,
+            4_000,
+            TransferReason::Loss,
+        )
+        .unwrap();
+        
+        assert_eq!(coverage.from_collateral, 1_000);
+        assert_eq!(coverage.uncovered, 3_000);
+        assert_eq!(position_account.lamports(), 0);
+        assert_eq!(market_account.lamports(), 51_000);
+        assert_eq!(market_state.bad_debt, 3_000);
+    }
+}
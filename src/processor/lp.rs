@@ -0,0 +1,358 @@
+//! Liquidity provider deposits, withdrawals and fee claims against a market's LP pool.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::{invoke, invoke_signed},
+    system_instruction,
+    sysvar::{rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn load_lp_position(
+    lp_position_account: &AccountInfo,
+    market_mint: &Pubkey,
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<LpPositionAccount, ProgramError> {
+    let (lp_position_pda, _lp_position_bump) = find_lp_position_address(market_mint, owner, program_id);
+    
+    assert_key_eq(lp_position_account, &lp_position_pda)?;
+    
+    assert_owned_by(lp_position_account, program_id)?;
+    
+    deserialize_padded::<LpPositionAccount>(&lp_position_account.data.borrow())
+}
+
+/// Deposits lamports into a market's LP pool, minting shares at the current pool value and
+/// creating the provider's LP position on first use. SPL-settled markets take the settlement
+/// asset instead, from the provider's token account into the market's settlement vault, both
+/// passed after the system program with the token program.
+pub(crate) fn process_deposit_liquidity(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    deposit_data: LiquidityDepositData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let lp_position_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let provider_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(provider_account)?;
+    
+    check_not_blacklisted(accounts, &[provider_account.key], program_id)?;
+    
+    if deposit_data.amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        provider_account,
+        system_program,
+        &deposit_data.market_mint,
+        program_id,
+    )?;
+    
+    if !market_state.is_active() {
+        msg!("Market is not active, liquidity can only be withdrawn");
+        return Err(DexError::MarketNotActive.into());
+    }
+    
+    // Token accounts come right after the system program for SPL-settled markets.
+    let token_accounts = if market_state.is_native_settlement() {
+        None
+    } else {
+        let provider_token_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        
+        verify_settlement_vault(vault_account, token_program, &market_state, program_id)?;
+        
+        Some((provider_token_account, vault_account, token_program))
+    };
+    
+    let liquidity = match token_accounts {
+        Some((_, vault_account, _)) => token_account_amount(vault_account)?,
+        None => market_account.lamports(),
+    };
+    
+    if deposit_data.amount > market_state.liquidity_headroom(liquidity) {
+        msg!("Deposit exceeds the market liquidity cap");
+        return Err(DexError::MarketLiquidityCapExceeded.into());
+    }
+    
+    let (lp_position_pda, lp_position_bump) =
+        find_lp_position_address(&deposit_data.market_mint, provider_account.key, program_id);
+    
+    assert_key_eq(lp_position_account, &lp_position_pda)?;
+    
+    if lp_position_account.data_is_empty() {
+        let rent = Rent::get()?;
+        
+        invoke_signed(
+            &system_instruction::create_account(
+                provider_account.key,
+                lp_position_account.key,
+                rent.minimum_balance(LpPositionAccount::LEN),
+                LpPositionAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                provider_account.clone(),
+                lp_position_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"uranus_lp",
+                deposit_data.market_mint.as_ref(),
+                provider_account.key.as_ref(),
+                &[lp_position_bump],
+            ]],
+        )?;
+        
+        LpPositionAccount {
+            owner: *provider_account.key,
+            market_mint: deposit_data.market_mint,
+            fee_per_share_paid: market_state.lp_fee_per_share,
+            ..LpPositionAccount::default()
+        }
+        .serialize(&mut &mut lp_position_account.data.borrow_mut()[..])?;
+        
+        msg!("LP position created: {}", lp_position_account.key);
+    }
+    
+    let mut lp_position = load_lp_position(
+        lp_position_account,
+        &deposit_data.market_mint,
+        provider_account.key,
+        program_id,
+    )?;
+    lp_position.checkpoint_fees(market_state.lp_fee_per_share);
+    
+    let pool_value = match token_accounts {
+        Some((_, vault_account, _)) => settlement_pool_value(vault_account, &market_state)?,
+        None => lp_pool_value(market_account, &market_state)?,
+    };
+    let adopted = market_state.adopt_unowned_liquidity(pool_value);
+    if adopted > 0 {
+        msg!("Minted {} protocol shares for unowned pool liquidity", adopted);
+    }
+    let shares = lp_shares_for_deposit(deposit_data.amount, pool_value, market_state.total_lp_shares);
+    
+    if shares == 0 {
+        msg!("Deposit too small to mint a share");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    match token_accounts {
+        Some((provider_token_account, vault_account, token_program)) => token_transfer(
+            provider_token_account,
+            vault_account,
+            provider_account,
+            token_program,
+            deposit_data.amount,
+            &[],
+        )?,
+        None => invoke(
+            &system_instruction::transfer(
+                provider_account.key,
+                market_account.key,
+                deposit_data.amount,
+            ),
+            &[
+                provider_account.clone(),
+                market_account.clone(),
+                system_program.clone(),
+            ],
+        )?,
+    }
+    
+    lp_position.shares = lp_position.shares.saturating_add(shares);
+    market_state.total_lp_shares = market_state.total_lp_shares.saturating_add(shares);
+    market_state.record_deposit(deposit_data.amount);
+    
+    lp_position.serialize(&mut *lp_position_account.data.borrow_mut())?;
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::LiquidityDeposited(LiquidityEvent {
+        market_mint: deposit_data.market_mint,
+        provider: *provider_account.key,
+        amount: deposit_data.amount,
+        shares,
+        total_shares: market_state.total_lp_shares,
+    }))?;
+    
+    msg!("Deposited {} for {} LP shares", deposit_data.amount, shares);
+    
+    Ok(())
+}
+
+/// Burns LP shares for their current value in lamports, or in the settlement asset for
+/// SPL-settled markets (owner token account, settlement vault and token program after the
+/// system program). Accrued fees stay on the position for `CLAIM_LP_FEES`.
+pub(crate) fn process_withdraw_liquidity(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    withdraw_data: LiquidityWithdrawData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let lp_position_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    check_not_frozen(accounts, &[owner_account.key], program_id)?;
+    
+    let market_state = prepare_market_state(
+        market_account,
+        owner_account,
+        system_program,
+        &withdraw_data.market_mint,
+        program_id,
+    )?;
+    let mut lp_position = load_lp_position(
+        lp_position_account,
+        &withdraw_data.market_mint,
+        owner_account.key,
+        program_id,
+    )?;
+    
+    if withdraw_data.shares == 0 || withdraw_data.shares > lp_position.shares {
+        msg!("LP position holds {} shares", lp_position.shares);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    lp_position.checkpoint_fees(market_state.lp_fee_per_share);
+    
+    if market_state.is_native_settlement() {
+        let amount = lp_share_value(
+            withdraw_data.shares,
+            lp_pool_value(market_account, &market_state)?,
+            market_state.total_lp_shares,
+        );
+        
+        move_lamports(market_account, owner_account, amount, TransferReason::LiquidityWithdrawal)?;
+        
+        return finish_withdrawal(lp_position_account, market_account, owner_account, lp_position, market_state, &withdraw_data, amount);
+    }
+    
+    let owner_token_account = next_account_info(accounts_iter)?;
+    let vault_account = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    
+    let vault_bump = verify_settlement_vault(vault_account, token_program, &market_state, program_id)?;
+    let amount = lp_share_value(
+        withdraw_data.shares,
+        settlement_pool_value(vault_account, &market_state)?,
+        market_state.total_lp_shares,
+    );
+    
+    settlement_vault_payout(
+        vault_account,
+        owner_token_account,
+        token_program,
+        &withdraw_data.market_mint,
+        vault_bump,
+        amount,
+    )?;
+    
+    finish_withdrawal(lp_position_account, market_account, owner_account, lp_position, market_state, &withdraw_data, amount)
+}
+
+fn finish_withdrawal(
+    lp_position_account: &AccountInfo,
+    market_account: &AccountInfo,
+    owner_account: &AccountInfo,
+    mut lp_position: LpPositionAccount,
+    mut market_state: MarketState,
+    withdraw_data: &LiquidityWithdrawData,
+    amount: u64,
+) -> ProgramResult {
+    lp_position.shares = lp_position.shares.saturating_sub(withdraw_data.shares);
+    market_state.total_lp_shares = market_state.total_lp_shares.saturating_sub(withdraw_data.shares);
+    market_state.record_payout(amount);
+    
+    lp_position.serialize(&mut *lp_position_account.data.borrow_mut())?;
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::LiquidityWithdrawn(LiquidityEvent {
+        market_mint: withdraw_data.market_mint,
+        provider: *owner_account.key,
+        amount,
+        shares: withdraw_data.shares,
+        total_shares: market_state.total_lp_shares,
+    }))?;
+    
+    msg!("Withdrew {} for {} LP shares", amount, withdraw_data.shares);
+    
+    Ok(())
+}
+
+/// Pays out an LP position's share of collected fees without touching its shares; whatever
+/// the market cannot cover above its rent floor stays accrued for a later claim.
+pub(crate) fn process_claim_lp_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    claim_data: ClaimLpFeesData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let lp_position_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        owner_account,
+        system_program,
+        &claim_data.market_mint,
+        program_id,
+    )?;
+    let mut lp_position = load_lp_position(
+        lp_position_account,
+        &claim_data.market_mint,
+        owner_account.key,
+        program_id,
+    )?;
+    
+    lp_position.checkpoint_fees(market_state.lp_fee_per_share);
+    
+    let available = market_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(market_account.data_len()));
+    let claimed = lp_position.accrued_fees.min(available);
+    
+    move_lamports(market_account, owner_account, claimed, TransferReason::LpFeeClaim)?;
+    
+    lp_position.accrued_fees = lp_position.accrued_fees.saturating_sub(claimed);
+    market_state.unclaimed_lp_fees = market_state.unclaimed_lp_fees.saturating_sub(claimed);
+    market_state.record_payout(claimed);
+    
+    lp_position.serialize(&mut *lp_position_account.data.borrow_mut())?;
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::LpFeesClaimed(LpFeeClaimEvent {
+        market_mint: claim_data.market_mint,
+        owner: *owner_account.key,
+        amount: claimed,
+        remaining: lp_position.accrued_fees,
+    }))?;
+    
+    msg!("Claimed {} lamports of LP fees, {} still accrued", claimed, lp_position.accrued_fees);
+    
+    Ok(())
+}
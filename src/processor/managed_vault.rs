@@ -0,0 +1,869 @@
+//! Manager-operated vaults: depositors pool lamports for shares and the manager trades the pool
+//! within the vault's risk limits through positions owned by the vault PDA.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::{invoke, invoke_signed},
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn load_managed_vault(
+    vault_account: &AccountInfo,
+    manager: &Pubkey,
+    vault_id: u64,
+    program_id: &Pubkey,
+) -> Result<ManagedVaultAccount, ProgramError> {
+    let (vault_pda, _vault_bump) = find_managed_vault_address(manager, vault_id, program_id);
+    
+    assert_key_eq(vault_account, &vault_pda)?;
+    
+    assert_owned_by(vault_account, program_id)?;
+    
+    deserialize_padded::<ManagedVaultAccount>(&vault_account.data.borrow())
+}
+
+pub(crate) fn load_vault_depositor(
+    depositor_account: &AccountInfo,
+    vault: &Pubkey,
+    depositor: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<VaultDepositorAccount, ProgramError> {
+    let (depositor_pda, _depositor_bump) = find_vault_depositor_address(vault, depositor, program_id);
+    
+    assert_key_eq(depositor_account, &depositor_pda)?;
+    
+    assert_owned_by(depositor_account, program_id)?;
+    
+    deserialize_padded::<VaultDepositorAccount>(&depositor_account.data.borrow())
+}
+
+/// Lamports the vault holds above its rent that are neither deployed in positions nor owed to
+/// queued deposits and processed withdrawals.
+pub(crate) fn managed_vault_free_lamports(
+    vault_account: &AccountInfo,
+    vault: &ManagedVaultAccount,
+) -> Result<u64, ProgramError> {
+    Ok(vault_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(vault_account.data_len()))
+        .saturating_sub(vault.pending_deposits)
+        .saturating_sub(vault.reserved_withdrawals))
+}
+
+/// Net asset value backing the vault's shares: free lamports plus the collateral of its
+/// tracked positions.
+pub(crate) fn managed_vault_net_asset_value(
+    vault_account: &AccountInfo,
+    vault: &ManagedVaultAccount,
+) -> Result<u64, ProgramError> {
+    Ok(managed_vault_free_lamports(vault_account, vault)?.saturating_add(vault.deployed_collateral()))
+}
+
+/// Pays the manager the performance fee on the share price gain above the high-water mark and
+/// raises the mark to the post-fee share price, so a recovery back up to an earlier peak is
+/// never charged twice. Returns the fee paid.
+pub(crate) fn settle_performance_fee(
+    vault_account: &AccountInfo,
+    manager_account: &AccountInfo,
+    vault: &mut ManagedVaultAccount,
+) -> Result<u64, ProgramError> {
+    if vault.total_shares == 0 {
+        return Ok(0);
+    }
+    
+    let net_asset_value = managed_vault_net_asset_value(vault_account, vault)?;
+    let share_price = lp_share_price(net_asset_value, vault.total_shares);
+    
+    if share_price <= vault.high_water_mark {
+        return Ok(0);
+    }
+    
+    let fee = performance_fee(share_price, vault.high_water_mark, vault.total_shares, vault.performance_fee_bps)
+        .min(managed_vault_free_lamports(vault_account, vault)?);
+    
+    move_lamports(vault_account, manager_account, fee, TransferReason::PerformanceFee)?;
+    
+    vault.high_water_mark = lp_share_price(net_asset_value.saturating_sub(fee), vault.total_shares)
+        .max(vault.high_water_mark);
+    vault.fees_paid = vault.fees_paid.saturating_add(fee);
+    
+    if fee > 0 {
+        emit_event(&DexEvent::PerformanceFeePaid(ManagedVaultEvent {
+            vault: *vault_account.key,
+            manager: vault.manager,
+            account: vault.manager,
+            position_nonce: 0,
+            amount: fee,
+            shares: vault.total_shares,
+            pnl: 0,
+            net_asset_value: net_asset_value.saturating_sub(fee),
+        }))?;
+        
+        msg!("Performance fee: {} lamports, high-water mark: {}", fee, vault.high_water_mark);
+    }
+    
+    Ok(fee)
+}
+
+/// Creates a managed vault operated by the signer under the given risk limits.
+pub(crate) fn process_create_managed_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    create_data: CreateManagedVaultData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let vault_account = next_account_info(accounts_iter)?;
+    let manager_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(manager_account)?;
+    
+    if create_data.max_leverage == 0 || create_data.max_leverage > MAXIMUM_LEVERAGE {
+        msg!("Vault leverage limit must be between 1 and {}", MAXIMUM_LEVERAGE);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if create_data.performance_fee_bps > MAX_PERFORMANCE_FEE_BPS {
+        msg!("Performance fee must be at most {} bps", MAX_PERFORMANCE_FEE_BPS);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if create_data.epoch_slots == 0 {
+        msg!("Vault epochs must last at least one slot");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if create_data.max_deployed_bps == 0 || create_data.max_deployed_bps as u64 > BASIS_POINTS_DIVISOR {
+        msg!("Deployment limit must be between 1 and {} bps", BASIS_POINTS_DIVISOR);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (vault_pda, vault_bump) = find_managed_vault_address(manager_account.key, create_data.vault_id, program_id);
+    
+    assert_key_eq(vault_account, &vault_pda)?;
+    
+    if !vault_account.data_is_empty() {
+        msg!("Managed vault {} already exists", create_data.vault_id);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            manager_account.key,
+            vault_account.key,
+            Rent::get()?.minimum_balance(ManagedVaultAccount::LEN),
+            ManagedVaultAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            manager_account.clone(),
+            vault_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_managed_vault",
+            manager_account.key.as_ref(),
+            &create_data.vault_id.to_le_bytes(),
+            &[vault_bump],
+        ]],
+    )?;
+    
+    let vault = ManagedVaultAccount {
+        manager: *manager_account.key,
+        vault_id: create_data.vault_id,
+        total_shares: 0,
+        max_leverage: create_data.max_leverage,
+        max_position_collateral: create_data.max_position_collateral,
+        max_deployed_bps: create_data.max_deployed_bps,
+        created_slot: Clock::get()?.slot,
+        performance_fee_bps: create_data.performance_fee_bps,
+        high_water_mark: LP_FEE_PER_SHARE_PRECISION,
+        fees_paid: 0,
+        epoch_slots: create_data.epoch_slots,
+        current_epoch: 0,
+        epoch_start_slot: Clock::get()?.slot,
+        pending_deposits: 0,
+        pending_withdrawal_shares: 0,
+        reserved_withdrawals: 0,
+        positions: Vec::new(),
+    };
+    vault.serialize(&mut *vault_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::ManagedVaultCreated(ManagedVaultEvent {
+        vault: *vault_account.key,
+        manager: vault.manager,
+        account: vault.manager,
+        position_nonce: 0,
+        amount: 0,
+        shares: 0,
+        pnl: 0,
+        net_asset_value: 0,
+    }))?;
+    
+    msg!(
+        "Managed vault {} created: leverage up to {}x, {} bps deployable",
+        vault.vault_id,
+        vault.max_leverage,
+        vault.max_deployed_bps
+    );
+    
+    Ok(())
+}
+
+/// Queues a deposit for the vault's current epoch: the lamports move into the vault at once but
+/// only mint shares, at the epoch's closing share price, once the epoch is processed.
+pub(crate) fn process_deposit_managed_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    deposit_data: ManagedVaultDepositData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let vault_account = next_account_info(accounts_iter)?;
+    let request_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    if deposit_data.amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut vault = load_managed_vault(vault_account, &deposit_data.manager, deposit_data.vault_id, program_id)?;
+    
+    create_vault_request(
+        vault_account,
+        request_account,
+        owner_account,
+        system_program,
+        &vault,
+        VAULT_REQUEST_DEPOSIT,
+        deposit_data.amount,
+        program_id,
+    )?;
+    
+    invoke(
+        &system_instruction::transfer(
+            owner_account.key,
+            vault_account.key,
+            deposit_data.amount,
+        ),
+        &[
+            owner_account.clone(),
+            vault_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    vault.pending_deposits = vault.pending_deposits.saturating_add(deposit_data.amount);
+    vault.serialize(&mut *vault_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::ManagedVaultDepositRequested(ManagedVaultEvent {
+        vault: *vault_account.key,
+        manager: vault.manager,
+        account: *owner_account.key,
+        position_nonce: vault.current_epoch,
+        amount: deposit_data.amount,
+        shares: 0,
+        pnl: 0,
+        net_asset_value: managed_vault_net_asset_value(vault_account, &vault)?,
+    }))?;
+    
+    msg!("Queued a {} lamport deposit for epoch {}", deposit_data.amount, vault.current_epoch);
+    
+    Ok(())
+}
+
+/// Queues a withdrawal for the vault's current epoch, escrowing the shares; they are redeemed at
+/// the epoch's closing share price once the epoch is processed.
+pub(crate) fn process_withdraw_managed_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    withdraw_data: ManagedVaultWithdrawData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let vault_account = next_account_info(accounts_iter)?;
+    let request_account = next_account_info(accounts_iter)?;
+    let depositor_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let mut vault = load_managed_vault(vault_account, &withdraw_data.manager, withdraw_data.vault_id, program_id)?;
+    let mut depositor = load_vault_depositor(depositor_account, vault_account.key, owner_account.key, program_id)?;
+    
+    if withdraw_data.shares == 0 || withdraw_data.shares > depositor.shares {
+        msg!("Invalid share amount, holding {}", depositor.shares);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    create_vault_request(
+        vault_account,
+        request_account,
+        owner_account,
+        system_program,
+        &vault,
+        VAULT_REQUEST_WITHDRAWAL,
+        withdraw_data.shares,
+        program_id,
+    )?;
+    
+    depositor.net_deposits = depositor.net_deposits.saturating_sub(
+        ((depositor.net_deposits as u128).saturating_mul(withdraw_data.shares as u128)
+            / depositor.shares as u128) as u64,
+    );
+    depositor.shares = depositor.shares.saturating_sub(withdraw_data.shares);
+    depositor.serialize(&mut *depositor_account.data.borrow_mut())?;
+    
+    vault.pending_withdrawal_shares = vault.pending_withdrawal_shares.saturating_add(withdraw_data.shares);
+    vault.serialize(&mut *vault_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::ManagedVaultWithdrawalRequested(ManagedVaultEvent {
+        vault: *vault_account.key,
+        manager: vault.manager,
+        account: *owner_account.key,
+        position_nonce: vault.current_epoch,
+        amount: 0,
+        shares: withdraw_data.shares,
+        pnl: 0,
+        net_asset_value: managed_vault_net_asset_value(vault_account, &vault)?,
+    }))?;
+    
+    msg!("Queued a {} share withdrawal for epoch {}", withdraw_data.shares, vault.current_epoch);
+    
+    Ok(())
+}
+
+/// Creates the depositor's request account for the vault's current epoch. A depositor has at
+/// most one request per vault outstanding and must claim it before queueing another.
+#[allow(clippy::too_many_arguments)]
+fn create_vault_request<'a>(
+    vault_account: &AccountInfo<'a>,
+    request_account: &AccountInfo<'a>,
+    owner_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    vault: &ManagedVaultAccount,
+    kind: u8,
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (request_pda, request_bump) = find_vault_request_address(vault_account.key, owner_account.key, program_id);
+    
+    assert_key_eq(request_account, &request_pda)?;
+    
+    if !request_account.data_is_empty() {
+        msg!("A vault request is already outstanding, claim it first");
+        return Err(DexError::VaultRequestPending.into());
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            owner_account.key,
+            request_account.key,
+            Rent::get()?.minimum_balance(VaultRequestAccount::LEN),
+            VaultRequestAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            owner_account.clone(),
+            request_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_vault_request",
+            vault_account.key.as_ref(),
+            owner_account.key.as_ref(),
+            &[request_bump],
+        ]],
+    )?;
+    
+    VaultRequestAccount {
+        vault: *vault_account.key,
+        depositor: *owner_account.key,
+        epoch: vault.current_epoch,
+        kind,
+        amount,
+    }
+    .serialize(&mut *request_account.data.borrow_mut())?;
+    
+    Ok(())
+}
+
+/// Permissionless crank closing the vault's current epoch once `epoch_slots` have passed. The
+/// epoch's share price is fixed in an epoch record, queued deposits mint their shares and
+/// queued withdrawals are redeemed at it; the redeemed lamports are reserved for claims. Fails
+/// while the vault lacks the undeployed lamports to pay the withdrawals, so the manager has to
+/// free liquidity first.
+pub(crate) fn process_crank_vault_epoch(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    epoch_data: ManagedVaultEpochData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let vault_account = next_account_info(accounts_iter)?;
+    let epoch_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(keeper_account)?;
+    
+    let mut vault = load_managed_vault(vault_account, &epoch_data.manager, epoch_data.vault_id, program_id)?;
+    let current_slot = Clock::get()?.slot;
+    let epoch_end_slot = vault.epoch_start_slot.saturating_add(vault.epoch_slots);
+    
+    if current_slot < epoch_end_slot {
+        msg!("Epoch {} ends at slot {}", vault.current_epoch, epoch_end_slot);
+        return Err(DexError::VaultEpochNotEnded.into());
+    }
+    
+    let share_price = lp_share_price(managed_vault_net_asset_value(vault_account, &vault)?, vault.total_shares);
+    let withdrawal_value = ((vault.pending_withdrawal_shares as u128).saturating_mul(share_price)
+        / LP_FEE_PER_SHARE_PRECISION)
+        .min(u64::MAX as u128) as u64;
+    let epoch_rent = Rent::get()?.minimum_balance(VaultEpochAccount::LEN);
+    
+    if withdrawal_value.saturating_add(epoch_rent)
+        > managed_vault_free_lamports(vault_account, &vault)?.saturating_add(vault.pending_deposits)
+    {
+        msg!("Withdrawals of {} lamports exceed the vault's undeployed funds", withdrawal_value);
+        return Err(DexError::VaultFundsDeployed.into());
+    }
+    
+    let (epoch_pda, epoch_bump) = find_vault_epoch_address(vault_account.key, vault.current_epoch, program_id);
+    
+    assert_key_eq(epoch_account, &epoch_pda)?;
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            keeper_account.key,
+            epoch_account.key,
+            epoch_rent,
+            VaultEpochAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            keeper_account.clone(),
+            epoch_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_vault_epoch",
+            vault_account.key.as_ref(),
+            &vault.current_epoch.to_le_bytes(),
+            &[epoch_bump],
+        ]],
+    )?;
+    
+    VaultEpochAccount {
+        vault: *vault_account.key,
+        epoch: vault.current_epoch,
+        share_price,
+        settled_slot: current_slot,
+    }
+    .serialize(&mut *epoch_account.data.borrow_mut())?;
+    
+    let minted = ((vault.pending_deposits as u128).saturating_mul(LP_FEE_PER_SHARE_PRECISION) / share_price.max(1))
+        .min(u64::MAX as u128) as u64;
+    let was_empty = vault.total_shares == 0;
+    
+    vault.total_shares = vault
+        .total_shares
+        .saturating_add(minted)
+        .saturating_sub(vault.pending_withdrawal_shares);
+    vault.reserved_withdrawals = vault.reserved_withdrawals.saturating_add(withdrawal_value);
+    vault.pending_deposits = 0;
+    vault.pending_withdrawal_shares = 0;
+    
+    move_lamports(vault_account, keeper_account, epoch_rent, TransferReason::KeeperReimbursement)?;
+    
+    if was_empty || vault.total_shares == 0 {
+        vault.high_water_mark = lp_share_price(managed_vault_net_asset_value(vault_account, &vault)?, vault.total_shares);
+    }
+    
+    emit_event(&DexEvent::ManagedVaultEpochProcessed(ManagedVaultEvent {
+        vault: *vault_account.key,
+        manager: vault.manager,
+        account: *epoch_account.key,
+        position_nonce: vault.current_epoch,
+        amount: withdrawal_value,
+        shares: minted,
+        pnl: 0,
+        net_asset_value: managed_vault_net_asset_value(vault_account, &vault)?,
+    }))?;
+    
+    msg!(
+        "Epoch {} processed at share price {}: minted {} shares, reserved {} lamports",
+        vault.current_epoch,
+        share_price,
+        minted,
+        withdrawal_value
+    );
+    
+    vault.current_epoch = vault.current_epoch.saturating_add(1);
+    vault.epoch_start_slot = current_slot;
+    vault.serialize(&mut *vault_account.data.borrow_mut())?;
+    
+    Ok(())
+}
+
+/// Claims a request once its epoch is processed: a deposit credits its shares to the depositor's
+/// share account (created on first use), a withdrawal pays out its reserved lamports. The
+/// request account is closed and its rent returned.
+pub(crate) fn process_claim_vault_request(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    claim_data: ManagedVaultEpochData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let vault_account = next_account_info(accounts_iter)?;
+    let request_account = next_account_info(accounts_iter)?;
+    let epoch_account = next_account_info(accounts_iter)?;
+    let depositor_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let mut vault = load_managed_vault(vault_account, &claim_data.manager, claim_data.vault_id, program_id)?;
+    let (request_pda, _request_bump) = find_vault_request_address(vault_account.key, owner_account.key, program_id);
+    
+    if request_account.key != &request_pda || request_account.owner != program_id {
+        msg!("Invalid vault request account");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let request = deserialize_padded::<VaultRequestAccount>(&request_account.data.borrow())?;
+    
+    if request.epoch >= vault.current_epoch {
+        msg!("Epoch {} has not been processed yet", request.epoch);
+        return Err(DexError::VaultEpochNotEnded.into());
+    }
+    
+    let (epoch_pda, _epoch_bump) = find_vault_epoch_address(vault_account.key, request.epoch, program_id);
+    
+    if epoch_account.key != &epoch_pda || epoch_account.owner != program_id {
+        msg!("Invalid vault epoch account");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let epoch = deserialize_padded::<VaultEpochAccount>(&epoch_account.data.borrow())?;
+    let (depositor_pda, depositor_bump) =
+        find_vault_depositor_address(vault_account.key, owner_account.key, program_id);
+    
+    assert_key_eq(depositor_account, &depositor_pda)?;
+    
+    let (amount, shares) = if request.kind == VAULT_REQUEST_DEPOSIT {
+        let shares = ((request.amount as u128).saturating_mul(LP_FEE_PER_SHARE_PRECISION) / epoch.share_price.max(1))
+            .min(u64::MAX as u128) as u64;
+        
+        let mut depositor = if depositor_account.data_is_empty() {
+            invoke_signed(
+                &system_instruction::create_account(
+                    owner_account.key,
+                    depositor_account.key,
+                    Rent::get()?.minimum_balance(VaultDepositorAccount::LEN),
+                    VaultDepositorAccount::LEN as u64,
+                    program_id,
+                ),
+                &[
+                    owner_account.clone(),
+                    depositor_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[
+                    b"uranus_vault_depositor",
+                    vault_account.key.as_ref(),
+                    owner_account.key.as_ref(),
+                    &[depositor_bump],
+                ]],
+            )?;
+            
+            VaultDepositorAccount {
+                vault: *vault_account.key,
+                depositor: *owner_account.key,
+                ..VaultDepositorAccount::default()
+            }
+        } else {
+            load_vault_depositor(depositor_account, vault_account.key, owner_account.key, program_id)?
+        };
+        
+        depositor.shares = depositor.shares.saturating_add(shares);
+        depositor.net_deposits = depositor.net_deposits.saturating_add(request.amount);
+        depositor.serialize(&mut *depositor_account.data.borrow_mut())?;
+        
+        (request.amount, shares)
+    } else {
+        let amount = ((request.amount as u128).saturating_mul(epoch.share_price) / LP_FEE_PER_SHARE_PRECISION)
+            .min(vault.reserved_withdrawals as u128) as u64;
+        
+        move_lamports(vault_account, owner_account, amount, TransferReason::ManagedVaultWithdrawal)?;
+        vault.reserved_withdrawals = vault.reserved_withdrawals.saturating_sub(amount);
+        vault.serialize(&mut *vault_account.data.borrow_mut())?;
+        
+        if depositor_account.owner == program_id
+            && load_vault_depositor(depositor_account, vault_account.key, owner_account.key, program_id)?.shares == 0
+        {
+            refund_order_account(depositor_account, owner_account)?;
+        }
+        
+        (amount, request.amount)
+    };
+    
+    refund_order_account(request_account, owner_account)?;
+    
+    let event = ManagedVaultEvent {
+        vault: *vault_account.key,
+        manager: vault.manager,
+        account: *owner_account.key,
+        position_nonce: request.epoch,
+        amount,
+        shares,
+        pnl: 0,
+        net_asset_value: managed_vault_net_asset_value(vault_account, &vault)?,
+    };
+    
+    if request.kind == VAULT_REQUEST_DEPOSIT {
+        emit_event(&DexEvent::ManagedVaultDeposited(event))?;
+    } else {
+        emit_event(&DexEvent::ManagedVaultWithdrawn(event))?;
+    }
+    
+    msg!("Claimed epoch {} request: {} lamports, {} shares", request.epoch, amount, shares);
+    
+    Ok(())
+}
+
+/// Manager opens a vault-owned position at the spread-adjusted oracle price with `amount` of the
+/// vault's free lamports, within the vault's leverage, per-position and deployment limits. The
+/// manager fronts the new accounts' rent and is reimbursed from the vault.
+pub(crate) fn process_managed_vault_open(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    open_data: ManagedVaultOpenData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let vault_account = next_account_info(accounts_iter)?;
+    let manager_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let nonce_counter_account = next_account_info(accounts_iter)?;
+    let position_index_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(manager_account)?;
+    
+    if open_data.direction != POSITION_LONG && open_data.direction != POSITION_SHORT {
+        msg!("Invalid direction");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let config = load_config(config_account, program_id)?;
+    let mut vault = load_managed_vault(vault_account, manager_account.key, open_data.vault_id, program_id)?;
+    
+    if open_data.leverage == 0 || open_data.leverage > vault.max_leverage {
+        msg!("Leverage must be between 1 and {}", vault.max_leverage);
+        return Err(DexError::VaultRiskLimitExceeded.into());
+    }
+    
+    if open_data.amount.saturating_mul(open_data.leverage as u64) < MIN_POSITION_SIZE_LAMPORTS {
+        msg!("Position too small");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if vault.max_position_collateral != 0 && open_data.amount > vault.max_position_collateral {
+        msg!("Position amount exceeds the vault limit of {}", vault.max_position_collateral);
+        return Err(DexError::VaultRiskLimitExceeded.into());
+    }
+    
+    if vault.positions.len() >= MAX_MANAGED_VAULT_POSITIONS {
+        msg!("Managed vault already holds {} positions", vault.positions.len());
+        return Err(DexError::VaultRiskLimitExceeded.into());
+    }
+    
+    let net_asset_value = managed_vault_net_asset_value(vault_account, &vault)?;
+    let deployable = (net_asset_value as u128)
+        .saturating_mul(vault.max_deployed_bps as u128)
+        / BASIS_POINTS_DIVISOR as u128;
+    
+    if vault.deployed_collateral().saturating_add(open_data.amount) as u128 > deployable {
+        msg!("Position would deploy more than {} bps of the vault", vault.max_deployed_bps);
+        return Err(DexError::VaultRiskLimitExceeded.into());
+    }
+    
+    let required = open_data
+        .amount
+        .saturating_add(Rent::get()?.minimum_balance(PositionAccount::LEN));
+    
+    if managed_vault_free_lamports(vault_account, &vault)? < required {
+        msg!("Vault cannot fund {} lamports", required);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    let symbol_registry = load_symbol_registry(symbol_registry_account, &open_data.market_mint, program_id)?;
+    verify_market_address(market_account, &open_data.market_mint, program_id)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let mark_price = load_price_feed(price_feed_account, &open_data.market_mint, &config, program_id)?.effective(open_data.direction, true);
+    let manager_lamports = manager_account.lamports();
+    
+    let position = open_funded_position(
+        accounts,
+        vault_account,
+        manager_account,
+        vault_account,
+        position_account,
+        nonce_counter_account,
+        position_index_account,
+        market_account,
+        dex_fees_account,
+        system_program,
+        &mut market_state,
+        &config,
+        &open_data.market_mint,
+        symbol_registry.market_symbol,
+        open_data.direction,
+        open_data.leverage,
+        open_data.amount,
+        mark_price,
+        TransferReason::ManagedVaultFill,
+        program_id,
+    )?;
+    save_market_state(market_account, &market_state)?;
+    
+    let reimbursement = manager_lamports.saturating_sub(manager_account.lamports());
+    move_lamports(vault_account, manager_account, reimbursement, TransferReason::KeeperReimbursement)?;
+    
+    vault.positions.push(ManagedVaultPosition {
+        position_nonce: position.position_nonce,
+        collateral: position.paid_amount,
+    });
+    vault.serialize(&mut *vault_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::ManagedVaultPositionOpened(ManagedVaultEvent {
+        vault: *vault_account.key,
+        manager: vault.manager,
+        account: *position_account.key,
+        position_nonce: position.position_nonce,
+        amount: position.paid_amount,
+        shares: vault.total_shares,
+        pnl: 0,
+        net_asset_value: managed_vault_net_asset_value(vault_account, &vault)?,
+    }))?;
+    
+    msg!(
+        "Managed vault {} opened position {} at {}",
+        vault.vault_id,
+        position.position_nonce,
+        position.entry_price
+    );
+    
+    Ok(())
+}
+
+/// Manager closes a vault position at the oracle price, settling back into the vault. A position
+/// that was already liquidated or stopped out is just dropped from the vault's books, its
+/// remaining collateral having been returned to the vault by that settlement. Each settlement
+/// then charges the performance fee on any gain above the high-water mark.
+pub(crate) fn process_managed_vault_close(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    close_data: ManagedVaultCloseData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let vault_account = next_account_info(accounts_iter)?;
+    let manager_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(manager_account)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut vault = load_managed_vault(vault_account, manager_account.key, close_data.vault_id, program_id)?;
+    
+    let slot = vault.position_slot(close_data.position_nonce).ok_or_else(|| {
+        msg!("Position {} is not held by the vault", close_data.position_nonce);
+        ProgramError::InvalidArgument
+    })?;
+    
+    let (position_pda, _position_bump) =
+        find_position_address(vault_account.key, close_data.position_nonce, program_id);
+    
+    assert_key_eq(position_account, &position_pda)?;
+    
+    let open_position = if position_account.owner == program_id && !position_account.data_is_empty() {
+        try_load_position_account(position_account)
+            .ok()
+            .filter(|position| position.is_open() && position.validate(vault_account.key, close_data.position_nonce).is_ok())
+    } else {
+        None
+    };
+    
+    let mut final_pnl = 0;
+    
+    if let Some(position) = open_position {
+        verify_market_address(market_account, &position.market_mint, program_id)?;
+        
+        let oracle_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?;
+        let mark_price = oracle_price.effective(position.direction, false);
+        let mut market_state = touch_market_state(market_account, program_id)?;
+        
+        final_pnl = settle_at_mark_price(
+            &position,
+            position_account,
+            vault_account,
+            market_account,
+            &mut market_state,
+            None,
+            &config,
+            accounts,
+            mark_price,
+            TransferReason::Profit,
+            program_id,
+        )?;
+        
+        emit_settlement_price(&position, &oracle_price, mark_price)?;
+    } else {
+        msg!("Position {} was already settled", close_data.position_nonce);
+    }
+    
+    vault.positions.remove(slot);
+    settle_performance_fee(vault_account, manager_account, &mut vault)?;
+    vault.serialize(&mut *vault_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::ManagedVaultPositionClosed(ManagedVaultEvent {
+        vault: *vault_account.key,
+        manager: vault.manager,
+        account: *position_account.key,
+        position_nonce: close_data.position_nonce,
+        amount: 0,
+        shares: vault.total_shares,
+        pnl: final_pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        net_asset_value: managed_vault_net_asset_value(vault_account, &vault)?,
+    }))?;
+    
+    msg!("Managed vault {} closed position {}, pnl: {}", vault.vault_id, close_data.position_nonce, final_pnl);
+    
+    Ok(())
+}
@@ -0,0 +1,181 @@
+//! Cross-margin deposits, withdrawals and draws.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::{invoke, invoke_signed},
+    system_instruction,
+    sysvar::{rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn load_margin_account(
+    margin_account: &AccountInfo,
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<MarginAccount, ProgramError> {
+    let (margin_pda, _margin_bump) = find_margin_address(owner, program_id);
+    
+    assert_key_eq(margin_account, &margin_pda)?;
+    
+    assert_owned_by(margin_account, program_id)?;
+    
+    MarginAccount::try_from_slice(&margin_account.data.borrow())
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+pub(crate) fn cross_margin_account<'a, 'b>(
+    position: &PositionAccount,
+    margin_account: Option<&'a AccountInfo<'b>>,
+    program_id: &Pubkey,
+) -> Result<Option<&'a AccountInfo<'b>>, ProgramError> {
+    if position.margin_mode != MARGIN_MODE_CROSS {
+        return Ok(None);
+    }
+    
+    let margin_account = margin_account.ok_or_else(|| {
+        msg!("Cross position requires the owner's margin account");
+        ProgramError::from(DexError::MarginAccountRequired)
+    })?;
+    
+    load_margin_account(margin_account, &position.owner, program_id)?;
+    
+    Ok(Some(margin_account))
+}
+
+pub(crate) fn margin_available(margin_account: &AccountInfo) -> Result<u64, ProgramError> {
+    let rent = Rent::get()?;
+    
+    Ok(margin_account
+        .lamports()
+        .saturating_sub(rent.minimum_balance(margin_account.data_len())))
+}
+
+pub(crate) fn draw_from_margin(
+    margin_account: &AccountInfo,
+    destination_account: &AccountInfo,
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    let drawn = amount.min(margin_available(margin_account)?);
+    
+    move_lamports(margin_account, destination_account, drawn, TransferReason::MarginDraw)?;
+    
+    Ok(drawn)
+}
+
+pub(crate) fn process_deposit_margin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    margin_data: MarginTransferData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let margin_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let (margin_pda, margin_bump) = find_margin_address(owner_account.key, program_id);
+    
+    assert_key_eq(margin_account, &margin_pda)?;
+    
+    if margin_account.data_is_empty() {
+        let margin = MarginAccount {
+            owner: *owner_account.key,
+        };
+        
+        let serialized_data = margin.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+        let data_len = serialized_data.len();
+        
+        let rent = Rent::get()?;
+        let minimum_balance = rent.minimum_balance(data_len);
+        
+        let margin_seeds = &[
+            b"uranus_margin".as_ref(),
+            owner_account.key.as_ref(),
+            &[margin_bump],
+        ];
+        
+        invoke_signed(
+            &system_instruction::create_account(
+                owner_account.key,
+                margin_account.key,
+                minimum_balance,
+                data_len as u64,
+                program_id,
+            ),
+            &[
+                owner_account.clone(),
+                margin_account.clone(),
+                system_program.clone(),
+            ],
+            &[margin_seeds],
+        )?;
+        
+        margin.serialize(&mut *margin_account.data.borrow_mut())?;
+    } else {
+        load_margin_account(margin_account, owner_account.key, program_id)?;
+    }
+    
+    invoke(
+        &system_instruction::transfer(
+            owner_account.key,
+            margin_account.key,
+            margin_data.amount,
+        ),
+        &[
+            owner_account.clone(),
+            margin_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    msg!("Margin deposited: {} lamports", margin_data.amount);
+    
+    Ok(())
+}
+
+pub(crate) fn process_withdraw_margin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    margin_data: MarginTransferData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let margin_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    load_margin_account(margin_account, owner_account.key, program_id)?;
+    check_not_frozen(accounts, &[owner_account.key], program_id)?;
+    
+    let available = margin_available(margin_account)?;
+    
+    if available < margin_data.amount {
+        msg!("Insufficient margin balance");
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    check_portfolio_health(
+        accounts,
+        owner_account.key,
+        available - margin_data.amount,
+        None,
+        &load_config(config_account, program_id)?,
+        program_id,
+    )?;
+    
+    move_lamports(margin_account, owner_account, margin_data.amount, TransferReason::MarginWithdrawal)?;
+    
+    msg!("Margin withdrawn: {} lamports", margin_data.amount);
+    
+    Ok(())
+}
@@ -0,0 +1,1691 @@
+//! Per-market state, admin market parameters, funding and liquidity transfers.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Creates a market's liquidity PDA with initialized state (config admin only). Positions and
+/// orders can only be opened on markets created here. Repeating it for a market this
+/// instruction already created succeeds without changes; a listed or migrated market conflicts.
+pub(crate) fn process_init_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    market_data: InitMarketData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let symbol_registry = load_symbol_registry(symbol_registry_account, &market_data.market_mint, program_id)?;
+    
+    let (market_liquidity_pda, market_bump) = find_market_address(&market_data.market_mint, program_id);
+    
+    assert_key_eq(market_account, &market_liquidity_pda)?;
+    
+    if market_account.owner == program_id {
+        let market_state = deserialize_padded::<MarketState>(&market_account.data.borrow())?;
+        
+        if market_state.lister != Pubkey::default() || market_state.migrated_to != Pubkey::default() {
+            msg!("Market already initialized through a listing or migration");
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        
+        msg!("Market {} already initialized", market_account.key);
+        return Ok(());
+    }
+    
+    let rent_deposit = Rent::get()?.minimum_balance(MarketState::LEN);
+    
+    create_pda_account(
+        market_account,
+        admin_account,
+        system_program,
+        rent_deposit,
+        MarketState::LEN,
+        &[b"uranus_market", market_data.market_mint.as_ref(), MARKET_SEED_VERSION, &[market_bump]],
+        program_id,
+    )?;
+    
+    // Liquidity only enters through deposits the market's ledger records.
+    let stray = market_account.lamports().saturating_sub(rent_deposit);
+    move_lamports(market_account, admin_account, stray, TransferReason::CollateralReturn)?;
+    
+    MarketState {
+        market_mint: market_data.market_mint,
+        ..MarketState::default()
+    }
+    .serialize(&mut *market_account.data.borrow_mut())?;
+    
+    let slot = Clock::get()?.slot;
+    
+    emit_event(&DexEvent::MarketInitialized(MarketInitializedEvent {
+        market_mint: market_data.market_mint,
+        market: *market_account.key,
+        authority: *admin_account.key,
+        slot,
+    }))?;
+    
+    msg!(
+        "Market {} initialized for {} ({})",
+        market_account.key,
+        fixed_array_to_string(&symbol_registry.market_symbol)?,
+        market_data.market_mint
+    );
+    
+    Ok(())
+}
+
+/// Lists a market without the admin: the lister posts the config's listing bond and names an
+/// oracle feed, and the market stays pending until `ACTIVATE_MARKET` after the veto window.
+pub(crate) fn process_list_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    listing_data: ListMarketData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let lister_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(lister_account)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if !config.feature_enabled(FEATURE_PERMISSIONLESS_LISTING) {
+        msg!("Permissionless listing is disabled");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if symbol_registry_account.data_is_empty() {
+        write_symbol_registry(
+            symbol_registry_account,
+            lister_account,
+            system_program,
+            &SymbolRegistryAccount {
+                market_mint: listing_data.market_mint,
+                market_symbol: listing_data.market_symbol,
+                decimals: listing_data.decimals,
+            },
+            program_id,
+        )?;
+    }
+    
+    let symbol_registry = load_symbol_registry(symbol_registry_account, &listing_data.market_mint, program_id)?;
+    
+    let (market_liquidity_pda, market_bump) = find_market_address(&listing_data.market_mint, program_id);
+    
+    assert_key_eq(market_account, &market_liquidity_pda)?;
+    
+    if market_account.owner == program_id {
+        msg!("Market already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let rent = Rent::get()?;
+    let bond = config.listing_bond_lamports;
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            lister_account.key,
+            market_account.key,
+            rent.minimum_balance(MarketState::LEN).saturating_add(bond),
+            MarketState::LEN as u64,
+            program_id,
+        ),
+        &[
+            lister_account.clone(),
+            market_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[b"uranus_market", listing_data.market_mint.as_ref(), MARKET_SEED_VERSION, &[market_bump]]],
+    )?;
+    
+    let slot = Clock::get()?.slot;
+    
+    MarketState {
+        market_mint: listing_data.market_mint,
+        status: MARKET_STATUS_PENDING_LISTING,
+        lister: *lister_account.key,
+        listing_bond: bond,
+        listing_slot: slot,
+        oracle_feed: listing_data.oracle_feed,
+        ..MarketState::default()
+    }
+    .serialize(&mut *market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::MarketListed(MarketListingEvent {
+        market_mint: listing_data.market_mint,
+        market: *market_account.key,
+        lister: *lister_account.key,
+        bond,
+        oracle_feed: listing_data.oracle_feed,
+        slot,
+    }))?;
+    
+    msg!(
+        "Market {} listed for {} with {} lamports bond, activatable at slot {}",
+        market_account.key,
+        fixed_array_to_string(&symbol_registry.market_symbol)?,
+        bond,
+        slot.saturating_add(config.listing_timelock_slots)
+    );
+    
+    Ok(())
+}
+
+/// Market state of a listing still in `MARKET_STATUS_PENDING_LISTING`.
+fn load_pending_listing(
+    market_account: &AccountInfo,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<MarketState, ProgramError> {
+    verify_market_address(market_account, market_mint, program_id)?;
+    
+    if market_account.owner != program_id {
+        return Err(DexError::MarketNotInitialized.into());
+    }
+    
+    let market_state = load_market_state(market_account, program_id)?;
+    
+    if market_state.status != MARKET_STATUS_PENDING_LISTING {
+        msg!("Market has no pending listing");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    Ok(market_state)
+}
+
+/// Permissionless once the veto window has passed: activates the market and refunds the bond.
+pub(crate) fn process_activate_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    listing_data: MarketListingData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let lister_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut market_state = load_pending_listing(market_account, &listing_data.market_mint, program_id)?;
+    
+    if lister_account.key != &market_state.lister {
+        msg!("Bond refund must go to the lister {}", market_state.lister);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let slot = Clock::get()?.slot;
+    let activation_slot = market_state.listing_activation_slot(config.listing_timelock_slots);
+    
+    if slot < activation_slot {
+        msg!("Listing can be activated at slot {}", activation_slot);
+        return Err(DexError::ListingTimelockPending.into());
+    }
+    
+    let bond = market_state.listing_bond;
+    move_lamports(market_account, lister_account, bond, TransferReason::ListingBondRefund)?;
+    
+    market_state.status = MARKET_STATUS_ACTIVE;
+    market_state.listing_bond = 0;
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::MarketActivated(MarketListingEvent {
+        market_mint: listing_data.market_mint,
+        market: *market_account.key,
+        lister: market_state.lister,
+        bond,
+        oracle_feed: market_state.oracle_feed,
+        slot,
+    }))?;
+    
+    msg!("Market {} activated, bond of {} lamports refunded", market_account.key, bond);
+    
+    Ok(())
+}
+
+/// Config admin rejects a pending listing inside its veto window: the bond goes to the fee
+/// vault, the rent back to the lister, and the market PDA is closed.
+pub(crate) fn process_veto_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    listing_data: MarketListingData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let lister_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    assert_fee_vault(dex_fees_account, program_id)?;
+    
+    let market_state = load_pending_listing(market_account, &listing_data.market_mint, program_id)?;
+    
+    if lister_account.key != &market_state.lister {
+        msg!("Rent refund must go to the lister {}", market_state.lister);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let slot = Clock::get()?.slot;
+    
+    if slot >= market_state.listing_activation_slot(config.listing_timelock_slots) {
+        return Err(DexError::ListingVetoWindowClosed.into());
+    }
+    
+    let bond = market_state.listing_bond.min(market_account.lamports());
+    move_lamports(market_account, dex_fees_account, bond, TransferReason::ListingBondSlash)?;
+    move_lamports(market_account, lister_account, market_account.lamports(), TransferReason::RentRefund)?;
+    zero_account_data(market_account)?;
+    
+    emit_event(&DexEvent::MarketVetoed(MarketListingEvent {
+        market_mint: listing_data.market_mint,
+        market: *market_account.key,
+        lister: market_state.lister,
+        bond,
+        oracle_feed: market_state.oracle_feed,
+        slot,
+    }))?;
+    
+    msg!("Market listing {} vetoed, bond of {} lamports slashed", market_account.key, bond);
+    
+    Ok(())
+}
+
+/// Puts an active market into close-only mode (config admin only); after `grace_slots` anyone
+/// can settle the remaining positions at the oracle price.
+pub(crate) fn process_delist_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    delist_data: DelistMarketData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    verify_market_address(market_account, &delist_data.market_mint, program_id)?;
+    
+    let mut market_state = load_tradable_market_state(market_account, program_id)?;
+    
+    if market_account.data_len() < MarketState::LEN {
+        msg!("Market state not stored, account needs resizing");
+        return Err(DexError::AccountNeedsMigration.into());
+    }
+    
+    let wind_down_slot = Clock::get()?.slot.saturating_add(delist_data.grace_slots);
+    
+    market_state.status = MARKET_STATUS_CLOSE_ONLY;
+    market_state.wind_down_slot = wind_down_slot;
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::MarketDelisted(MarketDelistedEvent {
+        market_mint: delist_data.market_mint,
+        market: *market_account.key,
+        wind_down_slot,
+    }))?;
+    
+    msg!("Market {} is close-only, positions settle from slot {}", market_account.key, wind_down_slot);
+    
+    Ok(())
+}
+
+/// Closes a wound-down market with no open interest left (config admin only), returning its
+/// liquidity to the DEX authority and reclaiming the PDA's rent.
+pub(crate) fn process_close_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    close_data: CloseMarketData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    assert_key_eq(dex_account, &load_authority(accounts, program_id)?.dex_authority)?;
+    
+    verify_market_address(market_account, &close_data.market_mint, program_id)?;
+    
+    if market_account.owner != program_id {
+        return Err(DexError::MarketNotInitialized.into());
+    }
+    
+    let market_state = load_market_state(market_account, program_id)?;
+    
+    if !market_state.is_winding_down(Clock::get()?.slot) {
+        msg!("Market must be delisted and past slot {}", market_state.wind_down_slot);
+        return Err(DexError::MarketWindDownPending.into());
+    }
+    
+    if market_state.open_interest_long != 0 || market_state.open_interest_short != 0 {
+        msg!(
+            "Open interest remaining: {} long, {} short",
+            market_state.open_interest_long,
+            market_state.open_interest_short
+        );
+        return Err(DexError::MarketHasOpenInterest.into());
+    }
+    
+    let returned_liquidity = market_account.lamports();
+    move_lamports(market_account, dex_account, returned_liquidity, TransferReason::MarketClosure)?;
+    zero_account_data(market_account)?;
+    
+    emit_event(&DexEvent::MarketClosed(MarketClosedEvent {
+        market_mint: close_data.market_mint,
+        market: *market_account.key,
+        returned_liquidity,
+    }))?;
+    
+    msg!("Market {} closed, {} lamports returned", market_account.key, returned_liquidity);
+    
+    Ok(())
+}
+
+/// Closes a wound-down market left with nothing in it (config admin only): no open interest,
+/// LP shares, unclaimed LP fees, listing bond or liquidity above the rent floor. The rent goes to
+/// the DEX fee treasury, so dead markets stop holding it forever. SPL-settled markets keep their
+/// settlement vault and cannot be reclaimed here.
+pub(crate) fn process_reclaim_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    reclaim_data: CloseMarketData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    assert_key_eq(treasury_account, &load_authority(accounts, program_id)?.fees_wallet)?;
+    
+    verify_market_address(market_account, &reclaim_data.market_mint, program_id)?;
+    
+    if market_account.owner != program_id {
+        return Err(DexError::MarketNotInitialized.into());
+    }
+    
+    let market_state = load_market_state(market_account, program_id)?;
+    
+    if !market_state.is_winding_down(Clock::get()?.slot) {
+        msg!("Market must be delisted and past slot {}", market_state.wind_down_slot);
+        return Err(DexError::MarketWindDownPending.into());
+    }
+    
+    check_native_settlement(&market_state)?;
+    
+    if market_state.open_interest_long != 0 || market_state.open_interest_short != 0 {
+        msg!(
+            "Open interest remaining: {} long, {} short",
+            market_state.open_interest_long,
+            market_state.open_interest_short
+        );
+        return Err(DexError::MarketHasOpenInterest.into());
+    }
+    
+    let liquidity = market_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(market_account.data_len()));
+    
+    if market_state.total_lp_shares != 0
+        || market_state.unclaimed_lp_fees != 0
+        || market_state.listing_bond != 0
+        || liquidity != 0
+    {
+        msg!(
+            "Market still holds {} lamports of liquidity and {} LP shares",
+            liquidity,
+            market_state.total_lp_shares
+        );
+        return Err(DexError::MarketNotEmpty.into());
+    }
+    
+    let reclaimed_rent = market_account.lamports();
+    move_lamports(market_account, treasury_account, reclaimed_rent, TransferReason::RentRefund)?;
+    zero_account_data(market_account)?;
+    
+    emit_event(&DexEvent::MarketClosed(MarketClosedEvent {
+        market_mint: reclaim_data.market_mint,
+        market: *market_account.key,
+        returned_liquidity: reclaimed_rent,
+    }))?;
+    
+    msg!("Market {} reclaimed, {} lamports of rent to the treasury", market_account.key, reclaimed_rent);
+    
+    Ok(())
+}
+
+/// Seed version under which `market_account` is the market PDA for `market_mint`, with its bump.
+pub(crate) fn market_seed_version(
+    market_account: &AccountInfo,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Option<(&'static [u8], u8)> {
+    MARKET_SEED_VERSIONS.iter().find_map(|version| {
+        let (address, bump) = find_market_address_with_version(market_mint, version, program_id);
+        (address == *market_account.key).then_some((*version, bump))
+    })
+}
+
+/// Accepts the market PDA of `market_mint` under any supported seed version, unless it has been
+/// migrated and only holds a redirect to its new address.
+pub(crate) fn verify_market_address(
+    market_account: &AccountInfo,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if market_seed_version(market_account, market_mint, program_id).is_none() {
+        msg!("Invalid market account");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let market_state = load_market_state(market_account, program_id)?;
+    
+    if market_state.status == MARKET_STATUS_MIGRATED {
+        msg!("Market migrated to {}", market_state.migrated_to);
+        return Err(DexError::MarketMigrated.into());
+    }
+    
+    Ok(())
+}
+
+/// Moves a market's liquidity and state to its PDA under the latest seed version (config admin
+/// only, who funds the new account's rent). The old PDA keeps its rent and a `migrated_to`
+/// redirect so stale clients get `MarketMigrated` instead of an empty market.
+pub(crate) fn process_migrate_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    migrate_data: MigrateMarketData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let old_market_account = next_account_info(accounts_iter)?;
+    let new_market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let market_mint = migrate_data.market_mint;
+    
+    verify_market_address(old_market_account, &market_mint, program_id)?;
+    
+    if old_market_account.owner != program_id {
+        return Err(DexError::MarketNotInitialized.into());
+    }
+    
+    let latest_version = MARKET_SEED_VERSIONS[MARKET_SEED_VERSIONS.len() - 1];
+    let (new_market_pda, new_market_bump) = find_market_address_with_version(&market_mint, latest_version, program_id);
+    
+    if new_market_account.key != &new_market_pda || old_market_account.key == &new_market_pda {
+        msg!("New market account must be the {} PDA", String::from_utf8_lossy(latest_version));
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if new_market_account.owner == program_id {
+        msg!("Market already initialized");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let mut market_state = prepare_market_state(
+        old_market_account,
+        admin_account,
+        system_program,
+        &market_mint,
+        program_id,
+    )?;
+    market_state.accrue_borrow(old_market_account.lamports(), Clock::get()?.slot);
+    
+    let rent = Rent::get()?;
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            admin_account.key,
+            new_market_account.key,
+            rent.minimum_balance(MarketState::LEN),
+            MarketState::LEN as u64,
+            program_id,
+        ),
+        &[
+            admin_account.clone(),
+            new_market_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[b"uranus_market", market_mint.as_ref(), latest_version, &[new_market_bump]]],
+    )?;
+    
+    let liquidity = old_market_account
+        .lamports()
+        .saturating_sub(rent.minimum_balance(old_market_account.data_len()));
+    move_lamports(old_market_account, new_market_account, liquidity, TransferReason::MarketMigration)?;
+    
+    market_state.serialize(&mut *new_market_account.data.borrow_mut())?;
+    
+    MarketState {
+        market_mint,
+        status: MARKET_STATUS_MIGRATED,
+        migrated_to: *new_market_account.key,
+        ..MarketState::default()
+    }
+    .serialize(&mut *old_market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::MarketMigrated(MarketMigratedEvent {
+        market_mint,
+        from: *old_market_account.key,
+        to: *new_market_account.key,
+        liquidity,
+    }))?;
+    
+    msg!("Market {} migrated to {} with {} lamports", old_market_account.key, new_market_account.key, liquidity);
+    
+    Ok(())
+}
+
+/// Moves a position to another market (config admin only), with its share of the old market's
+/// liquidity in proportion to its size over the old market's open interest. Accrued funding and
+/// borrow carry over by rebasing the snapshots onto the new market's indices. Positions being
+/// settled or holding a residual claim cannot move.
+pub(crate) fn process_migrate_position_market(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    migrate_data: MigratePositionMarketData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let old_market_account = next_account_info(accounts_iter)?;
+    let new_market_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let mut position = try_load_position_account(position_account)?;
+    
+    if position.position_nonce != migrate_data.position_nonce {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    position.validate_address(position_account.key, program_id)?;
+    validate_market_symbol(&migrate_data.new_market_symbol)?;
+    
+    if !matches!(position.status(), PositionStatus::Pending | PositionStatus::Open) {
+        msg!("Position {} is closing and cannot change market", position.position_nonce);
+        return Err(DexError::InvalidPositionTransition.into());
+    }
+    
+    if migrate_data.new_market_mint == position.market_mint {
+        msg!("Position is already on market {}", position.market_mint);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    verify_market_address(old_market_account, &position.market_mint, program_id)?;
+    verify_market_address(new_market_account, &migrate_data.new_market_mint, program_id)?;
+    
+    let mut old_state = touch_market_state(old_market_account, program_id)?;
+    load_tradable_market_state(new_market_account, program_id)?;
+    let mut new_state = touch_market_state(new_market_account, program_id)?;
+    
+    if new_state.contract_type != position.contract_type {
+        msg!("Market {} does not trade this contract type", migrate_data.new_market_mint);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let rent = Rent::get()?;
+    let available = old_market_account
+        .lamports()
+        .saturating_sub(rent.minimum_balance(old_market_account.data_len()));
+    let open_interest = old_state.open_interest_long.saturating_add(old_state.open_interest_short);
+    let liquidity = migration_liquidity(available, position.position_size, open_interest)
+        .min(new_state.liquidity_headroom(new_market_account.lamports()));
+    
+    let funding_delta = old_state.funding_index(position.direction).saturating_sub(position.funding_index_snapshot);
+    let borrow_delta = old_state.cumulative_borrow_index.saturating_sub(position.borrow_index_snapshot);
+    position.funding_index_snapshot = new_state.funding_index(position.direction).saturating_sub(funding_delta);
+    position.borrow_index_snapshot = new_state.cumulative_borrow_index.saturating_sub(borrow_delta);
+    
+    old_state.remove_open_interest(position.direction, position.position_size);
+    new_state.add_open_interest(position.direction, position.position_size);
+    save_market_state(old_market_account, &old_state)?;
+    save_market_state(new_market_account, &new_state)?;
+    
+    if liquidity > 0 {
+        transfer_market_liquidity(old_market_account, new_market_account, liquidity, program_id)?;
+    }
+    
+    let from_market_mint = position.market_mint;
+    position.market_mint = migrate_data.new_market_mint;
+    position.market_symbol = migrate_data.new_market_symbol;
+    save_position_account(position_account, &position)?;
+    
+    emit_event(&DexEvent::PositionMarketMigrated(PositionMarketMigratedEvent {
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        from_market_mint,
+        to_market_mint: position.market_mint,
+        liquidity,
+    }))?;
+    
+    msg!("Position {} moved from market {} to {} with {} lamports", position.position_nonce, from_market_mint, position.market_mint, liquidity);
+    
+    Ok(())
+}
+
+/// State of a market that accepts new positions and orders.
+pub(crate) fn load_tradable_market_state(
+    market_account: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<MarketState, ProgramError> {
+    if market_account.owner != program_id {
+        msg!("Market has not been initialized");
+        return Err(DexError::MarketNotInitialized.into());
+    }
+    
+    let market_state = load_market_state(market_account, program_id)?;
+    
+    if !market_state.is_active() {
+        msg!("Market is not active");
+        return Err(DexError::MarketNotActive.into());
+    }
+    
+    check_native_settlement(&market_state)?;
+    
+    Ok(market_state)
+}
+
+/// State of a market PDA; markets created without data read as all-default.
+pub(crate) fn load_market_state(market_account: &AccountInfo, program_id: &Pubkey) -> Result<MarketState, ProgramError> {
+    if market_account.owner != program_id || market_account.data_is_empty() {
+        return Ok(MarketState::default());
+    }
+    
+    deserialize_padded::<MarketState>(&market_account.data.borrow())
+}
+
+/// Verifies a market PDA and grows it to the current `MarketState` layout before it is written.
+pub(crate) fn prepare_market_state<'a>(
+    market_account: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<MarketState, ProgramError> {
+    verify_market_address(market_account, market_mint, program_id)?;
+    
+    assert_owned_by(market_account, program_id)?;
+    assert_writable(market_account)?;
+    
+    let data_len = MarketState::LEN;
+    if market_account.data_len() < data_len {
+        resize_account(market_account, payer_account, system_program, data_len)?;
+    }
+    
+    let mut market_state = deserialize_padded::<MarketState>(&market_account.data.borrow())?;
+    market_state.market_mint = *market_mint;
+    
+    Ok(market_state)
+}
+
+/// Fee schedule for a market PDA; markets without state or overrides use the global defaults.
+pub(crate) fn market_fee_schedule(market_account: &AccountInfo, program_id: &Pubkey) -> Result<FeeSchedule, ProgramError> {
+    Ok(load_market_state(market_account, program_id)?.fee_schedule())
+}
+
+/// Loads a market's state with borrow interest accrued up to the current slot.
+pub(crate) fn touch_market_state(market_account: &AccountInfo, program_id: &Pubkey) -> Result<MarketState, ProgramError> {
+    let mut market_state = load_market_state(market_account, program_id)?;
+    market_state.accrue_borrow(market_account.lamports(), Clock::get()?.slot);
+    
+    Ok(market_state)
+}
+
+/// Funding and borrow accrued by `position` when its market is among `accounts`, zero otherwise.
+/// Health checks take it out of equity so the liquidation threshold moves with unsettled charges.
+pub(crate) fn position_accrued_charges(accounts: &[AccountInfo], position: &PositionAccount, program_id: &Pubkey) -> Result<i128, ProgramError> {
+    let Some(market_account) = accounts
+        .iter()
+        .find(|account| market_seed_version(account, &position.market_mint, program_id).is_some())
+    else {
+        return Ok(0);
+    };
+    
+    Ok(accrued_charges(position, &touch_market_state(market_account, program_id)?))
+}
+
+/// Writes market state back if the PDA already has room for the current layout; markets that
+/// predate it are brought up to size by the next `INITIALIZE` or admin update.
+pub(crate) fn save_market_state(market_account: &AccountInfo, market_state: &MarketState) -> ProgramResult {
+    if market_account.data_len() < MarketState::LEN {
+        msg!("Market state not stored, account needs resizing");
+        return Ok(());
+    }
+    
+    assert_writable(market_account)?;
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_borrow_curve(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    curve_data: BorrowCurveData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    if curve_data.kink_utilization_bps as u64 > BASIS_POINTS_DIVISOR {
+        msg!("Kink utilization must not exceed 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &curve_data.market_mint,
+        program_id,
+    )?;
+    
+    // Interest up to now accrues on the old curve.
+    market_state.accrue_borrow(market_account.lamports(), Clock::get()?.slot);
+    
+    market_state.borrow_base_rate_bps = curve_data.base_rate_bps;
+    market_state.borrow_slope1_bps = curve_data.slope1_bps;
+    market_state.borrow_slope2_bps = curve_data.slope2_bps;
+    market_state.borrow_kink_utilization_bps = curve_data.kink_utilization_bps;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!(
+        "Borrow curve for {}: base {} bps, slope {} / {} bps, kink {} bps",
+        curve_data.market_mint,
+        curve_data.base_rate_bps,
+        curve_data.slope1_bps,
+        curve_data.slope2_bps,
+        curve_data.kink_utilization_bps
+    );
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_market_spread(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    spread_data: MarketSpreadData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    if spread_data.base_bps > spread_data.max_bps || spread_data.max_bps as u64 >= BASIS_POINTS_DIVISOR {
+        msg!("Spread must stay between the base and a cap below 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &spread_data.market_mint,
+        program_id,
+    )?;
+    
+    market_state.spread_base_bps = spread_data.base_bps;
+    market_state.spread_utilization_factor_bps = spread_data.utilization_factor_bps;
+    market_state.spread_skew_factor_bps = spread_data.skew_factor_bps;
+    market_state.spread_max_bps = spread_data.max_bps;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!(
+        "Spread for {}: base {} bps, utilization factor {} bps, skew factor {} bps, max {} bps",
+        spread_data.market_mint,
+        spread_data.base_bps,
+        spread_data.utilization_factor_bps,
+        spread_data.skew_factor_bps,
+        spread_data.max_bps
+    );
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_min_holding(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    holding_data: MinHoldingData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &holding_data.market_mint,
+        program_id,
+    )?;
+    
+    market_state.min_holding_slots = holding_data.min_holding_slots;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!("Minimum holding for {}: {} slots", holding_data.market_mint, holding_data.min_holding_slots);
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_market_liquidity_cap(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cap_data: MarketLiquidityCapData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &cap_data.market_mint,
+        program_id,
+    )?;
+    
+    market_state.max_liquidity = cap_data.max_liquidity;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!("Liquidity cap for {}: {} lamports", cap_data.market_mint, cap_data.max_liquidity);
+    
+    if cap_data.max_liquidity != 0 && market_account.lamports() > cap_data.max_liquidity {
+        msg!(
+            "Market already holds {} lamports, inflows are blocked until it drops below the cap",
+            market_account.lamports()
+        );
+    }
+    
+    Ok(())
+}
+
+/// Overrides the config `max_owner_notional` for opens on one market; zero falls back to it.
+pub(crate) fn process_set_market_owner_notional_cap(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cap_data: MarketOwnerNotionalCapData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &cap_data.market_mint,
+        program_id,
+    )?;
+    
+    market_state.max_owner_notional = cap_data.max_owner_notional;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!("Owner notional cap for {}: {} lamports", cap_data.market_mint, cap_data.max_owner_notional);
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_funding_params(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    params_data: FundingParamsData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    if params_data.max_funding_rate_bps as u64 > BASIS_POINTS_DIVISOR {
+        msg!("Funding rate cap cannot exceed 100%");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &params_data.market_mint,
+        program_id,
+    )?;
+    
+    market_state.funding_interval_slots = params_data.funding_interval_slots;
+    market_state.max_funding_rate_bps = params_data.max_funding_rate_bps;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!(
+        "Funding for {}: every {} slots, capped at {} bps",
+        params_data.market_mint,
+        params_data.funding_interval_slots,
+        params_data.max_funding_rate_bps
+    );
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_twap_threshold(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    threshold_data: TwapThresholdData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &threshold_data.market_mint,
+        program_id,
+    )?;
+    
+    market_state.twap_threshold_notional = threshold_data.twap_threshold_notional;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!(
+        "Opens above {} on {} must be TWAP-executed",
+        threshold_data.twap_threshold_notional,
+        threshold_data.market_mint
+    );
+    
+    Ok(())
+}
+
+/// Switches a market between linear and inverse contracts (config admin only). Open positions
+/// keep the contract type they were opened with, so the market must have no open interest.
+pub(crate) fn process_set_contract_type(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    contract_data: ContractTypeData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    if contract_data.contract_type > CONTRACT_TYPE_INVERSE {
+        msg!("Unknown contract type {}", contract_data.contract_type);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &contract_data.market_mint,
+        program_id,
+    )?;
+    
+    if market_state.open_interest_long != 0 || market_state.open_interest_short != 0 {
+        msg!("Contract type can only change on a market without open interest");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    market_state.contract_type = contract_data.contract_type;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!(
+        "Market {} trades {} contracts",
+        contract_data.market_mint,
+        if contract_data.contract_type == CONTRACT_TYPE_INVERSE { "inverse" } else { "linear" }
+    );
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_leverage_tiers(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    tiers_data: LeverageTiersData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let mut previous: Option<LeverageTier> = None;
+    for tier in tiers_data.tiers.iter().filter(|tier| tier.max_leverage != 0) {
+        if tier.max_leverage > MAXIMUM_LEVERAGE {
+            msg!("Tier leverage above the {}x maximum", MAXIMUM_LEVERAGE);
+            return Err(DexError::InvalidConfig.into());
+        }
+        
+        if let Some(previous) = previous {
+            if previous.max_notional == 0
+                || (tier.max_notional != 0 && tier.max_notional <= previous.max_notional)
+                || tier.max_leverage > previous.max_leverage
+            {
+                msg!("Tiers must grow in size and shrink in leverage");
+                return Err(DexError::InvalidConfig.into());
+            }
+        }
+        
+        previous = Some(*tier);
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &tiers_data.market_mint,
+        program_id,
+    )?;
+    
+    market_state.leverage_tiers = tiers_data.tiers;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    for tier in market_state.leverage_tiers.iter().filter(|tier| tier.max_leverage != 0) {
+        msg!("Leverage tier: up to {} -> {}x", tier.max_notional, tier.max_leverage);
+    }
+    
+    Ok(())
+}
+
+pub(crate) fn process_accrue_funding(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    funding_data: FundingData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    load_authority(accounts, program_id)?.check_role(dex_account, OPERATOR_ROLE_PRICE_SETTER)?;
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        dex_account,
+        system_program,
+        &funding_data.market_mint,
+        program_id,
+    )?;
+    
+    let slot = Clock::get()?.slot;
+    let elapsed = slot.saturating_sub(market_state.funding_updated_slot);
+    
+    if market_state.funding_interval_slots != 0
+        && market_state.funding_updated_slot != 0
+        && elapsed < market_state.funding_interval_slots
+    {
+        msg!("Funding accrues every {} slots, {} elapsed", market_state.funding_interval_slots, elapsed);
+        return Err(DexError::FundingIntervalNotElapsed.into());
+    }
+    
+    let mut long_index_delta = funding_data.long_index_delta as i128;
+    let mut short_index_delta = funding_data.short_index_delta as i128;
+    
+    if let Some(max_delta) = market_state.max_funding_index_delta() {
+        long_index_delta = long_index_delta.clamp(-max_delta, max_delta);
+        short_index_delta = short_index_delta.clamp(-max_delta, max_delta);
+        
+        if long_index_delta != funding_data.long_index_delta as i128
+            || short_index_delta != funding_data.short_index_delta as i128
+        {
+            msg!("Funding capped at {} bps", market_state.max_funding_rate_bps);
+        }
+    }
+    
+    market_state.cumulative_funding_long = market_state.cumulative_funding_long.saturating_add(long_index_delta);
+    market_state.cumulative_funding_short = market_state.cumulative_funding_short.saturating_add(short_index_delta);
+    market_state.funding_updated_slot = slot;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!(
+        "Funding index for {}: long {}, short {}",
+        funding_data.market_mint,
+        market_state.cumulative_funding_long,
+        market_state.cumulative_funding_short
+    );
+    
+    Ok(())
+}
+
+pub(crate) fn optional_market_fee_schedule(
+    market_account: Option<&AccountInfo>,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<FeeSchedule, ProgramError> {
+    let Some(market_account) = market_account else {
+        return Ok(FeeSchedule::default());
+    };
+    
+    verify_market_address(market_account, market_mint, program_id)?;
+    
+    market_fee_schedule(market_account, program_id)
+}
+
+pub(crate) fn process_set_market_fees(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fees_data: MarketFeesData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    if fees_data.base_fee_bps != 0
+        && (fees_data.base_fee_bps < config.min_market_base_fee_bps
+            || fees_data.base_fee_bps > config.max_market_base_fee_bps)
+    {
+        msg!(
+            "Base fee must be within {}-{} bps",
+            config.min_market_base_fee_bps,
+            config.max_market_base_fee_bps
+        );
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    if fees_data.leverage_fee_bps > config.max_market_leverage_fee_bps {
+        msg!("Leverage fee must not exceed {} bps", config.max_market_leverage_fee_bps);
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &fees_data.market_mint,
+        program_id,
+    )?;
+    market_state.base_fee_bps = fees_data.base_fee_bps;
+    market_state.leverage_fee_bps = fees_data.leverage_fee_bps;
+    market_state.maker_fee_bps = fees_data.maker_fee_bps;
+    
+    let fees = market_state.fee_schedule();
+    if fees.maker_fee_bps > fees.base_fee_bps {
+        msg!("Maker fee must not exceed the base fee of {} bps", fees.base_fee_bps);
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!(
+        "Market fees for {}: base {} bps, leverage {} bps, maker {} bps",
+        fees_data.market_mint,
+        fees.base_fee_bps,
+        fees.leverage_fee_bps,
+        fees.maker_fee_bps
+    );
+    
+    Ok(())
+}
+
+pub(crate) fn process_market_transfer(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    transfer_data: MarketTransferData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let _from_market_account = next_account_info(accounts_iter)?;
+    let _to_market_account = next_account_info(accounts_iter)?;
+    let from_pda = next_account_info(accounts_iter)?;
+    let to_pda = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    
+    load_authority(accounts, program_id)?.check_role(dex_account, OPERATOR_ROLE_TREASURER).inspect_err(|_| {
+        msg!("Unauthorized market transfer attempt");
+    })?;
+    
+    verify_market_address(from_pda, &transfer_data.from_market_mint, program_id)?;
+    verify_market_address(to_pda, &transfer_data.to_market_mint, program_id)?;
+    
+    assert_owned_by(from_pda, program_id)?;
+    
+    assert_owned_by(to_pda, program_id)?;
+    
+    if from_pda.lamports() == 0 {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    let from_balance = from_pda.lamports();
+    if from_balance < transfer_data.amount {
+        msg!("Insufficient balance in from_market PDA. Has: {}, Requested: {}", 
+             from_balance, transfer_data.amount);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    let rent = Rent::get()?;
+    let min_balance = rent.minimum_balance(from_pda.data_len());
+    if from_pda.lamports().saturating_sub(transfer_data.amount) < min_balance {
+        msg!("Transfer would make from_pda not rent exempt");
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    if from_pda.key == to_pda.key {
+        msg!("Cannot transfer to the same market PDA");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let headroom = load_market_state(to_pda, program_id)?.liquidity_headroom(to_pda.lamports());
+    if transfer_data.amount > headroom {
+        msg!("Transfer exceeds to_market liquidity cap, headroom: {} lamports", headroom);
+        return Err(DexError::MarketLiquidityCapExceeded.into());
+    }
+    
+    transfer_market_liquidity(from_pda, to_pda, transfer_data.amount, program_id)?;
+    
+    msg!("Market PDA transfer completed:");
+    msg!("  From market mint: {}", transfer_data.from_market_mint);
+    msg!("  To market mint: {}", transfer_data.to_market_mint);
+    msg!("  Amount: {} lamports", transfer_data.amount);
+    msg!("  From PDA balance after: {} lamports", from_pda.lamports());
+    msg!("  To PDA balance after: {} lamports", to_pda.lamports());
+    
+    Ok(())
+}
+
+/// Moves `amount` of liquidity between two market PDAs, recording it as a payout in the source
+/// market's ledger and a deposit in the destination's.
+fn transfer_market_liquidity(
+    from_account: &AccountInfo,
+    to_account: &AccountInfo,
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let mut from_state = load_market_state(from_account, program_id)?;
+    let mut to_state = load_market_state(to_account, program_id)?;
+    
+    move_lamports(from_account, to_account, amount, TransferReason::MarketTransfer)?;
+    
+    from_state.record_payout(amount);
+    to_state.record_deposit(amount);
+    save_market_state(from_account, &from_state)?;
+    save_market_state(to_account, &to_state)
+}
+
+/// Moves liquidity between `market_accounts` towards `targets` (amounts above each rent floor),
+/// pairing surpluses with deficits, never filling a market past its liquidity cap and moving at
+/// most `max_moved` in total; returns the amount moved.
+pub(crate) fn rebalance_liquidity(
+    market_accounts: &[&AccountInfo],
+    targets: &[u64],
+    max_moved: u64,
+    program_id: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let rent = Rent::get()?;
+    let available = |account: &AccountInfo| {
+        account.lamports().saturating_sub(rent.minimum_balance(account.data_len()))
+    };
+    let mut moved: u64 = 0;
+    
+    for (from_index, from_account) in market_accounts.iter().enumerate() {
+        for (to_index, to_account) in market_accounts.iter().enumerate() {
+            let surplus = available(from_account).saturating_sub(targets[from_index]);
+            let deficit = targets[to_index]
+                .saturating_sub(available(to_account))
+                .min(load_market_state(to_account, program_id)?.liquidity_headroom(to_account.lamports()));
+            let amount = surplus.min(deficit).min(max_moved.saturating_sub(moved));
+            
+            if amount == 0 {
+                continue;
+            }
+            
+            transfer_market_liquidity(from_account, to_account, amount, program_id)?;
+            moved = moved.saturating_add(amount);
+            
+            msg!("Rebalance: {} lamports {} -> {}", amount, from_account.key, to_account.key);
+        }
+    }
+    
+    Ok(moved)
+}
+
+/// Batched `MARKET_TRANSFER` (DEX authority only): splits the combined liquidity of the given
+/// markets by target weight, with the same PDA and rent-floor checks on every leg.
+pub(crate) fn process_rebalance_markets(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    rebalance_data: RebalanceData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let dex_account = next_account_info(accounts_iter)?;
+    
+    load_authority(accounts, program_id)?.check_role(dex_account, OPERATOR_ROLE_TREASURER).inspect_err(|_| {
+        msg!("Unauthorized market rebalance attempt");
+    })?;
+    
+    let legs = &rebalance_data.legs;
+    
+    if legs.len() < 2 || legs.len() > MAX_REBALANCE_MARKETS {
+        msg!("Rebalance needs between 2 and {} markets", MAX_REBALANCE_MARKETS);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if legs.iter().map(|leg| leg.weight_bps as u64).sum::<u64>() != BASIS_POINTS_DIVISOR {
+        msg!("Rebalance weights must sum to {} bps", BASIS_POINTS_DIVISOR);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let rent = Rent::get()?;
+    let mut market_accounts = Vec::with_capacity(legs.len());
+    let mut total: u64 = 0;
+    
+    for leg in legs {
+        let market_account = next_account_info(accounts_iter)?;
+        
+        verify_market_address(market_account, &leg.market_mint, program_id)?;
+        
+        assert_owned_by(market_account, program_id)?;
+        
+        if market_accounts.iter().any(|other: &&AccountInfo| other.key == market_account.key) {
+            msg!("Market {} listed twice", leg.market_mint);
+            return Err(ProgramError::InvalidArgument);
+        }
+        
+        total = total.saturating_add(
+            market_account
+                .lamports()
+                .saturating_sub(rent.minimum_balance(market_account.data_len())),
+        );
+        market_accounts.push(market_account);
+    }
+    
+    let weights: Vec<u16> = legs.iter().map(|leg| leg.weight_bps).collect();
+    let targets = rebalance_targets(total, &weights);
+    let moved = rebalance_liquidity(&market_accounts, &targets, u64::MAX, program_id)?;
+    
+    msg!("Rebalanced {} lamports across {} markets", moved, legs.len());
+    
+    Ok(())
+}
+
+/// Permissionless crank moving liquidity towards the config's rebalance targets, at most
+/// `rebalance_max_per_call` per call. Market PDAs are passed after the config in target order.
+pub(crate) fn process_crank_rebalance(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let config_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if config.rebalance_max_per_call == 0 || config.active_rebalance_targets().count() < 2 {
+        msg!("Automated rebalancing is not configured");
+        return Err(DexError::InvalidConfig.into());
+    }
+    
+    let rent = Rent::get()?;
+    let mut market_accounts = Vec::with_capacity(MAX_REBALANCE_MARKETS);
+    let mut weights = Vec::with_capacity(MAX_REBALANCE_MARKETS);
+    let mut total: u64 = 0;
+    
+    for target in config.active_rebalance_targets() {
+        let market_account = next_account_info(accounts_iter)?;
+        
+        verify_market_address(market_account, &target.market_mint, program_id)?;
+        
+        assert_owned_by(market_account, program_id)?;
+        
+        total = total.saturating_add(
+            market_account
+                .lamports()
+                .saturating_sub(rent.minimum_balance(market_account.data_len())),
+        );
+        market_accounts.push(market_account);
+        weights.push(target.weight_bps);
+    }
+    
+    let targets = rebalance_targets(total, &weights);
+    let moved = rebalance_liquidity(&market_accounts, &targets, config.rebalance_max_per_call, program_id)?;
+    
+    msg!("Rebalance crank moved {} lamports", moved);
+    
+    Ok(())
+}
+
+/// Market liquidity above the rent floor less fees still owed to LP positions, the value LP
+/// shares are priced against.
+pub(crate) fn lp_pool_value(market_account: &AccountInfo, market_state: &MarketState) -> Result<u64, ProgramError> {
+    Ok(market_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(market_account.data_len()))
+        .saturating_sub(market_state.unclaimed_lp_fees))
+}
+
+/// Rejects an open of `notional` above the config's `max_trade_liquidity_bps` share of the
+/// market's liquidity above rent, a payout the pool could not realistically cover.
+pub(crate) fn check_price_impact(market_account: &AccountInfo, notional: u64, config: &GlobalConfig) -> ProgramResult {
+    if config.max_trade_liquidity_bps == 0 {
+        return Ok(());
+    }
+    
+    let liquidity = market_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(market_account.data_len()));
+    
+    if (notional as u128).saturating_mul(BASIS_POINTS_DIVISOR as u128)
+        > (liquidity as u128).saturating_mul(config.max_trade_liquidity_bps as u128)
+    {
+        msg!(
+            "Notional {} exceeds {} bps of market liquidity {}",
+            notional,
+            config.max_trade_liquidity_bps,
+            liquidity
+        );
+        return Err(DexError::PriceImpactTooHigh.into());
+    }
+    
+    Ok(())
+}
+
+/// Seeds a market from the fee wallet treasury (config admin plus treasury signature) and mints
+/// the matching LP shares to the protocol, so bootstrap liquidity is accounted for like any
+/// other deposit.
+pub(crate) fn process_bootstrap_liquidity(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    bootstrap_data: BootstrapLiquidityData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    if !treasury_account.is_signer || treasury_account.key != &load_authority(accounts, program_id)?.fees_wallet {
+        msg!("Bootstrap liquidity must come from the DEX fee treasury");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    if bootstrap_data.amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &bootstrap_data.market_mint,
+        program_id,
+    )?;
+    
+    check_native_settlement(&market_state)?;
+    
+    if bootstrap_data.amount > market_state.liquidity_headroom(market_account.lamports()) {
+        msg!("Bootstrap exceeds the market liquidity cap");
+        return Err(DexError::MarketLiquidityCapExceeded.into());
+    }
+    
+    let pool_value = lp_pool_value(market_account, &market_state)?;
+    market_state.adopt_unowned_liquidity(pool_value);
+    let shares = lp_shares_for_deposit(bootstrap_data.amount, pool_value, market_state.total_lp_shares);
+    
+    invoke(
+        &system_instruction::transfer(
+            treasury_account.key,
+            market_account.key,
+            bootstrap_data.amount,
+        ),
+        &[
+            treasury_account.clone(),
+            market_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    market_state.total_lp_shares = market_state.total_lp_shares.saturating_add(shares);
+    market_state.protocol_lp_shares = market_state.protocol_lp_shares.saturating_add(shares);
+    market_state.record_deposit(bootstrap_data.amount);
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::LiquidityBootstrapped(LiquidityEvent {
+        market_mint: bootstrap_data.market_mint,
+        provider: *treasury_account.key,
+        amount: bootstrap_data.amount,
+        shares,
+        total_shares: market_state.total_lp_shares,
+    }))?;
+    
+    msg!(
+        "Bootstrapped {} with {} lamports for {} protocol shares ({} total)",
+        bootstrap_data.market_mint,
+        bootstrap_data.amount,
+        shares,
+        market_state.total_lp_shares
+    );
+    
+    Ok(())
+}
@@ -0,0 +1,1240 @@
+//! Instruction dispatch and the account helpers shared by every handler.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::{invoke, invoke_signed},
+    system_instruction,
+    sysvar::{rent::Rent, Sysvar},
+};
+
+mod audit;
+mod authority;
+mod compliance;
+mod compressed;
+mod config;
+mod copy;
+mod dca;
+mod epoch;
+mod fee_vault;
+mod guards;
+mod history;
+mod liquidation;
+mod managed_vault;
+mod margin;
+mod market;
+mod oracle;
+mod orders;
+mod portfolio;
+mod position;
+mod lp;
+mod settlement;
+mod swap;
+mod twap;
+mod user_stats;
+mod vault;
+mod view;
+
+use crate::events::emit_event;
+use crate::*;
+
+use self::audit::*;
+use self::authority::*;
+use self::compliance::*;
+use self::compressed::*;
+use self::config::*;
+use self::copy::*;
+use self::dca::*;
+use self::epoch::*;
+use self::fee_vault::*;
+use self::guards::*;
+use self::history::*;
+use self::liquidation::*;
+use self::lp::*;
+use self::managed_vault::*;
+use self::margin::*;
+use self::market::*;
+use self::oracle::*;
+use self::orders::*;
+use self::portfolio::*;
+use self::position::*;
+use self::settlement::*;
+use self::swap::*;
+use self::twap::*;
+use self::user_stats::*;
+use self::vault::*;
+use self::view::*;
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    if instruction_data.is_empty() {
+        msg!("Empty instruction data");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let instruction_type = instruction_data[0];
+    
+    if !allowed_during_shutdown(instruction_type) {
+        check_not_shut_down(accounts, program_id)?;
+    }
+
+    match instruction_type {
+        INSTRUCTION_INITIALIZE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let initialize_data = InitializePositionData::try_from_slice(&instruction_data[1..])?;
+            process_initialize(program_id, accounts, initialize_data)
+        },
+        INSTRUCTION_DEX_MODIFY => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let dex_data = DexModifyData::unpack(&instruction_data[1..])?;
+            process_dex_modify(program_id, accounts, dex_data)
+        },
+        INSTRUCTION_USER_MODIFY => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let user_data = UserModifyData::unpack(&instruction_data[1..])?;
+            process_user_modify(program_id, accounts, user_data)
+        },
+        INSTRUCTION_PROCESS_PNL => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let pnl_data = ProcessPnlData::try_from_slice(&instruction_data[1..])?;
+            process_pnl(program_id, accounts, pnl_data)
+        },
+        INSTRUCTION_FORCE_CLOSE => {
+            process_force_close(program_id, accounts)
+        },
+        INSTRUCTION_MARKET_TRANSFER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let transfer_data = MarketTransferData::try_from_slice(&instruction_data[1..])?;
+            process_market_transfer(program_id, accounts, transfer_data)
+        },
+        INSTRUCTION_INITIALIZE_CONFIG => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let config_data = ConfigData::try_from_slice(&instruction_data[1..])?;
+            process_initialize_config(program_id, accounts, config_data)
+        },
+        INSTRUCTION_UPDATE_CONFIG => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let config_data = ConfigData::try_from_slice(&instruction_data[1..])?;
+            process_update_config(program_id, accounts, config_data)
+        },
+        INSTRUCTION_REGISTER_SYMBOL => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let register_data = RegisterSymbolData::try_from_slice(&instruction_data[1..])?;
+            process_register_symbol(program_id, accounts, register_data)
+        },
+        INSTRUCTION_MIGRATE_ACCOUNT => {
+            process_migrate_account(program_id, accounts)
+        },
+        INSTRUCTION_RESIZE_POSITION => {
+            process_resize_position(program_id, accounts)
+        },
+        INSTRUCTION_FORCE_CLOSE_UNRECOVERABLE => {
+            process_force_close_unrecoverable(program_id, accounts)
+        },
+        INSTRUCTION_MARK_CORRUPTED => {
+            process_mark_corrupted(program_id, accounts)
+        },
+        INSTRUCTION_OPERATOR_HEARTBEAT => {
+            process_operator_heartbeat(program_id, accounts)
+        },
+        INSTRUCTION_INIT_POSITION_HISTORY => {
+            process_init_position_history(program_id, accounts)
+        },
+        INSTRUCTION_INITIALIZE_AUTHORITY => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let authority_data = AuthorityData::try_from_slice(&instruction_data[1..])?;
+            process_initialize_authority(program_id, accounts, authority_data)
+        },
+        INSTRUCTION_UPDATE_AUTHORITY => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let authority_data = AuthorityData::try_from_slice(&instruction_data[1..])?;
+            process_update_authority(program_id, accounts, authority_data)
+        },
+        INSTRUCTION_SET_OPERATOR => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let operator_data = SetOperatorData::try_from_slice(&instruction_data[1..])?;
+            process_set_operator(program_id, accounts, operator_data)
+        },
+        INSTRUCTION_LIQUIDATE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let liquidate_data = LiquidateData::try_from_slice(&instruction_data[1..])?;
+            process_liquidate(program_id, accounts, liquidate_data)
+        },
+        INSTRUCTION_DEPOSIT_MARGIN => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let margin_data = MarginTransferData::try_from_slice(&instruction_data[1..])?;
+            process_deposit_margin(program_id, accounts, margin_data)
+        },
+        INSTRUCTION_WITHDRAW_MARGIN => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let margin_data = MarginTransferData::try_from_slice(&instruction_data[1..])?;
+            process_withdraw_margin(program_id, accounts, margin_data)
+        },
+        INSTRUCTION_UPDATE_PRICE_FEED => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let feed_data = PriceFeedData::try_from_slice(&instruction_data[1..])?;
+            process_update_price_feed(program_id, accounts, feed_data)
+        },
+        INSTRUCTION_CANCEL_POSITION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let cancel_data = CancelPositionData::try_from_slice(&instruction_data[1..])?;
+            process_cancel_position(program_id, accounts, cancel_data)
+        },
+        INSTRUCTION_EXPIRE_POSITION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let expire_data = ExpirePositionData::try_from_slice(&instruction_data[1..])?;
+            process_expire_position(program_id, accounts, expire_data)
+        },
+        INSTRUCTION_VIEW_POSITION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let view_data = ViewPositionData::try_from_slice(&instruction_data[1..])?;
+            process_view_position(program_id, accounts, view_data)
+        },
+        INSTRUCTION_QUOTE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let initialize_data = InitializePositionData::try_from_slice(&instruction_data[1..])?;
+            process_quote(program_id, accounts, initialize_data)
+        },
+        INSTRUCTION_SET_FEATURE_FLAGS => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let flags_data = FeatureFlagsData::try_from_slice(&instruction_data[1..])?;
+            process_set_feature_flags(program_id, accounts, flags_data)
+        },
+        INSTRUCTION_SET_MARKET_FEES => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let fees_data = MarketFeesData::try_from_slice(&instruction_data[1..])?;
+            process_set_market_fees(program_id, accounts, fees_data)
+        },
+        INSTRUCTION_PLACE_ORDER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let order_data = PlaceOrderData::try_from_slice(&instruction_data[1..])?;
+            process_place_order(program_id, accounts, order_data)
+        },
+        INSTRUCTION_CANCEL_ORDER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let cancel_data = CancelOrderData::try_from_slice(&instruction_data[1..])?;
+            process_cancel_order(program_id, accounts, cancel_data)
+        },
+        INSTRUCTION_MATCH_ORDERS => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let match_data = MatchOrdersData::try_from_slice(&instruction_data[1..])?;
+            process_match_orders(program_id, accounts, match_data)
+        },
+        INSTRUCTION_TAKEOVER_POSITION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let takeover_data = TakeoverData::try_from_slice(&instruction_data[1..])?;
+            process_takeover_position(program_id, accounts, takeover_data)
+        },
+        INSTRUCTION_ACCRUE_FUNDING => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let funding_data = FundingData::try_from_slice(&instruction_data[1..])?;
+            process_accrue_funding(program_id, accounts, funding_data)
+        },
+        INSTRUCTION_SET_BORROW_CURVE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let curve_data = BorrowCurveData::try_from_slice(&instruction_data[1..])?;
+            process_set_borrow_curve(program_id, accounts, curve_data)
+        },
+        INSTRUCTION_SET_MARKET_SPREAD => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let spread_data = MarketSpreadData::try_from_slice(&instruction_data[1..])?;
+            process_set_market_spread(program_id, accounts, spread_data)
+        },
+        INSTRUCTION_SET_LEVERAGE_TIERS => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let tiers_data = LeverageTiersData::try_from_slice(&instruction_data[1..])?;
+            process_set_leverage_tiers(program_id, accounts, tiers_data)
+        },
+        INSTRUCTION_INITIALIZE_INSURANCE_FUND => {
+            process_initialize_insurance_fund(program_id, accounts)
+        },
+        INSTRUCTION_SET_MIN_HOLDING => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let holding_data = MinHoldingData::try_from_slice(&instruction_data[1..])?;
+            process_set_min_holding(program_id, accounts, holding_data)
+        },
+        INSTRUCTION_INIT_MARKET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let market_data = InitMarketData::try_from_slice(&instruction_data[1..])?;
+            process_init_market(program_id, accounts, market_data)
+        },
+        INSTRUCTION_LIST_MARKET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let listing_data = ListMarketData::try_from_slice(&instruction_data[1..])?;
+            process_list_market(program_id, accounts, listing_data)
+        },
+        INSTRUCTION_ACTIVATE_MARKET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let listing_data = MarketListingData::try_from_slice(&instruction_data[1..])?;
+            process_activate_market(program_id, accounts, listing_data)
+        },
+        INSTRUCTION_VETO_MARKET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let listing_data = MarketListingData::try_from_slice(&instruction_data[1..])?;
+            process_veto_market(program_id, accounts, listing_data)
+        },
+        INSTRUCTION_DELIST_MARKET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let delist_data = DelistMarketData::try_from_slice(&instruction_data[1..])?;
+            process_delist_market(program_id, accounts, delist_data)
+        },
+        INSTRUCTION_SETTLE_DELISTED_POSITION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let settle_data = SettleDelistedData::try_from_slice(&instruction_data[1..])?;
+            process_settle_delisted_position(program_id, accounts, settle_data)
+        },
+        INSTRUCTION_CLOSE_MARKET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let close_data = CloseMarketData::try_from_slice(&instruction_data[1..])?;
+            process_close_market(program_id, accounts, close_data)
+        },
+        INSTRUCTION_MIGRATE_MARKET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let migrate_data = MigrateMarketData::try_from_slice(&instruction_data[1..])?;
+            process_migrate_market(program_id, accounts, migrate_data)
+        },
+        INSTRUCTION_DEPOSIT_PROGRAM_VAULT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let vault_data = VaultTransferData::try_from_slice(&instruction_data[1..])?;
+            process_deposit_program_vault(program_id, accounts, vault_data)
+        },
+        INSTRUCTION_WITHDRAW_PROGRAM_VAULT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let vault_data = VaultTransferData::try_from_slice(&instruction_data[1..])?;
+            process_withdraw_program_vault(program_id, accounts, vault_data)
+        },
+        INSTRUCTION_REBALANCE_MARKETS => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let rebalance_data = RebalanceData::try_from_slice(&instruction_data[1..])?;
+            process_rebalance_markets(program_id, accounts, rebalance_data)
+        },
+        INSTRUCTION_SET_REBALANCE_TARGETS => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let targets_data = RebalanceTargetsData::try_from_slice(&instruction_data[1..])?;
+            process_set_rebalance_targets(program_id, accounts, targets_data)
+        },
+        INSTRUCTION_SET_FEE_ROUTES => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let routes_data = FeeRoutesData::try_from_slice(&instruction_data[1..])?;
+            process_set_fee_routes(program_id, accounts, routes_data)
+        },
+        INSTRUCTION_INIT_FEE_VAULT => {
+            process_init_fee_vault(program_id, accounts)
+        },
+        INSTRUCTION_WITHDRAW_FEES => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let withdraw_data = WithdrawFeesData::try_from_slice(&instruction_data[1..])?;
+            process_withdraw_fees(program_id, accounts, withdraw_data)
+        },
+        INSTRUCTION_VIEW_PROTOCOL_REVENUE => {
+            process_view_protocol_revenue(program_id, accounts)
+        },
+        INSTRUCTION_SCHEDULE_SHUTDOWN => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let shutdown_data = ScheduleShutdownData::try_from_slice(&instruction_data[1..])?;
+            process_schedule_shutdown(program_id, accounts, shutdown_data)
+        },
+        INSTRUCTION_CRANK_REBALANCE => {
+            process_crank_rebalance(program_id, accounts)
+        },
+        INSTRUCTION_INITIALIZE_MARKET_INSURANCE_FUND => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let insurance_data = MarketInsuranceFundData::try_from_slice(&instruction_data[1..])?;
+            process_initialize_market_insurance_fund(program_id, accounts, insurance_data)
+        },
+        INSTRUCTION_SET_MARKET_LIQUIDITY_CAP => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let cap_data = MarketLiquidityCapData::try_from_slice(&instruction_data[1..])?;
+            process_set_market_liquidity_cap(program_id, accounts, cap_data)
+        },
+        INSTRUCTION_BOOTSTRAP_LIQUIDITY => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let bootstrap_data = BootstrapLiquidityData::try_from_slice(&instruction_data[1..])?;
+            process_bootstrap_liquidity(program_id, accounts, bootstrap_data)
+        },
+        INSTRUCTION_DEPOSIT_LIQUIDITY => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let deposit_data = LiquidityDepositData::try_from_slice(&instruction_data[1..])?;
+            process_deposit_liquidity(program_id, accounts, deposit_data)
+        },
+        INSTRUCTION_WITHDRAW_LIQUIDITY => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let withdraw_data = LiquidityWithdrawData::try_from_slice(&instruction_data[1..])?;
+            process_withdraw_liquidity(program_id, accounts, withdraw_data)
+        },
+        INSTRUCTION_CLAIM_LP_FEES => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let claim_data = ClaimLpFeesData::try_from_slice(&instruction_data[1..])?;
+            process_claim_lp_fees(program_id, accounts, claim_data)
+        },
+        INSTRUCTION_VIEW_LP_SHARE_PRICE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let view_data = ViewLpSharePriceData::try_from_slice(&instruction_data[1..])?;
+            process_view_lp_share_price(program_id, accounts, view_data)
+        },
+        INSTRUCTION_CLAIM_RESIDUAL_PROFIT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let claim_data = ClaimResidualProfitData::try_from_slice(&instruction_data[1..])?;
+            process_claim_residual_profit(program_id, accounts, claim_data)
+        },
+        INSTRUCTION_REALIZE_PROFIT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let realize_data = RealizeProfitData::try_from_slice(&instruction_data[1..])?;
+            process_realize_profit(program_id, accounts, realize_data)
+        },
+        INSTRUCTION_ADD_POSITION_MARGIN => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let margin_data = PositionMarginData::try_from_slice(&instruction_data[1..])?;
+            process_add_position_margin(program_id, accounts, margin_data)
+        },
+        INSTRUCTION_REMOVE_POSITION_MARGIN => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let margin_data = PositionMarginData::try_from_slice(&instruction_data[1..])?;
+            process_remove_position_margin(program_id, accounts, margin_data)
+        },
+        INSTRUCTION_STOP_OUT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let stop_out_data = StopOutData::try_from_slice(&instruction_data[1..])?;
+            process_stop_out(program_id, accounts, stop_out_data)
+        },
+        INSTRUCTION_SET_GUARANTEED_STOP => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let stop_data = GuaranteedStopData::try_from_slice(&instruction_data[1..])?;
+            process_set_guaranteed_stop(program_id, accounts, stop_data)
+        },
+        INSTRUCTION_TRIGGER_GUARANTEED_STOP => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let trigger_data = TriggerGuaranteedStopData::try_from_slice(&instruction_data[1..])?;
+            process_trigger_guaranteed_stop(program_id, accounts, trigger_data)
+        },
+        INSTRUCTION_PLACE_CLOSE_ORDER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let place_data = PlaceCloseOrderData::try_from_slice(&instruction_data[1..])?;
+            process_place_close_order(program_id, accounts, place_data)
+        },
+        INSTRUCTION_CANCEL_CLOSE_ORDER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let cancel_data = CloseOrderData::try_from_slice(&instruction_data[1..])?;
+            process_cancel_close_order(program_id, accounts, cancel_data)
+        },
+        INSTRUCTION_EXECUTE_CLOSE_ORDER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let execute_data = CloseOrderData::try_from_slice(&instruction_data[1..])?;
+            process_execute_close_order(program_id, accounts, execute_data)
+        },
+        INSTRUCTION_PLACE_SCHEDULED_ORDER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let place_data = PlaceScheduledOrderData::try_from_slice(&instruction_data[1..])?;
+            process_place_scheduled_order(program_id, accounts, place_data)
+        },
+        INSTRUCTION_CANCEL_SCHEDULED_ORDER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let cancel_data = ScheduledOrderData::try_from_slice(&instruction_data[1..])?;
+            process_cancel_scheduled_order(program_id, accounts, cancel_data)
+        },
+        INSTRUCTION_EXECUTE_SCHEDULED_ORDER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let execute_data = ScheduledOrderData::try_from_slice(&instruction_data[1..])?;
+            process_execute_scheduled_order(program_id, accounts, execute_data)
+        },
+        INSTRUCTION_CREATE_DCA_SCHEDULE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let create_data = CreateDcaScheduleData::try_from_slice(&instruction_data[1..])?;
+            process_create_dca_schedule(program_id, accounts, create_data)
+        },
+        INSTRUCTION_CANCEL_DCA_SCHEDULE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let cancel_data = DcaScheduleData::try_from_slice(&instruction_data[1..])?;
+            process_cancel_dca_schedule(program_id, accounts, cancel_data)
+        },
+        INSTRUCTION_CRANK_DCA => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let crank_data = DcaScheduleData::try_from_slice(&instruction_data[1..])?;
+            process_crank_dca(program_id, accounts, crank_data)
+        },
+        INSTRUCTION_REGISTER_LEAD_TRADER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let register_data = RegisterLeadTraderData::try_from_slice(&instruction_data[1..])?;
+            process_register_lead_trader(program_id, accounts, register_data)
+        },
+        INSTRUCTION_FOLLOW_LEAD_TRADER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let follow_data = FollowLeadTraderData::try_from_slice(&instruction_data[1..])?;
+            process_follow_lead_trader(program_id, accounts, follow_data)
+        },
+        INSTRUCTION_UNFOLLOW_LEAD_TRADER => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let unfollow_data = UnfollowLeadTraderData::try_from_slice(&instruction_data[1..])?;
+            process_unfollow_lead_trader(program_id, accounts, unfollow_data)
+        },
+        INSTRUCTION_COPY_OPEN => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let copy_data = CopyTradeData::try_from_slice(&instruction_data[1..])?;
+            process_copy_open(program_id, accounts, copy_data)
+        },
+        INSTRUCTION_COPY_CLOSE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let copy_data = CopyTradeData::try_from_slice(&instruction_data[1..])?;
+            process_copy_close(program_id, accounts, copy_data)
+        },
+        INSTRUCTION_CREATE_MANAGED_VAULT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let create_data = CreateManagedVaultData::try_from_slice(&instruction_data[1..])?;
+            process_create_managed_vault(program_id, accounts, create_data)
+        },
+        INSTRUCTION_DEPOSIT_MANAGED_VAULT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let deposit_data = ManagedVaultDepositData::try_from_slice(&instruction_data[1..])?;
+            process_deposit_managed_vault(program_id, accounts, deposit_data)
+        },
+        INSTRUCTION_WITHDRAW_MANAGED_VAULT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let withdraw_data = ManagedVaultWithdrawData::try_from_slice(&instruction_data[1..])?;
+            process_withdraw_managed_vault(program_id, accounts, withdraw_data)
+        },
+        INSTRUCTION_MANAGED_VAULT_OPEN => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let open_data = ManagedVaultOpenData::try_from_slice(&instruction_data[1..])?;
+            process_managed_vault_open(program_id, accounts, open_data)
+        },
+        INSTRUCTION_MANAGED_VAULT_CLOSE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let close_data = ManagedVaultCloseData::try_from_slice(&instruction_data[1..])?;
+            process_managed_vault_close(program_id, accounts, close_data)
+        },
+        INSTRUCTION_CRANK_VAULT_EPOCH => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let epoch_data = ManagedVaultEpochData::try_from_slice(&instruction_data[1..])?;
+            process_crank_vault_epoch(program_id, accounts, epoch_data)
+        },
+        INSTRUCTION_CLAIM_VAULT_REQUEST => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let claim_data = ManagedVaultEpochData::try_from_slice(&instruction_data[1..])?;
+            process_claim_vault_request(program_id, accounts, claim_data)
+        },
+        INSTRUCTION_VIEW_PORTFOLIO_HEALTH => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let view_data = ViewPortfolioHealthData::try_from_slice(&instruction_data[1..])?;
+            process_view_portfolio_health(program_id, accounts, view_data)
+        },
+        INSTRUCTION_NET_POSITIONS => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let net_data = NetPositionsData::try_from_slice(&instruction_data[1..])?;
+            process_net_positions(program_id, accounts, net_data)
+        },
+        INSTRUCTION_SET_HEDGE_MODE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let hedge_data = SetHedgeModeData::try_from_slice(&instruction_data[1..])?;
+            process_set_hedge_mode(program_id, accounts, hedge_data)
+        },
+        INSTRUCTION_REGISTER_INTEGRATOR => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let integrator_data = RegisterIntegratorData::try_from_slice(&instruction_data[1..])?;
+            process_register_integrator(program_id, accounts, integrator_data)
+        },
+        INSTRUCTION_ISSUE_ATTESTATION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let attestation_data = IssueAttestationData::try_from_slice(&instruction_data[1..])?;
+            process_issue_attestation(program_id, accounts, attestation_data)
+        },
+        INSTRUCTION_REVOKE_ATTESTATION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let revoke_data = RevokeAttestationData::try_from_slice(&instruction_data[1..])?;
+            process_revoke_attestation(program_id, accounts, revoke_data)
+        },
+        INSTRUCTION_FREEZE_ACCOUNT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let freeze_data = FreezeData::try_from_slice(&instruction_data[1..])?;
+            process_set_freeze(program_id, accounts, freeze_data, true)
+        },
+        INSTRUCTION_UNFREEZE_ACCOUNT => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let freeze_data = FreezeData::try_from_slice(&instruction_data[1..])?;
+            process_set_freeze(program_id, accounts, freeze_data, false)
+        },
+        INSTRUCTION_BLACKLIST_ADD => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let blacklist_data = BlacklistData::try_from_slice(&instruction_data[1..])?;
+            process_update_blacklist(program_id, accounts, blacklist_data, true)
+        },
+        INSTRUCTION_BLACKLIST_REMOVE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let blacklist_data = BlacklistData::try_from_slice(&instruction_data[1..])?;
+            process_update_blacklist(program_id, accounts, blacklist_data, false)
+        },
+        INSTRUCTION_SET_MARKET_OWNER_NOTIONAL_CAP => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let cap_data = MarketOwnerNotionalCapData::try_from_slice(&instruction_data[1..])?;
+            process_set_market_owner_notional_cap(program_id, accounts, cap_data)
+        },
+        INSTRUCTION_MARGIN_CALL => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let margin_call_data = MarginCallData::try_from_slice(&instruction_data[1..])?;
+            process_margin_call(program_id, accounts, margin_call_data)
+        },
+        INSTRUCTION_VIEW_HEALTH_FACTOR => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let view_data = ViewHealthFactorData::try_from_slice(&instruction_data[1..])?;
+            process_view_health_factor(program_id, accounts, view_data)
+        },
+        INSTRUCTION_QUEUE_LIQUIDATION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let queue_data = QueueLiquidationData::try_from_slice(&instruction_data[1..])?;
+            process_queue_liquidation(program_id, accounts, queue_data)
+        },
+        INSTRUCTION_SET_FALLBACK_ORACLE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let fallback_data = SetFallbackOracleData::try_from_slice(&instruction_data[1..])?;
+            process_set_fallback_oracle(program_id, accounts, fallback_data)
+        },
+        INSTRUCTION_SET_PRICE_SOURCE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let source_data = SetPriceSourceData::try_from_slice(&instruction_data[1..])?;
+            process_set_price_source(program_id, accounts, source_data)
+        },
+        INSTRUCTION_SYNC_PRICE_FEED => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let sync_data = SyncPriceFeedData::try_from_slice(&instruction_data[1..])?;
+            process_sync_price_feed(program_id, accounts, sync_data)
+        },
+        INSTRUCTION_SET_FUNDING_PARAMS => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let params_data = FundingParamsData::try_from_slice(&instruction_data[1..])?;
+            process_set_funding_params(program_id, accounts, params_data)
+        },
+        INSTRUCTION_SET_SETTLEMENT_ASSET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let asset_data = SettlementAssetData::try_from_slice(&instruction_data[1..])?;
+            process_set_settlement_asset(program_id, accounts, asset_data)
+        },
+        INSTRUCTION_SET_CONTRACT_TYPE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let contract_data = ContractTypeData::try_from_slice(&instruction_data[1..])?;
+            process_set_contract_type(program_id, accounts, contract_data)
+        },
+        INSTRUCTION_SWAP => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let swap_data = SwapData::try_from_slice(&instruction_data[1..])?;
+            process_swap(program_id, accounts, swap_data)
+        },
+        INSTRUCTION_SET_TWAP_THRESHOLD => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let threshold_data = TwapThresholdData::try_from_slice(&instruction_data[1..])?;
+            process_set_twap_threshold(program_id, accounts, threshold_data)
+        },
+        INSTRUCTION_CREATE_TWAP_OPEN => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let create_data = CreateTwapOpenData::try_from_slice(&instruction_data[1..])?;
+            process_create_twap_open(program_id, accounts, create_data)
+        },
+        INSTRUCTION_CANCEL_TWAP_OPEN => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let cancel_data = TwapScheduleData::try_from_slice(&instruction_data[1..])?;
+            process_cancel_twap_open(program_id, accounts, cancel_data)
+        },
+        INSTRUCTION_CRANK_TWAP => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let crank_data = TwapScheduleData::try_from_slice(&instruction_data[1..])?;
+            process_crank_twap(program_id, accounts, crank_data)
+        },
+        INSTRUCTION_ROLLOVER_EPOCH => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let rollover_data = EpochRolloverData::try_from_slice(&instruction_data[1..])?;
+            process_rollover_epoch(program_id, accounts, rollover_data)
+        },
+        INSTRUCTION_VERIFY_INVARIANTS => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let verify_data = VerifyInvariantsData::try_from_slice(&instruction_data[1..])?;
+            process_verify_invariants(program_id, accounts, verify_data)
+        },
+        INSTRUCTION_RECLAIM_MARKET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let reclaim_data = CloseMarketData::try_from_slice(&instruction_data[1..])?;
+            process_reclaim_market(program_id, accounts, reclaim_data)
+        },
+        INSTRUCTION_COLLECT_GARBAGE => {
+            process_collect_garbage(program_id, accounts)
+        },
+        INSTRUCTION_INITIALIZE_POSITION_TREE => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let tree_data = PositionTreeData::try_from_slice(&instruction_data[1..])?;
+            process_initialize_position_tree(program_id, accounts, tree_data)
+        },
+        INSTRUCTION_OPEN_COMPRESSED_POSITION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let open_data = OpenCompressedPositionData::try_from_slice(&instruction_data[1..])?;
+            process_open_compressed_position(program_id, accounts, open_data)
+        },
+        INSTRUCTION_ADD_COMPRESSED_COLLATERAL => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let collateral_data = CompressedCollateralData::try_from_slice(&instruction_data[1..])?;
+            process_add_compressed_collateral(program_id, accounts, collateral_data)
+        },
+        INSTRUCTION_CLOSE_COMPRESSED_POSITION => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let close_data = CloseCompressedPositionData::try_from_slice(&instruction_data[1..])?;
+            process_close_compressed_position(program_id, accounts, close_data)
+        },
+        INSTRUCTION_MIGRATE_POSITION_MARKET => {
+            if instruction_data.len() < 2 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let migrate_data = MigratePositionMarketData::try_from_slice(&instruction_data[1..])?;
+            process_migrate_position_market(program_id, accounts, migrate_data)
+        },
+        _ => {
+            msg!("Invalid instruction type: {}", instruction_type);
+            Err(ProgramError::InvalidInstructionData)
+        }
+    }
+}
+
+pub(crate) fn try_load_position_account(position_account: &AccountInfo) -> Result<PositionAccount, ProgramError> {
+    if position_account.data_len() == LEGACY_POSITION_ACCOUNT_LEN {
+        msg!("Position account uses legacy layout, migrate first");
+        return Err(DexError::AccountNeedsMigration.into());
+    }
+    
+    let position = load_position_unchecked(position_account)?;
+    
+    if PositionAccount::is_corrupted(&position_account.data.borrow()) {
+        msg!("Position {} fails its checksum", position.position_nonce);
+        return Err(DexError::PositionCorrupted.into());
+    }
+    
+    Ok(position)
+}
+
+/// Decodes a position without checking its checksum, for the instructions that close corrupted
+/// accounts.
+pub(crate) fn load_position_unchecked(position_account: &AccountInfo) -> Result<PositionAccount, ProgramError> {
+    let data = position_account.data.borrow();
+    
+    // A shorter account with a checksum ends in it, ahead of the fields added since; dropping it
+    // lets those fields and the checksum pad to zero instead of decoding the old checksum.
+    let fields = if data.len() < PositionAccount::LEN && data[0] >= PositionAccount::FIRST_CHECKSUM_VERSION {
+        &data[..data.len().saturating_sub(4)]
+    } else {
+        &data[..]
+    };
+    
+    if let Ok(position) = deserialize_padded::<PositionAccount>(fields) {
+        return Ok(position);
+    }
+    
+    msg!("Invalid position data");
+    msg!("Position account data length: {}", data.len());
+
+    Err(ProgramError::InvalidAccountData)
+}
+
+/// Serializes a position and stamps its checksum.
+pub(crate) fn save_position_account(position_account: &AccountInfo, position: &PositionAccount) -> ProgramResult {
+    let mut data = position_account.data.borrow_mut();
+    position.serialize(&mut &mut data[..])?;
+    
+    PositionAccount::stamp_checksum(&mut data)
+}
+
+pub(crate) fn move_lamports(
+    from_account: &AccountInfo,
+    to_account: &AccountInfo,
+    amount: u64,
+    reason: TransferReason,
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+    
+    assert_writable(from_account)?;
+    assert_writable(to_account)?;
+    
+    **from_account.lamports.borrow_mut() = from_account
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **to_account.lamports.borrow_mut() = to_account
+        .lamports()
+        .saturating_add(amount);
+    
+    emit_event(&DexEvent::LamportTransfer(LamportTransferEvent {
+        from: *from_account.key,
+        to: *to_account.key,
+        amount,
+        reason,
+    }))
+}
+
+/// Fee type whose config routes apply to a fee paid under `reason`.
+fn routed_fee_type(reason: TransferReason) -> Option<u8> {
+    match reason {
+        TransferReason::OpenFee => Some(FEE_TYPE_OPEN),
+        TransferReason::CloseFee | TransferReason::ProfitFee => Some(FEE_TYPE_CLOSE),
+        TransferReason::InsuranceFee => Some(FEE_TYPE_LIQUIDATION),
+        _ => None,
+    }
+}
+
+/// Pays `amount` from `from_account` across the config's routes for `fee_type`, each taking its
+/// share, and the remainder to `default_account`. Once a route is set its destination must be
+/// among `accounts`, so callers cannot divert the fee to the default sink by leaving it out.
+/// A `default_account` that is `from_account` keeps the remainder in place. Returns the amount
+/// sent to routes.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn route_fee(
+    accounts: &[AccountInfo],
+    from_account: &AccountInfo,
+    default_account: &AccountInfo,
+    amount: u64,
+    fee_type: u8,
+    reason: TransferReason,
+    config: &GlobalConfig,
+) -> Result<u64, ProgramError> {
+    let mut remaining = amount;
+    
+    for route in config.fee_routes_for(fee_type) {
+        let destination_account = accounts.iter().find(|account| account.key == &route.destination).ok_or_else(|| {
+            msg!("Fee destination {} missing", route.destination);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+        
+        let share = fee_route_share(amount, route.share_bps).min(remaining);
+        move_lamports(from_account, destination_account, share, reason)?;
+        remaining -= share;
+    }
+    
+    if default_account.key != from_account.key {
+        move_lamports(from_account, default_account, remaining, reason)?;
+    }
+    
+    Ok(amount - remaining)
+}
+
+/// Sends the routed shares of a settled borrow fee out of the market, which otherwise keeps the
+/// whole fee for its LPs. Only liquidity above the market's rent floor is paid out.
+pub(crate) fn route_borrow_fee(
+    accounts: &[AccountInfo],
+    market_account: &AccountInfo,
+    market_state: &mut MarketState,
+    borrow_fee: i128,
+    config: &GlobalConfig,
+) -> ProgramResult {
+    if borrow_fee <= 0 || config.fee_routes_for(FEE_TYPE_BORROW).next().is_none() {
+        return Ok(());
+    }
+    
+    let rent_floor = Rent::get()?.minimum_balance(market_account.data_len());
+    let fee = u64::try_from(borrow_fee)
+        .unwrap_or(u64::MAX)
+        .min(market_account.lamports().saturating_sub(rent_floor));
+    
+    let routed = route_fee(
+        accounts,
+        market_account,
+        market_account,
+        fee,
+        FEE_TYPE_BORROW,
+        TransferReason::BorrowFee,
+        config,
+    )?;
+    market_state.record_payout(routed);
+    
+    msg!("Borrow fee routed: {} of {} lamports", routed, borrow_fee);
+    
+    Ok(())
+}
+
+/// Collects `fee` from `from_account`, sending `lp_fee_share_bps` of it to the market's LP pool
+/// and the rest to `protocol_account` under `reason`, or across the config's fee routes for
+/// that kind of fee. A fee already held by the market keeps
+/// its LP leg in place. Unless `FEATURE_LP_FEE_COMPOUNDING` is on, the LP leg is credited to
+/// `market_state`'s fee accumulator, which the caller saves. The market's ledger records the LP
+/// leg as a deposit, or the protocol leg as a payout when the market pays the fee itself.
+/// Returns `(protocol_fee, lp_fee)`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn split_fee(
+    accounts: &[AccountInfo],
+    from_account: &AccountInfo,
+    protocol_account: &AccountInfo,
+    market_account: &AccountInfo,
+    market_mint: &Pubkey,
+    market_state: &mut MarketState,
+    fee: u64,
+    reason: TransferReason,
+    config: &GlobalConfig,
+) -> Result<(u64, u64), ProgramError> {
+    if fee == 0 {
+        return Ok((0, 0));
+    }
+    
+    let (protocol_fee, lp_fee) = split_lp_fee(fee, config.lp_fee_share_bps);
+    market_state.record_fee(protocol_fee, lp_fee);
+    
+    match routed_fee_type(reason) {
+        Some(fee_type) => {
+            route_fee(accounts, from_account, protocol_account, protocol_fee, fee_type, reason, config)?;
+        },
+        None => move_lamports(from_account, protocol_account, protocol_fee, reason)?,
+    }
+    
+    if from_account.key != market_account.key {
+        move_lamports(from_account, market_account, lp_fee, TransferReason::LpFeeShare)?;
+        market_state.record_deposit(lp_fee);
+    } else {
+        market_state.record_payout(protocol_fee);
+    }
+    
+    // Compounding leaves the LP leg in the pool, where it raises the share price instead.
+    if !config.feature_enabled(FEATURE_LP_FEE_COMPOUNDING) {
+        market_state.accrue_lp_fee(lp_fee);
+    }
+    
+    emit_event(&DexEvent::FeeSplit(FeeSplitEvent {
+        market_mint: *market_mint,
+        reason,
+        fee,
+        protocol_fee,
+        lp_fee,
+    }))?;
+    
+    Ok((protocol_fee, lp_fee))
+}
+
+pub(crate) fn zero_account_data(account: &AccountInfo) -> ProgramResult {
+    assert_writable(account)?;
+    
+    let mut data = account.try_borrow_mut_data()?;
+
+    let len = data.len();
+    for i in 0..len {
+        data[i] = 0;
+    }
+    Ok(())
+}
+
+pub(crate) fn serialized_len<T: BorshSerialize + Default>() -> Result<usize, ProgramError> {
+    T::default()
+        .try_to_vec()
+        .map(|data| data.len())
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Deserializes an append-only account, treating bytes missing from older layouts as zero.
+pub(crate) fn deserialize_padded<T: BorshSerialize + BorshDeserialize + Default>(data: &[u8]) -> Result<T, ProgramError> {
+    let current_len = serialized_len::<T>()?;
+    
+    if data.len() < current_len {
+        let mut padded = data.to_vec();
+        padded.resize(current_len, 0);
+        return T::try_from_slice(&padded).map_err(|_| ProgramError::InvalidAccountData);
+    }
+    
+    T::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Creates a PDA holding `rent_deposit`. An address that already received lamports cannot go
+/// through `create_account`, so it is topped up to the deposit, allocated and assigned instead;
+/// anything it held beyond the deposit stays on it for the caller to return or keep.
+pub(crate) fn create_pda_account<'a>(
+    pda_account: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    rent_deposit: u64,
+    data_len: usize,
+    seeds: &[&[u8]],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let accounts = [
+        payer_account.clone(),
+        pda_account.clone(),
+        system_program.clone(),
+    ];
+    
+    if pda_account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                pda_account.key,
+                rent_deposit,
+                data_len as u64,
+                program_id,
+            ),
+            &accounts,
+            &[seeds],
+        );
+    }
+    
+    let top_up = rent_deposit.saturating_sub(pda_account.lamports());
+    
+    if top_up > 0 {
+        invoke(&system_instruction::transfer(payer_account.key, pda_account.key, top_up), &accounts)?;
+    }
+    
+    invoke_signed(&system_instruction::allocate(pda_account.key, data_len as u64), &accounts, &[seeds])?;
+    invoke_signed(&system_instruction::assign(pda_account.key, program_id), &accounts, &[seeds])
+}
+
+pub(crate) fn resize_account<'a>(
+    account: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    new_len: usize,
+) -> ProgramResult {
+    assert_writable(account)?;
+    
+    let rent = Rent::get()?;
+    let minimum_balance = rent.minimum_balance(new_len);
+    let current_lamports = account.lamports();
+    
+    if current_lamports < minimum_balance {
+        let top_up = minimum_balance.saturating_sub(current_lamports);
+        
+        invoke(
+            &system_instruction::transfer(
+                payer_account.key,
+                account.key,
+                top_up,
+            ),
+            &[
+                payer_account.clone(),
+                account.clone(),
+                system_program.clone(),
+            ],
+        )?;
+        
+        msg!("Rent top-up: {} lamports", top_up);
+    }
+    
+    account.realloc(new_len, true)
+}
@@ -0,0 +1,494 @@
+//! Oracle price feeds: per-market price sources, publishing, syncing and validation.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Byte layout of a Pyth v2 price account.
+const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+const PYTH_ACCOUNT_TYPE_PRICE: u32 = 3;
+const PYTH_STATUS_TRADING: u32 = 1;
+const PYTH_EXPONENT_OFFSET: usize = 20;
+const PYTH_AGGREGATE_OFFSET: usize = 208;
+
+/// Byte offset of `latest_confirmed_round` in a Switchboard v2 aggregator, past its 8-byte
+/// discriminator.
+const SWITCHBOARD_ROUND_OFFSET: usize = 341;
+
+/// The price a handler acts on, and which of the feed's sources (`PRICE_SOURCE_*`) supplied it.
+/// `shade` is the Pyth confidence interval, zero for every other source.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Price {
+    pub price: u64,
+    pub confidence: u64,
+    pub publish_slot: u64,
+    pub source: u8,
+    pub shade: u64,
+}
+
+impl Price {
+    /// The price moved by `shade` against a trader on `direction`: buying (opening a long,
+    /// closing a short) pays the top of the interval and selling gets the bottom, so oracle
+    /// uncertainty costs the trader rather than the vault.
+    pub fn effective(&self, direction: i8, opening: bool) -> u64 {
+        if (direction == POSITION_LONG) == opening {
+            self.price.saturating_add(self.shade)
+        } else {
+            self.price.saturating_sub(self.shade).max(1)
+        }
+    }
+}
+
+fn read_bytes<const N: usize>(data: &[u8], offset: usize) -> Result<[u8; N], ProgramError> {
+    data.get(offset..offset + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| DexError::InvalidPriceSource.into())
+}
+
+fn pyth_price(data: &[u8]) -> Result<Price, ProgramError> {
+    let magic = u32::from_le_bytes(read_bytes(data, 0)?);
+    let account_type = u32::from_le_bytes(read_bytes(data, 8)?);
+    
+    if magic != PYTH_MAGIC || account_type != PYTH_ACCOUNT_TYPE_PRICE {
+        msg!("Not a Pyth price account");
+        return Err(DexError::InvalidPriceSource.into());
+    }
+    
+    let exponent = i32::from_le_bytes(read_bytes(data, PYTH_EXPONENT_OFFSET)?);
+    let price = i64::from_le_bytes(read_bytes(data, PYTH_AGGREGATE_OFFSET)?);
+    let confidence = u64::from_le_bytes(read_bytes(data, PYTH_AGGREGATE_OFFSET + 8)?);
+    let status = u32::from_le_bytes(read_bytes(data, PYTH_AGGREGATE_OFFSET + 16)?);
+    let publish_slot = u64::from_le_bytes(read_bytes(data, PYTH_AGGREGATE_OFFSET + 24)?);
+    
+    if status != PYTH_STATUS_TRADING {
+        msg!("Pyth price is not trading");
+        return Err(DexError::InvalidPriceSource.into());
+    }
+    
+    let confidence = scale_price(confidence as i128, exponent).unwrap_or(0);
+    
+    Ok(Price {
+        price: scale_price(price as i128, exponent).ok_or(DexError::InvalidPriceSource)?,
+        confidence,
+        publish_slot,
+        source: PRICE_SOURCE_PRIMARY,
+        shade: confidence,
+    })
+}
+
+fn switchboard_price(data: &[u8]) -> Result<Price, ProgramError> {
+    // `AggregatorRound`: num_success u32, num_error u32, is_closed u8, round_open_slot u64,
+    // round_open_timestamp i64, then `result` and `std_deviation` as (i128 mantissa, u32 scale).
+    let round_open_slot = u64::from_le_bytes(read_bytes(data, SWITCHBOARD_ROUND_OFFSET + 9)?);
+    let result_offset = SWITCHBOARD_ROUND_OFFSET + 25;
+    let mantissa = i128::from_le_bytes(read_bytes(data, result_offset)?);
+    let scale = u32::from_le_bytes(read_bytes(data, result_offset + 16)?);
+    let deviation = i128::from_le_bytes(read_bytes(data, result_offset + 20)?);
+    let deviation_scale = u32::from_le_bytes(read_bytes(data, result_offset + 36)?);
+    
+    let exponent = -i32::try_from(scale).map_err(|_| DexError::InvalidPriceSource)?;
+    let deviation_exponent = -i32::try_from(deviation_scale).map_err(|_| DexError::InvalidPriceSource)?;
+    
+    Ok(Price {
+        price: scale_price(mantissa, exponent).ok_or(DexError::InvalidPriceSource)?,
+        confidence: scale_price(deviation, deviation_exponent).unwrap_or(0),
+        publish_slot: round_open_slot,
+        source: PRICE_SOURCE_PRIMARY,
+        shade: 0,
+    })
+}
+
+/// Reads the current price from `source_account` as `source` lays it out. This is the one place
+/// a new price source plugs in; handlers only see `load_price_feed`.
+pub(crate) fn get_price(source: &PriceSource, source_account: &AccountInfo, clock: &Clock) -> Result<Price, ProgramError> {
+    match source {
+        PriceSource::OperatorAttested => {
+            let price_feed = deserialize_padded::<PriceFeedAccount>(&source_account.data.borrow())?;
+            
+            Ok(Price {
+                price: price_feed.price,
+                confidence: price_feed.confidence,
+                publish_slot: price_feed.publish_slot,
+                source: PRICE_SOURCE_PRIMARY,
+                shade: 0,
+            })
+        },
+        PriceSource::Pyth { feed } => {
+            if source_account.key != feed {
+                msg!("Expected Pyth feed {}", feed);
+                return Err(DexError::InvalidPriceSource.into());
+            }
+            
+            pyth_price(&source_account.data.borrow())
+        },
+        PriceSource::Switchboard { aggregator } => {
+            if source_account.key != aggregator {
+                msg!("Expected Switchboard aggregator {}", aggregator);
+                return Err(DexError::InvalidPriceSource.into());
+            }
+            
+            switchboard_price(&source_account.data.borrow())
+        },
+        PriceSource::Fixed { price } => Ok(Price {
+            price: *price,
+            confidence: 0,
+            publish_slot: clock.slot,
+            source: PRICE_SOURCE_PRIMARY,
+            shade: 0,
+        }),
+    }
+}
+
+fn price_is_fresh(price: u64, publish_slot: u64, slot: u64, config: &GlobalConfig) -> bool {
+    price != 0 && (config.max_price_staleness_slots == 0
+        || slot.saturating_sub(publish_slot) <= config.max_price_staleness_slots)
+}
+
+/// Reads `market_mint`'s price, falling back to the secondary source when the primary is
+/// stale or unset and the market has a fresh fallback price.
+pub(crate) fn load_price_feed(
+    price_feed_account: &AccountInfo,
+    market_mint: &Pubkey,
+    config: &GlobalConfig,
+    program_id: &Pubkey,
+) -> Result<Price, ProgramError> {
+    let (price_feed_pda, _feed_bump) = find_price_feed_address(market_mint, program_id);
+    
+    assert_key_eq(price_feed_account, &price_feed_pda)?;
+    
+    assert_owned_by(price_feed_account, program_id)?;
+    
+    let price_feed = deserialize_padded::<PriceFeedAccount>(&price_feed_account.data.borrow())
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    
+    let clock = Clock::get()?;
+    
+    // Pyth and Switchboard prices are synced into the feed, so only a fixed price is read live.
+    let primary = match price_feed.source {
+        PriceSource::Fixed { .. } => get_price(&price_feed.source, price_feed_account, &clock)?,
+        _ => Price {
+            price: price_feed.price,
+            confidence: price_feed.confidence,
+            publish_slot: price_feed.publish_slot,
+            source: PRICE_SOURCE_PRIMARY,
+            shade: match price_feed.source {
+                PriceSource::Pyth { .. } => price_feed.confidence,
+                _ => 0,
+            },
+        },
+    };
+    
+    if price_is_fresh(primary.price, primary.publish_slot, clock.slot, config) {
+        return Ok(primary);
+    }
+    
+    if price_feed.fallback_authority != Pubkey::default()
+        && price_is_fresh(price_feed.fallback_price, price_feed.fallback_publish_slot, clock.slot, config)
+    {
+        msg!("Primary oracle price unavailable, using fallback");
+        
+        return Ok(Price {
+            price: price_feed.fallback_price,
+            confidence: price_feed.fallback_confidence,
+            publish_slot: price_feed.fallback_publish_slot,
+            source: PRICE_SOURCE_FALLBACK,
+            shade: 0,
+        });
+    }
+    
+    msg!("Oracle price is {} slots old", clock.slot.saturating_sub(price_feed.publish_slot));
+    Err(DexError::StalePrice.into())
+}
+
+/// `settled_price` is what the position actually closed at, after any confidence shading.
+pub(crate) fn emit_settlement_price(position: &PositionAccount, oracle_price: &Price, settled_price: u64) -> ProgramResult {
+    emit_event(&DexEvent::SettlementPriced(SettlementPriceEvent {
+        market_mint: position.market_mint,
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        price: settled_price,
+        source: oracle_price.source,
+        publish_slot: oracle_price.publish_slot,
+    }))
+}
+
+/// Loads `market_mint`'s feed, creating it or growing a pre-fallback one at `payer`'s expense.
+fn prepare_price_feed<'a>(
+    price_feed_account: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<PriceFeedAccount, ProgramError> {
+    let (price_feed_pda, feed_bump) = find_price_feed_address(market_mint, program_id);
+    
+    assert_key_eq(price_feed_account, &price_feed_pda)?;
+    
+    if price_feed_account.data_is_empty() {
+        let data_len = PriceFeedAccount::LEN;
+        
+        let rent = Rent::get()?;
+        let minimum_balance = rent.minimum_balance(data_len);
+        
+        let feed_seeds = &[
+            b"uranus_oracle".as_ref(),
+            market_mint.as_ref(),
+            &[feed_bump],
+        ];
+        
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                price_feed_account.key,
+                minimum_balance,
+                data_len as u64,
+                program_id,
+            ),
+            &[
+                payer_account.clone(),
+                price_feed_account.clone(),
+                system_program.clone(),
+            ],
+            &[feed_seeds],
+        )?;
+        
+        return Ok(PriceFeedAccount {
+            market_mint: *market_mint,
+            ..PriceFeedAccount::default()
+        });
+    }
+    
+    assert_owned_by(price_feed_account, program_id)?;
+    
+    let price_feed = deserialize_padded::<PriceFeedAccount>(&price_feed_account.data.borrow())?;
+    
+    if price_feed_account.data_len() < PriceFeedAccount::LEN {
+        resize_account(price_feed_account, payer_account, system_program, PriceFeedAccount::LEN)?;
+    }
+    
+    Ok(price_feed)
+}
+
+pub(crate) fn check_price_band(price: u64, oracle_price: u64, band_bps: u16) -> ProgramResult {
+    let deviation = price.abs_diff(oracle_price) as u128;
+    let allowed = (oracle_price as u128).saturating_mul(band_bps as u128) / BASIS_POINTS_DIVISOR as u128;
+    
+    if deviation > allowed {
+        msg!("Price {} outside {} bps band around oracle {}", price, band_bps, oracle_price);
+        return Err(DexError::PriceOutsideBand.into());
+    }
+    
+    Ok(())
+}
+
+pub(crate) fn process_update_price_feed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    feed_data: PriceFeedData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let oracle_authority_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    assert_signer(oracle_authority_account)?;
+    
+    // Only the primary authority may create the feed; the fallback authority is recorded in it.
+    let is_primary = oracle_authority_account.key == &config.oracle_authority;
+    
+    if !is_primary {
+        if price_feed_account.owner != program_id || price_feed_account.data_is_empty() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        
+        let fallback_authority = deserialize_padded::<PriceFeedAccount>(&price_feed_account.data.borrow())?.fallback_authority;
+        
+        if fallback_authority == Pubkey::default() || oracle_authority_account.key != &fallback_authority {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+    }
+    
+    let mut price_feed = prepare_price_feed(
+        price_feed_account,
+        oracle_authority_account,
+        system_program,
+        &feed_data.market_mint,
+        program_id,
+    )?;
+    
+    let clock = Clock::get()?;
+    
+    if is_primary && price_feed.source != PriceSource::OperatorAttested {
+        msg!("Market is priced by {:?}", price_feed.source);
+        return Err(DexError::InvalidPriceSource.into());
+    }
+    
+    if !is_primary {
+        price_feed.fallback_price = feed_data.price;
+        price_feed.fallback_confidence = feed_data.confidence;
+        price_feed.fallback_publish_slot = clock.slot;
+        
+        price_feed.serialize(&mut *price_feed_account.data.borrow_mut())?;
+        
+        msg!("Fallback price updated: {} (conf {}) at slot {}", feed_data.price, feed_data.confidence, clock.slot);
+        
+        return Ok(());
+    }
+    
+    price_feed.price = feed_data.price;
+    price_feed.confidence = feed_data.confidence;
+    price_feed.publish_slot = clock.slot;
+    
+    price_feed.serialize(&mut *price_feed_account.data.borrow_mut())?;
+    
+    msg!("Price feed updated: {} (conf {}) at slot {}", price_feed.price, price_feed.confidence, price_feed.publish_slot);
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_fallback_oracle(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    fallback_data: SetFallbackOracleData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let mut price_feed = prepare_price_feed(
+        price_feed_account,
+        admin_account,
+        system_program,
+        &fallback_data.market_mint,
+        program_id,
+    )?;
+    
+    if price_feed.fallback_authority != fallback_data.fallback_authority {
+        price_feed.fallback_price = 0;
+        price_feed.fallback_confidence = 0;
+        price_feed.fallback_publish_slot = 0;
+    }
+    
+    price_feed.fallback_authority = fallback_data.fallback_authority;
+    
+    price_feed.serialize(&mut *price_feed_account.data.borrow_mut())?;
+    
+    msg!("Fallback oracle for {}: {}", fallback_data.market_mint, fallback_data.fallback_authority);
+    
+    Ok(())
+}
+
+pub(crate) fn process_set_price_source(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    source_data: SetPriceSourceData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    if let PriceSource::Fixed { price: 0 } = source_data.source {
+        msg!("Fixed price must be non-zero");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut price_feed = prepare_price_feed(
+        price_feed_account,
+        admin_account,
+        system_program,
+        &source_data.market_mint,
+        program_id,
+    )?;
+    
+    // A price from the old source must not stand in for the new one until it is synced.
+    if price_feed.source != source_data.source {
+        price_feed.price = 0;
+        price_feed.confidence = 0;
+        price_feed.publish_slot = 0;
+    }
+    
+    price_feed.source = source_data.source;
+    
+    zero_account_data(price_feed_account)?;
+    price_feed.serialize(&mut *price_feed_account.data.borrow_mut())?;
+    
+    msg!("Price source for {}: {:?}", source_data.market_mint, source_data.source);
+    
+    Ok(())
+}
+
+/// Permissionless: copies the latest price from a market's Pyth or Switchboard account into its
+/// feed, where every handler reads it.
+pub(crate) fn process_sync_price_feed(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    sync_data: SyncPriceFeedData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let source_account = next_account_info(accounts_iter)?;
+    
+    let (price_feed_pda, _feed_bump) = find_price_feed_address(&sync_data.market_mint, program_id);
+    
+    assert_key_eq(price_feed_account, &price_feed_pda)?;
+    
+    assert_owned_by(price_feed_account, program_id)?;
+    
+    if price_feed_account.data_len() < PriceFeedAccount::LEN {
+        return Err(DexError::AccountNeedsMigration.into());
+    }
+    
+    let mut price_feed = deserialize_padded::<PriceFeedAccount>(&price_feed_account.data.borrow())?;
+    
+    if !matches!(price_feed.source, PriceSource::Pyth { .. } | PriceSource::Switchboard { .. }) {
+        msg!("Market is priced by {:?}, nothing to sync", price_feed.source);
+        return Err(DexError::InvalidPriceSource.into());
+    }
+    
+    let clock = Clock::get()?;
+    let price = get_price(&price_feed.source, source_account, &clock)?;
+    
+    if price.publish_slot <= price_feed.publish_slot {
+        msg!("Feed already holds the price from slot {}", price_feed.publish_slot);
+        return Ok(());
+    }
+    
+    price_feed.price = price.price;
+    price_feed.confidence = price.confidence;
+    price_feed.publish_slot = price.publish_slot.min(clock.slot);
+    
+    price_feed.serialize(&mut *price_feed_account.data.borrow_mut())?;
+    
+    msg!("Price feed synced: {} (conf {}) from slot {}", price.price, price.confidence, price.publish_slot);
+    
+    Ok(())
+}
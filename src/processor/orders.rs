@@ -0,0 +1,1272 @@
+//! Limit order books and the matching crank.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::{invoke, invoke_signed},
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn load_order_book(
+    book_account: &AccountInfo,
+    market_mint: &Pubkey,
+    side: i8,
+    program_id: &Pubkey,
+) -> Result<OrderBookSide, ProgramError> {
+    let (book_pda, _book_bump) = find_order_book_address(market_mint, side, program_id);
+    
+    assert_key_eq(book_account, &book_pda)?;
+    
+    assert_owned_by(book_account, program_id)?;
+    
+    OrderBookSide::deserialize(&mut &book_account.data.borrow()[..])
+        .map_err(|_| ProgramError::InvalidAccountData)
+}
+
+pub(crate) fn process_place_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    order_data: PlaceOrderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let owner_account = next_account_info(accounts_iter)?;
+    let book_account = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let nonce_counter_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    check_not_frozen(accounts, &[owner_account.key], program_id)?;
+    check_not_blacklisted(accounts, &[owner_account.key], program_id)?;
+    
+    if order_data.side != POSITION_LONG && order_data.side != POSITION_SHORT {
+        msg!("Invalid side");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if order_data.price == 0 {
+        msg!("Limit price must be positive");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let leverage = order_data.leverage.clamp(1, MAXIMUM_LEVERAGE);
+    if order_data.paid_amount.saturating_mul(leverage as u64) < MIN_POSITION_SIZE_LAMPORTS {
+        msg!("Order size too small");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if order_data.display_amount != 0
+        && (order_data.display_amount >= order_data.paid_amount
+            || order_data.display_amount.saturating_mul(leverage as u64) < MIN_POSITION_SIZE_LAMPORTS)
+    {
+        msg!("Iceberg display size must be a tradable part of the order");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let symbol_registry = load_symbol_registry(symbol_registry_account, &order_data.market_mint, program_id)?;
+    
+    verify_market_address(market_account, &order_data.market_mint, program_id)?;
+    
+    check_leverage_tier(
+        &load_tradable_market_state(market_account, program_id)?,
+        order_data.paid_amount.saturating_mul(leverage as u64),
+        leverage,
+    )?;
+    
+    let (book_pda, book_bump) = find_order_book_address(&order_data.market_mint, order_data.side, program_id);
+    
+    assert_key_eq(book_account, &book_pda)?;
+    
+    if book_account.data_is_empty() && book_account.lamports() == 0 {
+        let data_len = OrderBookSide::space(MAX_ORDERS_PER_SIDE);
+        let rent = Rent::get()?;
+        let minimum_balance = rent.minimum_balance(data_len);
+        
+        let book_seeds = &[
+            b"uranus_book",
+            order_data.market_mint.as_ref(),
+            order_book_side_seed(order_data.side),
+            &[book_bump],
+        ];
+        
+        invoke_signed(
+            &system_instruction::create_account(
+                owner_account.key,
+                book_account.key,
+                minimum_balance,
+                data_len as u64,
+                program_id,
+            ),
+            &[
+                owner_account.clone(),
+                book_account.clone(),
+                system_program.clone(),
+            ],
+            &[book_seeds],
+        )?;
+        
+        OrderBookSide {
+            market_mint: order_data.market_mint,
+            side: order_data.side,
+            ..OrderBookSide::default()
+        }
+        .serialize(&mut *book_account.data.borrow_mut())?;
+    }
+    
+    reserve_order_position(
+        position_account,
+        owner_account,
+        nonce_counter_account,
+        system_program,
+        &order_data,
+        leverage,
+        symbol_registry.market_symbol,
+        program_id,
+    )?;
+    
+    let mut book = load_order_book(book_account, &order_data.market_mint, order_data.side, program_id)?;
+    
+    let displayed = if order_data.display_amount == 0 { order_data.paid_amount } else { order_data.display_amount };
+    let order = Order {
+        order_id: book.next_order_id,
+        owner: *owner_account.key,
+        price: order_data.price,
+        paid_amount: displayed,
+        leverage,
+        position_nonce: order_data.position_nonce,
+        placed_slot: Clock::get()?.slot,
+        display_amount: order_data.display_amount,
+        hidden_amount: order_data.paid_amount.saturating_sub(displayed),
+    };
+    book.next_order_id = book.next_order_id.saturating_add(1);
+    book.insert(order.clone())?;
+    
+    invoke(
+        &system_instruction::transfer(
+            owner_account.key,
+            book_account.key,
+            order_data.paid_amount,
+        ),
+        &[
+            owner_account.clone(),
+            book_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    book.serialize(&mut *book_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::OrderPlaced(OrderEvent {
+        market_mint: order_data.market_mint,
+        side: order_data.side,
+        order_id: order.order_id,
+        owner: order.owner,
+        price: order.price,
+        paid_amount: order.paid_amount,
+    }))?;
+    
+    msg!("Order {} placed at {} for {} lamports ({} displayed)", order.order_id, order.price, order.total_amount(), order.paid_amount);
+    
+    Ok(())
+}
+
+/// Checks that an order's target position can absorb its fill. A nonce with no position yet is
+/// claimed from the owner's counter and reserved with an empty position account, paid by the owner.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn reserve_order_position<'a>(
+    position_account: &AccountInfo<'a>,
+    owner_account: &AccountInfo<'a>,
+    nonce_counter_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    order_data: &PlaceOrderData,
+    leverage: u8,
+    market_symbol: [u8; MAX_SYMBOL_LENGTH],
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (position_pda, position_bump) = find_position_address(owner_account.key, order_data.position_nonce, program_id);
+    
+    assert_key_eq(position_account, &position_pda)?;
+    
+    if !position_account.data_is_empty() {
+        assert_owned_by(position_account, program_id)?;
+        
+        let position = try_load_position_account(position_account)?;
+        
+        if !position.is_open()
+            || position.direction != order_data.side
+            || position.market_mint != order_data.market_mint
+            || position.leverage != leverage
+        {
+            msg!("Position {} cannot absorb this order", position.position_nonce);
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        return Ok(());
+    }
+    
+    let claimed_nonce = claim_position_nonce(
+        nonce_counter_account,
+        owner_account.key,
+        owner_account,
+        system_program,
+        program_id,
+    )?;
+    
+    if claimed_nonce != order_data.position_nonce {
+        msg!("Expected position nonce {}", claimed_nonce);
+        return Err(DexError::NonceMismatch.into());
+    }
+    
+    let position = PositionAccount {
+        version: POSITION_ACCOUNT_VERSION,
+        owner: *owner_account.key,
+        market_mint: order_data.market_mint,
+        market_symbol,
+        leverage,
+        position_nonce: order_data.position_nonce,
+        direction: order_data.side,
+        margin_mode: MARGIN_MODE_ISOLATED,
+        open_slot: Clock::get()?.slot,
+        ..PositionAccount::default()
+    };
+    
+    let data_len = PositionAccount::LEN;
+    let minimum_balance = Rent::get()?.minimum_balance(data_len);
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            owner_account.key,
+            position_account.key,
+            minimum_balance,
+            data_len as u64,
+            program_id,
+        ),
+        &[
+            owner_account.clone(),
+            position_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_position",
+            owner_account.key.as_ref(),
+            &order_data.position_nonce.to_le_bytes(),
+            &[position_bump],
+        ]],
+    )?;
+    
+    save_position_account(position_account, &position)?;
+    
+    msg!("Position {} reserved for order", order_data.position_nonce);
+    
+    Ok(())
+}
+
+pub(crate) fn process_cancel_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cancel_data: CancelOrderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let owner_account = next_account_info(accounts_iter)?;
+    let book_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let mut book = load_order_book(book_account, &cancel_data.market_mint, cancel_data.side, program_id)?;
+    let order = book.remove(cancel_data.order_id)?;
+    
+    if &order.owner != owner_account.key {
+        msg!("Order {} belongs to another owner", order.order_id);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    book.serialize(&mut *book_account.data.borrow_mut())?;
+    
+    move_lamports(book_account, owner_account, order.total_amount(), TransferReason::OrderRefund)?;
+    
+    emit_event(&DexEvent::OrderCancelled(OrderEvent {
+        market_mint: cancel_data.market_mint,
+        side: cancel_data.side,
+        order_id: order.order_id,
+        owner: order.owner,
+        price: order.price,
+        paid_amount: order.total_amount(),
+    }))?;
+    
+    msg!("Order {} cancelled", order.order_id);
+    
+    Ok(())
+}
+
+/// Collateral actually consumed from `order` for a fill of `notional`; a fill that exhausts the
+/// order takes everything left so no dust stays behind in the book.
+pub(crate) fn order_fill_amount(order: &Order, notional: u64) -> u64 {
+    let order_notional = order.paid_amount.saturating_mul(order.leverage as u64);
+    
+    if notional >= order_notional {
+        order.paid_amount
+    } else {
+        notional / order.leverage as u64
+    }
+}
+
+/// Empty position created by `PLACE_ORDER` to hold a claimed nonce until the order fills.
+pub(crate) fn is_reserved_position(position: &PositionAccount) -> bool {
+    position.is_open() && !position.is_confirmed() && position.position_size == 0
+}
+
+/// Whether the order's own position can take a fill: it must still exist, be open on the order's
+/// side, market and leverage, and be either reserved or already priced. Any other account than
+/// the order's position is an error rather than a position that cannot fill.
+pub(crate) fn position_can_absorb_fill(
+    program_id: &Pubkey,
+    position_account: &AccountInfo,
+    order: &Order,
+    direction: i8,
+    market_mint: &Pubkey,
+) -> Result<bool, ProgramError> {
+    let (position_pda, _position_bump) = find_position_address(&order.owner, order.position_nonce, program_id);
+    
+    if position_account.key != &position_pda {
+        msg!("Invalid position account for order {}", order.order_id);
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Fills never create position accounts: `PLACE_ORDER` reserves an empty position under a
+    // freshly claimed nonce, so a closed position's address cannot be brought back by a fill.
+    if position_account.data_is_empty() || position_account.owner != program_id {
+        msg!("Position {} for order {} no longer exists", order.position_nonce, order.order_id);
+        return Ok(false);
+    }
+    
+    let position = try_load_position_account(position_account)?;
+    
+    if !position.is_open()
+        || position.direction != direction
+        || &position.market_mint != market_mint
+        || position.leverage != order.leverage
+        || !(is_reserved_position(&position) || position.entry_price != 0)
+    {
+        msg!("Position {} cannot absorb fill", position.position_nonce);
+        return Ok(false);
+    }
+    
+    Ok(true)
+}
+
+/// Credits a fill to the order's position, opening the reserved position on the first fill and
+/// averaging the entry price (weighted by notional, matching how `unrealized_pnl` scales) on later ones.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn apply_fill_to_position<'a>(
+    program_id: &Pubkey,
+    position_account: &AccountInfo<'a>,
+    book_account: &AccountInfo<'a>,
+    order: &Order,
+    direction: i8,
+    market_mint: &Pubkey,
+    price: u64,
+    collateral: u64,
+    fee: u64,
+    config: &GlobalConfig,
+    market_state: &MarketState,
+) -> ProgramResult {
+    if !position_can_absorb_fill(program_id, position_account, order, direction, market_mint)? {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    let mut position = try_load_position_account(position_account)?;
+    
+    let added_size = collateral.saturating_mul(order.leverage as u64);
+    
+    position = if is_reserved_position(&position) {
+        move_lamports(book_account, position_account, collateral, TransferReason::OrderFill)?;
+        
+        PositionAccount {
+            entry_price: price,
+            paid_amount: collateral,
+            position_size: added_size,
+            confirmed: 1,
+            open_fee: fee,
+            open_slot: Clock::get()?.slot,
+            funding_index_snapshot: market_state.funding_index(direction),
+            borrow_index_snapshot: market_state.cumulative_borrow_index,
+            contract_type: market_state.contract_type,
+            open_base_fee_bps: market_state.fee_schedule().base_fee_bps as u16,
+            open_leverage_fee_bps: market_state.fee_schedule().leverage_fee_bps as u16,
+            ..position
+        }
+    } else {
+        move_lamports(book_account, position_account, collateral, TransferReason::OrderFill)?;
+        
+        let old_size = position.position_size as u128;
+        let new_size = added_size as u128;
+        let weighted = old_size
+            .saturating_mul(price as u128)
+            .saturating_add(new_size.saturating_mul(position.entry_price as u128));
+        
+        if weighted != 0 {
+            position.entry_price = (old_size.saturating_add(new_size))
+                .saturating_mul(position.entry_price as u128)
+                .saturating_mul(price as u128)
+                .checked_div(weighted)
+                .unwrap_or(0)
+                .min(u64::MAX as u128) as u64;
+        }
+        position.funding_index_snapshot = if old_size.saturating_add(new_size) == 0 {
+            market_state.funding_index(direction)
+        } else {
+            ((old_size as i128)
+                .saturating_mul(position.funding_index_snapshot)
+                .saturating_add((new_size as i128).saturating_mul(market_state.funding_index(direction))))
+                / (old_size.saturating_add(new_size) as i128)
+        };
+        position.paid_amount = position.paid_amount.saturating_add(collateral);
+        position.position_size = position.position_size.saturating_add(added_size);
+        position.open_fee = position.open_fee.saturating_add(fee);
+        position
+    };
+    
+    position.refresh_liquidation_price(config.maintenance_margin_bps);
+    
+    save_position_account(position_account, &position)?;
+    
+    Ok(())
+}
+
+pub(crate) fn process_match_orders(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    match_data: MatchOrdersData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let bids_account = next_account_info(accounts_iter)?;
+    let asks_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let _system_program = next_account_info(accounts_iter)?;
+    
+    assert_fee_vault(dex_fees_account, program_id)?;
+    
+    let market_mint = match_data.market_mint;
+    let config = load_config(config_account, program_id)?;
+    load_symbol_registry(symbol_registry_account, &market_mint, program_id)?;
+    verify_market_address(market_account, &market_mint, program_id)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let fees = market_state.fee_schedule();
+    
+    if !market_state.is_active() {
+        msg!("Market is not active, resting orders can only be cancelled");
+        return Err(DexError::MarketNotActive.into());
+    }
+    
+    let mut bids = load_order_book(bids_account, &market_mint, POSITION_LONG, program_id)?;
+    let mut asks = load_order_book(asks_account, &market_mint, POSITION_SHORT, program_id)?;
+    
+    let mut fills: u8 = 0;
+    let current_slot = Clock::get()?.slot;
+    // Orders whose position cannot take a fill are stepped over rather than failing the crank;
+    // they keep resting until their owner cancels them.
+    let (mut bid_index, mut ask_index) = (0, 0);
+    
+    while fills < match_data.max_fills {
+        let (Some(bid), Some(ask)) = (bids.orders.get(bid_index).cloned(), asks.orders.get(ask_index).cloned()) else {
+            break;
+        };
+        
+        if bid.price < ask.price {
+            break;
+        }
+        
+        // Position accounts are supplied per fill as (bid position, ask position); running out
+        // of them is how the cranker bounds the work done in one transaction.
+        let (Ok(bid_position_account), Ok(ask_position_account)) =
+            (next_account_info(accounts_iter), next_account_info(accounts_iter))
+        else {
+            break;
+        };
+        
+        let bid_fillable = position_can_absorb_fill(program_id, bid_position_account, &bid, POSITION_LONG, &market_mint)?;
+        let ask_fillable = position_can_absorb_fill(program_id, ask_position_account, &ask, POSITION_SHORT, &market_mint)?;
+        
+        if !(bid_fillable && ask_fillable) {
+            for (order, fillable, index) in [(&bid, bid_fillable, &mut bid_index), (&ask, ask_fillable, &mut ask_index)] {
+                if !fillable {
+                    *index += 1;
+                    
+                    msg!("Order {} skipped, its position cannot absorb a fill", order.order_id);
+                }
+            }
+            
+            continue;
+        }
+        
+        let maker_side = if bid.placed_slot < ask.placed_slot { POSITION_LONG } else { POSITION_SHORT };
+        let price = if maker_side == POSITION_LONG { bid.price } else { ask.price };
+        let (bid_role, ask_role) = if maker_side == POSITION_LONG {
+            (FillRole::Maker, FillRole::Taker)
+        } else {
+            (FillRole::Taker, FillRole::Maker)
+        };
+        
+        let notional = bid
+            .paid_amount
+            .saturating_mul(bid.leverage as u64)
+            .min(ask.paid_amount.saturating_mul(ask.leverage as u64));
+        
+        let bid_amount = order_fill_amount(&bid, notional);
+        let ask_amount = order_fill_amount(&ask, notional);
+        let bid_fee = fill_fee(bid_amount, bid.leverage, bid_role, &fees);
+        let ask_fee = fill_fee(ask_amount, ask.leverage, ask_role, &fees);
+        
+        split_fee(
+            accounts,
+            bids_account,
+            dex_fees_account,
+            market_account,
+            &market_mint,
+            &mut market_state,
+            bid_fee,
+            TransferReason::OpenFee,
+            &config,
+        )?;
+        split_fee(
+            accounts,
+            asks_account,
+            dex_fees_account,
+            market_account,
+            &market_mint,
+            &mut market_state,
+            ask_fee,
+            TransferReason::OpenFee,
+            &config,
+        )?;
+        
+        apply_fill_to_position(
+            program_id,
+            bid_position_account,
+            bids_account,
+            &bid,
+            POSITION_LONG,
+            &market_mint,
+            price,
+            bid_amount.saturating_sub(bid_fee),
+            bid_fee,
+            &config,
+            &market_state,
+        )?;
+        apply_fill_to_position(
+            program_id,
+            ask_position_account,
+            asks_account,
+            &ask,
+            POSITION_SHORT,
+            &market_mint,
+            price,
+            ask_amount.saturating_sub(ask_fee),
+            ask_fee,
+            &config,
+            &market_state,
+        )?;
+        
+        market_state.add_open_interest(
+            POSITION_LONG,
+            bid_amount.saturating_sub(bid_fee).saturating_mul(bid.leverage as u64),
+        );
+        market_state.add_open_interest(
+            POSITION_SHORT,
+            ask_amount.saturating_sub(ask_fee).saturating_mul(ask.leverage as u64),
+        );
+        
+        // A filled iceberg slice is replenished from its hidden remainder and requeued behind
+        // the orders already resting at its price.
+        // Skipped orders rest ahead of the index at a better or equal price, so the requeued
+        // slice always lands behind them.
+        for (book, order, amount, index) in [(&mut bids, &bid, bid_amount, bid_index), (&mut asks, &ask, ask_amount, ask_index)] {
+            if amount >= order.paid_amount {
+                let mut filled = book.orders.remove(index);
+                filled.paid_amount = 0;
+                
+                if filled.replenish(current_slot) {
+                    book.insert(filled)?;
+                }
+            } else {
+                book.orders[index].paid_amount = order.paid_amount.saturating_sub(amount);
+            }
+        }
+        
+        for (order, role, amount, fee) in [(&bid, bid_role, bid_amount, bid_fee), (&ask, ask_role, ask_amount, ask_fee)] {
+            emit_event(&DexEvent::FeeCharged(FeeChargedEvent {
+                market_mint,
+                payer: order.owner,
+                position_nonce: order.position_nonce,
+                role,
+                amount,
+                fee,
+            }))?;
+        }
+        
+        emit_event(&DexEvent::OrderFilled(FillEvent {
+            market_mint,
+            bid_order_id: bid.order_id,
+            ask_order_id: ask.order_id,
+            bid_owner: bid.owner,
+            ask_owner: ask.owner,
+            maker_side,
+            price,
+            notional,
+            bid_fee,
+            ask_fee,
+        }))?;
+        
+        fills = fills.saturating_add(1);
+    }
+    
+    bids.serialize(&mut *bids_account.data.borrow_mut())?;
+    asks.serialize(&mut *asks_account.data.borrow_mut())?;
+    save_market_state(market_account, &market_state)?;
+    
+    msg!("Matched {} fills", fills);
+    
+    Ok(())
+}
+
+pub(crate) fn load_close_order(
+    close_order_account: &AccountInfo,
+    owner: &Pubkey,
+    position_nonce: u64,
+    program_id: &Pubkey,
+) -> Result<CloseOrderAccount, ProgramError> {
+    let (close_order_pda, _close_order_bump) = find_close_order_address(owner, position_nonce, program_id);
+    
+    assert_key_eq(close_order_account, &close_order_pda)?;
+    
+    assert_owned_by(close_order_account, program_id)?;
+    
+    deserialize_padded::<CloseOrderAccount>(&close_order_account.data.borrow())
+}
+
+/// Returns all of an order account's remaining lamports to its owner and wipes it.
+pub(crate) fn refund_order_account(order_account: &AccountInfo, owner_account: &AccountInfo) -> ProgramResult {
+    let lamports = order_account.lamports();
+    move_lamports(order_account, owner_account, lamports, TransferReason::RentRefund)?;
+    
+    zero_account_data(order_account)
+}
+
+/// Rests a take-profit and/or stop-loss price against an open position; any keeper can close the
+/// position through `EXECUTE_CLOSE_ORDER` once the oracle reaches either.
+pub(crate) fn process_place_close_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    place_data: PlaceCloseOrderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let close_order_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, place_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if !position.is_open() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (close_order_pda, close_order_bump) =
+        find_close_order_address(owner_account.key, place_data.position_nonce, program_id);
+    
+    assert_key_eq(close_order_account, &close_order_pda)?;
+    
+    if close_order_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                owner_account.key,
+                close_order_account.key,
+                Rent::get()?.minimum_balance(CloseOrderAccount::LEN),
+                CloseOrderAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                owner_account.clone(),
+                close_order_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"uranus_close_order",
+                owner_account.key.as_ref(),
+                &place_data.position_nonce.to_le_bytes(),
+                &[close_order_bump],
+            ]],
+        )?;
+    } else if close_order_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let close_order = CloseOrderAccount {
+        owner: *owner_account.key,
+        market_mint: position.market_mint,
+        position_nonce: place_data.position_nonce,
+        direction: position.direction,
+        limit_price: place_data.limit_price,
+        placed_slot: Clock::get()?.slot,
+        stop_price: place_data.stop_price,
+    };
+    
+    if !close_order.is_valid() {
+        msg!("Close order needs a take-profit or stop-loss price, with the stop on the losing side");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    close_order.serialize(&mut *close_order_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::CloseOrderPlaced(CloseOrderEvent {
+        owner: close_order.owner,
+        position_nonce: close_order.position_nonce,
+        market_mint: close_order.market_mint,
+        limit_price: close_order.limit_price,
+        stop_price: close_order.stop_price,
+        mark_price: 0,
+    }))?;
+    
+    msg!(
+        "Close order for position {}, take-profit: {}, stop-loss: {}",
+        place_data.position_nonce,
+        place_data.limit_price,
+        place_data.stop_price
+    );
+    
+    Ok(())
+}
+
+/// Removes a close order and refunds its rent; works whether or not the position still exists.
+pub(crate) fn process_cancel_close_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cancel_data: CloseOrderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let close_order_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let close_order = load_close_order(
+        close_order_account,
+        owner_account.key,
+        cancel_data.position_nonce,
+        program_id,
+    )?;
+    
+    refund_order_account(close_order_account, owner_account)?;
+    
+    emit_event(&DexEvent::CloseOrderCancelled(CloseOrderEvent {
+        owner: close_order.owner,
+        position_nonce: close_order.position_nonce,
+        market_mint: close_order.market_mint,
+        limit_price: close_order.limit_price,
+        stop_price: close_order.stop_price,
+        mark_price: 0,
+    }))?;
+    
+    msg!("Close order for position {} cancelled", cancel_data.position_nonce);
+    
+    Ok(())
+}
+
+/// Permissionless keeper execution of a close order once the oracle price has reached either
+/// leg. The position settles at the oracle price and the whole order is closed with it, so the
+/// other leg of an OCO pair can never fire against a position that is already gone.
+pub(crate) fn process_execute_close_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    execute_data: CloseOrderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let close_order_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let margin_account = next_account_info(accounts_iter).ok();
+    
+    assert_signer(keeper_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let close_order = load_close_order(
+        close_order_account,
+        owner_account.key,
+        execute_data.position_nonce,
+        program_id,
+    )?;
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, execute_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if !position.is_open() || !position.is_confirmed() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    let oracle_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?;
+    let mark_price = oracle_price.effective(position.direction, false);
+    
+    if !close_order.is_triggered(mark_price) {
+        msg!(
+            "Oracle price {} has reached neither the take-profit at {} nor the stop-loss at {}",
+            mark_price,
+            close_order.limit_price,
+            close_order.stop_price
+        );
+        return Err(DexError::CloseOrderNotTriggered.into());
+    }
+    
+    let leg = if close_order.take_profit_triggered(mark_price) { "take-profit" } else { "stop-loss" };
+    
+    let cross_margin = cross_margin_account(&position, margin_account, program_id)?;
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let final_pnl = settle_at_mark_price(
+        &position,
+        position_account,
+        owner_account,
+        market_account,
+        &mut market_state,
+        cross_margin,
+        &config,
+        accounts,
+        mark_price,
+        TransferReason::Profit,
+        program_id,
+    )?;
+    
+    emit_settlement_price(&position, &oracle_price, mark_price)?;
+    
+    refund_order_account(close_order_account, owner_account)?;
+    
+    emit_event(&DexEvent::CloseOrderExecuted(CloseOrderEvent {
+        owner: close_order.owner,
+        position_nonce: close_order.position_nonce,
+        market_mint: close_order.market_mint,
+        limit_price: close_order.limit_price,
+        stop_price: close_order.stop_price,
+        mark_price,
+    }))?;
+    
+    msg!(
+        "Close order {} leg for position {} executed at {}, pnl: {}",
+        leg,
+        execute_data.position_nonce,
+        mark_price,
+        final_pnl
+    );
+    
+    Ok(())
+}
+
+pub(crate) fn load_scheduled_order(
+    scheduled_account: &AccountInfo,
+    owner: &Pubkey,
+    position_nonce: u64,
+    program_id: &Pubkey,
+) -> Result<ScheduledOrderAccount, ProgramError> {
+    let (scheduled_pda, _scheduled_bump) = find_scheduled_order_address(owner, position_nonce, program_id);
+    
+    assert_key_eq(scheduled_account, &scheduled_pda)?;
+    
+    assert_owned_by(scheduled_account, program_id)?;
+    
+    deserialize_padded::<ScheduledOrderAccount>(&scheduled_account.data.borrow())
+}
+
+/// Schedules an open or close that keepers may execute at the oracle price once its slot and
+/// timestamp have passed. A scheduled open reserves its position like `PLACE_ORDER` and escrows
+/// the collateral in the scheduled order account.
+pub(crate) fn process_place_scheduled_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    place_data: PlaceScheduledOrderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let scheduled_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let nonce_counter_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    verify_market_address(market_account, &place_data.market_mint, program_id)?;
+    
+    let mut scheduled = ScheduledOrderAccount {
+        owner: *owner_account.key,
+        market_mint: place_data.market_mint,
+        position_nonce: place_data.position_nonce,
+        kind: place_data.kind,
+        execute_after_slot: place_data.execute_after_slot,
+        execute_after_timestamp: place_data.execute_after_timestamp,
+        placed_slot: Clock::get()?.slot,
+        ..ScheduledOrderAccount::default()
+    };
+    
+    match place_data.kind {
+        SCHEDULED_ORDER_OPEN => {
+            if place_data.direction != POSITION_LONG && place_data.direction != POSITION_SHORT {
+                msg!("Invalid direction");
+                return Err(ProgramError::InvalidArgument);
+            }
+            
+            let leverage = place_data.leverage.clamp(1, MAXIMUM_LEVERAGE);
+            if place_data.paid_amount.saturating_mul(leverage as u64) < MIN_POSITION_SIZE_LAMPORTS {
+                msg!("Order size too small");
+                return Err(ProgramError::InvalidArgument);
+            }
+            
+            let symbol_registry = load_symbol_registry(symbol_registry_account, &place_data.market_mint, program_id)?;
+            
+            check_leverage_tier(
+                &load_tradable_market_state(market_account, program_id)?,
+                place_data.paid_amount.saturating_mul(leverage as u64),
+                leverage,
+            )?;
+            
+            reserve_order_position(
+                position_account,
+                owner_account,
+                nonce_counter_account,
+                system_program,
+                &PlaceOrderData {
+                    market_mint: place_data.market_mint,
+                    side: place_data.direction,
+                    price: 0,
+                    paid_amount: place_data.paid_amount,
+                    leverage,
+                    position_nonce: place_data.position_nonce,
+                    display_amount: 0,
+                },
+                leverage,
+                symbol_registry.market_symbol,
+                program_id,
+            )?;
+            
+            scheduled.direction = place_data.direction;
+            scheduled.leverage = leverage;
+            scheduled.paid_amount = place_data.paid_amount;
+        },
+        SCHEDULED_ORDER_CLOSE => {
+            assert_owned_by(position_account, program_id)?;
+            
+            let position = try_load_position_account(position_account)?;
+            
+            position.validate(owner_account.key, place_data.position_nonce)?;
+            position.validate_address(position_account.key, program_id)?;
+            
+            if !position.is_open() || position.market_mint != place_data.market_mint {
+                return Err(ProgramError::InvalidArgument);
+            }
+            
+            scheduled.direction = position.direction;
+        },
+        _ => {
+            msg!("Invalid scheduled order kind");
+            return Err(ProgramError::InvalidArgument);
+        },
+    }
+    
+    let (scheduled_pda, scheduled_bump) =
+        find_scheduled_order_address(owner_account.key, place_data.position_nonce, program_id);
+    
+    assert_key_eq(scheduled_account, &scheduled_pda)?;
+    
+    if !scheduled_account.data_is_empty() {
+        msg!("Position {} already has a scheduled order", place_data.position_nonce);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            owner_account.key,
+            scheduled_account.key,
+            Rent::get()?
+                .minimum_balance(ScheduledOrderAccount::LEN)
+                .saturating_add(scheduled.paid_amount),
+            ScheduledOrderAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            owner_account.clone(),
+            scheduled_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_scheduled",
+            owner_account.key.as_ref(),
+            &place_data.position_nonce.to_le_bytes(),
+            &[scheduled_bump],
+        ]],
+    )?;
+    
+    scheduled.serialize(&mut *scheduled_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::ScheduledOrderPlaced(ScheduledOrderEvent {
+        owner: scheduled.owner,
+        position_nonce: scheduled.position_nonce,
+        market_mint: scheduled.market_mint,
+        kind: scheduled.kind,
+        execute_after_slot: scheduled.execute_after_slot,
+        execute_after_timestamp: scheduled.execute_after_timestamp,
+        mark_price: 0,
+    }))?;
+    
+    msg!(
+        "Scheduled order for position {} after slot {} and timestamp {}",
+        scheduled.position_nonce,
+        scheduled.execute_after_slot,
+        scheduled.execute_after_timestamp
+    );
+    
+    Ok(())
+}
+
+/// Removes a scheduled order, refunding any escrowed collateral along with its rent.
+pub(crate) fn process_cancel_scheduled_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cancel_data: ScheduledOrderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let scheduled_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let scheduled = load_scheduled_order(
+        scheduled_account,
+        owner_account.key,
+        cancel_data.position_nonce,
+        program_id,
+    )?;
+    
+    move_lamports(scheduled_account, owner_account, scheduled.paid_amount, TransferReason::OrderRefund)?;
+    refund_order_account(scheduled_account, owner_account)?;
+    
+    emit_event(&DexEvent::ScheduledOrderCancelled(ScheduledOrderEvent {
+        owner: scheduled.owner,
+        position_nonce: scheduled.position_nonce,
+        market_mint: scheduled.market_mint,
+        kind: scheduled.kind,
+        execute_after_slot: scheduled.execute_after_slot,
+        execute_after_timestamp: scheduled.execute_after_timestamp,
+        mark_price: 0,
+    }))?;
+    
+    msg!("Scheduled order for position {} cancelled", cancel_data.position_nonce);
+    
+    Ok(())
+}
+
+/// Permissionless keeper execution of a scheduled order whose slot and timestamp have passed.
+/// Opens fill the reserved position at the spread-adjusted oracle price and pay the taker fee;
+/// closes settle at the oracle price.
+pub(crate) fn process_execute_scheduled_order(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    execute_data: ScheduledOrderData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let scheduled_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let margin_account = next_account_info(accounts_iter).ok();
+    
+    assert_signer(keeper_account)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let scheduled = load_scheduled_order(
+        scheduled_account,
+        owner_account.key,
+        execute_data.position_nonce,
+        program_id,
+    )?;
+    
+    let clock = Clock::get()?;
+    
+    if !scheduled.is_ready(clock.slot, clock.unix_timestamp) {
+        msg!(
+            "Scheduled for slot {} and timestamp {}, now {} and {}",
+            scheduled.execute_after_slot,
+            scheduled.execute_after_timestamp,
+            clock.slot,
+            clock.unix_timestamp
+        );
+        return Err(DexError::ScheduledOrderNotReady.into());
+    }
+    
+    verify_market_address(market_account, &scheduled.market_mint, program_id)?;
+    
+    let oracle_price = load_price_feed(price_feed_account, &scheduled.market_mint, &config, program_id)?;
+    let mark_price = oracle_price.price;
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    
+    if scheduled.kind == SCHEDULED_ORDER_OPEN {
+        assert_fee_vault(dex_fees_account, program_id)?;
+        
+        if !market_state.is_active() {
+            return Err(DexError::MarketNotActive.into());
+        }
+        
+        check_native_settlement(&market_state)?;
+        check_leverage_tier(
+            &market_state,
+            scheduled.paid_amount.saturating_mul(scheduled.leverage as u64),
+            scheduled.leverage,
+        )?;
+        check_price_impact(
+            market_account,
+            scheduled.paid_amount.saturating_mul(scheduled.leverage as u64),
+            &config,
+        )?;
+        
+        let price = apply_spread(
+            oracle_price.effective(scheduled.direction, true),
+            scheduled.direction,
+            market_state.spread_bps(market_account.lamports()),
+            true,
+        );
+        let fee = fill_fee(scheduled.paid_amount, scheduled.leverage, FillRole::Taker, &market_state.fee_schedule());
+        let collateral = scheduled.paid_amount.saturating_sub(fee);
+        
+        split_fee(
+            accounts,
+            scheduled_account,
+            dex_fees_account,
+            market_account,
+            &scheduled.market_mint,
+            &mut market_state,
+            fee,
+            TransferReason::OpenFee,
+            &config,
+        )?;
+        apply_fill_to_position(
+            program_id,
+            position_account,
+            scheduled_account,
+            &Order {
+                order_id: 0,
+                owner: scheduled.owner,
+                price,
+                paid_amount: scheduled.paid_amount,
+                leverage: scheduled.leverage,
+                position_nonce: scheduled.position_nonce,
+                placed_slot: scheduled.placed_slot,
+                ..Order::default()
+            },
+            scheduled.direction,
+            &scheduled.market_mint,
+            price,
+            collateral,
+            fee,
+            &config,
+            &market_state,
+        )?;
+        
+        market_state.add_open_interest(scheduled.direction, collateral.saturating_mul(scheduled.leverage as u64));
+        save_market_state(market_account, &market_state)?;
+        
+        emit_event(&DexEvent::FeeCharged(FeeChargedEvent {
+            market_mint: scheduled.market_mint,
+            payer: scheduled.owner,
+            position_nonce: scheduled.position_nonce,
+            role: FillRole::Taker,
+            amount: scheduled.paid_amount,
+            fee,
+        }))?;
+        
+        msg!("Scheduled open of position {} filled at {}", scheduled.position_nonce, price);
+    } else {
+        assert_owned_by(position_account, program_id)?;
+        
+        let position = try_load_position_account(position_account)?;
+        
+        position.validate(owner_account.key, execute_data.position_nonce)?;
+        position.validate_address(position_account.key, program_id)?;
+        
+        if !position.is_open() || !position.is_confirmed() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        let cross_margin = cross_margin_account(&position, margin_account, program_id)?;
+        let exit_price = oracle_price.effective(position.direction, false);
+        let final_pnl = settle_at_mark_price(
+            &position,
+            position_account,
+            owner_account,
+            market_account,
+            &mut market_state,
+            cross_margin,
+            &config,
+            accounts,
+            exit_price,
+            TransferReason::Profit,
+            program_id,
+        )?;
+        
+        emit_settlement_price(&position, &oracle_price, exit_price)?;
+        
+        msg!("Scheduled close of position {} at {}, pnl: {}", scheduled.position_nonce, exit_price, final_pnl);
+    }
+    
+    refund_order_account(scheduled_account, owner_account)?;
+    
+    emit_event(&DexEvent::ScheduledOrderExecuted(ScheduledOrderEvent {
+        owner: scheduled.owner,
+        position_nonce: scheduled.position_nonce,
+        market_mint: scheduled.market_mint,
+        kind: scheduled.kind,
+        execute_after_slot: scheduled.execute_after_slot,
+        execute_after_timestamp: scheduled.execute_after_timestamp,
+        mark_price,
+    }))?;
+    
+    Ok(())
+}
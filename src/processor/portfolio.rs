@@ -0,0 +1,248 @@
+//! Gathers an owner's positions for the portfolio-margin risk engine, enforces its health floor
+//! on margin withdrawals and new opens along with the per-owner notional cap, and keeps the
+//! per-market hedge mode choice.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Prices every live position in `owner`'s position index at its market's oracle. The index,
+/// each indexed position and each of their markets' price feeds must be among `accounts`, so a
+/// position cannot be left out of the portfolio; an owner without an index has no positions.
+/// Markets whose hedge mode account is among `accounts` and enabled are not netted.
+pub(crate) fn load_portfolio(
+    accounts: &[AccountInfo],
+    owner: &Pubkey,
+    config: &GlobalConfig,
+    program_id: &Pubkey,
+) -> Result<Vec<PortfolioPosition>, ProgramError> {
+    let index_account = find_position_index_account(accounts, owner, program_id).ok_or_else(|| {
+        msg!("Portfolio check requires the owner's position index");
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    
+    if index_account.data_is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    let index = load_position_index(index_account, owner, program_id)?;
+    let mut portfolio = Vec::with_capacity(index.nonces.len());
+    
+    for nonce in index.nonces {
+        let (position_pda, _position_bump) = find_position_address(owner, nonce, program_id);
+        let position_account = accounts
+            .iter()
+            .find(|account| account.key == &position_pda)
+            .ok_or_else(|| {
+                msg!("Portfolio check requires position {}", nonce);
+                ProgramError::NotEnoughAccountKeys
+            })?;
+        
+        if position_account.owner != program_id || position_account.data_is_empty() {
+            continue;
+        }
+        
+        let position = try_load_position_account(position_account)?;
+        
+        if !position.is_open() {
+            continue;
+        }
+        
+        let (price_feed_pda, _feed_bump) = find_price_feed_address(&position.market_mint, program_id);
+        let price_feed_account = accounts
+            .iter()
+            .find(|account| account.key == &price_feed_pda)
+            .ok_or_else(|| {
+                msg!("Portfolio check requires the price feed for {}", position.market_mint);
+                ProgramError::NotEnoughAccountKeys
+            })?;
+        let mark_price = load_price_feed(price_feed_account, &position.market_mint, config, program_id)?.effective(position.direction, false);
+        
+        let hedged = hedge_mode_enabled(accounts, owner, &position.market_mint, program_id)?;
+        
+        portfolio.push(PortfolioPosition::from_position(&position, mark_price, hedged)?);
+    }
+    
+    Ok(portfolio)
+}
+
+/// Fails unless `owner`'s portfolio, with `pending` added and `shared_margin` as its margin
+/// balance, stays at or above the config `portfolio_health_floor_bps`. A zero floor disables
+/// the check.
+pub(crate) fn check_portfolio_health(
+    accounts: &[AccountInfo],
+    owner: &Pubkey,
+    shared_margin: u64,
+    pending: Option<PortfolioPosition>,
+    config: &GlobalConfig,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    if config.portfolio_health_floor_bps == 0 {
+        return Ok(());
+    }
+    
+    let mut portfolio = load_portfolio(accounts, owner, config, program_id)?;
+    portfolio.extend(pending);
+    
+    let health = portfolio_health(&portfolio, shared_margin, config.maintenance_margin_bps);
+    let health_factor = health.health_factor_bps();
+    
+    if health_factor < config.portfolio_health_floor_bps as u64 {
+        msg!(
+            "Portfolio health {} bps below the {} bps floor (equity {}, requirement {})",
+            health_factor,
+            config.portfolio_health_floor_bps,
+            health.equity,
+            health.maintenance
+        );
+        return Err(DexError::PortfolioHealthTooLow.into());
+    }
+    
+    Ok(())
+}
+
+/// The owner's free cross margin if their margin account is among `accounts`, else zero.
+pub(crate) fn find_shared_margin(
+    accounts: &[AccountInfo],
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let (margin_pda, _margin_bump) = find_margin_address(owner, program_id);
+    
+    match accounts
+        .iter()
+        .find(|account| account.key == &margin_pda && account.owner == program_id)
+    {
+        Some(margin_account) => margin_available(margin_account),
+        None => Ok(0),
+    }
+}
+
+/// Fails when an open of `pending_notional` on a market would lift `owner`'s aggregate notional
+/// across all their positions, at oracle prices, above the cap: the market's
+/// `max_owner_notional` when set, otherwise the config's. No cap is enforced when both are zero;
+/// otherwise the accounts `load_portfolio` needs must be passed.
+pub(crate) fn check_owner_notional(
+    accounts: &[AccountInfo],
+    owner: &Pubkey,
+    pending_notional: u64,
+    market_state: &MarketState,
+    config: &GlobalConfig,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let cap = if market_state.max_owner_notional != 0 {
+        market_state.max_owner_notional
+    } else {
+        config.max_owner_notional
+    };
+    
+    if cap == 0 {
+        return Ok(());
+    }
+    
+    let notional = load_portfolio(accounts, owner, config, program_id)?
+        .iter()
+        .fold(pending_notional as u128, |total, position| total.saturating_add(position.notional));
+    
+    if notional > cap as u128 {
+        msg!("Owner notional {} would exceed the cap of {} lamports", notional, cap);
+        return Err(DexError::OwnerNotionalCapExceeded.into());
+    }
+    
+    Ok(())
+}
+
+/// True if `owner`'s hedge mode account for `market_mint` is among `accounts` and enabled.
+pub(crate) fn hedge_mode_enabled(
+    accounts: &[AccountInfo],
+    owner: &Pubkey,
+    market_mint: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<bool, ProgramError> {
+    let (hedge_pda, _hedge_bump) = find_hedge_mode_address(owner, market_mint, program_id);
+    
+    match accounts
+        .iter()
+        .find(|account| account.key == &hedge_pda && account.owner == program_id)
+    {
+        Some(hedge_account) => Ok(deserialize_padded::<HedgeModeAccount>(&hedge_account.data.borrow())?.is_enabled()),
+        None => Ok(false),
+    }
+}
+
+/// Switches the signer between one-way and hedge mode on a market, creating the mode account
+/// on first use.
+pub(crate) fn process_set_hedge_mode(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    hedge_data: SetHedgeModeData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let hedge_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let (hedge_pda, hedge_bump) = find_hedge_mode_address(owner_account.key, &hedge_data.market_mint, program_id);
+    
+    assert_key_eq(hedge_account, &hedge_pda)?;
+    
+    if hedge_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                owner_account.key,
+                hedge_account.key,
+                Rent::get()?.minimum_balance(HedgeModeAccount::LEN),
+                HedgeModeAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                owner_account.clone(),
+                hedge_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"uranus_hedge",
+                owner_account.key.as_ref(),
+                hedge_data.market_mint.as_ref(),
+                &[hedge_bump],
+            ]],
+        )?;
+    } else if hedge_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    HedgeModeAccount {
+        owner: *owner_account.key,
+        market_mint: hedge_data.market_mint,
+        enabled: hedge_data.enabled as u8,
+        updated_slot: Clock::get()?.slot,
+    }
+    .serialize(&mut *hedge_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::HedgeModeSet(HedgeModeEvent {
+        owner: *owner_account.key,
+        market_mint: hedge_data.market_mint,
+        enabled: hedge_data.enabled,
+    }))?;
+    
+    msg!(
+        "{} mode on market {}",
+        if hedge_data.enabled { "Hedge" } else { "One-way" },
+        hedge_data.market_mint
+    );
+    
+    Ok(())
+}
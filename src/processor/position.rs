@@ -0,0 +1,3992 @@
+//! Position lifecycle: open, modify, settle, cancel, expire and account upgrades.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::{invoke, invoke_signed},
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn process_initialize(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    initialize_data: InitializePositionData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let payer_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    let _dex_fees_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let fee_escrow_account = next_account_info(accounts_iter)?;
+    let position_index_account = next_account_info(accounts_iter)?;
+    let nonce_counter_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(payer_account)?;
+    
+    if initialize_data.position_size < MIN_POSITION_SIZE_LAMPORTS {
+        msg!("Position size too small");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let fees = market_fee_schedule(market_account, program_id)?;
+    let (leverage, total_fee, position_amount_after_fees, actual_position_size) =
+        open_terms(initialize_data.paid_amount, initialize_data.leverage, FillRole::Taker, &fees);
+    
+    if leverage != initialize_data.leverage {
+        msg!("Leverage adjusted to {}x", leverage);
+    }
+    
+    // The claimed size may sit anywhere between the size net of the open fee, less the fee
+    // grossed up by leverage, and the gross paid amount at the clamped leverage; anything above
+    // implies more leverage than the position gets.
+    let max_claimed_size = initialize_data.paid_amount.saturating_mul(leverage as u64);
+    let min_claimed_size = actual_position_size.saturating_sub(total_fee.saturating_mul(leverage as u64));
+    
+    if initialize_data.position_size > max_claimed_size || initialize_data.position_size < min_claimed_size {
+        msg!(
+            "Claimed position size {} outside {}..={} for {} paid at {}x",
+            initialize_data.position_size,
+            min_claimed_size,
+            max_claimed_size,
+            initialize_data.paid_amount,
+            leverage
+        );
+        return Err(DexError::PositionSizeMismatch.into());
+    }
+    
+    check_leverage_tier(&load_market_state(market_account, program_id)?, actual_position_size, leverage)?;
+    
+    if actual_position_size < MIN_POSITION_SIZE_LAMPORTS {
+        msg!("Position size after fees too small");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if (position_amount_after_fees as u128).saturating_mul(BASIS_POINTS_DIVISOR as u128)
+        < (actual_position_size as u128).saturating_mul(config.initial_margin_bps as u128)
+    {
+        msg!("Collateral below initial margin of {} bps", config.initial_margin_bps);
+        return Err(DexError::InsufficientInitialMargin.into());
+    }
+    
+    if initialize_data.platform_fee_bps > config.max_platform_fee_bps {
+        msg!("Platform fee share capped at {} bps", config.max_platform_fee_bps);
+        return Err(DexError::PlatformFeeTooHigh.into());
+    }
+    
+    // The routing frontend's account and its registry entry follow the nonce counter and are
+    // only read when it earns a share.
+    let platform = if initialize_data.platform_fee_bps > 0 {
+        let platform_account = next_account_info(accounts_iter)?;
+        let integrator_account = next_account_info(accounts_iter)?;
+        let integrator = load_integrator(integrator_account, platform_account.key, program_id)?;
+        
+        if initialize_data.platform_fee_bps > integrator.fee_share_bps {
+            msg!("Integrator fee share capped at {} bps", integrator.fee_share_bps);
+            return Err(DexError::PlatformFeeTooHigh.into());
+        }
+        
+        Some((platform_account, integrator_account, integrator))
+    } else {
+        None
+    };
+    let platform_fee = platform_fee_share(total_fee, initialize_data.platform_fee_bps);
+    let escrowed_fee = total_fee.saturating_sub(platform_fee);
+    
+    if initialize_data.direction != POSITION_LONG && initialize_data.direction != POSITION_SHORT {
+        msg!("Invalid direction");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if initialize_data.margin_mode != MARGIN_MODE_ISOLATED && initialize_data.margin_mode != MARGIN_MODE_CROSS {
+        return Err(DexError::InvalidMarginMode.into());
+    }
+    
+    let symbol_registry = load_symbol_registry(
+        symbol_registry_account,
+        &initialize_data.market_mint,
+        program_id
+    )?;
+    
+    if symbol_registry.market_symbol != initialize_data.market_symbol {
+        msg!("Client symbol ignored, using registered symbol");
+    }
+    
+    verify_market_address(market_account, &initialize_data.market_mint, program_id)?;
+    
+    let tradable_market_state = load_tradable_market_state(market_account, program_id)?;
+    
+    if tradable_market_state.twap_threshold_notional != 0 && actual_position_size > tradable_market_state.twap_threshold_notional {
+        msg!("Opens above {} must be split with CREATE_TWAP_OPEN", tradable_market_state.twap_threshold_notional);
+        return Err(DexError::TwapRequired.into());
+    }
+    
+    check_price_impact(market_account, actual_position_size, &config)?;
+    admit_open(
+        accounts,
+        owner_account.key,
+        payer_account,
+        system_program,
+        &initialize_data.market_mint,
+        initialize_data.direction,
+        actual_position_size,
+        position_amount_after_fees,
+        &tradable_market_state,
+        &config,
+        program_id,
+    )?;
+    
+    assert_key_eq(dex_account, &load_authority(accounts, program_id)?.dex_authority)?;
+    
+    let claimed_nonce = claim_position_nonce(
+        nonce_counter_account,
+        owner_account.key,
+        payer_account,
+        system_program,
+        program_id,
+    )?;
+    
+    if claimed_nonce != initialize_data.position_nonce {
+        msg!("Expected position nonce {}", claimed_nonce);
+        return Err(DexError::NonceMismatch.into());
+    }
+    
+    let (position_pda, bump_seed) = find_position_address(
+        owner_account.key,
+        initialize_data.position_nonce,
+        program_id
+    );
+    
+    if position_pda != *position_account.key {
+        msg!("Invalid position account");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let (fee_escrow_pda, fee_escrow_bump) = find_fee_escrow_address(program_id);
+    
+    assert_key_eq(fee_escrow_account, &fee_escrow_pda)?;
+    
+    if fee_escrow_account.data_is_empty() && fee_escrow_account.lamports() == 0 {
+        let rent = Rent::get()?;
+        let minimum_balance = rent.minimum_balance(0);
+        
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                fee_escrow_account.key,
+                minimum_balance,
+                0,
+                program_id,
+            ),
+            &[
+                payer_account.clone(),
+                fee_escrow_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[b"uranus_fee_escrow", &[fee_escrow_bump]]],
+        )?;
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        payer_account,
+        system_program,
+        &initialize_data.market_mint,
+        program_id,
+    )?;
+    market_state.accrue_borrow(market_account.lamports(), Clock::get()?.slot);
+    
+    match skew_adjustment(&mut market_state, initialize_data.direction, actual_position_size, true, &config) {
+        SkewAdjustment::Surcharge(surcharge) => {
+            if surcharge > 0 {
+                invoke(
+                    &system_instruction::transfer(
+                        payer_account.key,
+                        market_account.key,
+                        surcharge,
+                    ),
+                    &[
+                        payer_account.clone(),
+                        market_account.clone(),
+                        system_program.clone(),
+                    ],
+                )?;
+                
+                market_state.record_deposit(surcharge);
+                
+                msg!("Skew surcharge: {} lamports", surcharge);
+            }
+        },
+        SkewAdjustment::Rebate(rebate) => {
+            move_lamports(market_account, owner_account, rebate, TransferReason::SkewRebate)?;
+            market_state.record_payout(rebate);
+            
+            msg!("Skew rebate: {} lamports", rebate);
+        },
+    }
+    
+    market_state.add_open_interest(initialize_data.direction, actual_position_size);
+    save_market_state(market_account, &market_state)?;
+    
+    let position = PositionAccount {
+        version: POSITION_ACCOUNT_VERSION,
+        owner: *owner_account.key,
+        market_mint: initialize_data.market_mint,
+        market_symbol: symbol_registry.market_symbol,
+        entry_price: 0,
+        liquidation_price: 0,
+        paid_amount: position_amount_after_fees,
+        position_size: actual_position_size,
+        leverage,
+        closed: 0,
+        position_nonce: initialize_data.position_nonce,
+        pnl: 0,
+        direction: initialize_data.direction,
+        margin_mode: initialize_data.margin_mode,
+        confirmed: 0,
+        open_fee: escrowed_fee,
+        open_slot: Clock::get()?.slot,
+        liquidation_auction_slot: 0,
+        funding_index_snapshot: market_state.funding_index(initialize_data.direction),
+        borrow_index_snapshot: market_state.cumulative_borrow_index,
+        residual_profit: 0,
+        residual_fee: 0,
+        guaranteed_stop_price: 0,
+        client_tag: initialize_data.client_tag,
+        margin_call_slot: 0,
+        contract_type: market_state.contract_type,
+        close_request_slot: 0,
+        open_base_fee_bps: fees.base_fee_bps as u16,
+        open_leverage_fee_bps: fees.leverage_fee_bps as u16,
+        checksum: 0,
+    };
+    
+    let serialized_data = position.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    let data_len = serialized_data.len();
+    
+    // The payer funds rent exemption on top of the collateral, so even the smallest position
+    // cannot be reaped and `paid_amount` only ever counts trading collateral.
+    let rent = Rent::get()?;
+    let rent_deposit = rent.minimum_balance(data_len);
+    let position_lamports = position_amount_after_fees
+        .checked_add(rent_deposit)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    
+    let seeds = &[
+        b"uranus_position",
+        owner_account.key.as_ref(),
+        &initialize_data.position_nonce.to_le_bytes(),
+        &[bump_seed],
+    ];
+    
+    create_pda_account(
+        position_account,
+        payer_account,
+        system_program,
+        rent_deposit,
+        data_len,
+        seeds,
+        program_id,
+    )?;
+    
+    // Fee and collateral arrive in one transfer of `paid_amount` and the program splits the fee
+    // out, so the account ends up holding exactly its rent deposit plus the recorded collateral.
+    invoke(
+        &system_instruction::transfer(
+            payer_account.key,
+            position_account.key,
+            initialize_data.paid_amount,
+        ),
+        &[
+            payer_account.clone(),
+            position_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    move_lamports(position_account, fee_escrow_account, escrowed_fee, TransferReason::OpenFee)?;
+    
+    if let Some((platform_account, integrator_account, mut integrator)) = platform {
+        move_lamports(position_account, platform_account, platform_fee, TransferReason::OpenFee)?;
+        
+        emit_event(&DexEvent::PlatformFeePaid(PlatformFeeEvent {
+            platform: *platform_account.key,
+            market_mint: initialize_data.market_mint,
+            owner: *owner_account.key,
+            position_nonce: initialize_data.position_nonce,
+            fee: total_fee,
+            platform_fee,
+        }))?;
+        
+        integrator.record_open(actual_position_size, platform_fee);
+        integrator.serialize(&mut *integrator_account.data.borrow_mut())?;
+        
+        msg!("Platform fee: {} lamports to {}", platform_fee, platform_account.key);
+    }
+    
+    // Lamports someone sent to the address before it was created are not collateral.
+    let excess = position_account.lamports().saturating_sub(position_lamports);
+    move_lamports(position_account, payer_account, excess, TransferReason::CollateralReturn)?;
+    
+    if position_account.lamports() != position_lamports {
+        msg!("Position holds {} lamports, expected {}", position_account.lamports(), position_lamports);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    save_position_account(position_account, &position)?;
+    
+    append_to_position_index(
+        position_index_account,
+        owner_account.key,
+        payer_account,
+        system_program,
+        initialize_data.position_nonce,
+        program_id,
+    )?;
+    
+    emit_event(&DexEvent::FeeCharged(FeeChargedEvent {
+        market_mint: initialize_data.market_mint,
+        payer: *owner_account.key,
+        position_nonce: initialize_data.position_nonce,
+        role: FillRole::Taker,
+        amount: initialize_data.paid_amount,
+        fee: total_fee,
+    }))?;
+    
+    msg!("Position initialized: nonce {}", initialize_data.position_nonce);
+    msg!("Fee: {} lamports ({} escrowed until confirmed)", total_fee, escrowed_fee);
+    msg!("Locked: {} lamports", position_amount_after_fees);
+    msg!("Rent deposit: {} lamports", rent_deposit);
+    msg!("Leverage: {}x", leverage);
+    msg!("Ticker: {}", fixed_array_to_string(&symbol_registry.market_symbol)?);
+    msg!("Market mint: {}", initialize_data.market_mint);
+    msg!("Direction: {}", if initialize_data.direction == POSITION_LONG { "Long" } else { "Short" });
+    msg!("Position size: {}", actual_position_size);
+    msg!("Margin mode: {}", if initialize_data.margin_mode == MARGIN_MODE_CROSS { "Cross" } else { "Isolated" });
+    
+    Ok(())
+}
+
+pub(crate) fn process_dex_modify(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    dex_data: DexModifyData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let fee_escrow_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    
+    load_authority(accounts, program_id)?.check_role(dex_account, OPERATOR_ROLE_PRICE_SETTER)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut position = try_load_position_account(position_account)?;
+    
+    if position.position_nonce != dex_data.position_nonce {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if position_account.data_len() < PositionAccount::LEN {
+        msg!("Position {} predates the current layout, resize it with RESIZE_POSITION", position.position_nonce);
+        return Err(DexError::AccountNeedsMigration.into());
+    }
+    
+    if dex_data.new_entry_price == 0 && position.entry_price != 0 {
+        msg!("Position {} entry price cannot be cleared", position.position_nonce);
+        return Err(DexError::InvalidPositionTransition.into());
+    }
+    
+    let mut entry_price = dex_data.new_entry_price;
+    
+    if dex_data.new_entry_price != 0 && dex_data.new_entry_price != position.entry_price {
+        let price_feed = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?;
+        check_price_band(dex_data.new_entry_price, price_feed.price, config.price_band_bps)?;
+        
+        verify_market_address(market_account, &position.market_mint, program_id)?;
+        
+        let spread_bps = load_market_state(market_account, program_id)?.spread_bps(market_account.lamports());
+        entry_price = apply_spread(dex_data.new_entry_price, position.direction, spread_bps, true);
+        
+        msg!("Entry price {} after {} bps spread", entry_price, spread_bps);
+    }
+    
+    let liquidation_price = compute_liquidation_price(
+        entry_price,
+        position.position_size,
+        position.paid_amount,
+        position.direction,
+        position.contract_type,
+        config.maintenance_margin_bps,
+    );
+    
+    if dex_data.new_liquidation_price != 0 {
+        check_liquidation_price(
+            dex_data.new_liquidation_price,
+            liquidation_price,
+            entry_price,
+            position.direction,
+            config.liquidation_price_tolerance_bps,
+        )?;
+    }
+    
+    position.entry_price = entry_price;
+    position.liquidation_price = liquidation_price;
+    
+    if position.confirmed == 0 && position.entry_price != 0 {
+        verify_market_address(market_account, &position.market_mint, program_id)?;
+        assert_fee_vault(dex_fees_account, program_id)?;
+        
+        let mut market_state = load_market_state(market_account, program_id)?;
+        let released = position.open_fee.min(escrow_available(fee_escrow_account, program_id)?);
+        let (_, lp_fee) = split_fee(
+            accounts,
+            fee_escrow_account,
+            dex_fees_account,
+            market_account,
+            &position.market_mint,
+            &mut market_state,
+            released,
+            TransferReason::OpenFee,
+            &config,
+        )?;
+        save_market_state(market_account, &market_state)?;
+        position.confirmed = 1;
+        
+        msg!(
+            "Position {} confirmed, open fee released: {} ({} to LPs)",
+            position.position_nonce,
+            released,
+            lp_fee
+        );
+    }
+    // The DEX may only request a close on an open, priced position; settlement moves it further.
+    if dex_data.new_close_state != position.closed
+        && (dex_data.new_close_state != 1 || position.status() != PositionStatus::Open)
+    {
+        msg!("Position {} cannot move from close state {} to {}", position.position_nonce, position.closed, dex_data.new_close_state);
+        return Err(DexError::InvalidPositionTransition.into());
+    }
+    
+    position.closed = dex_data.new_close_state;
+    position.pnl = dex_data.new_pnl;
+    
+    if PositionAccount::is_current_layout(&position_account.data.borrow()) {
+        position.write_dex_fields(&mut position_account.data.borrow_mut())?;
+    } else {
+        position.upgrade_version();
+        save_position_account(position_account, &position)?;
+    }
+    
+    msg!("Position {} updated", position.position_nonce);
+    
+    Ok(())
+}
+
+pub(crate) fn process_user_modify(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    user_data: UserModifyData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let user_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(user_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut position = try_load_position_account(position_account)?;
+    
+    if position.position_nonce != user_data.position_nonce {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let dex_user = find_authority(accounts, program_id)?.is_some_and(|authority| authority.has_role(user_account.key, OPERATOR_ROLE_SETTLER));
+    
+    if position.owner != *user_account.key && !dex_user {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if !position.is_open() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if user_data.close_position && position.status() == PositionStatus::Pending {
+        msg!("Position {} has no entry price; use CANCEL_POSITION or EXPIRE_POSITION", position.position_nonce);
+        return Err(DexError::PositionNotConfirmed.into());
+    }
+    
+    if user_data.close_position && user_account.key == &position.owner {
+        check_close_not_frozen(accounts, &[&position.owner, position_account.key], &config, program_id)?;
+        
+        // Same-slot round trips are always rejected; the config can widen the window.
+        let min_age = config.min_close_slots.max(1);
+        let age = Clock::get()?.slot.saturating_sub(position.open_slot);
+        
+        if age < min_age {
+            msg!("Position {} is {} slots old, closes allowed after {}", position.position_nonce, age, min_age);
+            return Err(DexError::CloseTooSoon.into());
+        }
+        
+        verify_market_address(market_account, &position.market_mint, program_id)?;
+        
+        let min_holding_slots = load_market_state(market_account, program_id)?.min_holding_slots;
+        
+        // Only winners are held back; losing positions may always exit.
+        if age < min_holding_slots {
+            let mark_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?.price;
+            
+            if unrealized_pnl(&position, mark_price)? > 0 {
+                msg!("Profitable close needs {} slots of holding, position has {}", min_holding_slots, age);
+                return Err(DexError::MinHoldingPeriod.into());
+            }
+        }
+    }
+    
+    if user_data.close_position {
+        position.closed = 1;
+        position.close_request_slot = Clock::get()?.slot;
+        msg!("Position {} marked to close at slot {}", position.position_nonce, position.close_request_slot);
+    }
+    
+    save_position_account(position_account, &position)?;
+    
+    Ok(())
+}
+
+pub(crate) fn process_pnl(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    pnl_data: ProcessPnlData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let _fee_escrow_account = next_account_info(accounts_iter)?;
+    let margin_account = next_account_info(accounts_iter).ok();
+    
+    load_authority(accounts, program_id)?.check_role(dex_account, OPERATOR_ROLE_SETTLER)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    assert_fee_vault(dex_fees_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, pnl_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if !position.is_pending_settlement() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    assert_owned_by(market_account, program_id)?;
+    
+    if position.status() != PositionStatus::CloseRequested {
+        msg!("Position {} has no entry price; refund it with CANCEL_POSITION or EXPIRE_POSITION", position.position_nonce);
+        return Err(DexError::PositionNotConfirmed.into());
+    }
+    
+    check_pnl_magnitude(&position, pnl_data.final_pnl)?;
+    
+    let price_feed = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?;
+    let exit_price = price_feed.effective(position.direction, false);
+    check_settlement_pnl(&position, pnl_data.final_pnl, exit_price, config.pnl_tolerance_bps)?;
+    check_pnl_bounds(&position, pnl_data.final_pnl, exit_price, config.price_band_bps)?;
+    emit_settlement_price(&position, &price_feed, exit_price)?;
+    
+    // The rent deposit stays in the account until the branch is known: a residual claim keeps
+    // the account open on it, every other branch refunds it once the collateral is settled.
+    let rent_deposit = position_rent_deposit(&position, position_account)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let funding = accrued_funding(&position, &market_state);
+    let borrow_fee = accrued_borrow_fee(&position, &market_state);
+    let spread_cost = (position.position_size as i128)
+        .saturating_mul(market_state.spread_bps(market_account.lamports()) as i128)
+        / BASIS_POINTS_DIVISOR as i128;
+    let final_pnl = i64::try_from(
+        (pnl_data.final_pnl as i128)
+            .saturating_sub(funding)
+            .saturating_sub(borrow_fee)
+            .saturating_sub(spread_cost),
+    )
+    .map_err(|_| DexError::PnlOutOfRange)?;
+    
+    msg!("Funding: {}, borrow fee: {}, exit spread: {}", funding, borrow_fee, spread_cost);
+    
+    record_settlement_history(accounts, &position, final_pnl as i128, program_id)?;
+    
+    match skew_adjustment(&mut market_state, position.direction, position.position_size, false, &config) {
+        SkewAdjustment::Surcharge(surcharge) => {
+            let charged = surcharge.min(position_account.lamports().saturating_sub(rent_deposit));
+            move_lamports(position_account, market_account, charged, TransferReason::SkewSurcharge)?;
+            market_state.record_deposit(charged);
+            market_state.skew_rebate_pool = market_state
+                .skew_rebate_pool
+                .saturating_sub(surcharge.saturating_sub(charged));
+            
+            msg!("Skew surcharge: {} lamports", charged);
+        },
+        SkewAdjustment::Rebate(rebate) => {
+            move_lamports(market_account, owner_account, rebate, TransferReason::SkewRebate)?;
+            market_state.record_payout(rebate);
+            
+            msg!("Skew rebate: {} lamports", rebate);
+        },
+    }
+    
+    market_state.remove_open_interest(position.direction, position.position_size);
+    
+    let cross_margin = cross_margin_account(&position, margin_account, program_id)?;
+    
+    let close_fee = close_fee(position.position_size, config.close_fee_bps)
+        .min(position_account.lamports().saturating_sub(rent_deposit));
+    let (_, close_lp_fee) = split_fee(
+        accounts,
+        position_account,
+        dex_fees_account,
+        market_account,
+        &position.market_mint,
+        &mut market_state,
+        close_fee,
+        TransferReason::CloseFee,
+        &config,
+    )?;
+    
+    msg!("Close fee: {} lamports ({} to LPs)", close_fee, close_lp_fee);
+    
+    let position_lamports = position_account.lamports().saturating_sub(rent_deposit);
+    let market_lamports = market_account.lamports();
+    
+    msg!("Position lamports: {}", position_lamports);
+    msg!("Market lamports: {}", market_lamports);
+    
+    if final_pnl > 0 {
+        let pnl_amount = u64::try_from(final_pnl).map_err(|_| DexError::PnlOutOfRange)?;
+        
+        let total_fee = trading_fee(pnl_amount, position.leverage, &position.fee_schedule(market_state.fee_schedule()));
+        let profit_after_fee = pnl_amount.saturating_sub(total_fee);
+        let total_required = total_fee.saturating_add(profit_after_fee);
+        
+        let market_available = settlement_liquidity(
+            &config,
+            market_account,
+            &mut market_state,
+            find_market_insurance_account(accounts, &position.market_mint, program_id),
+            find_program_vault_account(accounts, program_id),
+            total_required,
+        )?;
+        
+        msg!("Required from market: {} lamports", total_required);
+        msg!("Market has: {} lamports, {} available for settlement", market_lamports, market_available);
+        
+        if market_available < total_required {
+            msg!("Insufficient market liquidity. Required: {}, Available: {}", total_required, market_available);
+            
+            let (fee_paid, profit_paid) = pay_profit_installment(
+                accounts,
+                market_account,
+                owner_account,
+                dex_fees_account,
+                &position.market_mint,
+                &mut market_state,
+                market_available,
+                total_fee,
+                profit_after_fee,
+                &config,
+            )?;
+            
+            // The account stays open on its rent deposit to carry the residual claim, so the
+            // collateral goes back whole; only an account short of a full deposit (opened before
+            // rent was funded separately) holds back the difference.
+            let rent_floor = Rent::get()?.minimum_balance(PositionAccount::LEN.max(position_account.data_len()));
+            let returned = position_lamports.saturating_sub(rent_floor.saturating_sub(rent_deposit));
+            pay_owner_or_sweep_dust(
+                position_account,
+                owner_account,
+                dex_fees_account,
+                returned,
+                profit_paid.saturating_add(returned),
+                TransferReason::CollateralReturn,
+                &config,
+            )?;
+            resize_account(
+                position_account,
+                dex_account,
+                system_program,
+                PositionAccount::LEN.max(position_account.data_len()),
+            )?;
+            
+            let mut position = position;
+            position.upgrade_version();
+            position.closed = POSITION_RESIDUAL_CLAIM;
+            position.pnl = final_pnl;
+            position.residual_profit = profit_after_fee.saturating_sub(profit_paid);
+            position.residual_fee = total_fee.saturating_sub(fee_paid);
+            save_position_account(position_account, &position)?;
+            
+            save_market_state(market_account, &market_state)?;
+            
+            emit_event(&DexEvent::ResidualProfit(ResidualProfitEvent {
+                owner: position.owner,
+                position_nonce: position.position_nonce,
+                market_mint: position.market_mint,
+                paid: profit_paid,
+                fee: fee_paid,
+                remaining: position.residual_profit,
+            }))?;
+            
+            msg!(
+                "Profit paid in part: {} now, {} owed once liquidity returns; collateral returned: {}",
+                profit_paid,
+                position.residual_profit,
+                returned
+            );
+            
+            return Ok(());
+        } else {
+            let (_, profit_lp_fee) = split_fee(
+                accounts,
+                market_account,
+                dex_fees_account,
+                market_account,
+                &position.market_mint,
+                &mut market_state,
+                total_fee,
+                TransferReason::ProfitFee,
+                &config,
+            )?;
+            let payout = profit_after_fee.saturating_add(position_lamports);
+            pay_owner_or_sweep_dust(
+                market_account,
+                owner_account,
+                dex_fees_account,
+                profit_after_fee,
+                payout,
+                TransferReason::Profit,
+                &config,
+            )?;
+            market_state.record_payout(profit_after_fee);
+            pay_owner_or_sweep_dust(
+                position_account,
+                owner_account,
+                dex_fees_account,
+                position_lamports,
+                payout,
+                TransferReason::CollateralReturn,
+                &config,
+            )?;
+            
+            msg!("Profit: {} (fee: {}, {} kept by LPs)", profit_after_fee, total_fee, profit_lp_fee);
+        }
+    
+    } else if final_pnl < 0 {
+        let pnl_abs = final_pnl
+            .checked_neg()
+            .and_then(|loss| u64::try_from(loss).ok())
+            .ok_or(DexError::PnlOutOfRange)?;
+        
+        if position_lamports <= pnl_abs {
+            // settle_loss draws on every lamport the account holds, so the rent goes back first.
+            move_lamports(position_account, owner_account, rent_deposit, TransferReason::RentRefund)?;
+            
+            let coverage = settle_loss(
+                &position,
+                position_account,
+                market_account,
+                cross_margin,
+                find_market_insurance_account(accounts, &position.market_mint, program_id),
+                &mut market_state,
+                pnl_abs,
+                TransferReason::Loss,
+            )?;
+            
+            msg!(
+                "Total loss: {} lamports, cross margin covered: {}",
+                coverage.from_collateral,
+                coverage.from_margin
+            );
+        } else {
+            let remaining_funds = position_lamports.saturating_sub(pnl_abs);
+            
+            move_lamports(position_account, market_account, pnl_abs, TransferReason::Loss)?;
+            market_state.record_deposit(pnl_abs);
+            
+            pay_owner_or_sweep_dust(
+                position_account,
+                owner_account,
+                dex_fees_account,
+                remaining_funds,
+                remaining_funds,
+                TransferReason::CollateralReturn,
+                &config,
+            )?;
+            
+            msg!("Loss: {}, remaining: {}", pnl_abs, remaining_funds);
+        }
+    } else {
+        pay_owner_or_sweep_dust(
+            position_account,
+            owner_account,
+            dex_fees_account,
+            position_lamports,
+            position_lamports,
+            TransferReason::CollateralReturn,
+            &config,
+        )?;
+        
+        msg!("Zero PnL: {} returned", position_lamports);
+    }
+    
+    let rent_refund = position_account.lamports();
+    move_lamports(position_account, owner_account, rent_refund, TransferReason::RentRefund)?;
+    
+    msg!("Rent deposit refunded: {} lamports", rent_deposit);
+    
+    route_borrow_fee(accounts, market_account, &mut market_state, borrow_fee, &config)?;
+    save_market_state(market_account, &market_state)?;
+    zero_account_data(position_account)?;
+    remove_from_position_index(
+        find_position_index_account(accounts, &position.owner, program_id),
+        &position.owner,
+        position.position_nonce,
+        program_id,
+    )?;
+    
+    msg!("Position {} closed", position.position_nonce);
+    
+    Ok(())
+}
+
+/// Pays one leg of a settlement's owner payout, or sweeps it to the fee vault when the whole
+/// payout `total` falls below the config `dust_threshold`.
+fn pay_owner_or_sweep_dust(
+    from_account: &AccountInfo,
+    owner_account: &AccountInfo,
+    dex_fees_account: &AccountInfo,
+    amount: u64,
+    total: u64,
+    reason: TransferReason,
+    config: &GlobalConfig,
+) -> ProgramResult {
+    if total < config.dust_threshold {
+        move_lamports(from_account, dex_fees_account, amount, TransferReason::DustSweep)?;
+        
+        msg!("Dust swept to fees: {} lamports", amount);
+    } else {
+        move_lamports(from_account, owner_account, amount, reason)?;
+    }
+    
+    Ok(())
+}
+
+/// Rent-exempt reserve held by the position on top of its collateral; zero for positions opened
+/// before the payer funded rent separately.
+pub(crate) fn position_rent_deposit(
+    position: &PositionAccount,
+    position_account: &AccountInfo,
+) -> Result<u64, ProgramError> {
+    let rent_minimum = Rent::get()?.minimum_balance(position_account.data_len());
+    
+    Ok(position_account.lamports().saturating_sub(position.paid_amount).min(rent_minimum))
+}
+
+/// First phase of a force close (DEX authority only): records that a position fails its
+/// checksum in a mark PDA paid for by the DEX, with the owner stored in it, and emits an event so
+/// the owner and observers can contest it before `FORCE_CLOSE` unlocks.
+pub(crate) fn process_mark_corrupted(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let mark_account = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    load_authority(accounts, program_id)?.check_role(dex_account, OPERATOR_ROLE_SETTLER)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let position = load_position_unchecked(position_account)?;
+    
+    if !PositionAccount::is_corrupted(&position_account.data.borrow()) {
+        msg!("Position {} passes its checksum", position.position_nonce);
+        return Err(DexError::PositionNotCorrupted.into());
+    }
+    
+    let (mark_pda, mark_bump) = find_corruption_mark_address(position_account.key, program_id);
+    
+    assert_key_eq(mark_account, &mark_pda)?;
+    
+    if !mark_account.data_is_empty() {
+        msg!("Position {} already marked", position_account.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            dex_account.key,
+            mark_account.key,
+            Rent::get()?.minimum_balance(CorruptionMarkAccount::LEN),
+            CorruptionMarkAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            dex_account.clone(),
+            mark_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_corruption",
+            position_account.key.as_ref(),
+            &[mark_bump],
+        ]],
+    )?;
+    
+    let mark = CorruptionMarkAccount {
+        position: *position_account.key,
+        owner: position.owner,
+        marked_by: *dex_account.key,
+        marked_slot: Clock::get()?.slot,
+    };
+    mark.serialize(&mut *mark_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::PositionMarkedCorrupted(CorruptionEvent {
+        position: mark.position,
+        owner: mark.owner,
+        marked_slot: mark.marked_slot,
+        close_slot: mark.close_slot(),
+    }))?;
+    
+    msg!("Position {} marked corrupted, force close from slot {}", position_account.key, mark.close_slot());
+    
+    Ok(())
+}
+
+/// Second phase: once the mark's delay has passed, settles the still-corrupted position's stored
+/// loss to the market and returns the rest to the owner recorded at marking. The mark's rent goes
+/// back to the DEX.
+pub(crate) fn process_force_close(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let mark_account = next_account_info(accounts_iter)?;
+    
+    load_authority(accounts, program_id)?.check_role(dex_account, OPERATOR_ROLE_SETTLER)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let position = load_position_unchecked(position_account).inspect_err(|_| {
+        msg!("Position not decodable, use FORCE_CLOSE_UNRECOVERABLE");
+    })?;
+    
+    if !PositionAccount::is_corrupted(&position_account.data.borrow()) {
+        msg!("Position {} passes its checksum, settle it normally", position.position_nonce);
+        return Err(DexError::PositionNotCorrupted.into());
+    }
+    
+    let (mark_pda, _mark_bump) = find_corruption_mark_address(position_account.key, program_id);
+    
+    assert_key_eq(mark_account, &mark_pda)?;
+    
+    if mark_account.data_is_empty() {
+        msg!("Position {} has not been marked corrupted", position_account.key);
+        return Err(ProgramError::UninitializedAccount);
+    }
+    
+    assert_owned_by(mark_account, program_id)?;
+    
+    let mark = CorruptionMarkAccount::try_from_slice(&mark_account.data.borrow())
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let slot = Clock::get()?.slot;
+    
+    if slot < mark.close_slot() {
+        msg!("Position {} can be force closed from slot {}", position_account.key, mark.close_slot());
+        return Err(DexError::ForceCloseDelayPending.into());
+    }
+    
+    if &position.owner != owner_account.key || &mark.owner != owner_account.key {
+        msg!("Owner account does not match the owner recorded at marking");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    assert_owned_by(market_account, program_id)?;
+    
+    msg!("Force closing position {} with stored pnl {}", position.position_nonce, position.pnl);
+    
+    let position_lamports = position_account.lamports();
+    let loss = if position.pnl < 0 {
+        position.pnl.unsigned_abs().min(position_lamports)
+    } else {
+        0
+    };
+    let returned = position_lamports.saturating_sub(loss);
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    market_state.remove_open_interest(position.direction, position.position_size);
+    market_state.record_deposit(loss);
+    save_market_state(market_account, &market_state)?;
+    
+    move_lamports(position_account, market_account, loss, TransferReason::Loss)?;
+    move_lamports(position_account, owner_account, returned, TransferReason::ForceClose)?;
+    
+    zero_account_data(position_account)?;
+    remove_from_position_index(
+        find_position_index_account(accounts, &position.owner, program_id),
+        &position.owner,
+        position.position_nonce,
+        program_id,
+    )?;
+    
+    let mark_lamports = mark_account.lamports();
+    move_lamports(mark_account, dex_account, mark_lamports, TransferReason::ForceClose)?;
+    zero_account_data(mark_account)?;
+    
+    emit_event(&DexEvent::PositionForceClosed(CorruptionEvent {
+        position: mark.position,
+        owner: mark.owner,
+        marked_slot: mark.marked_slot,
+        close_slot: slot,
+    }))?;
+    
+    msg!("Force closed position, loss to market: {}, returned: {}", loss, returned);
+    
+    Ok(())
+}
+
+pub(crate) fn process_force_close_unrecoverable(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let recipient_account = next_account_info(accounts_iter)?;
+    let dex_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    
+    load_authority(accounts, program_id)?.check_role(dex_account, OPERATOR_ROLE_SETTLER)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account).inspect_err(|_| {
+        msg!("Unrecoverable force close requires admin confirmation");
+    })?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    if load_position_unchecked(position_account).is_ok() {
+        msg!("Position is decodable, use FORCE_CLOSE");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    msg!("Force closing unrecoverable account {}", position_account.key);
+    
+    let position_lamports = position_account.lamports();
+    move_lamports(position_account, recipient_account, position_lamports, TransferReason::ForceClose)?;
+    
+    zero_account_data(position_account)?;
+    
+    msg!("Returned {} lamports to {}", position_lamports, recipient_account.key);
+    
+    Ok(())
+}
+
+/// Permissionless cleanup of a program-owned account whose data was zeroed on close but which
+/// still holds lamports. The rent is drained, `GC_CALLER_SHARE_BPS` of it to the signing caller
+/// and the rest to the DEX fee treasury, and the runtime then deletes the account.
+pub(crate) fn process_collect_garbage(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let garbage_account = next_account_info(accounts_iter)?;
+    let caller_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(caller_account)?;
+    assert_key_eq(treasury_account, &load_authority(accounts, program_id)?.fees_wallet)?;
+    assert_owned_by(garbage_account, program_id)?;
+    
+    if garbage_account.data_is_empty() || garbage_account.data.borrow().iter().any(|byte| *byte != 0) {
+        msg!("Account {} still holds data", garbage_account.key);
+        return Err(DexError::AccountNotZeroed.into());
+    }
+    
+    let reclaimed = garbage_account.lamports();
+    let caller_share = ((reclaimed as u128).saturating_mul(GC_CALLER_SHARE_BPS as u128)
+        / BASIS_POINTS_DIVISOR as u128) as u64;
+    let treasury_share = reclaimed.saturating_sub(caller_share);
+    
+    move_lamports(garbage_account, caller_account, caller_share, TransferReason::GarbageCollection)?;
+    move_lamports(garbage_account, treasury_account, treasury_share, TransferReason::GarbageCollection)?;
+    
+    emit_event(&DexEvent::AccountReclaimed(AccountReclaimedEvent {
+        account: *garbage_account.key,
+        caller: *caller_account.key,
+        caller_share,
+        treasury_share,
+    }))?;
+    
+    msg!(
+        "Collected {}: {} lamports to the caller, {} to the treasury",
+        garbage_account.key,
+        caller_share,
+        treasury_share
+    );
+    
+    Ok(())
+}
+
+pub(crate) fn check_liquidation_price(
+    liquidation_price: u64,
+    expected_price: u64,
+    entry_price: u64,
+    direction: i8,
+    tolerance_bps: u16,
+) -> ProgramResult {
+    let correct_side = if direction == POSITION_LONG {
+        liquidation_price < entry_price
+    } else {
+        liquidation_price > entry_price
+    };
+    
+    if !correct_side {
+        msg!("Liquidation price {} on wrong side of entry {}", liquidation_price, entry_price);
+        return Err(DexError::InvalidLiquidationPrice.into());
+    }
+    
+    let deviation = liquidation_price.abs_diff(expected_price) as u128;
+    let allowed = (entry_price as u128).saturating_mul(tolerance_bps as u128) / BASIS_POINTS_DIVISOR as u128;
+    
+    if deviation > allowed {
+        msg!("Liquidation price {} too far from leverage-implied {}", liquidation_price, expected_price);
+        return Err(DexError::InvalidLiquidationPrice.into());
+    }
+    
+    Ok(())
+}
+
+/// Rejects a reported PnL larger than `MAX_PNL_POSITION_MULTIPLE` times the position's size
+/// before any of it is converted or paid.
+pub(crate) fn check_pnl_magnitude(position: &PositionAccount, final_pnl: i64) -> ProgramResult {
+    let cap = (position.position_size as u128).saturating_mul(MAX_PNL_POSITION_MULTIPLE as u128);
+    
+    if final_pnl.unsigned_abs() as u128 > cap {
+        msg!("Final pnl {} exceeds {} for position size {}", final_pnl, cap, position.position_size);
+        return Err(DexError::PnlOutOfRange.into());
+    }
+    
+    Ok(())
+}
+
+/// Rejects a reported PnL the position could not produce at any exit price inside the config
+/// price band, whatever the pnl tolerance allows.
+pub(crate) fn check_pnl_bounds(
+    position: &PositionAccount,
+    final_pnl: i64,
+    exit_price: u64,
+    band_bps: u16,
+) -> ProgramResult {
+    let (lowest, highest) = pnl_bounds(position, exit_price, band_bps)?;
+    
+    if (final_pnl as i128) < lowest || (final_pnl as i128) > highest {
+        msg!("Final pnl {} outside {}..{} reachable within the price band", final_pnl, lowest, highest);
+        return Err(DexError::PnlOutOfRange.into());
+    }
+    
+    Ok(())
+}
+
+pub(crate) fn check_settlement_pnl(
+    position: &PositionAccount,
+    final_pnl: i64,
+    oracle_price: u64,
+    tolerance_bps: u16,
+) -> ProgramResult {
+    let expected_pnl = unrealized_pnl(position, oracle_price)?;
+    let deviation = (final_pnl as i128 - expected_pnl).unsigned_abs();
+    let allowed = (position.position_size as u128).saturating_mul(tolerance_bps as u128)
+        / BASIS_POINTS_DIVISOR as u128;
+    
+    if deviation > allowed {
+        msg!("Final pnl {} deviates from oracle pnl {} by more than {}", final_pnl, expected_pnl, allowed);
+        return Err(DexError::PnlMismatch.into());
+    }
+    
+    Ok(())
+}
+
+pub(crate) fn escrow_available(fee_escrow_account: &AccountInfo, program_id: &Pubkey) -> Result<u64, ProgramError> {
+    let (fee_escrow_pda, _fee_escrow_bump) = find_fee_escrow_address(program_id);
+    
+    assert_key_eq(fee_escrow_account, &fee_escrow_pda)?;
+    
+    assert_owned_by(fee_escrow_account, program_id)?;
+    
+    let rent = Rent::get()?;
+    
+    Ok(fee_escrow_account
+        .lamports()
+        .saturating_sub(rent.minimum_balance(fee_escrow_account.data_len())))
+}
+
+pub(crate) fn release_escrowed_fee(
+    fee_escrow_account: &AccountInfo,
+    destination_account: &AccountInfo,
+    amount: u64,
+    program_id: &Pubkey,
+) -> Result<u64, ProgramError> {
+    if amount == 0 {
+        return Ok(0);
+    }
+    
+    let released = amount.min(escrow_available(fee_escrow_account, program_id)?);
+    
+    move_lamports(fee_escrow_account, destination_account, released, TransferReason::OpenFee)?;
+    
+    Ok(released)
+}
+
+pub(crate) fn refund_unconfirmed_position(
+    position: &PositionAccount,
+    position_account: &AccountInfo,
+    owner_account: &AccountInfo,
+    market_account: &AccountInfo,
+    fee_escrow_account: &AccountInfo,
+    position_index_account: Option<&AccountInfo>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    market_state.remove_open_interest(position.direction, position.position_size);
+    save_market_state(market_account, &market_state)?;
+    
+    let position_lamports = position_account.lamports();
+    
+    move_lamports(position_account, owner_account, position_lamports, TransferReason::CollateralReturn)?;
+    
+    let refunded_fee = release_escrowed_fee(fee_escrow_account, owner_account, position.open_fee, program_id)?;
+    
+    zero_account_data(position_account)?;
+    remove_from_position_index(position_index_account, &position.owner, position.position_nonce, program_id)?;
+    
+    msg!("Unconfirmed position {} refunded", position.position_nonce);
+    msg!("Collateral returned: {}, fee refunded: {}", position_lamports, refunded_fee);
+    
+    Ok(())
+}
+
+pub(crate) fn process_cancel_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cancel_data: CancelPositionData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let fee_escrow_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, cancel_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if position.is_confirmed() {
+        msg!("Position {} already confirmed", position.position_nonce);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if is_reserved_position(&position) {
+        msg!("Position {} is reserved for a resting order, cancel the order first", position.position_nonce);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    refund_unconfirmed_position(
+        &position,
+        position_account,
+        owner_account,
+        market_account,
+        fee_escrow_account,
+        find_position_index_account(accounts, &position.owner, program_id),
+        program_id,
+    )
+}
+
+pub(crate) fn process_expire_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    expire_data: ExpirePositionData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let fee_escrow_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, expire_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if position.is_confirmed() {
+        msg!("Position {} already confirmed", position.position_nonce);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if is_reserved_position(&position) {
+        msg!("Position {} is reserved for a resting order", position.position_nonce);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    let clock = Clock::get()?;
+    let age = clock.slot.saturating_sub(position.open_slot);
+    
+    if config.confirmation_timeout_slots == 0 || age <= config.confirmation_timeout_slots {
+        msg!("Position {} unconfirmed for {} slots, timeout {}", position.position_nonce, age, config.confirmation_timeout_slots);
+        return Err(DexError::ConfirmationPending.into());
+    }
+    
+    msg!("Position {} expired after {} slots", position.position_nonce, age);
+    
+    refund_unconfirmed_position(
+        &position,
+        position_account,
+        owner_account,
+        market_account,
+        fee_escrow_account,
+        find_position_index_account(accounts, &position.owner, program_id),
+        program_id,
+    )
+}
+
+/// Permissionless settlement of a position on a delisted market once its grace period is over:
+/// PnL is taken at the oracle price net of funding and borrow, with no close or profit fee, and
+/// profit is capped at the market liquidity above its rent floor. On a live market the same
+/// settlement is open for positions their owner asked to close while the trustless paths are
+/// enabled, or once the DEX has left the owner's request unsettled for the config
+/// `close_settlement_deadline_slots`, so a close never waits on an offline operator.
+pub(crate) fn process_settle_delisted_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    settle_data: SettleDelistedData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let fee_escrow_account = next_account_info(accounts_iter)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, settle_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let slot = Clock::get()?.slot;
+    let winding_down = market_state.is_winding_down(slot);
+    
+    let trustless_close = !winding_down
+        && position.status() == PositionStatus::CloseRequested
+        && (position.close_deadline_passed(config.close_settlement_deadline_slots, slot)
+            || trustless_paths_enabled(&config, accounts, program_id, slot)?);
+    
+    if !(winding_down || trustless_close) {
+        msg!("Market must be delisted and past slot {}", market_state.wind_down_slot);
+        return Err(DexError::MarketWindDownPending.into());
+    }
+    
+    if is_reserved_position(&position) {
+        msg!("Position {} is reserved for a resting order, cancel the order first", position.position_nonce);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if !position.is_confirmed() {
+        return refund_unconfirmed_position(
+            &position,
+            position_account,
+            owner_account,
+            market_account,
+            fee_escrow_account,
+            find_position_index_account(accounts, &position.owner, program_id),
+            program_id,
+        );
+    }
+    
+    let price_feed = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?;
+    let exit_price = price_feed.effective(position.direction, false);
+    
+    settle_at_mark_price(
+        &position,
+        position_account,
+        owner_account,
+        market_account,
+        &mut market_state,
+        None,
+        &config,
+        accounts,
+        exit_price,
+        TransferReason::DelistSettlement,
+        program_id,
+    )?;
+    
+    emit_settlement_price(&position, &price_feed, exit_price)?;
+    
+    if winding_down {
+        msg!("Position {} settled at {} on delisted market", position.position_nonce, exit_price);
+    } else {
+        msg!("Position {} settled trustlessly at {}", position.position_nonce, exit_price);
+    }
+    
+    Ok(())
+}
+
+/// Closes a confirmed position at `mark_price` net of funding and borrow: profit is paid from
+/// the market (then its insurance sub-fund and the vault backstop) as far as they reach, losses
+/// go to the market through `settle_loss`, and the rest of the account's lamports return to the
+/// owner. Returns the settled PnL.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn settle_at_mark_price(
+    position: &PositionAccount,
+    position_account: &AccountInfo,
+    owner_account: &AccountInfo,
+    market_account: &AccountInfo,
+    market_state: &mut MarketState,
+    cross_margin: Option<&AccountInfo>,
+    config: &GlobalConfig,
+    accounts: &[AccountInfo],
+    mark_price: u64,
+    profit_reason: TransferReason,
+    program_id: &Pubkey,
+) -> Result<i128, ProgramError> {
+    let final_pnl = position
+        .unrealized_pnl(mark_price)?
+        .saturating_sub(accrued_funding(position, market_state))
+        .saturating_sub(accrued_borrow_fee(position, market_state));
+    
+    record_settlement_history(accounts, position, final_pnl, program_id)?;
+    
+    market_state.remove_open_interest(position.direction, position.position_size);
+    
+    let rent_deposit = position_rent_deposit(position, position_account)?;
+    move_lamports(position_account, owner_account, rent_deposit, TransferReason::RentRefund)?;
+    
+    if final_pnl > 0 {
+        let owed = final_pnl.min(u64::MAX as i128) as u64;
+        let profit = owed.min(settlement_liquidity(
+            config,
+            market_account,
+            market_state,
+            find_market_insurance_account(accounts, &position.market_mint, program_id),
+            find_program_vault_account(accounts, program_id),
+            owed,
+        )?);
+        
+        move_lamports(market_account, owner_account, profit, profit_reason)?;
+        market_state.record_payout(profit);
+        
+        msg!("Settlement profit: {} lamports", profit);
+    } else if final_pnl < 0 {
+        let coverage = settle_loss(
+            position,
+            position_account,
+            market_account,
+            cross_margin,
+            find_market_insurance_account(accounts, &position.market_mint, program_id),
+            market_state,
+            final_pnl.unsigned_abs().min(u64::MAX as u128) as u64,
+            TransferReason::Loss,
+        )?;
+        
+        msg!(
+            "Settlement loss: {} lamports, cross margin covered: {}",
+            coverage.from_collateral,
+            coverage.from_margin
+        );
+    }
+    
+    save_market_state(market_account, market_state)?;
+    
+    let remaining = position_account.lamports();
+    move_lamports(position_account, owner_account, remaining, TransferReason::CollateralReturn)?;
+    
+    zero_account_data(position_account)?;
+    remove_from_position_index(
+        find_position_index_account(accounts, &position.owner, program_id),
+        &position.owner,
+        position.position_nonce,
+        program_id,
+    )?;
+    
+    Ok(final_pnl)
+}
+
+/// Owner-level gates every open passes, whoever funds it: the market and protocol notional caps,
+/// the attestation, freeze and blacklist checks, and the portfolio health floor with the new
+/// position included. The open is then counted against the owner's rate limit, `payer_account`
+/// funding the user stats PDA on first use.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn admit_open<'a>(
+    accounts: &[AccountInfo<'a>],
+    owner: &Pubkey,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    market_mint: &Pubkey,
+    direction: i8,
+    notional: u64,
+    collateral: u64,
+    market_state: &MarketState,
+    config: &GlobalConfig,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    check_owner_notional(accounts, owner, notional, market_state, config, program_id)?;
+    check_attestation(accounts, owner, config, program_id)?;
+    check_not_frozen(accounts, &[owner], program_id)?;
+    check_not_blacklisted(accounts, &[payer_account.key, owner], program_id)?;
+    
+    check_portfolio_health(
+        accounts,
+        owner,
+        find_shared_margin(accounts, owner, program_id)?,
+        Some(PortfolioPosition {
+            market_mint: *market_mint,
+            direction,
+            notional: notional as u128,
+            collateral,
+            pnl: 0,
+            hedged: hedge_mode_enabled(accounts, owner, market_mint, program_id)?,
+        }),
+        config,
+        program_id,
+    )?;
+    
+    record_open(accounts, owner, payer_account, system_program, config, program_id)
+}
+
+/// Opens a confirmed isolated position for `owner_account` under its next nonce at the
+/// spread-adjusted oracle price, paid from the program-owned `funding_account`: `amount` covers
+/// the taker fee and the collateral. The keeper fronts the new accounts' rent and is left for
+/// the caller to reimburse; the caller also saves `market_state`. The owner goes through the same
+/// `admit_open` gates as `INITIALIZE`, so `accounts` must carry the owner's freeze and user stats
+/// PDAs and the blacklist registry PDA.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn open_funded_position<'a>(
+    accounts: &[AccountInfo<'a>],
+    funding_account: &AccountInfo<'a>,
+    keeper_account: &AccountInfo<'a>,
+    owner_account: &AccountInfo<'a>,
+    position_account: &AccountInfo<'a>,
+    nonce_counter_account: &AccountInfo<'a>,
+    position_index_account: &AccountInfo<'a>,
+    market_account: &AccountInfo<'a>,
+    dex_fees_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    market_state: &mut MarketState,
+    config: &GlobalConfig,
+    market_mint: &Pubkey,
+    market_symbol: [u8; MAX_SYMBOL_LENGTH],
+    direction: i8,
+    leverage: u8,
+    amount: u64,
+    mark_price: u64,
+    funding_reason: TransferReason,
+    program_id: &Pubkey,
+) -> Result<PositionAccount, ProgramError> {
+    assert_fee_vault(dex_fees_account, program_id)?;
+    
+    if !market_state.is_active() {
+        return Err(DexError::MarketNotActive.into());
+    }
+    
+    check_native_settlement(market_state)?;
+    check_leverage_tier(market_state, amount.saturating_mul(leverage as u64), leverage)?;
+    check_price_impact(market_account, amount.saturating_mul(leverage as u64), config)?;
+    
+    let price = apply_spread(mark_price, direction, market_state.spread_bps(market_account.lamports()), true);
+    let fees = market_state.fee_schedule();
+    let fee = fill_fee(amount, leverage, FillRole::Taker, &fees);
+    let collateral = amount.saturating_sub(fee);
+    let position_size = collateral.saturating_mul(leverage as u64);
+    
+    admit_open(
+        accounts,
+        owner_account.key,
+        keeper_account,
+        system_program,
+        market_mint,
+        direction,
+        position_size,
+        collateral,
+        market_state,
+        config,
+        program_id,
+    )?;
+    
+    let position_nonce = claim_position_nonce(
+        nonce_counter_account,
+        owner_account.key,
+        keeper_account,
+        system_program,
+        program_id,
+    )?;
+    let (position_pda, position_bump) = find_position_address(owner_account.key, position_nonce, program_id);
+    
+    if position_account.key != &position_pda {
+        msg!("Expected position account for nonce {}", position_nonce);
+        return Err(DexError::NonceMismatch.into());
+    }
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            keeper_account.key,
+            position_account.key,
+            Rent::get()?.minimum_balance(PositionAccount::LEN),
+            PositionAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            keeper_account.clone(),
+            position_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_position",
+            owner_account.key.as_ref(),
+            &position_nonce.to_le_bytes(),
+            &[position_bump],
+        ]],
+    )?;
+    
+    split_fee(
+        accounts,
+        funding_account,
+        dex_fees_account,
+        market_account,
+        market_mint,
+        market_state,
+        fee,
+        TransferReason::OpenFee,
+        config,
+    )?;
+    move_lamports(funding_account, position_account, collateral, funding_reason)?;
+    
+    let position = PositionAccount {
+        version: POSITION_ACCOUNT_VERSION,
+        owner: *owner_account.key,
+        market_mint: *market_mint,
+        market_symbol,
+        entry_price: price,
+        liquidation_price: compute_liquidation_price(
+            price,
+            position_size,
+            collateral,
+            direction,
+            market_state.contract_type,
+            config.maintenance_margin_bps,
+        ),
+        paid_amount: collateral,
+        position_size,
+        leverage,
+        position_nonce,
+        direction,
+        margin_mode: MARGIN_MODE_ISOLATED,
+        confirmed: 1,
+        open_fee: fee,
+        open_slot: Clock::get()?.slot,
+        funding_index_snapshot: market_state.funding_index(direction),
+        borrow_index_snapshot: market_state.cumulative_borrow_index,
+        contract_type: market_state.contract_type,
+        open_base_fee_bps: fees.base_fee_bps as u16,
+        open_leverage_fee_bps: fees.leverage_fee_bps as u16,
+        ..PositionAccount::default()
+    };
+    save_position_account(position_account, &position)?;
+    
+    append_to_position_index(
+        position_index_account,
+        owner_account.key,
+        keeper_account,
+        system_program,
+        position_nonce,
+        program_id,
+    )?;
+    
+    market_state.add_open_interest(direction, position_size);
+    
+    emit_event(&DexEvent::FeeCharged(FeeChargedEvent {
+        market_mint: *market_mint,
+        payer: *owner_account.key,
+        position_nonce,
+        role: FillRole::Taker,
+        amount,
+        fee,
+    }))?;
+    
+    Ok(position)
+}
+
+/// Returns the owner's next position nonce and advances the counter, creating it on first use.
+pub(crate) fn claim_position_nonce<'a>(
+    counter_account: &AccountInfo<'a>,
+    owner: &Pubkey,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> Result<u64, ProgramError> {
+    let (counter_pda, counter_bump) = find_nonce_counter_address(owner, program_id);
+    
+    assert_key_eq(counter_account, &counter_pda)?;
+    
+    let mut counter = if counter_account.data_is_empty() && counter_account.lamports() == 0 {
+        let data_len = NonceCounterAccount::LEN;
+        let minimum_balance = Rent::get()?.minimum_balance(data_len);
+        
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                counter_account.key,
+                minimum_balance,
+                data_len as u64,
+                program_id,
+            ),
+            &[
+                payer_account.clone(),
+                counter_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[b"uranus_nonce", owner.as_ref(), &[counter_bump]]],
+        )?;
+        
+        NonceCounterAccount {
+            owner: *owner,
+            next_nonce: 0,
+        }
+    } else {
+        assert_owned_by(counter_account, program_id)?;
+        
+        deserialize_padded::<NonceCounterAccount>(&counter_account.data.borrow())?
+    };
+    
+    let claimed = counter.next_nonce;
+    counter.next_nonce = claimed.checked_add(1).ok_or(ProgramError::ArithmeticOverflow)?;
+    counter.serialize(&mut *counter_account.data.borrow_mut())?;
+    
+    Ok(claimed)
+}
+
+pub(crate) fn load_position_index(
+    index_account: &AccountInfo,
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<PositionIndexAccount, ProgramError> {
+    let (index_pda, _index_bump) = find_position_index_address(owner, program_id);
+    
+    assert_key_eq(index_account, &index_pda)?;
+    
+    assert_owned_by(index_account, program_id)?;
+    
+    deserialize_padded::<PositionIndexAccount>(&index_account.data.borrow())
+}
+
+pub(crate) fn append_to_position_index<'a>(
+    index_account: &AccountInfo<'a>,
+    owner: &Pubkey,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    position_nonce: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (index_pda, index_bump) = find_position_index_address(owner, program_id);
+    
+    assert_key_eq(index_account, &index_pda)?;
+    
+    let mut index = if index_account.data_is_empty() && index_account.lamports() == 0 {
+        let data_len = PositionIndexAccount::space(0);
+        let minimum_balance = Rent::get()?.minimum_balance(data_len);
+        
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                index_account.key,
+                minimum_balance,
+                data_len as u64,
+                program_id,
+            ),
+            &[
+                payer_account.clone(),
+                index_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[b"uranus_position_index", owner.as_ref(), &[index_bump]]],
+        )?;
+        
+        PositionIndexAccount {
+            owner: *owner,
+            nonces: Vec::new(),
+        }
+    } else {
+        load_position_index(index_account, owner, program_id)?
+    };
+    
+    if !index.nonces.contains(&position_nonce) {
+        index.nonces.push(position_nonce);
+    }
+    
+    let data = index.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    resize_account(index_account, payer_account, system_program, data.len())?;
+    index_account.data.borrow_mut().copy_from_slice(&data);
+    
+    Ok(())
+}
+
+/// Drops `position_nonce` from the owner's index when the caller supplied it; closes that
+/// omit the index leave a stale entry, which clients skip when the position account is empty.
+pub(crate) fn remove_from_position_index(
+    index_account: Option<&AccountInfo>,
+    owner: &Pubkey,
+    position_nonce: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let Some(index_account) = index_account else {
+        return Ok(());
+    };
+    
+    let mut index = load_position_index(index_account, owner, program_id)?;
+    
+    if let Some(slot) = index.nonces.iter().position(|nonce| *nonce == position_nonce) {
+        index.nonces.swap_remove(slot);
+    }
+    
+    let data = index.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    index_account.realloc(data.len(), false)?;
+    index_account.data.borrow_mut().copy_from_slice(&data);
+    
+    Ok(())
+}
+
+/// The owner's position index if it is anywhere among `accounts`; it is optional on close paths.
+pub(crate) fn find_position_index_account<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+    owner: &Pubkey,
+    program_id: &Pubkey,
+) -> Option<&'a AccountInfo<'b>> {
+    let (index_pda, _index_bump) = find_position_index_address(owner, program_id);
+    
+    accounts.iter().find(|account| account.key == &index_pda)
+}
+
+pub(crate) fn process_resize_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let payer_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(payer_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let mut position = try_load_position_account(position_account)?;
+    let current_len = PositionAccount::LEN;
+    
+    if position_account.data_len() >= current_len {
+        msg!("Position {} already at current size", position.position_nonce);
+        return Ok(());
+    }
+    
+    let previous_len = position_account.data_len();
+    position.upgrade_version();
+    
+    resize_account(position_account, payer_account, system_program, current_len)?;
+    save_position_account(position_account, &position)?;
+    
+    msg!("Position {} resized from {} to {} bytes", position.position_nonce, previous_len, current_len);
+    
+    Ok(())
+}
+
+pub(crate) fn process_migrate_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let target_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    assert_owned_by(target_account, program_id)?;
+    
+    if target_account.data_len() != LEGACY_POSITION_ACCOUNT_LEN {
+        try_load_position_account(target_account)?;
+        msg!("Account already at version {}", POSITION_ACCOUNT_VERSION);
+        return Ok(());
+    }
+    
+    let legacy = PositionAccountV0::try_from_slice(&target_account.data.borrow())
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    let position = PositionAccount::from(legacy);
+    
+    let data_len = PositionAccount::LEN;
+    
+    resize_account(target_account, admin_account, system_program, data_len)?;
+    save_position_account(target_account, &position)?;
+    
+    msg!("Position {} migrated to version {}", position.position_nonce, position.version);
+    
+    Ok(())
+}
+
+/// Pays as much of an owed profit as `available` covers, taking the fee in proportion so the
+/// trader and the fee wallet share any shortfall. Returns `(fee_paid, profit_paid)`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pay_profit_installment(
+    accounts: &[AccountInfo],
+    market_account: &AccountInfo,
+    owner_account: &AccountInfo,
+    dex_fees_account: &AccountInfo,
+    market_mint: &Pubkey,
+    market_state: &mut MarketState,
+    available: u64,
+    fee_owed: u64,
+    profit_owed: u64,
+    config: &GlobalConfig,
+) -> Result<(u64, u64), ProgramError> {
+    let owed = fee_owed.saturating_add(profit_owed);
+    let paid = available.min(owed);
+    
+    if paid == 0 {
+        return Ok((0, 0));
+    }
+    
+    let fee_paid = ((fee_owed as u128).saturating_mul(paid as u128) / owed as u128) as u64;
+    let profit_paid = paid.saturating_sub(fee_paid);
+    
+    split_fee(
+        accounts,
+        market_account,
+        dex_fees_account,
+        market_account,
+        market_mint,
+        market_state,
+        fee_paid,
+        TransferReason::ProfitFee,
+        config,
+    )?;
+    move_lamports(market_account, owner_account, profit_paid, TransferReason::Profit)?;
+    market_state.record_payout(profit_paid);
+    
+    Ok((fee_paid, profit_paid))
+}
+
+/// Pays down a residual profit claim left by `PROCESS_PNL` from whatever the market (and its
+/// insurance sub-fund or the vault backstop, when passed) can now cover. Once nothing is owed
+/// the position account is closed and its rent returned.
+pub(crate) fn process_claim_residual_profit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    claim_data: ClaimResidualProfitData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    assert_fee_vault(dex_fees_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, claim_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if !position.has_residual_claim() {
+        msg!("Position {} has no residual profit claim", position.position_nonce);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    let mut market_state = load_market_state(market_account, program_id)?;
+    let owed = position.residual_profit.saturating_add(position.residual_fee);
+    let available = settlement_liquidity(
+        &config,
+        market_account,
+        &mut market_state,
+        find_market_insurance_account(accounts, &position.market_mint, program_id),
+        find_program_vault_account(accounts, program_id),
+        owed,
+    )?;
+    
+    let (fee_paid, profit_paid) = pay_profit_installment(
+        accounts,
+        market_account,
+        owner_account,
+        dex_fees_account,
+        &position.market_mint,
+        &mut market_state,
+        available,
+        position.residual_fee,
+        position.residual_profit,
+        &config,
+    )?;
+    save_market_state(market_account, &market_state)?;
+    
+    position.residual_profit = position.residual_profit.saturating_sub(profit_paid);
+    position.residual_fee = position.residual_fee.saturating_sub(fee_paid);
+    
+    emit_event(&DexEvent::ResidualProfit(ResidualProfitEvent {
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        market_mint: position.market_mint,
+        paid: profit_paid,
+        fee: fee_paid,
+        remaining: position.residual_profit,
+    }))?;
+    
+    if position.residual_profit > 0 {
+        save_position_account(position_account, &position)?;
+        
+        msg!("Residual profit paid: {}, still owed: {}", profit_paid, position.residual_profit);
+        
+        return Ok(());
+    }
+    
+    let rent_refund = position_account.lamports();
+    move_lamports(position_account, owner_account, rent_refund, TransferReason::RentRefund)?;
+    zero_account_data(position_account)?;
+    remove_from_position_index(
+        find_position_index_account(accounts, &position.owner, program_id),
+        &position.owner,
+        position.position_nonce,
+        program_id,
+    )?;
+    
+    msg!("Residual profit settled in full: {}, position {} closed", profit_paid, position.position_nonce);
+    
+    Ok(())
+}
+
+/// Pays out part of an open position's oracle-verified profit, net of funding and borrow, and
+/// moves its entry price so the remaining unrealized PnL drops by the realized amount. The
+/// position must still meet the initial margin afterwards.
+pub(crate) fn process_realize_profit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    realize_data: RealizeProfitData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    assert_fee_vault(dex_fees_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, realize_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    check_not_frozen(accounts, &[owner_account.key, position_account.key], program_id)?;
+    
+    if !position.is_open() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if realize_data.amount < config.min_realized_profit.max(1) {
+        msg!("Realized profit must be at least {} lamports", config.min_realized_profit);
+        return Err(DexError::ProfitBelowRealizeThreshold.into());
+    }
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let age = Clock::get()?.slot.saturating_sub(position.open_slot);
+    
+    if age < market_state.min_holding_slots {
+        msg!("Profit can be realized after {} slots of holding, position has {}", market_state.min_holding_slots, age);
+        return Err(DexError::MinHoldingPeriod.into());
+    }
+    
+    let mark_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?
+        .effective(position.direction, false);
+    let price_pnl = unrealized_pnl(&position, mark_price)?;
+    let net_pnl = price_pnl
+        .saturating_sub(accrued_funding(&position, &market_state))
+        .saturating_sub(accrued_borrow_fee(&position, &market_state));
+    
+    if realize_data.amount as i128 > net_pnl {
+        msg!("Requested {} exceeds unrealized profit {}", realize_data.amount, net_pnl);
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    let available = market_account
+        .lamports()
+        .saturating_sub(Rent::get()?.minimum_balance(market_account.data_len()));
+    
+    if available < realize_data.amount {
+        msg!("Market has {} lamports available, {} required", available, realize_data.amount);
+        return Err(DexError::InsufficientMarketLiquidity.into());
+    }
+    
+    let new_entry_price = entry_price_for_pnl(
+        mark_price,
+        position.position_size,
+        position.direction,
+        position.contract_type,
+        price_pnl.saturating_sub(realize_data.amount as i128),
+    );
+    
+    if new_entry_price == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    position.entry_price = new_entry_price;
+    position.refresh_liquidation_price(config.maintenance_margin_bps);
+    
+    if position.health(mark_price, config.initial_margin_bps, 0)?.health_bps() < BASIS_POINTS_DIVISOR {
+        msg!("Position would fall below the initial margin of {} bps", config.initial_margin_bps);
+        return Err(DexError::InsufficientInitialMargin.into());
+    }
+    
+    let fee = trading_fee(realize_data.amount, position.leverage, &position.fee_schedule(market_state.fee_schedule()));
+    let payout = realize_data.amount.saturating_sub(fee);
+    
+    split_fee(
+        accounts,
+        market_account,
+        dex_fees_account,
+        market_account,
+        &position.market_mint,
+        &mut market_state,
+        fee,
+        TransferReason::ProfitFee,
+        &config,
+    )?;
+    move_lamports(market_account, owner_account, payout, TransferReason::Profit)?;
+    
+    save_market_state(market_account, &market_state)?;
+    save_position_account(position_account, &position)?;
+    
+    emit_event(&DexEvent::ProfitRealized(ProfitRealizedEvent {
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        market_mint: position.market_mint,
+        mark_price,
+        amount: realize_data.amount,
+        fee,
+        new_entry_price,
+    }))?;
+    
+    msg!(
+        "Realized {} (fee: {}), entry price moved to {}",
+        payout,
+        fee,
+        new_entry_price
+    );
+    
+    Ok(())
+}
+
+/// Adds collateral to an open isolated position from the owner's wallet and moves its
+/// liquidation price accordingly.
+pub(crate) fn process_add_position_margin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    margin_data: PositionMarginData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, margin_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if position.status() != PositionStatus::Open || margin_data.amount == 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    invoke(
+        &system_instruction::transfer(
+            owner_account.key,
+            position_account.key,
+            margin_data.amount,
+        ),
+        &[
+            owner_account.clone(),
+            position_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    position.paid_amount = position.paid_amount.saturating_add(margin_data.amount);
+    position.refresh_liquidation_price(config.maintenance_margin_bps);
+    save_position_account(position_account, &position)?;
+    
+    msg!(
+        "Position {} collateral now {}, liquidation price {}",
+        position.position_nonce,
+        position.paid_amount,
+        position.liquidation_price
+    );
+    
+    Ok(())
+}
+
+/// Returns collateral from an open isolated position to its owner. At the oracle price and net
+/// of accrued funding and borrow, the position must still meet the initial margin; its
+/// liquidation price moves accordingly.
+pub(crate) fn process_remove_position_margin(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    margin_data: PositionMarginData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, margin_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    check_not_frozen(accounts, &[owner_account.key, position_account.key], program_id)?;
+    
+    if position.status() != PositionStatus::Open || margin_data.amount == 0 || margin_data.amount >= position.paid_amount {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    let market_state = touch_market_state(market_account, program_id)?;
+    let mark_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?
+        .effective(position.direction, false);
+    
+    position.paid_amount -= margin_data.amount;
+    position.refresh_liquidation_price(config.maintenance_margin_bps);
+    
+    let health = position.health(mark_price, config.initial_margin_bps, 0)?;
+    let equity = health
+        .equity
+        .saturating_sub(accrued_funding(&position, &market_state))
+        .saturating_sub(accrued_borrow_fee(&position, &market_state));
+    
+    if equity < health.maintenance as i128 {
+        msg!("Position would fall below the initial margin of {} bps", config.initial_margin_bps);
+        return Err(DexError::InsufficientInitialMargin.into());
+    }
+    
+    move_lamports(position_account, owner_account, margin_data.amount, TransferReason::MarginWithdrawal)?;
+    
+    save_market_state(market_account, &market_state)?;
+    save_position_account(position_account, &position)?;
+    
+    msg!(
+        "Position {} collateral now {}, liquidation price {}",
+        position.position_nonce,
+        position.paid_amount,
+        position.liquidation_price
+    );
+    
+    Ok(())
+}
+
+/// Permissionless keeper close of a position whose equity has fallen below the config's
+/// `stop_out_margin_bps` of notional, settled at the oracle price before it reaches the
+/// maintenance margin and its liquidation auction.
+pub(crate) fn process_stop_out(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    stop_out_data: StopOutData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let margin_account = next_account_info(accounts_iter).ok();
+    
+    assert_signer(keeper_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if config.stop_out_margin_bps == 0 {
+        return Err(DexError::StopOutDisabled.into());
+    }
+    
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, stop_out_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if !position.is_open() || !position.is_confirmed() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    
+    let oracle_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?;
+    let mark_price = oracle_price.effective(position.direction, false);
+    let cross_margin = cross_margin_account(&position, margin_account, program_id)?;
+    let shared_margin = match cross_margin {
+        Some(margin_account) => margin_available(margin_account)?,
+        None => 0,
+    };
+    let health_bps = position
+        .health(mark_price, config.stop_out_margin_bps, shared_margin)?
+        .net_of(position_accrued_charges(accounts, &position, program_id)?)
+        .health_bps();
+    
+    if health_bps >= BASIS_POINTS_DIVISOR {
+        msg!("Position is at {} bps of the stop-out requirement", health_bps);
+        return Err(DexError::PositionNotStoppedOut.into());
+    }
+    
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let final_pnl = settle_at_mark_price(
+        &position,
+        position_account,
+        owner_account,
+        market_account,
+        &mut market_state,
+        cross_margin,
+        &config,
+        accounts,
+        mark_price,
+        TransferReason::StopOutSettlement,
+        program_id,
+    )?;
+    
+    emit_settlement_price(&position, &oracle_price, mark_price)?;
+    
+    emit_event(&DexEvent::StoppedOut(StopOutEvent {
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        market_mint: position.market_mint,
+        keeper: *keeper_account.key,
+        mark_price,
+        pnl: final_pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        health_bps,
+    }))?;
+    
+    msg!("Position {} stopped out at {}, pnl: {}", position.position_nonce, mark_price, final_pnl);
+    
+    Ok(())
+}
+
+/// Buys a guaranteed stop in the slot the position opens: the owner pays
+/// `guaranteed_stop_premium_bps` of notional to the insurance fund, which in return absorbs any
+/// gap between the stop price and the oracle price when the stop is triggered.
+pub(crate) fn process_set_guaranteed_stop(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    stop_data: GuaranteedStopData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let insurance_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if config.guaranteed_stop_premium_bps == 0 {
+        return Err(DexError::GuaranteedStopDisabled.into());
+    }
+    
+    let mut position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, stop_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if !position.is_open() || position.position_size == 0 || position.guaranteed_stop_price != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    if Clock::get()?.slot != position.open_slot {
+        msg!("A guaranteed stop can only be bought in the slot the position opens");
+        return Err(DexError::InvalidStopPrice.into());
+    }
+    
+    verify_insurance_account(insurance_account, &position.market_mint, program_id)?;
+    
+    let mark_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?.price;
+    
+    // Until the DEX confirms an entry the oracle price stands in for it.
+    let reference = PositionAccount {
+        entry_price: if position.is_confirmed() { position.entry_price } else { mark_price },
+        ..position.clone()
+    };
+    let on_loss_side = if position.direction == POSITION_LONG {
+        stop_data.stop_price < mark_price
+    } else {
+        stop_data.stop_price > mark_price
+    };
+    
+    if stop_data.stop_price == 0
+        || !on_loss_side
+        || reference.unrealized_pnl(stop_data.stop_price)? <= -(position.paid_amount as i128)
+    {
+        msg!("Stop price must be on the losing side of {} and within the position's collateral", mark_price);
+        return Err(DexError::InvalidStopPrice.into());
+    }
+    
+    let premium = guaranteed_stop_premium(position.position_size, config.guaranteed_stop_premium_bps);
+    
+    invoke(
+        &system_instruction::transfer(
+            owner_account.key,
+            insurance_account.key,
+            premium,
+        ),
+        &[
+            owner_account.clone(),
+            insurance_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    if position_account.data_len() < PositionAccount::LEN {
+        resize_account(position_account, owner_account, system_program, PositionAccount::LEN)?;
+    }
+    
+    position.upgrade_version();
+    position.guaranteed_stop_price = stop_data.stop_price;
+    save_position_account(position_account, &position)?;
+    
+    emit_event(&DexEvent::GuaranteedStopSet(GuaranteedStopEvent {
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        market_mint: position.market_mint,
+        stop_price: stop_data.stop_price,
+        mark_price,
+        premium,
+        insured_gap: 0,
+    }))?;
+    
+    msg!(
+        "Guaranteed stop at {} for position {}, premium: {} lamports",
+        stop_data.stop_price,
+        position.position_nonce,
+        premium
+    );
+    
+    Ok(())
+}
+
+/// Permissionless keeper close of a position whose oracle price has reached its guaranteed
+/// stop. The position settles at exactly the stop price; the insurance fund pays the market
+/// whatever the oracle price gapped through it.
+pub(crate) fn process_trigger_guaranteed_stop(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    trigger_data: TriggerGuaranteedStopData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let insurance_account = next_account_info(accounts_iter)?;
+    let margin_account = next_account_info(accounts_iter).ok();
+    
+    assert_signer(keeper_account)?;
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let position = try_load_position_account(position_account)?;
+    
+    position.validate(owner_account.key, trigger_data.position_nonce)?;
+    position.validate_address(position_account.key, program_id)?;
+    
+    if !position.is_open() || !position.is_confirmed() || position.guaranteed_stop_price == 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    verify_market_address(market_account, &position.market_mint, program_id)?;
+    verify_insurance_account(insurance_account, &position.market_mint, program_id)?;
+    
+    let stop_price = position.guaranteed_stop_price;
+    let mark_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?.price;
+    let triggered = if position.direction == POSITION_LONG {
+        mark_price <= stop_price
+    } else {
+        mark_price >= stop_price
+    };
+    
+    if !triggered {
+        msg!("Oracle price {} has not reached the stop at {}", mark_price, stop_price);
+        return Err(DexError::StopNotTriggered.into());
+    }
+    
+    let gap = position
+        .unrealized_pnl(stop_price)?
+        .saturating_sub(position.unrealized_pnl(mark_price)?)
+        .clamp(0, u64::MAX as i128) as u64;
+    let insured_gap = gap.min(
+        insurance_account
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(insurance_account.data_len())),
+    );
+    
+    move_lamports(insurance_account, market_account, insured_gap, TransferReason::GuaranteedStopGap)?;
+    
+    if insured_gap < gap {
+        msg!("Insurance fund short of the {} lamport gap by {}", gap, gap - insured_gap);
+    }
+    
+    let cross_margin = cross_margin_account(&position, margin_account, program_id)?;
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    market_state.record_deposit(insured_gap);
+    let final_pnl = settle_at_mark_price(
+        &position,
+        position_account,
+        owner_account,
+        market_account,
+        &mut market_state,
+        cross_margin,
+        &config,
+        accounts,
+        stop_price,
+        TransferReason::Profit,
+        program_id,
+    )?;
+    
+    emit_event(&DexEvent::GuaranteedStopTriggered(GuaranteedStopEvent {
+        owner: position.owner,
+        position_nonce: position.position_nonce,
+        market_mint: position.market_mint,
+        stop_price,
+        mark_price,
+        premium: 0,
+        insured_gap,
+    }))?;
+    
+    msg!(
+        "Position {} stopped at {} (oracle {}), pnl: {}",
+        position.position_nonce,
+        stop_price,
+        mark_price,
+        final_pnl
+    );
+    
+    Ok(())
+}
+
+/// Nets an owner's isolated long against their isolated short on the same market at the oracle
+/// price: the position with the smaller notional is settled in full and the other is reduced by
+/// the same notional, realizing its share of PnL, funding and borrow. No close fee is charged
+/// and the smaller position's rent comes back, leaving only the residual exposure open.
+pub(crate) fn process_net_positions(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    net_data: NetPositionsData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let long_position_account = next_account_info(accounts_iter)?;
+    let short_position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    if long_position_account.owner != program_id || short_position_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    let config = load_config(config_account, program_id)?;
+    let long = try_load_position_account(long_position_account)?;
+    let short = try_load_position_account(short_position_account)?;
+    
+    long.validate(owner_account.key, net_data.long_position_nonce)?;
+    long.validate_address(long_position_account.key, program_id)?;
+    short.validate(owner_account.key, net_data.short_position_nonce)?;
+    short.validate_address(short_position_account.key, program_id)?;
+    
+    for position in [&long, &short] {
+        if !position.is_open() || !position.is_confirmed() || position.margin_mode != MARGIN_MODE_ISOLATED {
+            msg!("Position {} must be an open, confirmed isolated position", position.position_nonce);
+            return Err(DexError::PositionsNotNettable.into());
+        }
+    }
+    
+    if long.direction != POSITION_LONG || short.direction != POSITION_SHORT || long.market_mint != short.market_mint {
+        msg!("Netting needs a long and a short on the same market");
+        return Err(DexError::PositionsNotNettable.into());
+    }
+    
+    if hedge_mode_enabled(accounts, owner_account.key, &long.market_mint, program_id)? {
+        msg!("Market {} is in hedge mode", long.market_mint);
+        return Err(DexError::PositionsNotNettable.into());
+    }
+    
+    verify_market_address(market_account, &long.market_mint, program_id)?;
+    
+    let oracle_price = load_price_feed(price_feed_account, &long.market_mint, &config, program_id)?;
+    let mark_price = oracle_price.price;
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    let long_notional = long.notional(mark_price);
+    let short_notional = short.notional(mark_price);
+    
+    let (smaller, smaller_account, larger, larger_account, netted_notional) = if long_notional <= short_notional {
+        (&long, long_position_account, &short, short_position_account, long_notional)
+    } else {
+        (&short, short_position_account, &long, long_position_account, short_notional)
+    };
+    
+    let mut final_pnl = settle_at_mark_price(
+        smaller,
+        smaller_account,
+        owner_account,
+        market_account,
+        &mut market_state,
+        None,
+        &config,
+        accounts,
+        mark_price,
+        TransferReason::Profit,
+        program_id,
+    )?;
+    
+    emit_settlement_price(smaller, &oracle_price, mark_price)?;
+    
+    let larger_notional = larger.notional(mark_price).max(1);
+    
+    if netted_notional >= larger_notional {
+        final_pnl = final_pnl.saturating_add(settle_at_mark_price(
+            larger,
+            larger_account,
+            owner_account,
+            market_account,
+            &mut market_state,
+            None,
+            &config,
+            accounts,
+            mark_price,
+            TransferReason::Profit,
+            program_id,
+        )?);
+        
+        emit_settlement_price(larger, &oracle_price, mark_price)?;
+    } else {
+        final_pnl = final_pnl.saturating_add(reduce_position(
+            larger,
+            larger_account,
+            owner_account,
+            market_account,
+            &mut market_state,
+            &config,
+            accounts,
+            mark_price,
+            netted_notional,
+            larger_notional,
+            program_id,
+        )?);
+        save_market_state(market_account, &market_state)?;
+    }
+    
+    emit_event(&DexEvent::PositionsNetted(NettingEvent {
+        owner: *owner_account.key,
+        market_mint: long.market_mint,
+        long_position_nonce: long.position_nonce,
+        short_position_nonce: short.position_nonce,
+        mark_price,
+        netted_notional: netted_notional.min(u64::MAX as u128) as u64,
+        pnl: final_pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+    }))?;
+    
+    msg!(
+        "Netted {} notional of positions {} and {} at {}, pnl: {}",
+        netted_notional,
+        long.position_nonce,
+        short.position_nonce,
+        mark_price,
+        final_pnl
+    );
+    
+    Ok(())
+}
+
+/// Closes `closed_notional` of `position`'s `notional` at `mark_price`: the matching share of
+/// its size and collateral is released, with that share of PnL, funding and borrow settled
+/// against the market. A loss beyond the released collateral comes out of the remaining
+/// collateral. Returns the realized PnL; the caller saves `market_state`.
+#[allow(clippy::too_many_arguments)]
+fn reduce_position(
+    position: &PositionAccount,
+    position_account: &AccountInfo,
+    owner_account: &AccountInfo,
+    market_account: &AccountInfo,
+    market_state: &mut MarketState,
+    config: &GlobalConfig,
+    accounts: &[AccountInfo],
+    mark_price: u64,
+    closed_notional: u128,
+    notional: u128,
+    program_id: &Pubkey,
+) -> Result<i128, ProgramError> {
+    let share = |amount: u64| (amount as u128).saturating_mul(closed_notional) / notional;
+    let closed_size = share(position.position_size) as u64;
+    let closed_collateral = share(position.paid_amount) as u64;
+    let total_pnl = position
+        .unrealized_pnl(mark_price)?
+        .saturating_sub(accrued_funding(position, market_state))
+        .saturating_sub(accrued_borrow_fee(position, market_state));
+    let realized_pnl = total_pnl.saturating_mul(closed_notional as i128) / notional as i128;
+    let mut remaining_collateral = position.paid_amount.saturating_sub(closed_collateral);
+    let mut returned = closed_collateral;
+    
+    if realized_pnl > 0 {
+        let owed = realized_pnl.min(u64::MAX as i128) as u64;
+        let profit = owed.min(settlement_liquidity(
+            config,
+            market_account,
+            market_state,
+            find_market_insurance_account(accounts, &position.market_mint, program_id),
+            find_program_vault_account(accounts, program_id),
+            owed,
+        )?);
+        
+        move_lamports(market_account, owner_account, profit, TransferReason::Profit)?;
+        market_state.record_payout(profit);
+    } else if realized_pnl < 0 {
+        let loss = realized_pnl.unsigned_abs().min(u64::MAX as u128) as u64;
+        let from_closed = loss.min(closed_collateral);
+        let from_remaining = loss.saturating_sub(from_closed).min(remaining_collateral);
+        
+        move_lamports(position_account, market_account, from_closed.saturating_add(from_remaining), TransferReason::Loss)?;
+        market_state.record_deposit(from_closed.saturating_add(from_remaining));
+        returned = closed_collateral.saturating_sub(from_closed);
+        remaining_collateral = remaining_collateral.saturating_sub(from_remaining);
+    }
+    
+    move_lamports(position_account, owner_account, returned, TransferReason::CollateralReturn)?;
+    market_state.remove_open_interest(position.direction, closed_size);
+    
+    let mut reduced = position.clone();
+    reduced.position_size = position.position_size.saturating_sub(closed_size);
+    reduced.paid_amount = remaining_collateral;
+    reduced.refresh_liquidation_price(config.maintenance_margin_bps);
+    save_position_account(position_account, &reduced)?;
+    
+    Ok(realized_pnl)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use solana_program::{instruction::{AccountMeta, Instruction}, system_program};
+    
+    use super::*;
+    use crate::sim::{SimAccount, SimError, Simulator};
+    
+    const DEX_AUTHORITY: Pubkey = Pubkey::new_from_array([1; 32]);
+    const FEES_WALLET: Pubkey = Pubkey::new_from_array([2; 32]);
+    const SLOT: u64 = 1_000;
+    const ENTRY_PRICE: u64 = 1_000_000;
+    const NONCE: u64 = 7;
+    
+    struct PnlScenario {
+        sim: Simulator,
+        instruction: Instruction,
+        owner: Pubkey,
+        position: Pubkey,
+        market: Pubkey,
+        fee_vault: Pubkey,
+    }
+    
+    fn program_account<T: BorshSerialize>(lamports: u64, state: &T) -> SimAccount {
+        SimAccount {
+            lamports,
+            data: state.try_to_vec().unwrap(),
+            owner: crate::id(),
+            executable: false,
+        }
+    }
+    
+    /// A confirmed long, pending settlement, entered at the oracle price so that any `final_pnl`
+    /// within its size passes the (fully widened) pnl tolerance and price band.
+    fn pnl_scenario(position_lamports: u64, market_lamports: u64, final_pnl: i64, leverage: u8) -> PnlScenario {
+        let program_id = crate::id();
+        let rent = Rent::default();
+        let owner = Pubkey::new_unique();
+        let market_mint = Pubkey::new_unique();
+        let paid_amount = position_lamports - rent.minimum_balance(PositionAccount::LEN);
+        
+        let (position, _) = find_position_address(&owner, NONCE, &program_id);
+        let (market, _) = find_market_address(&market_mint, &program_id);
+        let (config, _) = find_config_address(&program_id);
+        let (price_feed, _) = find_price_feed_address(&market_mint, &program_id);
+        let (fee_escrow, _) = find_fee_escrow_address(&program_id);
+        let (fee_vault, _) = find_fee_vault_address(&program_id);
+        let (authority, _) = find_authority_address(&program_id);
+        
+        let mut sim = Simulator::new();
+        sim.warp_to_slot(SLOT);
+        sim.set_account(DEX_AUTHORITY, SimAccount::wallet(1_000_000_000));
+        sim.set_account(fee_vault, program_account(rent.minimum_balance(0), &()));
+        sim.set_account(owner, SimAccount::wallet(1_000_000_000));
+        sim.set_account(
+            authority,
+            program_account(rent.minimum_balance(AuthorityAccount::LEN), &AuthorityAccount {
+                admin: DEX_AUTHORITY,
+                dex_authority: DEX_AUTHORITY,
+                fees_wallet: FEES_WALLET,
+                ..AuthorityAccount::default()
+            }),
+        );
+        sim.set_account(
+            config,
+            program_account(rent.minimum_balance(GlobalConfig::LEN), &GlobalConfig {
+                version: CONFIG_ACCOUNT_VERSION,
+                admin: DEX_AUTHORITY,
+                dust_threshold: 5_000,
+                pnl_tolerance_bps: BASIS_POINTS_DIVISOR as u16,
+                price_band_bps: BASIS_POINTS_DIVISOR as u16,
+                close_fee_bps: 10,
+                lp_fee_share_bps: 5_000,
+                ..GlobalConfig::default()
+            }),
+        );
+        sim.set_account(
+            price_feed,
+            program_account(rent.minimum_balance(PriceFeedAccount::LEN), &PriceFeedAccount {
+                market_mint,
+                price: ENTRY_PRICE,
+                publish_slot: SLOT,
+                ..PriceFeedAccount::default()
+            }),
+        );
+        sim.set_account(
+            market,
+            program_account(market_lamports, &MarketState {
+                market_mint,
+                open_interest_long: paid_amount * leverage as u64,
+                ..MarketState::default()
+            }),
+        );
+        sim.set_account(fee_escrow, program_account(rent.minimum_balance(0), &()));
+        sim.set_account(
+            position,
+            program_account(position_lamports, &PositionAccount {
+                version: POSITION_ACCOUNT_VERSION,
+                owner,
+                market_mint,
+                entry_price: ENTRY_PRICE,
+                paid_amount,
+                position_size: paid_amount * leverage as u64,
+                leverage,
+                closed: 1,
+                confirmed: 1,
+                position_nonce: NONCE,
+                direction: POSITION_LONG,
+                open_slot: 1,
+                ..PositionAccount::default()
+            }),
+        );
+        
+        let mut data = vec![INSTRUCTION_PROCESS_PNL];
+        data.extend(ProcessPnlData { position_nonce: NONCE, final_pnl }.try_to_vec().unwrap());
+        
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &data,
+            vec![
+                AccountMeta::new(position, false),
+                AccountMeta::new(DEX_AUTHORITY, true),
+                AccountMeta::new(owner, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new(fee_vault, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new_readonly(price_feed, false),
+                AccountMeta::new(fee_escrow, false),
+                AccountMeta::new_readonly(authority, false),
+            ],
+        );
+        
+        PnlScenario { sim, instruction, owner, position, market, fee_vault }
+    }
+    
+    struct InitializeScenario {
+        sim: Simulator,
+        instruction: Instruction,
+        payer: Pubkey,
+        position: Pubkey,
+        fee_escrow: Pubkey,
+        created: Vec<Pubkey>,
+    }
+    
+    /// A first INITIALIZE for a fresh owner who also pays, on an active market with default
+    /// fees and every optional risk check disabled.
+    fn initialize_scenario(payer_lamports: u64, paid_amount: u64, position_size: u64, leverage: u8) -> InitializeScenario {
+        let program_id = crate::id();
+        let rent = Rent::default();
+        let payer = Pubkey::new_unique();
+        let market_mint = Pubkey::new_unique();
+        let mut market_symbol = [0; MAX_SYMBOL_LENGTH];
+        market_symbol[..8].copy_from_slice(b"SOL-PERP");
+        
+        let (position, _) = find_position_address(&payer, 0, &program_id);
+        let (market, _) = find_market_address(&market_mint, &program_id);
+        let (symbol_registry, _) = find_symbol_registry_address(&market_mint, &program_id);
+        let (config, _) = find_config_address(&program_id);
+        let (fee_escrow, _) = find_fee_escrow_address(&program_id);
+        let (position_index, _) = find_position_index_address(&payer, &program_id);
+        let (nonce_counter, _) = find_nonce_counter_address(&payer, &program_id);
+        let (user_stats, _) = find_user_stats_address(&payer, &program_id);
+        let (blacklist, _) = find_blacklist_address(&program_id);
+        let (freeze, _) = find_freeze_address(&payer, &program_id);
+        let (authority, _) = find_authority_address(&program_id);
+        
+        let mut sim = Simulator::new();
+        sim.warp_to_slot(SLOT);
+        sim.set_account(payer, SimAccount::wallet(payer_lamports));
+        sim.set_account(
+            authority,
+            program_account(rent.minimum_balance(AuthorityAccount::LEN), &AuthorityAccount {
+                admin: DEX_AUTHORITY,
+                dex_authority: DEX_AUTHORITY,
+                fees_wallet: FEES_WALLET,
+                ..AuthorityAccount::default()
+            }),
+        );
+        sim.set_account(
+            config,
+            program_account(rent.minimum_balance(GlobalConfig::LEN), &GlobalConfig {
+                version: CONFIG_ACCOUNT_VERSION,
+                admin: DEX_AUTHORITY,
+                ..GlobalConfig::default()
+            }),
+        );
+        sim.set_account(
+            symbol_registry,
+            program_account(rent.minimum_balance(SymbolRegistryAccount::LEN), &SymbolRegistryAccount {
+                market_mint,
+                market_symbol,
+                decimals: 9,
+            }),
+        );
+        sim.set_account(
+            market,
+            program_account(rent.minimum_balance(MarketState::LEN), &MarketState {
+                market_mint,
+                ..MarketState::default()
+            }),
+        );
+        sim.set_account(fee_escrow, program_account(rent.minimum_balance(0), &()));
+        
+        let mut data = vec![INSTRUCTION_INITIALIZE];
+        data.extend(
+            InitializePositionData {
+                market_mint,
+                market_symbol,
+                paid_amount,
+                position_size,
+                leverage,
+                position_nonce: 0,
+                direction: POSITION_LONG,
+                margin_mode: MARGIN_MODE_ISOLATED,
+                client_tag: [0; CLIENT_TAG_LENGTH],
+                platform_fee_bps: 0,
+            }
+            .try_to_vec()
+            .unwrap(),
+        );
+        
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &data,
+            vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new_readonly(payer, false),
+                AccountMeta::new(position, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new_readonly(DEX_AUTHORITY, false),
+                AccountMeta::new_readonly(FEES_WALLET, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(symbol_registry, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(fee_escrow, false),
+                AccountMeta::new(position_index, false),
+                AccountMeta::new(nonce_counter, false),
+                AccountMeta::new(user_stats, false),
+                AccountMeta::new_readonly(blacklist, false),
+                AccountMeta::new_readonly(freeze, false),
+                AccountMeta::new_readonly(authority, false),
+            ],
+        );
+        
+        InitializeScenario {
+            sim,
+            instruction,
+            payer,
+            position,
+            fee_escrow,
+            created: vec![position_index, nonce_counter, user_stats],
+        }
+    }
+    
+    /// Lamports the payer should lose on a successful open: `paid_amount`, the position's rent
+    /// deposit and the rent of the bookkeeping accounts created alongside it.
+    fn expected_open_cost(sim: &Simulator, created: &[Pubkey], paid_amount: u64) -> u64 {
+        let bookkeeping: u64 = created.iter().map(|key| sim.account(key).lamports).sum();
+        
+        paid_amount + Rent::default().minimum_balance(PositionAccount::LEN) + bookkeeping
+    }
+    
+    #[test]
+    fn initialize_takes_paid_amount_once_and_splits_out_the_fee() {
+        let paid_amount = 1_000_000_000;
+        let leverage = 5;
+        let (_, fee, collateral, size) = open_terms(paid_amount, leverage, FillRole::Taker, &MarketState::default().fee_schedule());
+        
+        let InitializeScenario { mut sim, instruction, payer, position, fee_escrow, created } =
+            initialize_scenario(10_000_000_000, paid_amount, size, leverage);
+        let escrow_before = sim.account(&fee_escrow).lamports;
+        
+        sim.process(&instruction).unwrap();
+        
+        let position_account = sim.account(&position);
+        let opened = PositionAccount::try_from_slice(&position_account.data).unwrap();
+        
+        assert_eq!(position_account.lamports, Rent::default().minimum_balance(PositionAccount::LEN) + collateral);
+        assert_eq!(opened.paid_amount, collateral);
+        assert_eq!(opened.open_fee, fee);
+        assert_eq!(sim.account(&fee_escrow).lamports, escrow_before + fee);
+        assert_eq!(sim.account(&payer).lamports, 10_000_000_000 - expected_open_cost(&sim, &created, paid_amount));
+    }
+    
+    #[test]
+    fn initialize_returns_lamports_sent_to_the_position_address() {
+        let paid_amount = 1_000_000_000;
+        let leverage = 5;
+        let rent_deposit = Rent::default().minimum_balance(PositionAccount::LEN);
+        let (_, _, collateral, size) = open_terms(paid_amount, leverage, FillRole::Taker, &MarketState::default().fee_schedule());
+        
+        // Below and above the rent deposit, so both the top-up and the refund paths run.
+        for stray in [1_000, rent_deposit + 250_000] {
+            let InitializeScenario { mut sim, instruction, payer, position, created, .. } =
+                initialize_scenario(10_000_000_000, paid_amount, size, leverage);
+            sim.set_account(position, SimAccount::wallet(stray));
+            
+            sim.process(&instruction).unwrap();
+            
+            assert_eq!(sim.account(&position).lamports, rent_deposit + collateral);
+            assert_eq!(
+                sim.account(&payer).lamports,
+                10_000_000_000 + stray - expected_open_cost(&sim, &created, paid_amount)
+            );
+        }
+    }
+    
+    #[test]
+    fn initialize_rejects_underfunded_payers_and_mismatched_sizes() {
+        let paid_amount = 1_000_000_000;
+        let leverage = 5;
+        let (_, _, _, size) = open_terms(paid_amount, leverage, FillRole::Taker, &MarketState::default().fee_schedule());
+        
+        let InitializeScenario { mut sim, instruction, payer, position, .. } =
+            initialize_scenario(paid_amount / 2, paid_amount, size, leverage);
+        
+        assert_eq!(sim.process(&instruction), Err(SimError::Program(ProgramError::InsufficientFunds)));
+        assert_eq!(sim.account(&payer).lamports, paid_amount / 2);
+        assert_eq!(sim.account(&position), SimAccount::wallet(0));
+        
+        for claimed in [paid_amount * leverage as u64 + 1, size / 2] {
+            let InitializeScenario { mut sim, instruction, payer, .. } =
+                initialize_scenario(10_000_000_000, paid_amount, claimed, leverage);
+            
+            assert_eq!(
+                sim.process(&instruction),
+                Err(SimError::Program(DexError::PositionSizeMismatch.into()))
+            );
+            assert_eq!(sim.account(&payer).lamports, 10_000_000_000);
+        }
+    }
+    
+    #[test]
+    fn process_pnl_sweeps_dust_payouts_in_every_branch() {
+        let rent = Rent::default();
+        let rent_floor = rent.minimum_balance(PositionAccount::LEN);
+        let market_floor = rent.minimum_balance(MarketState::LEN);
+        let collateral = 3_000_000;
+        
+        // Zero pnl, profit, partial loss and a profit the market cannot pay (residual claim, whose
+        // account stays open on its rent).
+        for (final_pnl, market_lamports, rent_refund) in [
+            (0, 10_000_000_000, rent_floor),
+            (1_000_000, 10_000_000_000, rent_floor),
+            (-1_000_000, 10_000_000_000, rent_floor),
+            (1_000_000, market_floor, 0),
+        ] {
+            let PnlScenario { mut sim, instruction, owner, fee_vault, .. } =
+                pnl_scenario(rent_floor + collateral, market_lamports, final_pnl, 1);
+            let (config, _) = find_config_address(&crate::id());
+            let mut config_account = sim.account(&config);
+            let mut config_state = GlobalConfig::deserialize(&mut &config_account.data[..]).unwrap();
+            config_state.dust_threshold = 10_000_000;
+            // Keeps the close fee's LP leg out of the market so the residual case pays nothing now.
+            config_state.lp_fee_share_bps = 0;
+            config_account.data = config_state.try_to_vec().unwrap();
+            sim.set_account(config, config_account);
+            
+            let owner_before = sim.account(&owner).lamports;
+            let fees_before = sim.account(&fee_vault).lamports;
+            
+            sim.process(&instruction).unwrap();
+            
+            assert_eq!(sim.account(&owner).lamports, owner_before + rent_refund, "pnl {}", final_pnl);
+            assert!(sim.logs().iter().any(|log| log.starts_with("Dust swept to fees")), "pnl {}", final_pnl);
+            assert!(sim.account(&fee_vault).lamports > fees_before, "pnl {}", final_pnl);
+        }
+    }
+    
+    #[test]
+    fn match_orders_skips_an_order_whose_position_cannot_fill() {
+        let program_id = crate::id();
+        let rent = Rent::default();
+        let market_mint = Pubkey::new_unique();
+        let (market, _) = find_market_address(&market_mint, &program_id);
+        let (config, _) = find_config_address(&program_id);
+        let (symbol_registry, _) = find_symbol_registry_address(&market_mint, &program_id);
+        let (fee_vault, _) = find_fee_vault_address(&program_id);
+        let (bids, _) = find_order_book_address(&market_mint, POSITION_LONG, &program_id);
+        let (asks, _) = find_order_book_address(&market_mint, POSITION_SHORT, &program_id);
+        
+        let mut sim = Simulator::new();
+        sim.warp_to_slot(SLOT);
+        sim.set_account(fee_vault, program_account(rent.minimum_balance(0), &()));
+        sim.set_account(
+            config,
+            program_account(rent.minimum_balance(GlobalConfig::LEN), &GlobalConfig {
+                version: CONFIG_ACCOUNT_VERSION,
+                admin: DEX_AUTHORITY,
+                ..GlobalConfig::default()
+            }),
+        );
+        sim.set_account(
+            symbol_registry,
+            program_account(rent.minimum_balance(SymbolRegistryAccount::LEN), &SymbolRegistryAccount {
+                market_mint,
+                market_symbol: [0; MAX_SYMBOL_LENGTH],
+                decimals: 9,
+            }),
+        );
+        sim.set_account(market, program_account(10_000_000_000, &MarketState { market_mint, ..MarketState::default() }));
+        
+        // The best bid's position is gone; the next bid and the ask hold reserved positions.
+        let order = |order_id: u64, price: u64, placed_slot: u64| Order {
+            order_id,
+            owner: Pubkey::new_unique(),
+            price,
+            paid_amount: 1_000_000,
+            leverage: 1,
+            position_nonce: order_id,
+            placed_slot,
+            ..Order::default()
+        };
+        let (orphaned, bid, ask) = (order(1, 110, 1), order(2, 105, 2), order(3, 100, 3));
+        let position_of = |order: &Order| find_position_address(&order.owner, order.position_nonce, &program_id).0;
+        for (order, direction) in [(&bid, POSITION_LONG), (&ask, POSITION_SHORT)] {
+            sim.set_account(
+                position_of(order),
+                program_account(rent.minimum_balance(PositionAccount::LEN), &PositionAccount {
+                    version: POSITION_ACCOUNT_VERSION,
+                    owner: order.owner,
+                    market_mint,
+                    leverage: 1,
+                    position_nonce: order.position_nonce,
+                    direction,
+                    open_slot: SLOT,
+                    ..PositionAccount::default()
+                }),
+            );
+        }
+        for (book, side, orders) in [(bids, POSITION_LONG, vec![orphaned.clone(), bid.clone()]), (asks, POSITION_SHORT, vec![ask.clone()])] {
+            let escrow: u64 = orders.iter().map(Order::total_amount).sum();
+            let mut data = OrderBookSide { market_mint, side, next_order_id: 4, orders }.try_to_vec().unwrap();
+            data.resize(OrderBookSide::space(MAX_ORDERS_PER_SIDE), 0);
+            sim.set_account(book, SimAccount {
+                lamports: rent.minimum_balance(data.len()) + escrow,
+                data,
+                owner: program_id,
+                executable: false,
+            });
+        }
+        
+        let mut data = vec![INSTRUCTION_MATCH_ORDERS];
+        data.extend(MatchOrdersData { market_mint, max_fills: 2 }.try_to_vec().unwrap());
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &data,
+            vec![
+                AccountMeta::new(bids, false),
+                AccountMeta::new(asks, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new_readonly(symbol_registry, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new(fee_vault, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(position_of(&orphaned), false),
+                AccountMeta::new(position_of(&ask), false),
+                AccountMeta::new(position_of(&bid), false),
+                AccountMeta::new(position_of(&ask), false),
+            ],
+        );
+        
+        sim.process(&instruction).unwrap();
+        
+        let resting = OrderBookSide::deserialize(&mut &sim.account(&bids).data[..]).unwrap().orders;
+        assert_eq!(resting.iter().map(|order| order.order_id).collect::<Vec<_>>(), vec![orphaned.order_id]);
+        assert!(OrderBookSide::deserialize(&mut &sim.account(&asks).data[..]).unwrap().orders.is_empty());
+        for order in [&bid, &ask] {
+            let filled = PositionAccount::deserialize(&mut &sim.account(&position_of(order)).data[..]).unwrap();
+            assert_eq!(filled.entry_price, bid.price);
+        }
+        assert!(sim.logs().iter().any(|log| log == "Order 1 skipped, its position cannot absorb a fill"));
+    }
+    
+    #[test]
+    fn cancel_position_rejects_a_position_reserved_for_an_order() {
+        let PnlScenario { mut sim, owner, position, market, .. } =
+            pnl_scenario(Rent::default().minimum_balance(PositionAccount::LEN) + 1_000_000, 10_000_000_000, 0, 1);
+        let filled = PositionAccount::deserialize(&mut &sim.account(&position).data[..]).unwrap();
+        let reserved = PositionAccount {
+            version: POSITION_ACCOUNT_VERSION,
+            owner,
+            market_mint: filled.market_mint,
+            position_nonce: NONCE,
+            direction: POSITION_LONG,
+            open_slot: SLOT,
+            ..PositionAccount::default()
+        };
+        sim.set_account(position, program_account(Rent::default().minimum_balance(PositionAccount::LEN), &reserved));
+        
+        let (fee_escrow, _) = find_fee_escrow_address(&crate::id());
+        let mut data = vec![INSTRUCTION_CANCEL_POSITION];
+        data.extend(CancelPositionData { position_nonce: NONCE }.try_to_vec().unwrap());
+        let instruction = Instruction::new_with_bytes(
+            crate::id(),
+            &data,
+            vec![
+                AccountMeta::new(position, false),
+                AccountMeta::new(owner, true),
+                AccountMeta::new(fee_escrow, false),
+                AccountMeta::new(market, false),
+            ],
+        );
+        
+        assert_eq!(sim.process(&instruction), Err(SimError::Program(ProgramError::InvalidAccountData)));
+        assert!(sim.logs().iter().any(|log| log.ends_with("is reserved for a resting order, cancel the order first")));
+    }
+    
+    #[test]
+    fn process_pnl_residual_claim_returns_all_collateral() {
+        let rent = Rent::default();
+        let rent_floor = rent.minimum_balance(PositionAccount::LEN);
+        let collateral = 3_000_000;
+        let PnlScenario { mut sim, instruction, owner, position, .. } =
+            pnl_scenario(rent_floor + collateral, rent.minimum_balance(MarketState::LEN), 1_000_000, 1);
+        let (config, _) = find_config_address(&crate::id());
+        let mut config_account = sim.account(&config);
+        let mut config_state = GlobalConfig::deserialize(&mut &config_account.data[..]).unwrap();
+        // Keeps the close fee's LP leg out of the market so no profit installment is paid now.
+        config_state.lp_fee_share_bps = 0;
+        config_account.data = config_state.try_to_vec().unwrap();
+        sim.set_account(config, config_account);
+        
+        let owner_before = sim.account(&owner).lamports;
+        
+        sim.process(&instruction).unwrap();
+        
+        let close_fee = close_fee(collateral, 10);
+        assert_eq!(sim.account(&owner).lamports, owner_before + collateral - close_fee);
+        assert_eq!(sim.account(&position).lamports, rent_floor);
+        let returned = format!("collateral returned: {}", collateral - close_fee);
+        assert!(sim.logs().iter().any(|log| log.ends_with(&returned)));
+        let claim = PositionAccount::deserialize(&mut &sim.account(&position).data[..]).unwrap();
+        assert!(claim.has_residual_claim());
+    }
+    
+    #[test]
+    fn process_pnl_never_debits_the_owner_for_a_loss_beyond_collateral() {
+        let rent_floor = Rent::default().minimum_balance(PositionAccount::LEN);
+        let collateral = 100_000_000;
+        let loss = 3 * collateral as i64;
+        let PnlScenario { mut sim, instruction, owner, position, market, .. } =
+            pnl_scenario(rent_floor + collateral, 10_000_000_000, -loss, 5);
+        let owner_before = sim.account(&owner).lamports;
+        
+        sim.process(&instruction).unwrap();
+        
+        let market_state = MarketState::deserialize(&mut &sim.account(&market).data[..]).unwrap();
+        
+        assert_eq!(sim.account(&position).lamports, 0);
+        assert_eq!(sim.account(&owner).lamports, owner_before + rent_floor);
+        // The close fee is owed on top of the loss and goes unpaid with it.
+        assert_eq!(market_state.bad_debt, loss as u64 + close_fee(5 * collateral, 10) - collateral);
+    }
+    
+    #[test]
+    fn dex_modify_requires_a_resize_and_stamps_the_checksum() {
+        let PnlScenario { mut sim, position, market, fee_vault, .. } = pnl_scenario(1_000_000_000, 10_000_000_000, 0, 5);
+        let program_id = crate::id();
+        
+        // The version 12 layout: every field up to `contract_type`, with no checksum.
+        let mut legacy = sim.account(&position);
+        let mut open = PositionAccount::deserialize(&mut &legacy.data[..]).unwrap();
+        open.version = PositionAccount::FIRST_CHECKSUM_VERSION - 1;
+        open.closed = 0;
+        legacy.data = open.try_to_vec().unwrap();
+        legacy.data.truncate(PositionAccount::LEN - 8 - 2 - 2 - 4);
+        sim.set_account(position, legacy.clone());
+        
+        let mut data = vec![INSTRUCTION_DEX_MODIFY];
+        data.extend(
+            DexModifyData {
+                new_entry_price: ENTRY_PRICE,
+                new_liquidation_price: 0,
+                position_nonce: NONCE,
+                new_close_state: 1,
+                new_pnl: 0,
+            }
+            .try_to_vec()
+            .unwrap(),
+        );
+        let dex_modify = Instruction::new_with_bytes(
+            program_id,
+            &data,
+            vec![
+                AccountMeta::new(position, false),
+                AccountMeta::new(DEX_AUTHORITY, true),
+                AccountMeta::new_readonly(find_config_address(&program_id).0, false),
+                AccountMeta::new_readonly(find_price_feed_address(&open.market_mint, &program_id).0, false),
+                AccountMeta::new(find_fee_escrow_address(&program_id).0, false),
+                AccountMeta::new(fee_vault, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new_readonly(find_authority_address(&program_id).0, false),
+            ],
+        );
+        let resize = Instruction::new_with_bytes(
+            program_id,
+            &[INSTRUCTION_RESIZE_POSITION],
+            vec![
+                AccountMeta::new(position, false),
+                AccountMeta::new(DEX_AUTHORITY, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        
+        assert_eq!(sim.process(&dex_modify), Err(SimError::Program(DexError::AccountNeedsMigration.into())));
+        assert_eq!(sim.account(&position), legacy);
+        
+        sim.process(&resize).unwrap();
+        sim.process(&dex_modify).unwrap();
+        
+        let data = sim.account(&position).data;
+        let reloaded = PositionAccount::deserialize(&mut &data[..]).unwrap();
+        
+        assert!(PositionAccount::is_current_layout(&data));
+        assert!(!PositionAccount::is_corrupted(&data));
+        assert_ne!(reloaded.checksum, 0);
+        assert_eq!(reloaded.closed, 1);
+        assert_eq!(reloaded.entry_price, ENTRY_PRICE);
+    }
+    
+    #[test]
+    fn takeover_records_collateral_net_of_the_rent_reserve() {
+        let program_id = crate::id();
+        let rent = Rent::default();
+        let owner = Pubkey::new_unique();
+        let liquidator = Pubkey::new_unique();
+        let market_mint = Pubkey::new_unique();
+        let paid_amount = 1_000_000_000;
+        let collateral_top_up = 1_000_000_000;
+        let position_rent = rent.minimum_balance(PositionAccount::LEN);
+        
+        let (position, _) = find_position_address(&owner, NONCE, &program_id);
+        let (new_position, _) = find_position_address(&liquidator, 0, &program_id);
+        let (nonce_counter, _) = find_nonce_counter_address(&liquidator, &program_id);
+        let (config, _) = find_config_address(&program_id);
+        let (price_feed, _) = find_price_feed_address(&market_mint, &program_id);
+        
+        let mut sim = Simulator::new();
+        sim.warp_to_slot(SLOT);
+        sim.set_account(owner, SimAccount::wallet(0));
+        sim.set_account(liquidator, SimAccount::wallet(10_000_000_000));
+        sim.set_account(
+            config,
+            program_account(rent.minimum_balance(GlobalConfig::LEN), &GlobalConfig {
+                version: CONFIG_ACCOUNT_VERSION,
+                admin: DEX_AUTHORITY,
+                maintenance_margin_bps: 500,
+                initial_margin_bps: 1_000,
+                ..GlobalConfig::default()
+            }),
+        );
+        // A 6% drop leaves the 10x long with 0.4 SOL of equity against 0.47 SOL of maintenance.
+        sim.set_account(
+            price_feed,
+            program_account(rent.minimum_balance(PriceFeedAccount::LEN), &PriceFeedAccount {
+                market_mint,
+                source: PriceSource::Fixed { price: ENTRY_PRICE / 100 * 94 },
+                ..PriceFeedAccount::default()
+            }),
+        );
+        sim.set_account(
+            position,
+            program_account(paid_amount + position_rent, &PositionAccount {
+                version: POSITION_ACCOUNT_VERSION,
+                owner,
+                market_mint,
+                entry_price: ENTRY_PRICE,
+                paid_amount,
+                position_size: paid_amount * 10,
+                leverage: 10,
+                confirmed: 1,
+                position_nonce: NONCE,
+                direction: POSITION_LONG,
+                open_slot: 1,
+                liquidation_auction_slot: SLOT - 10,
+                ..PositionAccount::default()
+            }),
+        );
+        
+        let mut data = vec![INSTRUCTION_TAKEOVER_POSITION];
+        data.extend(
+            TakeoverData { position_nonce: NONCE, new_position_nonce: 0, collateral_top_up }
+                .try_to_vec()
+                .unwrap(),
+        );
+        
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &data,
+            vec![
+                AccountMeta::new(position, false),
+                AccountMeta::new(liquidator, true),
+                AccountMeta::new(owner, false),
+                AccountMeta::new(new_position, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new_readonly(price_feed, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new(nonce_counter, false),
+            ],
+        );
+        
+        sim.process(&instruction).unwrap();
+        
+        let taken_over = PositionAccount::try_from_slice(&sim.account(&new_position).data).unwrap();
+        
+        assert_eq!(sim.account(&new_position).lamports, paid_amount + collateral_top_up + position_rent);
+        assert_eq!(taken_over.paid_amount, paid_amount + collateral_top_up);
+        assert_eq!(taken_over.owner, liquidator);
+    }
+    
+    struct LiquidateScenario {
+        sim: Simulator,
+        instruction: Instruction,
+        owner: Pubkey,
+        market_mint: Pubkey,
+        queue: Pubkey,
+        oracle_price: u64,
+    }
+    
+    /// A 10x long whose liquidation auction is running, 6% under water at the oracle, liquidated
+    /// by the DEX authority at `mark_price`. The config bands operator prices to 1%.
+    fn liquidate_scenario(mark_price: u64) -> LiquidateScenario {
+        let program_id = crate::id();
+        let rent = Rent::default();
+        let owner = Pubkey::new_unique();
+        let market_mint = Pubkey::new_unique();
+        let paid_amount = 1_000_000_000;
+        let oracle_price = ENTRY_PRICE / 100 * 94;
+        
+        let (position, _) = find_position_address(&owner, NONCE, &program_id);
+        let (market, _) = find_market_address(&market_mint, &program_id);
+        let (config, _) = find_config_address(&program_id);
+        let (price_feed, _) = find_price_feed_address(&market_mint, &program_id);
+        let (insurance_fund, _) = find_insurance_fund_address(&program_id);
+        let (queue, _) = find_liquidation_queue_address(&market_mint, &program_id);
+        let (authority, _) = find_authority_address(&program_id);
+        
+        let mut sim = Simulator::new();
+        sim.warp_to_slot(SLOT);
+        sim.set_account(owner, SimAccount::wallet(0));
+        sim.set_account(DEX_AUTHORITY, SimAccount::wallet(1_000_000_000));
+        sim.set_account(
+            authority,
+            program_account(rent.minimum_balance(AuthorityAccount::LEN), &AuthorityAccount {
+                admin: DEX_AUTHORITY,
+                dex_authority: DEX_AUTHORITY,
+                fees_wallet: FEES_WALLET,
+                ..AuthorityAccount::default()
+            }),
+        );
+        sim.set_account(
+            config,
+            program_account(rent.minimum_balance(GlobalConfig::LEN), &GlobalConfig {
+                version: CONFIG_ACCOUNT_VERSION,
+                admin: DEX_AUTHORITY,
+                maintenance_margin_bps: 500,
+                initial_margin_bps: 1_000,
+                price_band_bps: 100,
+                ..GlobalConfig::default()
+            }),
+        );
+        sim.set_account(
+            price_feed,
+            program_account(rent.minimum_balance(PriceFeedAccount::LEN), &PriceFeedAccount {
+                market_mint,
+                source: PriceSource::Fixed { price: oracle_price },
+                ..PriceFeedAccount::default()
+            }),
+        );
+        sim.set_account(insurance_fund, program_account(rent.minimum_balance(0), &()));
+        sim.set_account(
+            market,
+            program_account(10_000_000_000, &MarketState {
+                market_mint,
+                open_interest_long: paid_amount * 10,
+                ..MarketState::default()
+            }),
+        );
+        sim.set_account(
+            position,
+            program_account(paid_amount + rent.minimum_balance(PositionAccount::LEN), &PositionAccount {
+                version: POSITION_ACCOUNT_VERSION,
+                owner,
+                market_mint,
+                entry_price: ENTRY_PRICE,
+                paid_amount,
+                position_size: paid_amount * 10,
+                leverage: 10,
+                confirmed: 1,
+                position_nonce: NONCE,
+                direction: POSITION_LONG,
+                open_slot: 1,
+                liquidation_auction_slot: SLOT - 10,
+                ..PositionAccount::default()
+            }),
+        );
+        
+        let mut data = vec![INSTRUCTION_LIQUIDATE];
+        data.extend(LiquidateData { position_nonce: NONCE, mark_price }.try_to_vec().unwrap());
+        
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &data,
+            vec![
+                AccountMeta::new(position, false),
+                AccountMeta::new(DEX_AUTHORITY, true),
+                AccountMeta::new(owner, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new_readonly(price_feed, false),
+                AccountMeta::new(insurance_fund, false),
+                AccountMeta::new_readonly(authority, false),
+                AccountMeta::new(queue, false),
+            ],
+        );
+        
+        LiquidateScenario { sim, instruction, owner, market_mint, queue, oracle_price }
+    }
+    
+    #[test]
+    fn operator_liquidation_price_stays_within_the_oracle_band() {
+        let oracle_price = liquidate_scenario(0).oracle_price;
+        
+        for (mark_price, allowed) in [
+            (oracle_price, true),
+            (oracle_price + oracle_price / 100, true),
+            (oracle_price / 2, false),
+            (oracle_price + oracle_price / 50, false),
+        ] {
+            let LiquidateScenario { mut sim, instruction, .. } = liquidate_scenario(mark_price);
+            let result = sim.process(&instruction);
+            
+            if allowed {
+                assert_eq!(result, Ok(()), "mark price {}", mark_price);
+            } else {
+                assert_eq!(result, Err(SimError::Program(DexError::PriceOutsideBand.into())), "mark price {}", mark_price);
+            }
+        }
+    }
+    
+    #[test]
+    fn liquidation_follows_a_non_empty_queue() {
+        let rent = Rent::default();
+        
+        // Queue contents riskiest first, each entry flagged when it is the liquidated position.
+        for (queued, allowed) in [
+            (None, true),
+            (Some(vec![]), true),
+            (Some(vec![false]), false),
+            (Some(vec![true, false]), true),
+            (Some(vec![false, true]), false),
+        ] {
+            let oracle_price = liquidate_scenario(0).oracle_price;
+            let LiquidateScenario { mut sim, instruction, owner, market_mint, queue, .. } = liquidate_scenario(oracle_price);
+            
+            if let Some(entries) = &queued {
+                let entries = entries
+                    .iter()
+                    .enumerate()
+                    .map(|(index, liquidated)| LiquidationQueueEntry {
+                        owner: if *liquidated { owner } else { Pubkey::new_unique() },
+                        position_nonce: NONCE,
+                        health_bps: 100 * (index as u64 + 1),
+                        notional: 1,
+                        queued_slot: SLOT,
+                    })
+                    .collect();
+                let mut account = program_account(
+                    rent.minimum_balance(LiquidationQueueAccount::LEN),
+                    &LiquidationQueueAccount { market_mint, entries },
+                );
+                account.data.resize(LiquidationQueueAccount::LEN, 0);
+                sim.set_account(queue, account);
+            }
+            
+            let result = sim.process(&instruction);
+            
+            if allowed {
+                assert_eq!(result, Ok(()), "queue {:?}", queued);
+            } else {
+                assert_eq!(result, Err(SimError::Program(DexError::LiquidationOutOfOrder.into())), "queue {:?}", queued);
+            }
+        }
+    }
+    
+    struct DcaCrankScenario {
+        sim: Simulator,
+        instruction: Instruction,
+        owner: Pubkey,
+        position: Pubkey,
+        config: Pubkey,
+        user_stats: Pubkey,
+        blacklist: Pubkey,
+        freeze: Pubkey,
+    }
+    
+    /// A due DCA schedule with budget for two executions, cranked by a fresh keeper.
+    fn dca_crank_scenario() -> DcaCrankScenario {
+        let program_id = crate::id();
+        let rent = Rent::default();
+        let owner = Pubkey::new_unique();
+        let keeper = Pubkey::new_unique();
+        let market_mint = Pubkey::new_unique();
+        let amount_per_interval = 1_000_000_000;
+        let budget = 2 * (amount_per_interval + rent.minimum_balance(PositionAccount::LEN));
+        
+        let (schedule, _) = find_dca_schedule_address(&owner, 0, &program_id);
+        let (position, _) = find_position_address(&owner, 0, &program_id);
+        let (nonce_counter, _) = find_nonce_counter_address(&owner, &program_id);
+        let (position_index, _) = find_position_index_address(&owner, &program_id);
+        let (market, _) = find_market_address(&market_mint, &program_id);
+        let (symbol_registry, _) = find_symbol_registry_address(&market_mint, &program_id);
+        let (config, _) = find_config_address(&program_id);
+        let (price_feed, _) = find_price_feed_address(&market_mint, &program_id);
+        let (fee_vault, _) = find_fee_vault_address(&program_id);
+        let (blacklist, _) = find_blacklist_address(&program_id);
+        let (freeze, _) = find_freeze_address(&owner, &program_id);
+        let (user_stats, _) = find_user_stats_address(&owner, &program_id);
+        
+        let mut sim = Simulator::new();
+        sim.warp_to_slot(SLOT);
+        sim.set_account(owner, SimAccount::wallet(0));
+        sim.set_account(keeper, SimAccount::wallet(10_000_000_000));
+        sim.set_account(
+            schedule,
+            program_account(
+                rent.minimum_balance(DcaScheduleAccount::LEN) + budget,
+                &DcaScheduleAccount {
+                    owner,
+                    market_mint,
+                    direction: POSITION_LONG,
+                    leverage: 2,
+                    amount_per_interval,
+                    interval_slots: 100,
+                    remaining_budget: budget,
+                    next_execution_slot: SLOT,
+                    ..DcaScheduleAccount::default()
+                },
+            ),
+        );
+        sim.set_account(
+            config,
+            program_account(rent.minimum_balance(GlobalConfig::LEN), &GlobalConfig {
+                version: CONFIG_ACCOUNT_VERSION,
+                admin: DEX_AUTHORITY,
+                ..GlobalConfig::default()
+            }),
+        );
+        sim.set_account(
+            symbol_registry,
+            program_account(rent.minimum_balance(SymbolRegistryAccount::LEN), &SymbolRegistryAccount {
+                market_mint,
+                market_symbol: [0; MAX_SYMBOL_LENGTH],
+                decimals: 9,
+            }),
+        );
+        sim.set_account(market, program_account(10_000_000_000, &MarketState { market_mint, ..MarketState::default() }));
+        sim.set_account(
+            price_feed,
+            program_account(rent.minimum_balance(PriceFeedAccount::LEN), &PriceFeedAccount {
+                market_mint,
+                source: PriceSource::Fixed { price: ENTRY_PRICE },
+                ..PriceFeedAccount::default()
+            }),
+        );
+        sim.set_account(fee_vault, program_account(rent.minimum_balance(0), &()));
+        
+        let mut data = vec![INSTRUCTION_CRANK_DCA];
+        data.extend(DcaScheduleData { schedule_id: 0 }.try_to_vec().unwrap());
+        
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &data,
+            vec![
+                AccountMeta::new(schedule, false),
+                AccountMeta::new(keeper, true),
+                AccountMeta::new(owner, false),
+                AccountMeta::new(position, false),
+                AccountMeta::new(nonce_counter, false),
+                AccountMeta::new(position_index, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new_readonly(symbol_registry, false),
+                AccountMeta::new_readonly(config, false),
+                AccountMeta::new_readonly(price_feed, false),
+                AccountMeta::new(fee_vault, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(blacklist, false),
+                AccountMeta::new_readonly(freeze, false),
+                AccountMeta::new(user_stats, false),
+            ],
+        );
+        
+        DcaCrankScenario { sim, instruction, owner, position, config, user_stats, blacklist, freeze }
+    }
+    
+    #[test]
+    fn crank_dca_passes_the_initialize_open_gates() {
+        let rent = Rent::default();
+        
+        for missing in 0..3 {
+            let DcaCrankScenario { mut sim, mut instruction, user_stats, blacklist, freeze, .. } = dca_crank_scenario();
+            let missing = [blacklist, freeze, user_stats][missing];
+            instruction.accounts.retain(|meta| meta.pubkey != missing);
+            
+            assert_eq!(sim.process(&instruction), Err(SimError::Program(ProgramError::NotEnoughAccountKeys)));
+        }
+        
+        let DcaCrankScenario { sim: mut frozen, instruction, owner, freeze, .. } = dca_crank_scenario();
+        frozen.set_account(
+            freeze,
+            program_account(rent.minimum_balance(FreezeAccount::LEN), &FreezeAccount {
+                target: owner,
+                frozen: 1,
+                ..FreezeAccount::default()
+            }),
+        );
+        assert_eq!(frozen.process(&instruction), Err(SimError::Program(DexError::AccountFrozen.into())));
+        
+        let DcaCrankScenario { sim: mut blacklisted, instruction, owner, blacklist, .. } = dca_crank_scenario();
+        blacklisted.set_account(
+            blacklist,
+            program_account(rent.minimum_balance(BlacklistAccount::LEN), &BlacklistAccount {
+                entries: vec![BlacklistEntry { address: owner, reason_code: 1, added_slot: SLOT }],
+            }),
+        );
+        assert_eq!(blacklisted.process(&instruction), Err(SimError::Program(DexError::AddressBlacklisted.into())));
+        
+        let DcaCrankScenario { sim: mut limited, instruction, owner, config, user_stats, .. } = dca_crank_scenario();
+        limited.set_account(
+            config,
+            program_account(rent.minimum_balance(GlobalConfig::LEN), &GlobalConfig {
+                version: CONFIG_ACCOUNT_VERSION,
+                admin: DEX_AUTHORITY,
+                min_open_slot_gap: 10,
+                ..GlobalConfig::default()
+            }),
+        );
+        limited.set_account(
+            user_stats,
+            program_account(rent.minimum_balance(UserStatsAccount::LEN), &UserStatsAccount {
+                owner,
+                last_open_slot: SLOT - 1,
+                opens_in_slot: 1,
+                total_opens: 1,
+            }),
+        );
+        assert_eq!(limited.process(&instruction), Err(SimError::Program(DexError::OpenRateLimited.into())));
+        
+        let DcaCrankScenario { mut sim, instruction, position, user_stats, .. } = dca_crank_scenario();
+        sim.process(&instruction).unwrap();
+        assert_eq!(sim.account(&position).owner, crate::id());
+        assert_eq!(deserialize_padded::<UserStatsAccount>(&sim.account(&user_stats).data).unwrap().total_opens, 1);
+    }
+    
+    proptest! {
+        #[test]
+        fn process_pnl_conserves_and_bounds_every_branch(
+            collateral in 1_000u64..=100_000_000_000,
+            market_extra in 0u64..=1_000_000_000_000,
+            pnl_bps in -10_000i64..=10_000,
+            leverage in 1u8..=50,
+        ) {
+            let rent = Rent::default();
+            let position_lamports = rent.minimum_balance(PositionAccount::LEN) + collateral;
+            let market_floor = rent.minimum_balance(MarketState::LEN);
+            let position_size = collateral * leverage as u64;
+            let final_pnl = (position_size as i128 * pnl_bps as i128 / BASIS_POINTS_DIVISOR as i128) as i64;
+            
+            let PnlScenario { mut sim, instruction, owner, position, market, fee_vault } =
+                pnl_scenario(position_lamports, market_floor + market_extra, final_pnl, leverage);
+            
+            let keys: Vec<Pubkey> = instruction.accounts.iter().map(|meta| meta.pubkey).collect();
+            let total_before: u128 = keys.iter().map(|key| sim.account(key).lamports as u128).sum();
+            let owner_before = sim.account(&owner).lamports + sim.account(&position).lamports;
+            let fees_before = sim.account(&fee_vault).lamports;
+            
+            sim.process(&instruction).unwrap();
+            
+            let total_after: u128 = keys.iter().map(|key| sim.account(key).lamports as u128).sum();
+            let owner_after = sim.account(&owner).lamports + sim.account(&position).lamports;
+            
+            prop_assert_eq!(total_after, total_before, "lamports minted or burned");
+            prop_assert!(owner_after + collateral >= owner_before, "owner lost more than collateral");
+            prop_assert!(sim.account(&market).lamports >= market_floor, "market fell below its rent floor");
+            prop_assert!(sim.account(&fee_vault).lamports >= fees_before, "fee vault decreased");
+        }
+    }
+}
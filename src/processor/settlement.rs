@@ -0,0 +1,242 @@
+//! Settlement assets: native SOL markets hold their funds as lamports on the market PDA,
+//! SPL-settled markets in a settlement vault token account that is its own authority.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction,
+    sysvar::{rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// SPL token account layout: mint, owner, then the amount.
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const TOKEN_MINT_OFFSET: usize = 0;
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+
+const TOKEN_INSTRUCTION_TRANSFER: u8 = 3;
+const TOKEN_INSTRUCTION_INITIALIZE_ACCOUNT3: u8 = 18;
+
+/// Rejects SPL-settled markets in handlers that still move collateral as lamports.
+pub(crate) fn check_native_settlement(market_state: &MarketState) -> ProgramResult {
+    if !market_state.is_native_settlement() {
+        msg!("Market settles in {}", market_state.settlement_mint);
+        return Err(DexError::SettlementAssetUnsupported.into());
+    }
+    
+    Ok(())
+}
+
+pub(crate) fn token_account_amount(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    let data = token_account.data.borrow();
+    let amount = data
+        .get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    
+    Ok(u64::from_le_bytes(amount.try_into().map_err(|_| ProgramError::InvalidAccountData)?))
+}
+
+fn token_account_mint(token_account: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    let data = token_account.data.borrow();
+    let mint = data
+        .get(TOKEN_MINT_OFFSET..TOKEN_MINT_OFFSET + 32)
+        .ok_or(ProgramError::InvalidAccountData)?;
+    
+    Pubkey::try_from(mint).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Checks `vault_account` is `market_state`'s settlement vault and returns its bump.
+pub(crate) fn verify_settlement_vault(
+    vault_account: &AccountInfo,
+    token_program: &AccountInfo,
+    market_state: &MarketState,
+    program_id: &Pubkey,
+) -> Result<u8, ProgramError> {
+    let (vault_pda, vault_bump) = find_settlement_vault_address(&market_state.market_mint, program_id);
+    
+    assert_key_eq(vault_account, &vault_pda)?;
+    
+    if token_program.key != &SPL_TOKEN_PROGRAM_ID || vault_account.owner != &SPL_TOKEN_PROGRAM_ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    if token_account_mint(vault_account)? != market_state.settlement_mint {
+        msg!("Settlement vault does not hold {}", market_state.settlement_mint);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    Ok(vault_bump)
+}
+
+/// SPL-settled counterpart of a market's lamport balance above rent: the vault's token balance
+/// less LP fees not yet claimed.
+pub(crate) fn settlement_pool_value(vault_account: &AccountInfo, market_state: &MarketState) -> Result<u64, ProgramError> {
+    Ok(token_account_amount(vault_account)?.saturating_sub(market_state.unclaimed_lp_fees))
+}
+
+pub(crate) fn token_transfer<'a>(
+    source_account: &AccountInfo<'a>,
+    destination_account: &AccountInfo<'a>,
+    authority_account: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let mut data = Vec::with_capacity(9);
+    data.push(TOKEN_INSTRUCTION_TRANSFER);
+    data.extend_from_slice(&amount.to_le_bytes());
+    
+    invoke_signed(
+        &Instruction {
+            program_id: SPL_TOKEN_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(*source_account.key, false),
+                AccountMeta::new(*destination_account.key, false),
+                AccountMeta::new_readonly(*authority_account.key, true),
+            ],
+            data,
+        },
+        &[
+            source_account.clone(),
+            destination_account.clone(),
+            authority_account.clone(),
+            token_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+/// Pays `amount` of the settlement asset out of a market's vault.
+pub(crate) fn settlement_vault_payout<'a>(
+    vault_account: &AccountInfo<'a>,
+    destination_account: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    market_mint: &Pubkey,
+    vault_bump: u8,
+    amount: u64,
+) -> ProgramResult {
+    token_transfer(
+        vault_account,
+        destination_account,
+        vault_account,
+        token_program,
+        amount,
+        &[&[b"uranus_settlement_vault", market_mint.as_ref(), &[vault_bump]]],
+    )
+}
+
+/// Sets the asset a market settles in (config admin only, with `FEATURE_SPL_COLLATERAL` for SPL
+/// mints) while it has no open interest or LP shares, creating its settlement vault if needed.
+pub(crate) fn process_set_settlement_asset(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    asset_data: SettlementAssetData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let native = asset_data.settlement_mint == Pubkey::default();
+    
+    if !native && !config.feature_enabled(FEATURE_SPL_COLLATERAL) {
+        msg!("SPL settlement is disabled");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut market_state = prepare_market_state(
+        market_account,
+        admin_account,
+        system_program,
+        &asset_data.market_mint,
+        program_id,
+    )?;
+    
+    if market_state.open_interest_long != 0 || market_state.open_interest_short != 0 || market_state.total_lp_shares != 0 {
+        msg!("Settlement asset can only change on a market without positions or liquidity");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if !native {
+        let vault_account = next_account_info(accounts_iter)?;
+        let settlement_mint_account = next_account_info(accounts_iter)?;
+        let token_program = next_account_info(accounts_iter)?;
+        
+        if token_program.key != &SPL_TOKEN_PROGRAM_ID || settlement_mint_account.owner != &SPL_TOKEN_PROGRAM_ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        
+        if settlement_mint_account.key != &asset_data.settlement_mint {
+            msg!("Invalid settlement mint account");
+            return Err(ProgramError::InvalidArgument);
+        }
+        
+        let (vault_pda, vault_bump) = find_settlement_vault_address(&asset_data.market_mint, program_id);
+        
+        assert_key_eq(vault_account, &vault_pda)?;
+        
+        if vault_account.data_is_empty() {
+            invoke_signed(
+                &system_instruction::create_account(
+                    admin_account.key,
+                    vault_account.key,
+                    Rent::get()?.minimum_balance(TOKEN_ACCOUNT_LEN),
+                    TOKEN_ACCOUNT_LEN as u64,
+                    &SPL_TOKEN_PROGRAM_ID,
+                ),
+                &[
+                    admin_account.clone(),
+                    vault_account.clone(),
+                    system_program.clone(),
+                ],
+                &[&[b"uranus_settlement_vault", asset_data.market_mint.as_ref(), &[vault_bump]]],
+            )?;
+            
+            let mut data = Vec::with_capacity(33);
+            data.push(TOKEN_INSTRUCTION_INITIALIZE_ACCOUNT3);
+            data.extend_from_slice(vault_pda.as_ref());
+            
+            invoke(
+                &Instruction {
+                    program_id: SPL_TOKEN_PROGRAM_ID,
+                    accounts: vec![
+                        AccountMeta::new(*vault_account.key, false),
+                        AccountMeta::new_readonly(*settlement_mint_account.key, false),
+                    ],
+                    data,
+                },
+                &[
+                    vault_account.clone(),
+                    settlement_mint_account.clone(),
+                    token_program.clone(),
+                ],
+            )?;
+            
+            msg!("Settlement vault created: {}", vault_account.key);
+        } else if vault_account.owner != &SPL_TOKEN_PROGRAM_ID || token_account_mint(vault_account)? != asset_data.settlement_mint {
+            msg!("Settlement vault already holds another mint");
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+    
+    market_state.settlement_mint = asset_data.settlement_mint;
+    
+    market_state.serialize(&mut *market_account.data.borrow_mut())?;
+    
+    msg!("Market {} settles in {}", asset_data.market_mint, if native { "SOL".to_string() } else { asset_data.settlement_mint.to_string() });
+    
+    Ok(())
+}
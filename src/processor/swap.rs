@@ -0,0 +1,176 @@
+//! Constant-product spot swaps between the settlement assets of two markets, priced against
+//! each market's LP pool value, with the fee left in the input market's pool for its LPs.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction,
+};
+
+use super::*;
+
+/// Pool value backing one side of a swap: lamports above rent for native markets, the settlement
+/// vault's balance for SPL-settled ones, less unclaimed LP fees either way.
+fn swap_reserve(
+    market_account: &AccountInfo,
+    vault_account: Option<&AccountInfo>,
+    market_state: &MarketState,
+) -> Result<u64, ProgramError> {
+    match vault_account {
+        Some(vault_account) => settlement_pool_value(vault_account, market_state),
+        None => lp_pool_value(market_account, market_state),
+    }
+}
+
+/// Swaps the input market's settlement asset for the output market's (`FEATURE_SPOT_SWAP`).
+/// Accounts: input market, output market, user, config, system program, token program, then
+/// for an SPL-settled input the user's source token account and the input settlement vault, and
+/// for an SPL-settled output the user's destination token account and the output vault.
+pub(crate) fn process_swap(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    swap_data: SwapData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let input_market_account = next_account_info(accounts_iter)?;
+    let output_market_account = next_account_info(accounts_iter)?;
+    let user_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(user_account)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    if !config.feature_enabled(FEATURE_SPOT_SWAP) {
+        msg!("Spot swaps are disabled");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    check_not_blacklisted(accounts, &[user_account.key], program_id)?;
+    
+    if swap_data.amount_in == 0 || swap_data.input_market_mint == swap_data.output_market_mint {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mut input_state = prepare_market_state(
+        input_market_account,
+        user_account,
+        system_program,
+        &swap_data.input_market_mint,
+        program_id,
+    )?;
+    let mut output_state = prepare_market_state(
+        output_market_account,
+        user_account,
+        system_program,
+        &swap_data.output_market_mint,
+        program_id,
+    )?;
+    
+    if !input_state.is_active() || !output_state.is_active() {
+        msg!("Both markets must be active to swap");
+        return Err(DexError::MarketNotActive.into());
+    }
+    
+    if input_state.settlement_mint == output_state.settlement_mint {
+        msg!("Both markets settle in {}", input_state.settlement_mint);
+        return Err(DexError::SettlementAssetUnsupported.into());
+    }
+    
+    let input_tokens = if input_state.is_native_settlement() {
+        None
+    } else {
+        let source_token_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
+        
+        verify_settlement_vault(vault_account, token_program, &input_state, program_id)?;
+        
+        Some((source_token_account, vault_account))
+    };
+    let output_tokens = if output_state.is_native_settlement() {
+        None
+    } else {
+        let destination_token_account = next_account_info(accounts_iter)?;
+        let vault_account = next_account_info(accounts_iter)?;
+        let vault_bump = verify_settlement_vault(vault_account, token_program, &output_state, program_id)?;
+        
+        Some((destination_token_account, vault_account, vault_bump))
+    };
+    
+    let reserve_in = swap_reserve(input_market_account, input_tokens.map(|(_, vault)| vault), &input_state)?;
+    let reserve_out = swap_reserve(output_market_account, output_tokens.map(|(_, vault, _)| vault), &output_state)?;
+    let (amount_out, fee) = constant_product_output(swap_data.amount_in, reserve_in, reserve_out, config.swap_fee_bps);
+    
+    if amount_out == 0 || amount_out < swap_data.min_amount_out {
+        msg!("Swap returns {}, minimum {}", amount_out, swap_data.min_amount_out);
+        return Err(DexError::SwapSlippageExceeded.into());
+    }
+    
+    match input_tokens {
+        Some((source_token_account, vault_account)) => token_transfer(
+            source_token_account,
+            vault_account,
+            user_account,
+            token_program,
+            swap_data.amount_in,
+            &[],
+        )?,
+        None => invoke(
+            &system_instruction::transfer(
+                user_account.key,
+                input_market_account.key,
+                swap_data.amount_in,
+            ),
+            &[
+                user_account.clone(),
+                input_market_account.clone(),
+                system_program.clone(),
+            ],
+        )?,
+    }
+    
+    match output_tokens {
+        Some((destination_token_account, vault_account, vault_bump)) => settlement_vault_payout(
+            vault_account,
+            destination_token_account,
+            token_program,
+            &swap_data.output_market_mint,
+            vault_bump,
+            amount_out,
+        )?,
+        None => move_lamports(output_market_account, user_account, amount_out, TransferReason::SpotSwap)?,
+    }
+    
+    input_state.record_fee(0, fee);
+    input_state.record_deposit(swap_data.amount_in);
+    output_state.record_payout(amount_out);
+    
+    // The fee is already in the input pool; without compounding it becomes claimable.
+    if !config.feature_enabled(FEATURE_LP_FEE_COMPOUNDING) {
+        input_state.accrue_lp_fee(fee);
+    }
+    
+    input_state.serialize(&mut *input_market_account.data.borrow_mut())?;
+    output_state.serialize(&mut *output_market_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::Swapped(SwapEvent {
+        user: *user_account.key,
+        input_market_mint: swap_data.input_market_mint,
+        output_market_mint: swap_data.output_market_mint,
+        amount_in: swap_data.amount_in,
+        amount_out,
+        fee,
+    }))?;
+    
+    msg!("Swapped {} for {} ({} fee)", swap_data.amount_in, amount_out, fee);
+    
+    Ok(())
+}
@@ -0,0 +1,352 @@
+//! TWAP execution of large opens: escrowed collateral filled into one position in equal slices
+//! by a keeper crank, so no single entry carries the whole size.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn load_twap_schedule(
+    twap_account: &AccountInfo,
+    owner: &Pubkey,
+    position_nonce: u64,
+    program_id: &Pubkey,
+) -> Result<TwapScheduleAccount, ProgramError> {
+    let (twap_pda, _twap_bump) = find_twap_schedule_address(owner, position_nonce, program_id);
+    
+    assert_key_eq(twap_account, &twap_pda)?;
+    
+    assert_owned_by(twap_account, program_id)?;
+    
+    deserialize_padded::<TwapScheduleAccount>(&twap_account.data.borrow())
+}
+
+/// Escrows a large open in a TWAP schedule and reserves its position like `PLACE_ORDER`. Each
+/// slice must stay within the market's TWAP threshold; the first is due immediately.
+pub(crate) fn process_create_twap_open(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    create_data: CreateTwapOpenData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let twap_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let symbol_registry_account = next_account_info(accounts_iter)?;
+    let nonce_counter_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    if create_data.direction != POSITION_LONG && create_data.direction != POSITION_SHORT {
+        msg!("Invalid direction");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if create_data.slices < 2 || create_data.interval_slots == 0 {
+        msg!("A TWAP needs at least two slices and a one-slot interval");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let leverage = create_data.leverage.clamp(1, MAXIMUM_LEVERAGE);
+    let amount_per_slice = create_data.paid_amount / create_data.slices as u64;
+    let last_slice = create_data
+        .paid_amount
+        .saturating_sub(amount_per_slice.saturating_mul(create_data.slices.saturating_sub(1) as u64));
+    
+    if amount_per_slice.saturating_mul(leverage as u64) < MIN_POSITION_SIZE_LAMPORTS {
+        msg!("TWAP slice too small");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    verify_market_address(market_account, &create_data.market_mint, program_id)?;
+    
+    let market_state = load_tradable_market_state(market_account, program_id)?;
+    
+    check_leverage_tier(&market_state, create_data.paid_amount.saturating_mul(leverage as u64), leverage)?;
+    
+    if market_state.twap_threshold_notional != 0
+        && last_slice.saturating_mul(leverage as u64) > market_state.twap_threshold_notional
+    {
+        msg!("TWAP slices must stay within {} notional", market_state.twap_threshold_notional);
+        return Err(DexError::TwapRequired.into());
+    }
+    
+    let symbol_registry = load_symbol_registry(symbol_registry_account, &create_data.market_mint, program_id)?;
+    
+    reserve_order_position(
+        position_account,
+        owner_account,
+        nonce_counter_account,
+        system_program,
+        &PlaceOrderData {
+            market_mint: create_data.market_mint,
+            side: create_data.direction,
+            price: 0,
+            paid_amount: create_data.paid_amount,
+            leverage,
+            position_nonce: create_data.position_nonce,
+            display_amount: 0,
+        },
+        leverage,
+        symbol_registry.market_symbol,
+        program_id,
+    )?;
+    
+    let (twap_pda, twap_bump) = find_twap_schedule_address(owner_account.key, create_data.position_nonce, program_id);
+    
+    assert_key_eq(twap_account, &twap_pda)?;
+    
+    if !twap_account.data_is_empty() {
+        msg!("Position {} already has a TWAP schedule", create_data.position_nonce);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    
+    let current_slot = Clock::get()?.slot;
+    let schedule = TwapScheduleAccount {
+        owner: *owner_account.key,
+        market_mint: create_data.market_mint,
+        position_nonce: create_data.position_nonce,
+        direction: create_data.direction,
+        leverage,
+        slices: create_data.slices,
+        slices_filled: 0,
+        amount_per_slice,
+        interval_slots: create_data.interval_slots,
+        remaining_budget: create_data.paid_amount,
+        next_execution_slot: current_slot,
+        placed_slot: current_slot,
+    };
+    
+    invoke_signed(
+        &system_instruction::create_account(
+            owner_account.key,
+            twap_account.key,
+            Rent::get()?
+                .minimum_balance(TwapScheduleAccount::LEN)
+                .saturating_add(create_data.paid_amount),
+            TwapScheduleAccount::LEN as u64,
+            program_id,
+        ),
+        &[
+            owner_account.clone(),
+            twap_account.clone(),
+            system_program.clone(),
+        ],
+        &[&[
+            b"uranus_twap",
+            owner_account.key.as_ref(),
+            &create_data.position_nonce.to_le_bytes(),
+            &[twap_bump],
+        ]],
+    )?;
+    
+    schedule.serialize(&mut *twap_account.data.borrow_mut())?;
+    
+    emit_event(&DexEvent::TwapCreated(TwapEvent {
+        owner: schedule.owner,
+        position_nonce: schedule.position_nonce,
+        market_mint: schedule.market_mint,
+        price: 0,
+        amount: 0,
+        slices_filled: 0,
+        remaining_budget: schedule.remaining_budget,
+    }))?;
+    
+    msg!(
+        "TWAP for position {}: {} slices of {} lamports every {} slots",
+        schedule.position_nonce,
+        schedule.slices,
+        schedule.amount_per_slice,
+        schedule.interval_slots
+    );
+    
+    Ok(())
+}
+
+/// Stops a TWAP, refunding the unfilled collateral and the account's rent. Slices already
+/// filled stay in the position.
+pub(crate) fn process_cancel_twap_open(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    cancel_data: TwapScheduleData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let twap_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(owner_account)?;
+    
+    let schedule = load_twap_schedule(twap_account, owner_account.key, cancel_data.position_nonce, program_id)?;
+    
+    move_lamports(twap_account, owner_account, schedule.remaining_budget, TransferReason::OrderRefund)?;
+    refund_order_account(twap_account, owner_account)?;
+    
+    emit_event(&DexEvent::TwapCancelled(TwapEvent {
+        owner: schedule.owner,
+        position_nonce: schedule.position_nonce,
+        market_mint: schedule.market_mint,
+        price: 0,
+        amount: schedule.remaining_budget,
+        slices_filled: schedule.slices_filled,
+        remaining_budget: 0,
+    }))?;
+    
+    msg!(
+        "TWAP for position {} cancelled after {} of {} slices",
+        schedule.position_nonce,
+        schedule.slices_filled,
+        schedule.slices
+    );
+    
+    Ok(())
+}
+
+/// Permissionless crank that fills the next due slice into the TWAP's position at the
+/// spread-adjusted oracle price, paying the taker fee on it. The schedule closes itself and
+/// returns its rent to the owner after the last slice.
+pub(crate) fn process_crank_twap(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    crank_data: TwapScheduleData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let twap_account = next_account_info(accounts_iter)?;
+    let position_account = next_account_info(accounts_iter)?;
+    let keeper_account = next_account_info(accounts_iter)?;
+    let owner_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let dex_fees_account = next_account_info(accounts_iter)?;
+    
+    assert_signer(keeper_account)?;
+    
+    assert_fee_vault(dex_fees_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let mut schedule = load_twap_schedule(twap_account, owner_account.key, crank_data.position_nonce, program_id)?;
+    let current_slot = Clock::get()?.slot;
+    
+    if current_slot < schedule.next_execution_slot {
+        msg!("Next TWAP slice is due at slot {}", schedule.next_execution_slot);
+        return Err(DexError::TwapSliceNotDue.into());
+    }
+    
+    verify_market_address(market_account, &schedule.market_mint, program_id)?;
+    
+    let oracle_price = load_price_feed(price_feed_account, &schedule.market_mint, &config, program_id)?;
+    let mut market_state = touch_market_state(market_account, program_id)?;
+    
+    if !market_state.is_active() {
+        return Err(DexError::MarketNotActive.into());
+    }
+    
+    check_native_settlement(&market_state)?;
+    
+    let amount = schedule.next_slice_amount();
+    
+    check_price_impact(market_account, amount.saturating_mul(schedule.leverage as u64), &config)?;
+    
+    let price = apply_spread(
+        oracle_price.effective(schedule.direction, true),
+        schedule.direction,
+        market_state.spread_bps(market_account.lamports()),
+        true,
+    );
+    let fee = fill_fee(amount, schedule.leverage, FillRole::Taker, &market_state.fee_schedule());
+    let collateral = amount.saturating_sub(fee);
+    
+    split_fee(
+        accounts,
+        twap_account,
+        dex_fees_account,
+        market_account,
+        &schedule.market_mint,
+        &mut market_state,
+        fee,
+        TransferReason::OpenFee,
+        &config,
+    )?;
+    apply_fill_to_position(
+        program_id,
+        position_account,
+        twap_account,
+        &Order {
+            order_id: 0,
+            owner: schedule.owner,
+            price,
+            paid_amount: amount,
+            leverage: schedule.leverage,
+            position_nonce: schedule.position_nonce,
+            placed_slot: schedule.placed_slot,
+            ..Order::default()
+        },
+        schedule.direction,
+        &schedule.market_mint,
+        price,
+        collateral,
+        fee,
+        &config,
+        &market_state,
+    )?;
+    
+    market_state.add_open_interest(schedule.direction, collateral.saturating_mul(schedule.leverage as u64));
+    save_market_state(market_account, &market_state)?;
+    
+    schedule.remaining_budget = schedule.remaining_budget.saturating_sub(amount);
+    schedule.slices_filled = schedule.slices_filled.saturating_add(1);
+    schedule.next_execution_slot = current_slot.saturating_add(schedule.interval_slots);
+    
+    emit_event(&DexEvent::FeeCharged(FeeChargedEvent {
+        market_mint: schedule.market_mint,
+        payer: schedule.owner,
+        position_nonce: schedule.position_nonce,
+        role: FillRole::Taker,
+        amount,
+        fee,
+    }))?;
+    emit_event(&DexEvent::TwapSliceFilled(TwapEvent {
+        owner: schedule.owner,
+        position_nonce: schedule.position_nonce,
+        market_mint: schedule.market_mint,
+        price,
+        amount,
+        slices_filled: schedule.slices_filled,
+        remaining_budget: schedule.remaining_budget,
+    }))?;
+    
+    msg!(
+        "TWAP slice {} of {} for position {} filled at {}",
+        schedule.slices_filled,
+        schedule.slices,
+        schedule.position_nonce,
+        price
+    );
+    
+    if schedule.slices_filled >= schedule.slices || schedule.remaining_budget == 0 {
+        move_lamports(twap_account, owner_account, schedule.remaining_budget, TransferReason::OrderRefund)?;
+        refund_order_account(twap_account, owner_account)?;
+        
+        msg!("TWAP for position {} complete", schedule.position_nonce);
+        
+        return Ok(());
+    }
+    
+    schedule.serialize(&mut *twap_account.data.borrow_mut())?;
+    
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Per-owner activity stats and the open rate limit enforced from them.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Counts an open by `owner` in their stats PDA, which must be among `accounts` and is created
+/// at `payer_account`'s expense on first use. Rejects the open when it comes sooner than the
+/// config `min_open_slot_gap` after the previous one or exceeds `max_opens_per_slot`; a zero
+/// setting disables that limit.
+pub(crate) fn record_open<'a>(
+    accounts: &[AccountInfo<'a>],
+    owner: &Pubkey,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    config: &GlobalConfig,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (stats_pda, stats_bump) = find_user_stats_address(owner, program_id);
+    
+    let stats_account = accounts.iter().find(|account| account.key == &stats_pda).ok_or_else(|| {
+        msg!("User stats account for {} missing", owner);
+        ProgramError::NotEnoughAccountKeys
+    })?;
+    
+    let mut stats = if stats_account.data_is_empty() {
+        invoke_signed(
+            &system_instruction::create_account(
+                payer_account.key,
+                stats_account.key,
+                Rent::get()?.minimum_balance(UserStatsAccount::LEN),
+                UserStatsAccount::LEN as u64,
+                program_id,
+            ),
+            &[
+                payer_account.clone(),
+                stats_account.clone(),
+                system_program.clone(),
+            ],
+            &[&[
+                b"uranus_user_stats",
+                owner.as_ref(),
+                &[stats_bump],
+            ]],
+        )?;
+        
+        UserStatsAccount {
+            owner: *owner,
+            ..UserStatsAccount::default()
+        }
+    } else if stats_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    } else {
+        deserialize_padded::<UserStatsAccount>(&stats_account.data.borrow())?
+    };
+    
+    let slot = Clock::get()?.slot;
+    
+    if stats.total_opens > 0 && slot == stats.last_open_slot {
+        if config.max_opens_per_slot != 0 && stats.opens_in_slot >= config.max_opens_per_slot {
+            msg!("At most {} opens per slot", config.max_opens_per_slot);
+            return Err(DexError::OpenRateLimited.into());
+        }
+        
+        stats.opens_in_slot = stats.opens_in_slot.saturating_add(1);
+    } else {
+        stats.opens_in_slot = 1;
+    }
+    
+    if stats.total_opens > 0 && slot.saturating_sub(stats.last_open_slot) < config.min_open_slot_gap {
+        msg!(
+            "Last open at slot {}, next allowed at {}",
+            stats.last_open_slot,
+            stats.last_open_slot.saturating_add(config.min_open_slot_gap)
+        );
+        return Err(DexError::OpenRateLimited.into());
+    }
+    
+    stats.last_open_slot = slot;
+    stats.total_opens = stats.total_opens.saturating_add(1);
+    stats.serialize(&mut *stats_account.data.borrow_mut())?;
+    
+    Ok(())
+}
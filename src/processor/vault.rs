@@ -0,0 +1,200 @@
+//! Program vault: a global liquidity backstop for profit payouts a market cannot cover.
+
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke,
+    system_instruction,
+    sysvar::{rent::Rent, Sysvar},
+};
+
+use super::*;
+
+/// Creates the program vault unless it already exists, so racing bootstrap transactions both
+/// succeed. Lamports sent to the address before creation stay in the vault.
+pub(crate) fn create_program_vault_if_needed<'a>(
+    vault_account: &AccountInfo<'a>,
+    payer_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (vault_pda, vault_bump) = find_program_vault_address(program_id);
+    
+    assert_key_eq(vault_account, &vault_pda)?;
+    
+    if vault_account.owner == program_id {
+        return Ok(());
+    }
+    
+    create_pda_account(
+        vault_account,
+        payer_account,
+        system_program,
+        Rent::get()?.minimum_balance(0),
+        0,
+        &[b"uranus_program_vault", &[vault_bump]],
+        program_id,
+    )?;
+    
+    msg!("Program vault initialized: {}", vault_account.key);
+    
+    Ok(())
+}
+
+/// The program vault if it was passed anywhere in `accounts` and has been created.
+pub(crate) fn find_program_vault_account<'a, 'b>(
+    accounts: &'a [AccountInfo<'b>],
+    program_id: &Pubkey,
+) -> Option<&'a AccountInfo<'b>> {
+    let (vault_pda, _vault_bump) = find_program_vault_address(program_id);
+    
+    accounts
+        .iter()
+        .find(|account| account.key == &vault_pda && account.owner == program_id)
+}
+
+pub(crate) fn vault_available(vault_account: &AccountInfo) -> Result<u64, ProgramError> {
+    let rent = Rent::get()?;
+    
+    Ok(vault_account
+        .lamports()
+        .saturating_sub(rent.minimum_balance(vault_account.data_len())))
+}
+
+/// Tops a market up from the vault by `shortfall` when `FEATURE_VAULT_BACKSTOP` is on and the
+/// vault can cover all of it; returns the amount moved, so partial payouts never happen.
+pub(crate) fn draw_vault_backstop(
+    config: &GlobalConfig,
+    vault_account: Option<&AccountInfo>,
+    market_account: &AccountInfo,
+    shortfall: u64,
+) -> Result<u64, ProgramError> {
+    let Some(vault_account) = vault_account else {
+        return Ok(0);
+    };
+    
+    if !config.feature_enabled(FEATURE_VAULT_BACKSTOP) {
+        return Ok(0);
+    }
+    
+    if vault_available(vault_account)? < shortfall {
+        msg!("Program vault cannot cover the {} lamport shortfall", shortfall);
+        return Ok(0);
+    }
+    
+    move_lamports(vault_account, market_account, shortfall, TransferReason::VaultBackstop)?;
+    
+    Ok(shortfall)
+}
+
+/// Liquidity a market can pay `required` from: its balance above the rent floor, topped up
+/// first from the market's own insurance sub-fund and then from the program vault backstop.
+/// Top-ups are recorded as deposits in `market_state`'s ledger, which the caller saves.
+pub(crate) fn settlement_liquidity(
+    config: &GlobalConfig,
+    market_account: &AccountInfo,
+    market_state: &mut MarketState,
+    market_insurance_account: Option<&AccountInfo>,
+    vault_account: Option<&AccountInfo>,
+    required: u64,
+) -> Result<u64, ProgramError> {
+    let rent = Rent::get()?;
+    let mut market_available = market_account
+        .lamports()
+        .saturating_sub(rent.minimum_balance(market_account.data_len()));
+    
+    if market_available >= required {
+        return Ok(market_available);
+    }
+    
+    let insured = draw_market_insurance(market_insurance_account, market_account, required - market_available)?;
+    market_available = market_available.saturating_add(insured);
+    market_state.record_deposit(insured);
+    
+    if insured > 0 {
+        msg!("Market insurance draw: {} lamports", insured);
+    }
+    
+    if market_available >= required {
+        return Ok(market_available);
+    }
+    
+    let drawn = draw_vault_backstop(config, vault_account, market_account, required - market_available)?;
+    market_state.record_deposit(drawn);
+    
+    if drawn > 0 {
+        msg!("Program vault backstop: {} lamports", drawn);
+    }
+    
+    Ok(market_available.saturating_add(drawn))
+}
+
+pub(crate) fn process_deposit_program_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    vault_data: VaultTransferData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let vault_account = next_account_info(accounts_iter)?;
+    let depositor_account = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    
+    assert_signer(depositor_account)?;
+    
+    create_program_vault_if_needed(vault_account, depositor_account, system_program, program_id)?;
+    
+    invoke(
+        &system_instruction::transfer(
+            depositor_account.key,
+            vault_account.key,
+            vault_data.amount,
+        ),
+        &[
+            depositor_account.clone(),
+            vault_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+    
+    msg!("Program vault deposit: {} lamports", vault_data.amount);
+    
+    Ok(())
+}
+
+pub(crate) fn process_withdraw_program_vault(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    vault_data: VaultTransferData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let vault_account = next_account_info(accounts_iter)?;
+    let admin_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    
+    config.check_admin(admin_account)?;
+    
+    let (vault_pda, _vault_bump) = find_program_vault_address(program_id);
+    
+    if vault_account.key != &vault_pda || vault_account.owner != program_id {
+        msg!("Invalid program vault account");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    if vault_available(vault_account)? < vault_data.amount {
+        msg!("Insufficient program vault balance");
+        return Err(ProgramError::InsufficientFunds);
+    }
+    
+    move_lamports(vault_account, admin_account, vault_data.amount, TransferReason::VaultWithdrawal)?;
+    
+    msg!("Program vault withdrawal: {} lamports", vault_data.amount);
+    
+    Ok(())
+}
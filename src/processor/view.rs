@@ -0,0 +1,299 @@
+//! Read-only instructions that answer through return data.
+
+use borsh::BorshSerialize;
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::set_return_data,
+    sysvar::{rent::Rent, Sysvar},
+};
+
+use super::*;
+
+pub(crate) fn process_quote(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    initialize_data: InitializePositionData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter).ok();
+    
+    let config = load_config(config_account, program_id)?;
+    let fees = optional_market_fee_schedule(market_account, &initialize_data.market_mint, program_id)?;
+    let contract_type = market_account
+        .map(|market_account| load_market_state(market_account, program_id))
+        .transpose()?
+        .map_or(CONTRACT_TYPE_LINEAR, |market_state| market_state.contract_type);
+    let (leverage, open_fee, collateral, position_size) =
+        open_terms(initialize_data.paid_amount, initialize_data.leverage, FillRole::Taker, &fees);
+    
+    let estimated_entry_price = load_price_feed(
+        price_feed_account,
+        &initialize_data.market_mint,
+        &config,
+        program_id
+    )?.effective(initialize_data.direction, true);
+    
+    let quote = PositionQuote {
+        leverage,
+        open_fee,
+        collateral,
+        position_size,
+        estimated_entry_price,
+        estimated_liquidation_price: compute_liquidation_price(
+            estimated_entry_price,
+            position_size,
+            collateral,
+            initialize_data.direction,
+            contract_type,
+            config.maintenance_margin_bps,
+        ),
+    };
+    
+    let data = quote.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    set_return_data(&data);
+    
+    Ok(())
+}
+
+pub(crate) fn process_view_position(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    view_data: ViewPositionData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let market_account = next_account_info(accounts_iter).ok();
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let position = try_load_position_account(position_account)?;
+    let fees = optional_market_fee_schedule(market_account, &position.market_mint, program_id)?;
+    
+    let mark_price = if view_data.mark_price != 0 {
+        view_data.mark_price
+    } else {
+        load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?.effective(position.direction, false)
+    };
+    
+    let health = position
+        .health(mark_price, config.maintenance_margin_bps, 0)?
+        .net_of(position_accrued_charges(accounts, &position, program_id)?);
+    let profit = if health.pnl > 0 {
+        health.pnl.min(u64::MAX as i128) as u64
+    } else {
+        0
+    };
+    
+    let summary = PositionSummary {
+        position_nonce: position.position_nonce,
+        owner: position.owner,
+        market_mint: position.market_mint,
+        direction: position.direction,
+        leverage: position.leverage,
+        closed: position.closed,
+        confirmed: position.confirmed,
+        entry_price: position.entry_price,
+        liquidation_price: position.liquidation_price,
+        mark_price,
+        collateral: position.paid_amount,
+        notional: health.notional.min(u64::MAX as u128) as u64,
+        unrealized_pnl: health.pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        equity: health.equity.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        maintenance_requirement: health.maintenance.min(u64::MAX as u128) as u64,
+        health_bps: health.health_bps(),
+        profit_fee: trading_fee(profit, position.leverage, &position.fee_schedule(fees)),
+    };
+    
+    let data = summary.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    set_return_data(&data);
+    
+    Ok(())
+}
+
+pub(crate) fn process_view_health_factor(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    view_data: ViewHealthFactorData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let position_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    let price_feed_account = next_account_info(accounts_iter)?;
+    let margin_account = next_account_info(accounts_iter).ok();
+    
+    assert_owned_by(position_account, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let position = try_load_position_account(position_account)?;
+    
+    if position.position_nonce != view_data.position_nonce {
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    let mark_price = load_price_feed(price_feed_account, &position.market_mint, &config, program_id)?.effective(position.direction, false);
+    let shared_margin = match cross_margin_account(&position, margin_account, program_id)? {
+        Some(margin_account) => margin_available(margin_account)?,
+        None => 0,
+    };
+    let charges = position_accrued_charges(accounts, &position, program_id)?;
+    let health = position
+        .health(mark_price, config.maintenance_margin_bps, shared_margin)?
+        .net_of(charges);
+    // Unsettled charges eat into the collateral the stored liquidation price was computed from.
+    let effective_collateral = (position.paid_amount.saturating_add(shared_margin) as i128)
+        .saturating_sub(charges)
+        .clamp(0, u64::MAX as i128) as u64;
+    let liquidation_price = compute_liquidation_price(
+        position.entry_price,
+        position.position_size,
+        effective_collateral,
+        position.direction,
+        position.contract_type,
+        config.maintenance_margin_bps,
+    );
+    
+    let factor = HealthFactor {
+        position_nonce: position.position_nonce,
+        mark_price,
+        equity: health.equity.clamp(i64::MIN as i128, i64::MAX as i128) as i64,
+        maintenance_requirement: health.maintenance.min(u64::MAX as u128) as u64,
+        health_bps: health.health_bps(),
+        liquidatable: health.equity < health.maintenance as i128,
+        liquidation_price,
+        distance_bps: liquidation_distance_bps(mark_price, liquidation_price, position.direction),
+    };
+    
+    let data = factor.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    set_return_data(&data);
+    
+    Ok(())
+}
+
+pub(crate) fn process_view_lp_share_price(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    view_data: ViewLpSharePriceData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let market_account = next_account_info(accounts_iter)?;
+    let config_account = next_account_info(accounts_iter)?;
+    
+    verify_market_address(market_account, &view_data.market_mint, program_id)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let market_state = load_market_state(market_account, program_id)?;
+    let pool_value = lp_pool_value(market_account, &market_state)?;
+    
+    let price = LpSharePrice {
+        market_mint: view_data.market_mint,
+        pool_value,
+        total_shares: market_state.total_lp_shares,
+        protocol_shares: market_state.protocol_lp_shares,
+        share_price: lp_share_price(pool_value, market_state.total_lp_shares),
+        unclaimed_fees: market_state.unclaimed_lp_fees,
+        compounding: config.feature_enabled(FEATURE_LP_FEE_COMPOUNDING),
+    };
+    
+    let data = price.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    set_return_data(&data);
+    
+    Ok(())
+}
+
+/// Lamports a program-owned PDA holds above rent; zero while it is not initialized.
+fn pda_balance(account: &AccountInfo, expected: &Pubkey, program_id: &Pubkey) -> Result<u64, ProgramError> {
+    assert_key_eq(account, expected)?;
+    
+    if account.owner != program_id {
+        return Ok(0);
+    }
+    
+    Ok(account.lamports().saturating_sub(Rent::get()?.minimum_balance(account.data_len())))
+}
+
+/// Returns a `ProtocolRevenue` for dashboards. The authority, the fees wallet it names, the
+/// insurance fund, the fee vault and the program vault come first, then any number of market
+/// accounts each followed by its insurance PDA, whose statistics are summed.
+pub(crate) fn process_view_protocol_revenue(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let _authority_account = next_account_info(accounts_iter)?;
+    let treasury_account = next_account_info(accounts_iter)?;
+    let insurance_account = next_account_info(accounts_iter)?;
+    let fee_vault_account = next_account_info(accounts_iter)?;
+    let program_vault_account = next_account_info(accounts_iter)?;
+    
+    assert_key_eq(treasury_account, &load_authority(accounts, program_id)?.fees_wallet)?;
+    
+    let mut revenue = ProtocolRevenue {
+        treasury_balance: treasury_account.lamports(),
+        insurance_balance: pda_balance(insurance_account, &find_insurance_fund_address(program_id).0, program_id)?,
+        fee_vault_balance: pda_balance(fee_vault_account, &find_fee_vault_address(program_id).0, program_id)?,
+        program_vault_balance: pda_balance(program_vault_account, &find_program_vault_address(program_id).0, program_id)?,
+        ..ProtocolRevenue::default()
+    };
+    
+    while let Some(market_account) = accounts_iter.next() {
+        let market_insurance_account = next_account_info(accounts_iter)?;
+        let market_state = load_market_state(market_account, program_id)?;
+        
+        verify_market_address(market_account, &market_state.market_mint, program_id)?;
+        
+        revenue.markets = revenue.markets.saturating_add(1);
+        revenue.market_insurance_balance = revenue
+            .market_insurance_balance
+            .saturating_add(market_insurance_balance(market_insurance_account, &market_state.market_mint, program_id)?);
+        revenue.cumulative_volume = revenue.cumulative_volume.saturating_add(market_state.cumulative_volume);
+        revenue.cumulative_fees = revenue.cumulative_fees.saturating_add(market_state.cumulative_fees);
+        revenue.epoch_protocol_fees = revenue.epoch_protocol_fees.saturating_add(market_state.epoch_protocol_fees);
+        revenue.epoch_lp_fees = revenue.epoch_lp_fees.saturating_add(market_state.epoch_lp_fees);
+        revenue.unclaimed_lp_fees = revenue.unclaimed_lp_fees.saturating_add(market_state.unclaimed_lp_fees);
+        revenue.protocol_lp_shares = revenue.protocol_lp_shares.saturating_add(market_state.protocol_lp_shares);
+        revenue.bad_debt = revenue.bad_debt.saturating_add(market_state.bad_debt);
+    }
+    
+    let data = revenue.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    set_return_data(&data);
+    
+    Ok(())
+}
+
+/// Returns the owner's `PortfolioHealth`; the position index, the indexed positions, their price
+/// feeds and optionally the margin account follow the config account.
+pub(crate) fn process_view_portfolio_health(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    view_data: ViewPortfolioHealthData,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    
+    let config_account = next_account_info(accounts_iter)?;
+    
+    let config = load_config(config_account, program_id)?;
+    let portfolio = load_portfolio(accounts, &view_data.owner, &config, program_id)?;
+    let health = portfolio_health(
+        &portfolio,
+        find_shared_margin(accounts, &view_data.owner, program_id)?,
+        config.maintenance_margin_bps,
+    );
+    
+    let data = health.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+    set_return_data(&data);
+    
+    Ok(())
+}
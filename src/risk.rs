@@ -0,0 +1,112 @@
+//! Portfolio-margin risk engine: account-level equity and margin requirements across all of an
+//! owner's positions, with offsetting exposure on the same market netted.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::*;
+
+/// One position's contribution to its owner's portfolio at the current oracle price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortfolioPosition {
+    pub market_mint: Pubkey,
+    pub direction: i8,
+    pub notional: u128,
+    pub collateral: u64,
+    pub pnl: i128,
+    pub hedged: bool,
+}
+
+impl PortfolioPosition {
+    /// Prices `position` at `mark_price`; an unconfirmed position counts at its size with no PnL.
+    /// `hedged` positions are held in hedge mode and never netted.
+    pub fn from_position(position: &PositionAccount, mark_price: u64, hedged: bool) -> Result<Self, ProgramError> {
+        let pnl = if position.entry_price != 0 {
+            unrealized_pnl(position, mark_price)?
+        } else {
+            0
+        };
+        
+        Ok(Self {
+            market_mint: position.market_mint,
+            direction: position.direction,
+            notional: position.notional(mark_price),
+            collateral: position.paid_amount,
+            pnl,
+            hedged,
+        })
+    }
+}
+
+/// Account-level health. Equity is the shared margin plus every position's collateral and PnL;
+/// the maintenance requirement is charged on each market's net notional, so a long and a short
+/// on the same market only need margin for the difference. Positions held in hedge mode are
+/// charged on their full notional.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct PortfolioHealth {
+    pub positions: u32,
+    pub equity: i128,
+    pub gross_notional: u128,
+    pub net_notional: u128,
+    pub maintenance: u128,
+}
+
+impl PortfolioHealth {
+    /// Equity as a share of the netted maintenance requirement; `u64::MAX` with nothing at risk.
+    pub fn health_factor_bps(&self) -> u64 {
+        if self.maintenance == 0 {
+            return u64::MAX;
+        }
+        if self.equity <= 0 {
+            return 0;
+        }
+        
+        ((self.equity as u128).saturating_mul(BASIS_POINTS_DIVISOR as u128) / self.maintenance)
+            .min(u64::MAX as u128) as u64
+    }
+}
+
+/// Health of `positions` backed by `shared_margin`, the requirement charged at
+/// `maintenance_margin_bps` of the netted notional.
+pub fn portfolio_health(
+    positions: &[PortfolioPosition],
+    shared_margin: u64,
+    maintenance_margin_bps: u16,
+) -> PortfolioHealth {
+    let mut net_exposure: Vec<(Pubkey, i128)> = Vec::new();
+    let mut health = PortfolioHealth {
+        positions: positions.len() as u32,
+        equity: shared_margin as i128,
+        ..PortfolioHealth::default()
+    };
+    
+    for position in positions {
+        health.equity = health
+            .equity
+            .saturating_add(position.collateral as i128)
+            .saturating_add(position.pnl);
+        health.gross_notional = health.gross_notional.saturating_add(position.notional);
+        
+        if position.hedged {
+            health.net_notional = health.net_notional.saturating_add(position.notional);
+            continue;
+        }
+        
+        let signed = (position.notional.min(i128::MAX as u128) as i128).saturating_mul(position.direction as i128);
+        match net_exposure.iter_mut().find(|(mint, _)| mint == &position.market_mint) {
+            Some((_, exposure)) => *exposure = exposure.saturating_add(signed),
+            None => net_exposure.push((position.market_mint, signed)),
+        }
+    }
+    
+    health.net_notional = net_exposure
+        .iter()
+        .fold(health.net_notional, |total, (_, exposure)| total.saturating_add(exposure.unsigned_abs()));
+    health.maintenance = health
+        .net_notional
+        .saturating_mul(maintenance_margin_bps as u128)
+        / BASIS_POINTS_DIVISOR as u128;
+    
+    health
+}
@@ -0,0 +1,294 @@
+//! Borsh schema of every account, instruction payload and view return type, for clients in
+//! other languages to derive their codecs from. `export-schema` writes it as JSON.
+
+use std::collections::{BTreeMap, HashMap};
+
+use borsh::schema::{Declaration, Definition, Fields};
+use borsh::BorshSchema;
+
+use solana_program::pubkey::Pubkey;
+
+use crate::*;
+
+/// Root types by group, and every definition they reach.
+pub struct ProgramSchema {
+    pub accounts: Vec<Declaration>,
+    pub instructions: Vec<Declaration>,
+    pub returns: Vec<Declaration>,
+    pub definitions: BTreeMap<Declaration, Definition>,
+}
+
+macro_rules! roots {
+    ($definitions:ident, $($root:ty),* $(,)?) => {
+        vec![$({
+            <$root as BorshSchema>::add_definitions_recursively(&mut $definitions);
+            <$root as BorshSchema>::declaration()
+        }),*]
+    };
+}
+
+/// Written out rather than derived: the derive declares a struct per data-carrying variant that
+/// is never read, which trips `dead_code`.
+impl BorshSchema for PriceSource {
+    fn declaration() -> Declaration {
+        "PriceSource".to_string()
+    }
+    
+    fn add_definitions_recursively(definitions: &mut HashMap<Declaration, Definition>) {
+        let variants: [(&str, Vec<(&str, Declaration)>); 4] = [
+            ("OperatorAttested", vec![]),
+            ("Pyth", vec![("feed", Pubkey::declaration())]),
+            ("Switchboard", vec![("aggregator", Pubkey::declaration())]),
+            ("Fixed", vec![("price", u64::declaration())]),
+        ];
+        
+        let mut declared = Vec::new();
+        
+        for (variant, fields) in variants {
+            let declaration = format!("PriceSource{}", variant);
+            let fields = if fields.is_empty() {
+                Fields::Empty
+            } else {
+                Fields::NamedFields(fields.into_iter().map(|(name, field)| (name.to_string(), field)).collect())
+            };
+            
+            Self::add_definition(declaration.clone(), Definition::Struct { fields }, definitions);
+            declared.push((variant.to_string(), declaration));
+        }
+        
+        Self::add_definition(Self::declaration(), Definition::Enum { variants: declared }, definitions);
+        Pubkey::add_definitions_recursively(definitions);
+    }
+}
+
+pub fn program_schema() -> ProgramSchema {
+    let mut definitions = HashMap::new();
+    
+    let accounts = roots!(
+        definitions,
+        PositionAccount,
+        PositionAccountV0,
+        GlobalConfig,
+        RebalanceTarget,
+        FeeRoute,
+        MarketState,
+        LeverageTier,
+        SymbolRegistryAccount,
+        AttestationAccount,
+        FreezeAccount,
+        AuthorityAccount,
+        CorruptionMarkAccount,
+        HistoryRecord,
+        PositionHistoryAccount,
+        BlacklistEntry,
+        BlacklistAccount,
+        IntegratorAccount,
+        PriceSource,
+        PriceFeedAccount,
+        Order,
+        CloseOrderAccount,
+        ScheduledOrderAccount,
+        DcaScheduleAccount,
+        TwapScheduleAccount,
+        EpochSnapshotAccount,
+        CompressedPosition,
+        PositionTreeAccount,
+        LeadTraderAccount,
+        FollowerAccount,
+        CopyLinkAccount,
+        ManagedVaultAccount,
+        ManagedVaultPosition,
+        VaultDepositorAccount,
+        HedgeModeAccount,
+        VaultRequestAccount,
+        VaultEpochAccount,
+        OrderBookSide,
+        MarginAccount,
+        LpPositionAccount,
+        PositionIndexAccount,
+        LiquidationQueueEntry,
+        LiquidationQueueAccount,
+        UserStatsAccount,
+        NonceCounterAccount,
+    );
+    let instructions = roots!(
+        definitions,
+        InitializePositionData,
+        DexModifyData,
+        UserModifyData,
+        ProcessPnlData,
+        MarketTransferData,
+        RebalanceLeg,
+        RebalanceData,
+        RebalanceTargetsData,
+        FeeRoutesData,
+        WithdrawFeesData,
+        ScheduleShutdownData,
+        ConfigData,
+        FeatureFlagsData,
+        MarketFeesData,
+        PlaceOrderData,
+        CancelOrderData,
+        MatchOrdersData,
+        FundingData,
+        BorrowCurveData,
+        MarketSpreadData,
+        LeverageTiersData,
+        MinHoldingData,
+        MarketLiquidityCapData,
+        MarketOwnerNotionalCapData,
+        SettlementAssetData,
+        SwapData,
+        ContractTypeData,
+        FundingParamsData,
+        BootstrapLiquidityData,
+        LiquidityDepositData,
+        LiquidityWithdrawData,
+        ClaimLpFeesData,
+        ViewLpSharePriceData,
+        ClaimResidualProfitData,
+        RealizeProfitData,
+        StopOutData,
+        GuaranteedStopData,
+        TriggerGuaranteedStopData,
+        PlaceCloseOrderData,
+        CloseOrderData,
+        PlaceScheduledOrderData,
+        ScheduledOrderData,
+        CreateTwapOpenData,
+        TwapScheduleData,
+        EpochRolloverData,
+        VerifyInvariantsData,
+        PositionTreeData,
+        OpenCompressedPositionData,
+        CompressedCollateralData,
+        CloseCompressedPositionData,
+        TwapThresholdData,
+        CreateDcaScheduleData,
+        DcaScheduleData,
+        RegisterLeadTraderData,
+        FollowLeadTraderData,
+        UnfollowLeadTraderData,
+        CopyTradeData,
+        CreateManagedVaultData,
+        ManagedVaultDepositData,
+        ManagedVaultWithdrawData,
+        ManagedVaultEpochData,
+        ViewPortfolioHealthData,
+        ManagedVaultOpenData,
+        ManagedVaultCloseData,
+        InitMarketData,
+        ListMarketData,
+        MarketListingData,
+        DelistMarketData,
+        SettleDelistedData,
+        CloseMarketData,
+        MigrateMarketData,
+        AuthorityData,
+        SetOperatorData,
+        MigratePositionMarketData,
+        MarketInsuranceFundData,
+        MarginTransferData,
+        VaultTransferData,
+        PriceFeedData,
+        SetFallbackOracleData,
+        SetPriceSourceData,
+        SyncPriceFeedData,
+        CancelPositionData,
+        ExpirePositionData,
+        ViewPositionData,
+        ViewHealthFactorData,
+        LiquidateData,
+        QueueLiquidationData,
+        MarginCallData,
+        TakeoverData,
+        RegisterSymbolData,
+        NetPositionsData,
+        SetHedgeModeData,
+        RegisterIntegratorData,
+        IssueAttestationData,
+        RevokeAttestationData,
+        FreezeData,
+        BlacklistData,
+    );
+    let returns = roots!(
+        definitions,
+        PositionSummary,
+        HealthFactor,
+        PositionQuote,
+        LpSharePrice,
+        PortfolioHealth,
+        ProtocolRevenue,
+    );
+    
+    ProgramSchema {
+        accounts,
+        instructions,
+        returns,
+        definitions: definitions.into_iter().collect(),
+    }
+}
+
+impl ProgramSchema {
+    /// The schema as a JSON document with `accounts`, `instructions` and `returns` listing the
+    /// root declarations and `definitions` describing every non-primitive type by `kind`.
+    pub fn to_json(&self) -> String {
+        let definitions = self
+            .definitions
+            .iter()
+            .map(|(declaration, definition)| format!("    {}: {}", json_string(declaration), definition_json(definition)))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        
+        format!(
+            "{{\n  \"accounts\": {},\n  \"instructions\": {},\n  \"returns\": {},\n  \"definitions\": {{\n{}\n  }}\n}}\n",
+            json_list(&self.accounts),
+            json_list(&self.instructions),
+            json_list(&self.returns),
+            definitions
+        )
+    }
+}
+
+fn definition_json(definition: &Definition) -> String {
+    match definition {
+        Definition::Array { length, elements } => {
+            format!("{{\"kind\": \"array\", \"length\": {}, \"elements\": {}}}", length, json_string(elements))
+        },
+        Definition::Sequence { elements } => {
+            format!("{{\"kind\": \"sequence\", \"elements\": {}}}", json_string(elements))
+        },
+        Definition::Tuple { elements } => {
+            format!("{{\"kind\": \"tuple\", \"elements\": {}}}", json_list(elements))
+        },
+        Definition::Enum { variants } => {
+            format!("{{\"kind\": \"enum\", \"variants\": {}}}", json_pairs(variants))
+        },
+        Definition::Struct { fields: Fields::NamedFields(fields) } => {
+            format!("{{\"kind\": \"struct\", \"fields\": {}}}", json_pairs(fields))
+        },
+        Definition::Struct { fields: Fields::UnnamedFields(fields) } => {
+            format!("{{\"kind\": \"tuple_struct\", \"fields\": {}}}", json_list(fields))
+        },
+        Definition::Struct { fields: Fields::Empty } => "{\"kind\": \"struct\", \"fields\": []}".to_string(),
+    }
+}
+
+fn json_pairs(pairs: &[(String, Declaration)]) -> String {
+    let entries = pairs
+        .iter()
+        .map(|(name, declaration)| format!("[{}, {}]", json_string(name), json_string(declaration)))
+        .collect::<Vec<_>>();
+    
+    format!("[{}]", entries.join(", "))
+}
+
+fn json_list(items: &[Declaration]) -> String {
+    let entries = items.iter().map(|item| json_string(item)).collect::<Vec<_>>();
+    
+    format!("[{}]", entries.join(", "))
+}
+
+fn json_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,674 @@
+//! Deterministic off-chain replay. `Simulator` keeps accounts in memory, serializes them into
+//! the same aligned input buffer the runtime hands the program, and runs each instruction
+//! through `process_instruction` unchanged, so a recorded sequence of instruction datas
+//! reproduces the on-chain outcome exactly.
+//!
+//! The clock and rent sysvars come from the simulator, which only advances the slot when told
+//! to. System program CPIs (`CreateAccount`, `Transfer`, `Allocate`, `Assign`) are executed
+//! in-process with signer seeds checked against this program; any other CPI fails, so only
+//! natively settled markets can be replayed. As in the runtime, a failed instruction leaves
+//! every account untouched, and a successful one must conserve lamports and leave read-only
+//! accounts alone.
+//!
+//! Syscall stubs are process-wide; the simulator state behind them is per thread, so tests
+//! may run simulators in parallel.
+
+use std::{cell::RefCell, collections::BTreeMap, mem::size_of, ptr, sync::Once};
+
+use borsh::BorshDeserialize;
+use solana_program::{
+    account_info::AccountInfo,
+    clock::{Clock, Slot},
+    entrypoint::{deserialize, ProgramResult, BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER, SUCCESS},
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    program_utils::limited_deserialize,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::SystemInstruction,
+    system_program,
+};
+use thiserror::Error;
+
+use crate::DexEvent;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimAccount {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+impl SimAccount {
+    /// A system-owned wallet holding `lamports`.
+    pub fn wallet(lamports: u64) -> Self {
+        SimAccount {
+            lamports,
+            owner: system_program::id(),
+            ..SimAccount::default()
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SimError {
+    #[error("Instruction failed: {0}")]
+    Program(#[from] ProgramError),
+    #[error("Instruction targets program {0}, not this one")]
+    UnknownProgram(Pubkey),
+    #[error("Lamports not conserved: {before} before, {after} after")]
+    LamportsNotConserved { before: u128, after: u128 },
+    #[error("Read-only account {0} was modified")]
+    ReadOnlyModified(Pubkey),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SimStep {
+    Instruction(Instruction),
+    WarpToSlot(Slot),
+}
+
+/// The step a replay stopped at and why.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Step {index} failed: {error}")]
+pub struct SimFailure {
+    pub index: usize,
+    pub error: SimError,
+}
+
+#[derive(Default)]
+struct SimContext {
+    clock: Clock,
+    rent: Rent,
+    logs: Vec<String>,
+    data: Vec<Vec<u8>>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<SimContext> = RefCell::new(SimContext::default());
+}
+
+static INSTALL_STUBS: Once = Once::new();
+
+struct SimStubs;
+
+impl SyscallStubs for SimStubs {
+    fn sol_log(&self, message: &str) {
+        CONTEXT.with(|context| context.borrow_mut().logs.push(message.to_string()));
+    }
+    
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        CONTEXT.with(|context| {
+            context.borrow_mut().data.extend(fields.iter().map(|field| field.to_vec()));
+        });
+    }
+    
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = CONTEXT.with(|context| context.borrow().clock.clone());
+        
+        unsafe { ptr::write_unaligned(var_addr as *mut Clock, clock) };
+        
+        SUCCESS
+    }
+    
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let rent = CONTEXT.with(|context| context.borrow().rent);
+        
+        unsafe { ptr::write_unaligned(var_addr as *mut Rent, rent) };
+        
+        SUCCESS
+    }
+    
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        if instruction.program_id != system_program::id() {
+            self.sol_log(&format!("Simulator: CPI to {} is not supported", instruction.program_id));
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        
+        let mut accounts = Vec::with_capacity(instruction.accounts.len());
+        
+        for meta in &instruction.accounts {
+            let info = account_infos
+                .iter()
+                .find(|info| info.key == &meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            
+            let signed_by_program = signers_seeds.iter().any(|seeds| {
+                Pubkey::create_program_address(seeds, &crate::id()).ok() == Some(meta.pubkey)
+            });
+            
+            if meta.is_signer && !info.is_signer && !signed_by_program {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            
+            accounts.push(info);
+        }
+        
+        let system_instruction: SystemInstruction = limited_deserialize(&instruction.data, 1232)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        
+        match system_instruction {
+            SystemInstruction::CreateAccount { lamports, space, owner } => {
+                let (from, to) = (accounts.first(), accounts.get(1));
+                let (from, to) = (from.ok_or(ProgramError::NotEnoughAccountKeys)?, to.ok_or(ProgramError::NotEnoughAccountKeys)?);
+                
+                if to.lamports() > 0 || to.data_len() > 0 || to.owner != &system_program::id() {
+                    return Err(ProgramError::AccountAlreadyInitialized);
+                }
+                
+                system_transfer(from, to, lamports)?;
+                to.realloc(space as usize, true)?;
+                to.assign(&owner);
+            }
+            SystemInstruction::Transfer { lamports } => {
+                let (from, to) = (accounts.first(), accounts.get(1));
+                let (from, to) = (from.ok_or(ProgramError::NotEnoughAccountKeys)?, to.ok_or(ProgramError::NotEnoughAccountKeys)?);
+                
+                system_transfer(from, to, lamports)?;
+            }
+            SystemInstruction::Allocate { space } => {
+                let account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+                
+                if account.data_len() > 0 || account.owner != &system_program::id() {
+                    return Err(ProgramError::AccountAlreadyInitialized);
+                }
+                
+                account.realloc(space as usize, true)?;
+            }
+            SystemInstruction::Assign { owner } => {
+                let account = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+                
+                if account.owner != &system_program::id() {
+                    return Err(ProgramError::IllegalOwner);
+                }
+                
+                account.assign(&owner);
+            }
+            _ => {
+                self.sol_log("Simulator: unsupported system instruction");
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+fn system_transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if from.owner != &system_program::id() || from.data_len() > 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    
+    **from.lamports.borrow_mut() = from.lamports().checked_sub(lamports).ok_or(ProgramError::InsufficientFunds)?;
+    **to.lamports.borrow_mut() = to.lamports().checked_add(lamports).ok_or(ProgramError::ArithmeticOverflow)?;
+    
+    Ok(())
+}
+
+/// Accounts referenced by an instruction, deduplicated with their privileges merged the way
+/// a compiled message merges them.
+fn unique_accounts(instruction: &Instruction) -> Vec<(Pubkey, bool, bool)> {
+    let mut unique: Vec<(Pubkey, bool, bool)> = Vec::new();
+    
+    for meta in &instruction.accounts {
+        match unique.iter_mut().find(|(key, _, _)| key == &meta.pubkey) {
+            Some((_, is_signer, is_writable)) => {
+                *is_signer |= meta.is_signer;
+                *is_writable |= meta.is_writable;
+            }
+            None => unique.push((meta.pubkey, meta.is_signer, meta.is_writable)),
+        }
+    }
+    
+    unique
+}
+
+/// Reads the accounts back out of the input buffer, as the runtime does: the recorded data
+/// length (which `realloc` updates) is authoritative, not the length of the `AccountInfo`
+/// slices, which Borsh writes advance.
+fn read_output(input: &[u64], count: usize) -> Vec<SimAccount> {
+    let bytes = unsafe { std::slice::from_raw_parts(input.as_ptr() as *const u8, std::mem::size_of_val(input)) };
+    let read_u64 = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+    let mut accounts = Vec::with_capacity(count);
+    let mut offset = size_of::<u64>();
+    
+    while accounts.len() < count {
+        if bytes[offset] != NON_DUP_MARKER {
+            offset += size_of::<u64>();
+            continue;
+        }
+        
+        let executable = bytes[offset + 3] != 0;
+        let original_data_len = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        offset += size_of::<u64>() + size_of::<Pubkey>();
+        
+        let owner = Pubkey::new_from_array(bytes[offset..offset + 32].try_into().unwrap());
+        let lamports = read_u64(offset + 32);
+        let data_len = read_u64(offset + 40) as usize;
+        offset += size_of::<Pubkey>() + 2 * size_of::<u64>();
+        
+        accounts.push(SimAccount {
+            lamports,
+            data: bytes[offset..offset + data_len].to_vec(),
+            owner,
+            executable,
+        });
+        
+        offset += original_data_len + MAX_PERMITTED_DATA_INCREASE;
+        offset = offset.next_multiple_of(BPF_ALIGN_OF_U128) + size_of::<u64>();
+    }
+    
+    accounts
+}
+
+pub struct Simulator {
+    accounts: BTreeMap<Pubkey, SimAccount>,
+    clock: Clock,
+    rent: Rent,
+    logs: Vec<String>,
+    events: Vec<DexEvent>,
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Simulator {
+    pub fn new() -> Self {
+        INSTALL_STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(SimStubs));
+        });
+        
+        Simulator {
+            accounts: BTreeMap::new(),
+            clock: Clock::default(),
+            rent: Rent::default(),
+            logs: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+    
+    pub fn set_account(&mut self, key: Pubkey, account: SimAccount) {
+        self.accounts.insert(key, account);
+    }
+    
+    /// The account at `key`; keys never written read as empty system accounts.
+    pub fn account(&self, key: &Pubkey) -> SimAccount {
+        self.accounts.get(key).cloned().unwrap_or_else(|| SimAccount::wallet(0))
+    }
+    
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+    
+    pub fn clock_mut(&mut self) -> &mut Clock {
+        &mut self.clock
+    }
+    
+    pub fn rent_mut(&mut self) -> &mut Rent {
+        &mut self.rent
+    }
+    
+    pub fn warp_to_slot(&mut self, slot: Slot) {
+        self.clock.slot = slot;
+    }
+    
+    /// `msg!` output of every instruction processed so far, failed ones included.
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+    
+    /// Events emitted by instructions that succeeded.
+    pub fn events(&self) -> &[DexEvent] {
+        &self.events
+    }
+    
+    /// Runs `steps` in order, stopping at the first failing instruction.
+    pub fn replay(&mut self, steps: &[SimStep]) -> Result<(), SimFailure> {
+        for (index, step) in steps.iter().enumerate() {
+            match step {
+                SimStep::Instruction(instruction) => {
+                    self.process(instruction).map_err(|error| SimFailure { index, error })?;
+                }
+                SimStep::WarpToSlot(slot) => self.warp_to_slot(*slot),
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Runs one instruction and commits its account changes only if it succeeds.
+    pub fn process(&mut self, instruction: &Instruction) -> Result<(), SimError> {
+        if instruction.program_id != crate::id() {
+            return Err(SimError::UnknownProgram(instruction.program_id));
+        }
+        
+        let unique = unique_accounts(instruction);
+        let before: Vec<SimAccount> = unique.iter().map(|(key, _, _)| self.account(key)).collect();
+        let mut input = self.serialize_input(instruction, &unique, &before);
+        
+        CONTEXT.with(|context| {
+            *context.borrow_mut() = SimContext {
+                clock: self.clock.clone(),
+                rent: self.rent,
+                ..SimContext::default()
+            };
+        });
+        
+        let outcome = {
+            let (program_id, account_infos, instruction_data) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+            
+            crate::process_instruction(program_id, &account_infos, instruction_data)
+        };
+        
+        let context = CONTEXT.with(|context| std::mem::take(&mut *context.borrow_mut()));
+        self.logs.extend(context.logs);
+        
+        outcome?;
+        
+        let after = read_output(&input, unique.len());
+        
+        let lamports_before: u128 = before.iter().map(|account| account.lamports as u128).sum();
+        let lamports_after: u128 = after.iter().map(|account| account.lamports as u128).sum();
+        
+        if lamports_before != lamports_after {
+            return Err(SimError::LamportsNotConserved { before: lamports_before, after: lamports_after });
+        }
+        
+        for ((key, _, is_writable), (old, new)) in unique.iter().zip(before.iter().zip(after.iter())) {
+            if !is_writable && old != new {
+                return Err(SimError::ReadOnlyModified(*key));
+            }
+        }
+        
+        for ((key, _, _), account) in unique.iter().zip(after) {
+            self.accounts.insert(*key, account);
+        }
+        
+        self.events.extend(context.data.iter().filter_map(|data| DexEvent::try_from_slice(data).ok()));
+        
+        Ok(())
+    }
+    
+    /// The runtime's aligned input layout, in a `u64` buffer so that offsets stay aligned.
+    fn serialize_input(&self, instruction: &Instruction, unique: &[(Pubkey, bool, bool)], accounts: &[SimAccount]) -> Vec<u64> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(instruction.accounts.len() as u64).to_le_bytes());
+        
+        let mut serialized: Vec<Pubkey> = Vec::new();
+        
+        for meta in &instruction.accounts {
+            if let Some(position) = serialized.iter().position(|key| key == &meta.pubkey) {
+                bytes.push(position as u8);
+                bytes.extend_from_slice(&[0; 7]);
+                serialized.push(meta.pubkey);
+                continue;
+            }
+            
+            let index = unique.iter().position(|(key, _, _)| key == &meta.pubkey).unwrap();
+            let (key, is_signer, is_writable) = unique[index];
+            let account = &accounts[index];
+            
+            bytes.push(NON_DUP_MARKER);
+            bytes.push(is_signer as u8);
+            bytes.push(is_writable as u8);
+            bytes.push(account.executable as u8);
+            bytes.extend_from_slice(&[0; size_of::<u32>()]);
+            bytes.extend_from_slice(key.as_ref());
+            bytes.extend_from_slice(account.owner.as_ref());
+            bytes.extend_from_slice(&account.lamports.to_le_bytes());
+            bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&account.data);
+            bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            bytes.resize(bytes.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+            bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+            serialized.push(meta.pubkey);
+        }
+        
+        bytes.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&instruction.data);
+        bytes.extend_from_slice(instruction.program_id.as_ref());
+        
+        let mut words = vec![0u64; bytes.len().div_ceil(size_of::<u64>())];
+        
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), words.as_mut_ptr() as *mut u8, bytes.len()) };
+        
+        words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::instruction::AccountMeta;
+    
+    use super::*;
+    use crate::{
+        find_authority_address, find_blacklist_address, find_config_address, find_freeze_address, find_lp_position_address,
+        find_margin_address, find_market_address, AuthorityAccount, ConfigData, DexError, GlobalConfig,
+        LiquidityDepositData, LiquidityWithdrawData, LpPositionAccount, MarginTransferData, MarketState,
+        ScheduleShutdownData, CONFIG_ACCOUNT_VERSION, INSTRUCTION_DEPOSIT_LIQUIDITY, INSTRUCTION_DEPOSIT_MARGIN,
+        INSTRUCTION_INITIALIZE_CONFIG, INSTRUCTION_SCHEDULE_SHUTDOWN, INSTRUCTION_WITHDRAW_LIQUIDITY,
+        MARKET_STATUS_ACTIVE, SHUTDOWN_TIMELOCK_SLOTS,
+    };
+    
+    const DEX_AUTHORITY: Pubkey = Pubkey::new_from_array([1; 32]);
+    
+    #[test]
+    fn replays_config_initialization_and_rolls_back_failures() {
+        let mut sim = Simulator::new();
+        sim.set_account(DEX_AUTHORITY, SimAccount::wallet(10_000_000_000));
+        
+        let (authority_pda, _authority_bump) = find_authority_address(&crate::id());
+        let authority = AuthorityAccount { admin: DEX_AUTHORITY, dex_authority: DEX_AUTHORITY, ..AuthorityAccount::default() };
+        sim.set_account(authority_pda, SimAccount {
+            lamports: Rent::default().minimum_balance(AuthorityAccount::LEN),
+            data: borsh::to_vec(&authority).unwrap(),
+            owner: crate::id(),
+            executable: false,
+        });
+        
+        let zeros = [0u8; 1024];
+        let config_data = ConfigData::deserialize(&mut &zeros[..]).unwrap();
+        let mut data = vec![INSTRUCTION_INITIALIZE_CONFIG];
+        data.extend(borsh::to_vec(&config_data).unwrap());
+        
+        let (config_pda, _config_bump) = find_config_address(&crate::id());
+        let instruction = Instruction::new_with_bytes(
+            crate::id(),
+            &data,
+            vec![
+                AccountMeta::new(config_pda, false),
+                AccountMeta::new(DEX_AUTHORITY, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(authority_pda, false),
+            ],
+        );
+        
+        sim.replay(&[SimStep::WarpToSlot(100), SimStep::Instruction(instruction.clone())]).unwrap();
+        
+        let config_account = sim.account(&config_pda);
+        let config = GlobalConfig::deserialize(&mut &config_account.data[..]).unwrap();
+        
+        assert_eq!(config_account.owner, crate::id());
+        assert_eq!(config_account.lamports, Rent::default().minimum_balance(config_account.data.len()));
+        assert_eq!(config.admin, DEX_AUTHORITY);
+        assert_eq!(sim.account(&DEX_AUTHORITY).lamports, 10_000_000_000 - config_account.lamports);
+        
+        sim.replay(&[SimStep::Instruction(instruction.clone())]).unwrap();
+        
+        assert_eq!(sim.account(&config_pda), config_account);
+        assert!(sim.logs().iter().any(|log| log == "Config already initialized"));
+        
+        let mut conflicting = instruction;
+        conflicting.data = vec![INSTRUCTION_INITIALIZE_CONFIG];
+        conflicting.data.extend(borsh::to_vec(&ConfigData { dust_threshold: 1, ..config_data }).unwrap());
+        
+        let failure = sim.replay(&[SimStep::Instruction(conflicting)]).unwrap_err();
+        
+        assert_eq!(failure.index, 0);
+        assert_eq!(failure.error, SimError::Program(ProgramError::AccountAlreadyInitialized));
+        assert_eq!(sim.account(&config_pda), config_account);
+        assert!(sim.logs().iter().any(|log| log == "Config already initialized with different parameters"));
+        
+        let schedule = |cancel: bool| {
+            let mut data = vec![INSTRUCTION_SCHEDULE_SHUTDOWN];
+            data.extend(borsh::to_vec(&ScheduleShutdownData { cancel }).unwrap());
+            Instruction::new_with_bytes(
+                crate::id(),
+                &data,
+                vec![
+                    AccountMeta::new(config_pda, false),
+                    AccountMeta::new(DEX_AUTHORITY, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+            )
+        };
+        let shutdown_slot = |sim: &Simulator| GlobalConfig::deserialize(&mut &sim.account(&config_pda).data[..]).unwrap().shutdown_slot;
+        
+        sim.replay(&[SimStep::Instruction(schedule(false)), SimStep::Instruction(schedule(true))]).unwrap();
+        
+        assert_eq!(shutdown_slot(&sim), 0);
+        
+        sim.replay(&[SimStep::Instruction(schedule(false)), SimStep::WarpToSlot(100 + SHUTDOWN_TIMELOCK_SLOTS)]).unwrap();
+        
+        assert_eq!(shutdown_slot(&sim), 100 + SHUTDOWN_TIMELOCK_SLOTS);
+        
+        let failure = sim.replay(&[SimStep::Instruction(schedule(true))]).unwrap_err();
+        
+        assert_eq!(failure.error, SimError::Program(DexError::ProgramShutdown.into()));
+        assert_eq!(shutdown_slot(&sim), 100 + SHUTDOWN_TIMELOCK_SLOTS);
+    }
+    
+    #[test]
+    fn shutdown_rejects_gated_instructions_once_in_effect() {
+        let mut sim = Simulator::new();
+        let owner = Pubkey::new_unique();
+        sim.set_account(DEX_AUTHORITY, SimAccount::wallet(10_000_000_000));
+        sim.set_account(owner, SimAccount::wallet(10_000_000_000));
+        
+        let (config_pda, _config_bump) = find_config_address(&crate::id());
+        let mut config_data = borsh::to_vec(&GlobalConfig {
+            version: CONFIG_ACCOUNT_VERSION,
+            admin: DEX_AUTHORITY,
+            ..GlobalConfig::default()
+        })
+        .unwrap();
+        config_data.resize(GlobalConfig::LEN, 0);
+        sim.set_account(config_pda, SimAccount {
+            lamports: Rent::default().minimum_balance(GlobalConfig::LEN),
+            data: config_data,
+            owner: crate::id(),
+            executable: false,
+        });
+        
+        let mut data = vec![INSTRUCTION_SCHEDULE_SHUTDOWN];
+        data.extend(borsh::to_vec(&ScheduleShutdownData { cancel: false }).unwrap());
+        let schedule = Instruction::new_with_bytes(
+            crate::id(),
+            &data,
+            vec![
+                AccountMeta::new(config_pda, false),
+                AccountMeta::new(DEX_AUTHORITY, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        
+        let (margin_pda, _margin_bump) = find_margin_address(&owner, &crate::id());
+        let mut data = vec![INSTRUCTION_DEPOSIT_MARGIN];
+        data.extend(borsh::to_vec(&MarginTransferData { amount: 1_000_000 }).unwrap());
+        let deposit = Instruction::new_with_bytes(
+            crate::id(),
+            &data,
+            vec![
+                AccountMeta::new(margin_pda, false),
+                AccountMeta::new(owner, true),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(config_pda, false),
+            ],
+        );
+        let mut without_config = deposit.clone();
+        without_config.accounts.pop();
+        
+        sim.replay(&[
+            SimStep::WarpToSlot(100),
+            SimStep::Instruction(schedule),
+            SimStep::Instruction(deposit.clone()),
+        ])
+        .unwrap();
+        
+        let failure = sim.replay(&[SimStep::Instruction(without_config)]).unwrap_err();
+        
+        assert_eq!(failure.error, SimError::Program(ProgramError::NotEnoughAccountKeys));
+        
+        let margin_account = sim.account(&margin_pda);
+        let failure = sim
+            .replay(&[SimStep::WarpToSlot(100 + SHUTDOWN_TIMELOCK_SLOTS), SimStep::Instruction(deposit)])
+            .unwrap_err();
+        
+        assert_eq!(failure.error, SimError::Program(DexError::ProgramShutdown.into()));
+        assert_eq!(sim.account(&margin_pda), margin_account);
+    }
+    
+    #[test]
+    fn first_lp_deposit_cannot_claim_existing_liquidity() {
+        let mut sim = Simulator::new();
+        let provider = Pubkey::new_unique();
+        let market_mint = Pubkey::new_unique();
+        let pool = 10_000_000_000;
+        sim.set_account(provider, SimAccount::wallet(1_000_000_000));
+        
+        let (market_pda, _market_bump) = find_market_address(&market_mint, &crate::id());
+        let mut market_data = borsh::to_vec(&MarketState { market_mint, status: MARKET_STATUS_ACTIVE, ..MarketState::default() }).unwrap();
+        market_data.resize(MarketState::LEN, 0);
+        sim.set_account(market_pda, SimAccount {
+            lamports: Rent::default().minimum_balance(MarketState::LEN) + pool,
+            data: market_data,
+            owner: crate::id(),
+            executable: false,
+        });
+        
+        let (lp_position_pda, _lp_position_bump) = find_lp_position_address(&market_mint, &provider, &crate::id());
+        let (config_pda, _config_bump) = find_config_address(&crate::id());
+        let lp_instruction = |tag: u8, data: Vec<u8>, compliance: Pubkey| {
+            Instruction::new_with_bytes(
+                crate::id(),
+                &[vec![tag], data].concat(),
+                vec![
+                    AccountMeta::new(lp_position_pda, false),
+                    AccountMeta::new(market_pda, false),
+                    AccountMeta::new(provider, true),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new_readonly(compliance, false),
+                    AccountMeta::new_readonly(config_pda, false),
+                ],
+            )
+        };
+        let (blacklist_pda, _blacklist_bump) = find_blacklist_address(&crate::id());
+        let deposit = LiquidityDepositData { market_mint, amount: 1 };
+        
+        sim.process(&lp_instruction(INSTRUCTION_DEPOSIT_LIQUIDITY, borsh::to_vec(&deposit).unwrap(), blacklist_pda)).unwrap();
+        
+        let market = MarketState::deserialize(&mut &sim.account(&market_pda).data[..]).unwrap();
+        let lp_position = LpPositionAccount::deserialize(&mut &sim.account(&lp_position_pda).data[..]).unwrap();
+        
+        assert_eq!(market.protocol_lp_shares, pool);
+        assert_eq!(lp_position.shares, 1);
+        
+        let market_lamports = sim.account(&market_pda).lamports;
+        let (freeze_pda, _freeze_bump) = find_freeze_address(&provider, &crate::id());
+        let withdraw = LiquidityWithdrawData { market_mint, shares: lp_position.shares };
+        
+        sim.process(&lp_instruction(INSTRUCTION_WITHDRAW_LIQUIDITY, borsh::to_vec(&withdraw).unwrap(), freeze_pda)).unwrap();
+        
+        assert!(market_lamports - sim.account(&market_pda).lamports <= deposit.amount);
+    }
+}
@@ -0,0 +1,1929 @@
+//! Account layouts owned by the program and the methods that read them.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    keccak,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::*;
+
+/// New fields must be appended at the end and treat zero as their default, so that
+/// accounts written by an older version can be grown in place with `RESIZE_POSITION`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionAccount {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub market_symbol: [u8; MAX_SYMBOL_LENGTH],
+    pub entry_price: u64,
+    pub liquidation_price: u64,
+    pub paid_amount: u64,
+    pub position_size: u64,
+    pub leverage: u8,
+    pub closed: u8,
+    pub position_nonce: u64,
+    pub pnl: i64,
+    pub direction: i8,
+    pub margin_mode: u8,
+    pub confirmed: u8,
+    pub open_fee: u64,
+    pub open_slot: u64,
+    pub liquidation_auction_slot: u64,
+    pub funding_index_snapshot: i128,
+    pub borrow_index_snapshot: i128,
+    pub residual_profit: u64,
+    pub residual_fee: u64,
+    pub guaranteed_stop_price: u64,
+    pub client_tag: [u8; CLIENT_TAG_LENGTH],
+    pub margin_call_slot: u64,
+    pub contract_type: u8,
+    /// Slot of the owner's `USER_MODIFY` close request; zero while no close was requested.
+    pub close_request_slot: u64,
+    /// Market base and per-leverage fee in effect at open, charged on profit at settlement
+    /// whatever the market's fees are by then; `NO_FEE_SNAPSHOT_BPS` on positions brought to
+    /// the current layout from one that predates the snapshot.
+    pub open_base_fee_bps: u16,
+    pub open_leverage_fee_bps: u16,
+    /// FNV-1a of every byte before it, stamped on each save and checked on each load; zero on
+    /// accounts not written since it was added.
+    pub checksum: u32,
+}
+
+/// Unversioned position layout written before `PositionAccount::version` existed.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct PositionAccountV0 {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub market_symbol: [u8; MAX_SYMBOL_LENGTH],
+    pub entry_price: u64,
+    pub liquidation_price: u64,
+    pub paid_amount: u64,
+    pub position_size: u64,
+    pub leverage: u8,
+    pub closed: u8,
+    pub position_nonce: u64,
+    pub pnl: i64,
+    pub direction: i8,
+}
+
+impl From<PositionAccountV0> for PositionAccount {
+    fn from(legacy: PositionAccountV0) -> Self {
+        PositionAccount {
+            version: POSITION_ACCOUNT_VERSION,
+            owner: legacy.owner,
+            market_mint: legacy.market_mint,
+            market_symbol: legacy.market_symbol,
+            entry_price: legacy.entry_price,
+            liquidation_price: legacy.liquidation_price,
+            paid_amount: legacy.paid_amount,
+            position_size: legacy.position_size,
+            leverage: legacy.leverage,
+            closed: legacy.closed,
+            position_nonce: legacy.position_nonce,
+            pnl: legacy.pnl,
+            direction: legacy.direction,
+            margin_mode: MARGIN_MODE_ISOLATED,
+            confirmed: 0,
+            open_fee: 0,
+            open_slot: 0,
+            liquidation_auction_slot: 0,
+            funding_index_snapshot: 0,
+            borrow_index_snapshot: 0,
+            residual_profit: 0,
+            residual_fee: 0,
+            guaranteed_stop_price: 0,
+            client_tag: [0; CLIENT_TAG_LENGTH],
+            margin_call_slot: 0,
+            contract_type: CONTRACT_TYPE_LINEAR,
+            close_request_slot: 0,
+            open_base_fee_bps: PositionAccount::NO_FEE_SNAPSHOT_BPS,
+            open_leverage_fee_bps: PositionAccount::NO_FEE_SNAPSHOT_BPS,
+            checksum: 0,
+        }
+    }
+}
+
+/// Lifecycle of a position: `Pending` until the DEX sets an entry price, then `Open`, then
+/// `CloseRequested` once closed. Settlement only runs from `CloseRequested`; pending
+/// positions leave through `CANCEL_POSITION` or `EXPIRE_POSITION` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionStatus {
+    Pending,
+    Open,
+    CloseRequested,
+    ResidualClaim,
+}
+
+impl PositionAccount {
+    pub const LEN: usize = 1 + 32 + 32 + MAX_SYMBOL_LENGTH + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 1 + 1 + 1
+        + 8 + 8 + 8 + 16 + 16 + 8 + 8 + 8 + CLIENT_TAG_LENGTH + 8 + 1 + 8 + 2 + 2 + 4;
+    
+    const MARKET_MINT_OFFSET: usize = 1 + 32;
+    const ENTRY_PRICE_OFFSET: usize = Self::MARKET_MINT_OFFSET + 32 + MAX_SYMBOL_LENGTH;
+    const LIQUIDATION_PRICE_OFFSET: usize = Self::ENTRY_PRICE_OFFSET + 8;
+    const CLOSED_OFFSET: usize = Self::LIQUIDATION_PRICE_OFFSET + 8 + 8 + 8 + 1;
+    const PNL_OFFSET: usize = Self::CLOSED_OFFSET + 1 + 8;
+    const CONFIRMED_OFFSET: usize = Self::PNL_OFFSET + 8 + 1 + 1;
+    const CHECKSUM_OFFSET: usize = Self::LEN - 4;
+    /// First version whose layout ends in `checksum`.
+    pub const FIRST_CHECKSUM_VERSION: u8 = 13;
+    /// First version that snapshots the market fees at open.
+    pub const FIRST_FEE_SNAPSHOT_VERSION: u8 = 15;
+    /// Fee snapshot of a position without one, which pays the market's current fees instead.
+    pub const NO_FEE_SNAPSHOT_BPS: u16 = u16::MAX;
+    
+    /// Account data at the current version and full length, whose field offsets are fixed.
+    pub fn is_current_layout(data: &[u8]) -> bool {
+        data.len() >= Self::LEN && data[0] == POSITION_ACCOUNT_VERSION
+    }
+    
+    /// FNV-1a over `data`, with zero reserved for accounts that were never stamped.
+    pub fn checksum_of(data: &[u8]) -> u32 {
+        let hash = data.iter().fold(0x811c_9dc5u32, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x0100_0193));
+        
+        hash.max(1)
+    }
+    
+    /// Stamps the checksum of a serialized current-layout account into its last field.
+    pub fn stamp_checksum(data: &mut [u8]) -> ProgramResult {
+        if !Self::is_current_layout(data) {
+            msg!("Position layout has no checksum field");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        let checksum = Self::checksum_of(&data[..Self::CHECKSUM_OFFSET]);
+        data[Self::CHECKSUM_OFFSET..Self::LEN].copy_from_slice(&checksum.to_le_bytes());
+        
+        Ok(())
+    }
+    
+    /// Whether the stored checksum disagrees with the bytes it covers. Accounts that predate the
+    /// field or were never stamped carry no checksum and are never reported as corrupted.
+    pub fn is_corrupted(data: &[u8]) -> bool {
+        if !Self::is_current_layout(data) {
+            return false;
+        }
+        
+        let stored = u32::from_le_bytes(data[Self::CHECKSUM_OFFSET..Self::LEN].try_into().unwrap());
+        
+        stored != 0 && stored != Self::checksum_of(&data[..Self::CHECKSUM_OFFSET])
+    }
+    
+    /// Writes the fields `DEX_MODIFY` may change (entry and liquidation price, close state, pnl,
+    /// confirmation) at their fixed offsets instead of reserializing the whole
+    /// account. Only accounts in the current layout qualify.
+    pub fn write_dex_fields(&self, data: &mut [u8]) -> ProgramResult {
+        if !Self::is_current_layout(data) || self.version != POSITION_ACCOUNT_VERSION {
+            msg!("Position layout does not support in-place updates");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        
+        data[Self::ENTRY_PRICE_OFFSET..Self::ENTRY_PRICE_OFFSET + 8].copy_from_slice(&self.entry_price.to_le_bytes());
+        data[Self::LIQUIDATION_PRICE_OFFSET..Self::LIQUIDATION_PRICE_OFFSET + 8]
+            .copy_from_slice(&self.liquidation_price.to_le_bytes());
+        data[Self::CLOSED_OFFSET] = self.closed;
+        data[Self::PNL_OFFSET..Self::PNL_OFFSET + 8].copy_from_slice(&self.pnl.to_le_bytes());
+        data[Self::CONFIRMED_OFFSET] = self.confirmed;
+        
+        Self::stamp_checksum(data)?;
+        
+        Ok(())
+    }
+    
+    /// Rejects a position that belongs to someone else or was loaded for a different nonce.
+    pub fn validate(&self, owner: &Pubkey, position_nonce: u64) -> ProgramResult {
+        if self.position_nonce != position_nonce {
+            msg!("Position nonce mismatch");
+            return Err(ProgramError::InvalidArgument);
+        }
+        
+        if &self.owner != owner {
+            msg!("Owner account does not match position owner");
+            return Err(ProgramError::InvalidArgument);
+        }
+        
+        Ok(())
+    }
+    
+    /// Checks that `key` is the PDA this position lives at.
+    pub fn validate_address(&self, key: &Pubkey, program_id: &Pubkey) -> ProgramResult {
+        let (position_pda, _position_bump) = find_position_address(&self.owner, self.position_nonce, program_id);
+        
+        if key != &position_pda {
+            msg!("Invalid position account");
+            return Err(ProgramError::InvalidArgument);
+        }
+        
+        Ok(())
+    }
+    
+    pub fn is_open(&self) -> bool {
+        self.closed == 0
+    }
+    
+    /// Closed by the owner or the DEX and waiting for `PROCESS_PNL`.
+    pub fn is_pending_settlement(&self) -> bool {
+        self.closed == 1
+    }
+    
+    /// Settled while the market could not pay the whole profit; `residual_profit` is still owed.
+    pub fn has_residual_claim(&self) -> bool {
+        self.closed == POSITION_RESIDUAL_CLAIM
+    }
+    
+    /// The DEX has confirmed an entry price; until then the position can be cancelled or expired.
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed != 0 || self.entry_price != 0
+    }
+    
+    pub fn status(&self) -> PositionStatus {
+        if self.has_residual_claim() {
+            PositionStatus::ResidualClaim
+        } else if self.entry_price == 0 {
+            PositionStatus::Pending
+        } else if self.is_pending_settlement() {
+            PositionStatus::CloseRequested
+        } else {
+            PositionStatus::Open
+        }
+    }
+    
+    /// Whether the position was opened at a version that snapshots fees and still carries its
+    /// snapshot; a zero-fee snapshot is a real one.
+    pub fn has_fee_snapshot(&self) -> bool {
+        self.version >= Self::FIRST_FEE_SNAPSHOT_VERSION && self.open_base_fee_bps != Self::NO_FEE_SNAPSHOT_BPS
+    }
+    
+    /// Moves the position to `POSITION_ACCOUNT_VERSION`, marking one opened before fee snapshots
+    /// so it keeps paying the market's current fees.
+    pub fn upgrade_version(&mut self) {
+        if !self.has_fee_snapshot() {
+            self.open_base_fee_bps = Self::NO_FEE_SNAPSHOT_BPS;
+            self.open_leverage_fee_bps = Self::NO_FEE_SNAPSHOT_BPS;
+        }
+        
+        self.version = POSITION_ACCOUNT_VERSION;
+    }
+    
+    /// `market` with the base and per-leverage fee replaced by the ones snapshotted at open.
+    /// Positions without a snapshot pay the market's current fees.
+    pub fn fee_schedule(&self, market: FeeSchedule) -> FeeSchedule {
+        if !self.has_fee_snapshot() {
+            return market;
+        }
+        
+        FeeSchedule {
+            base_fee_bps: self.open_base_fee_bps as u64,
+            leverage_fee_bps: self.open_leverage_fee_bps as u64,
+            ..market
+        }
+    }
+    
+    /// Whether the owner's close request has waited `deadline_slots` without the DEX settling it.
+    /// A zero deadline, or a close the owner did not request, never passes.
+    pub fn close_deadline_passed(&self, deadline_slots: u64, slot: u64) -> bool {
+        deadline_slots != 0
+            && self.close_request_slot != 0
+            && self.status() == PositionStatus::CloseRequested
+            && slot >= self.close_request_slot.saturating_add(deadline_slots)
+    }
+    
+    /// Notional at `mark_price`, scaled from the entry notional the same way `unrealized_pnl` is.
+    /// Current notional in collateral units. Inverse contracts are fixed in quote terms, so their
+    /// collateral-denominated notional shrinks as the price rises.
+    pub fn notional(&self, mark_price: u64) -> u128 {
+        if self.entry_price == 0 {
+            return self.position_size as u128;
+        }
+        
+        if self.contract_type == CONTRACT_TYPE_INVERSE {
+            return (self.position_size as u128)
+                .saturating_mul(self.entry_price as u128)
+                / mark_price.max(1) as u128;
+        }
+        
+        (self.position_size as u128)
+            .saturating_mul(mark_price as u128)
+            / self.entry_price as u128
+    }
+    
+    pub fn unrealized_pnl(&self, mark_price: u64) -> Result<i128, ProgramError> {
+        unrealized_pnl(self, mark_price)
+    }
+    
+    pub fn health(&self, mark_price: u64, margin_bps: u16, shared_margin: u64) -> Result<PositionHealth, ProgramError> {
+        position_health(self, mark_price, margin_bps, shared_margin)
+    }
+    
+    /// Recomputes the stored liquidation price from the current entry price, size and
+    /// collateral; every instruction changing one of them calls this before saving.
+    pub fn refresh_liquidation_price(&mut self, maintenance_margin_bps: u16) {
+        self.liquidation_price = compute_liquidation_price(
+            self.entry_price,
+            self.position_size,
+            self.paid_amount,
+            self.direction,
+            self.contract_type,
+            maintenance_margin_bps,
+        );
+    }
+}
+
+/// Same append-only rule as `PositionAccount`: new fields go at the end with zero as default.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlobalConfig {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub dust_threshold: u64,
+    pub maintenance_margin_bps: u16,
+    pub initial_margin_bps: u16,
+    pub oracle_authority: Pubkey,
+    pub price_band_bps: u16,
+    pub max_price_staleness_slots: u64,
+    pub pnl_tolerance_bps: u16,
+    pub confirmation_timeout_slots: u64,
+    pub liquidation_price_tolerance_bps: u16,
+    pub feature_flags: u64,
+    pub min_market_base_fee_bps: u16,
+    pub max_market_base_fee_bps: u16,
+    pub max_market_leverage_fee_bps: u16,
+    pub auction_start_discount_bps: u16,
+    pub auction_max_discount_bps: u16,
+    pub auction_duration_slots: u64,
+    pub close_fee_bps: u16,
+    pub liquidation_fee_bps: u16,
+    pub liquidation_fee_keeper_share_bps: u16,
+    pub skew_surcharge_bps: u16,
+    pub skew_rebate_bps: u16,
+    pub min_close_slots: u64,
+    pub listing_bond_lamports: u64,
+    pub listing_timelock_slots: u64,
+    pub rebalance_targets: [RebalanceTarget; MAX_REBALANCE_MARKETS],
+    pub rebalance_max_per_call: u64,
+    pub lp_fee_share_bps: u16,
+    pub min_realized_profit: u64,
+    pub stop_out_margin_bps: u16,
+    pub guaranteed_stop_premium_bps: u16,
+    pub portfolio_health_floor_bps: u16,
+    pub max_platform_fee_bps: u16,
+    pub attestor: Pubkey,
+    pub freeze_close_timelock_slots: u64,
+    pub min_open_slot_gap: u64,
+    pub max_opens_per_slot: u16,
+    pub max_owner_notional: u64,
+    pub margin_call_threshold_bps: u16,
+    pub swap_fee_bps: u16,
+    pub max_trade_liquidity_bps: u16,
+    pub fee_routes: [FeeRoute; MAX_FEE_ROUTES],
+    pub close_settlement_deadline_slots: u64,
+    /// Slot from which the program only accepts `SHUTDOWN_ALLOWED_INSTRUCTIONS`; zero while no
+    /// shutdown is scheduled.
+    pub shutdown_slot: u64,
+}
+
+/// Share of the combined liquidity `CRANK_REBALANCE` steers a market towards; a zero weight
+/// marks the slot unused.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RebalanceTarget {
+    pub market_mint: Pubkey,
+    pub weight_bps: u16,
+}
+
+impl RebalanceTarget {
+    pub const LEN: usize = 32 + 2;
+}
+
+/// Sends `share_bps` of every fee of `fee_type` to `destination` instead of the fee's default
+/// sink; a zero share marks the slot unused.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FeeRoute {
+    pub fee_type: u8,
+    pub destination: Pubkey,
+    pub share_bps: u16,
+}
+
+impl FeeRoute {
+    pub const LEN: usize = 1 + 32 + 2;
+}
+
+impl GlobalConfig {
+    pub const LEN: usize = 1 + 32 + 8 + 2 + 2 + 32 + 2 + 8 + 2 + 8 + 2 + 8 + 2 + 2 + 2 + 2 + 2 + 8 + 2
+        + 2 + 2 + 2 + 2 + 8 + 8 + 8 + RebalanceTarget::LEN * MAX_REBALANCE_MARKETS + 8 + 2 + 8 + 2 + 2 + 2 + 2 + 32 + 8 + 8 + 2 + 8 + 2 + 2 + 2
+        + FeeRoute::LEN * MAX_FEE_ROUTES + 8 + 8;
+    
+    pub fn check_admin(&self, admin_account: &AccountInfo) -> ProgramResult {
+        if !admin_account.is_signer || admin_account.key != &self.admin {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        
+        Ok(())
+    }
+    
+    pub fn feature_enabled(&self, flag: u64) -> bool {
+        self.feature_flags & flag != 0
+    }
+    
+    pub fn active_rebalance_targets(&self) -> impl Iterator<Item = &RebalanceTarget> {
+        self.rebalance_targets.iter().filter(|target| target.weight_bps != 0)
+    }
+    
+    /// Whether a scheduled shutdown has taken effect at `slot`.
+    pub fn is_shut_down(&self, slot: u64) -> bool {
+        self.shutdown_slot != 0 && slot >= self.shutdown_slot
+    }
+    
+    pub fn fee_routes_for(&self, fee_type: u8) -> impl Iterator<Item = &FeeRoute> {
+        self.fee_routes
+            .iter()
+            .filter(move |route| route.share_bps != 0 && route.fee_type == fee_type)
+    }
+}
+
+/// Per-market state stored in the market liquidity PDA's data; zero fields fall back to the
+/// global defaults, and accounts created with no data read as all-default.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MarketState {
+    pub market_mint: Pubkey,
+    pub base_fee_bps: u16,
+    pub leverage_fee_bps: u16,
+    pub maker_fee_bps: u16,
+    pub cumulative_funding_long: i128,
+    pub cumulative_funding_short: i128,
+    pub funding_updated_slot: u64,
+    pub open_interest_long: u64,
+    pub open_interest_short: u64,
+    pub borrow_base_rate_bps: u16,
+    pub borrow_slope1_bps: u16,
+    pub borrow_slope2_bps: u16,
+    pub borrow_kink_utilization_bps: u16,
+    pub cumulative_borrow_index: i128,
+    pub borrow_updated_slot: u64,
+    pub spread_base_bps: u16,
+    pub spread_utilization_factor_bps: u16,
+    pub spread_skew_factor_bps: u16,
+    pub spread_max_bps: u16,
+    pub leverage_tiers: [LeverageTier; MAX_LEVERAGE_TIERS],
+    pub skew_rebate_pool: u64,
+    pub min_holding_slots: u64,
+    pub status: u8,
+    pub lister: Pubkey,
+    pub listing_bond: u64,
+    pub listing_slot: u64,
+    pub oracle_feed: Pubkey,
+    pub wind_down_slot: u64,
+    pub migrated_to: Pubkey,
+    pub max_liquidity: u64,
+    pub total_lp_shares: u64,
+    pub protocol_lp_shares: u64,
+    pub lp_fee_per_share: u128,
+    pub unclaimed_lp_fees: u64,
+    pub bad_debt: u64,
+    pub max_owner_notional: u64,
+    pub funding_interval_slots: u64,
+    pub max_funding_rate_bps: u16,
+    pub settlement_mint: Pubkey,
+    pub contract_type: u8,
+    pub twap_threshold_notional: u64,
+    pub cumulative_volume: u64,
+    pub cumulative_fees: u64,
+    pub stats_epoch: u64,
+    pub epoch_protocol_fees: u64,
+    pub epoch_lp_fees: u64,
+    pub total_deposited: u64,
+    pub total_paid_out: u64,
+}
+
+/// Maximum leverage for positions up to `max_notional`; a zero `max_notional` covers every
+/// larger size and a zero `max_leverage` marks the slot unused.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LeverageTier {
+    pub max_notional: u64,
+    pub max_leverage: u8,
+}
+
+impl LeverageTier {
+    pub const LEN: usize = 8 + 1;
+}
+
+/// Takers pay the base plus per-leverage fee; makers pay a flat rate on the filled amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeSchedule {
+    pub base_fee_bps: u64,
+    pub leverage_fee_bps: u64,
+    pub maker_fee_bps: u64,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        FeeSchedule {
+            base_fee_bps: BASE_FEE_BASIS_POINTS,
+            leverage_fee_bps: LEVERAGE_FEE_BASIS_POINTS,
+            maker_fee_bps: MAKER_FEE_BASIS_POINTS,
+        }
+    }
+}
+
+impl MarketState {
+    pub const LEN: usize = 32 + 2 + 2 + 2 + 16 + 16 + 8 + 8 + 8 + 2 + 2 + 2 + 2 + 16 + 8 + 2 + 2 + 2 + 2
+        + LeverageTier::LEN * MAX_LEVERAGE_TIERS + 8 + 8 + 1 + 32 + 8 + 8 + 32 + 8 + 32 + 8 + 8 + 8 + 16 + 8 + 8 + 8 + 8 + 2 + 32 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8;
+    
+    /// Native SOL markets leave `settlement_mint` unset; SPL-settled ones hold funds in their
+    /// settlement vault.
+    pub fn is_native_settlement(&self) -> bool {
+        self.settlement_mint == Pubkey::default()
+    }
+    
+    /// Whether new positions and orders may be opened; markets from `INIT_MARKET` start active,
+    /// permissionless listings only after their timelock.
+    pub fn is_active(&self) -> bool {
+        self.status == MARKET_STATUS_ACTIVE
+    }
+    
+    /// Lamports the market can still take in before reaching `max_liquidity`; zero means uncapped.
+    pub fn liquidity_headroom(&self, liquidity: u64) -> u64 {
+        if self.max_liquidity == 0 {
+            return u64::MAX;
+        }
+        
+        self.max_liquidity.saturating_sub(liquidity)
+    }
+    
+    /// Counts a fee collected on this market towards its lifetime total and the current epoch's
+    /// protocol and LP legs.
+    pub fn record_fee(&mut self, protocol_fee: u64, lp_fee: u64) {
+        self.cumulative_fees = self
+            .cumulative_fees
+            .saturating_add(protocol_fee)
+            .saturating_add(lp_fee);
+        self.epoch_protocol_fees = self.epoch_protocol_fees.saturating_add(protocol_fee);
+        self.epoch_lp_fees = self.epoch_lp_fees.saturating_add(lp_fee);
+    }
+    
+    /// Shadow ledger: counts value entering the market's pool (or settlement vault). Together
+    /// with `record_payout` the pool should hold rent plus deposits less payouts.
+    pub fn record_deposit(&mut self, amount: u64) {
+        self.total_deposited = self.total_deposited.saturating_add(amount);
+    }
+    
+    /// Shadow ledger: counts value leaving the market's pool (or settlement vault).
+    pub fn record_payout(&mut self, amount: u64) {
+        self.total_paid_out = self.total_paid_out.saturating_add(amount);
+    }
+    
+    /// Credits an LP fee to every share through the fee-per-share accumulator. Only the part
+    /// owed to LP positions becomes claimable; the protocol-owned shares' part stays in the pool.
+    pub fn accrue_lp_fee(&mut self, lp_fee: u64) {
+        if self.total_lp_shares == 0 || lp_fee == 0 {
+            return;
+        }
+        
+        let total_shares = self.total_lp_shares as u128;
+        let provider_shares = self.total_lp_shares.saturating_sub(self.protocol_lp_shares) as u128;
+        
+        self.lp_fee_per_share = self
+            .lp_fee_per_share
+            .saturating_add((lp_fee as u128).saturating_mul(LP_FEE_PER_SHARE_PRECISION) / total_shares);
+        self.unclaimed_lp_fees = self
+            .unclaimed_lp_fees
+            .saturating_add(((lp_fee as u128).saturating_mul(provider_shares) / total_shares) as u64);
+    }
+    
+    /// Mints shares for liquidity no share owns yet (left in a pool whose shares were all burned,
+    /// or added without minting any) to the protocol, so the next deposit is priced against it
+    /// instead of claiming it. Returns the shares minted.
+    pub fn adopt_unowned_liquidity(&mut self, pool_value: u64) -> u64 {
+        if self.total_lp_shares > 0 || pool_value == 0 {
+            return 0;
+        }
+        
+        self.total_lp_shares = pool_value;
+        self.protocol_lp_shares = self.protocol_lp_shares.saturating_add(pool_value);
+        
+        pool_value
+    }
+    
+    /// Delisted and past its grace period: remaining positions can be settled at the oracle price.
+    pub fn is_winding_down(&self, slot: u64) -> bool {
+        self.status == MARKET_STATUS_CLOSE_ONLY && slot >= self.wind_down_slot
+    }
+    
+    /// First slot at which a pending listing can be activated and can no longer be vetoed.
+    pub fn listing_activation_slot(&self, timelock_slots: u64) -> u64 {
+        self.listing_slot.saturating_add(timelock_slots)
+    }
+    
+    pub fn add_open_interest(&mut self, direction: i8, size: u64) {
+        self.cumulative_volume = self.cumulative_volume.saturating_add(size);
+        
+        if direction == POSITION_LONG {
+            self.open_interest_long = self.open_interest_long.saturating_add(size);
+        } else {
+            self.open_interest_short = self.open_interest_short.saturating_add(size);
+        }
+    }
+    
+    pub fn remove_open_interest(&mut self, direction: i8, size: u64) {
+        self.cumulative_volume = self.cumulative_volume.saturating_add(size);
+        
+        if direction == POSITION_LONG {
+            self.open_interest_long = self.open_interest_long.saturating_sub(size);
+        } else {
+            self.open_interest_short = self.open_interest_short.saturating_sub(size);
+        }
+    }
+    
+    /// Open notional on both sides as a share of the market's liquidity.
+    pub fn utilization_bps(&self, liquidity: u64) -> u64 {
+        let open_interest = self.open_interest_long.saturating_add(self.open_interest_short) as u128;
+        
+        if liquidity == 0 {
+            return if open_interest == 0 { 0 } else { BASIS_POINTS_DIVISOR };
+        }
+        
+        (open_interest.saturating_mul(BASIS_POINTS_DIVISOR as u128) / liquidity as u128)
+            .min(BASIS_POINTS_DIVISOR as u128) as u64
+    }
+    
+    /// Daily borrow rate on the kinked curve: `slope1` is reached at the kink, `slope2` is added
+    /// on top between the kink and full utilization.
+    pub fn borrow_rate_bps(&self, liquidity: u64) -> u64 {
+        let utilization = self.utilization_bps(liquidity);
+        let kink = self.borrow_kink_utilization_bps as u64;
+        let base = self.borrow_base_rate_bps as u64;
+        
+        if kink == 0 {
+            return base;
+        }
+        
+        let below_kink = (self.borrow_slope1_bps as u64).saturating_mul(utilization.min(kink)) / kink;
+        let above_kink = if utilization > kink && kink < BASIS_POINTS_DIVISOR {
+            (self.borrow_slope2_bps as u64).saturating_mul(utilization - kink) / (BASIS_POINTS_DIVISOR - kink)
+        } else {
+            0
+        };
+        
+        base.saturating_add(below_kink).saturating_add(above_kink)
+    }
+    
+    /// Long/short imbalance as a share of total open interest.
+    pub fn skew_bps(&self) -> u64 {
+        let total = self.open_interest_long.saturating_add(self.open_interest_short) as u128;
+        
+        if total == 0 {
+            return 0;
+        }
+        
+        (self.open_interest_long.abs_diff(self.open_interest_short) as u128)
+            .saturating_mul(BASIS_POINTS_DIVISOR as u128)
+            .checked_div(total)
+            .unwrap_or(0) as u64
+    }
+    
+    /// Whether opening (or closing) `size` on `direction` would shrink the long/short imbalance.
+    pub fn reduces_skew(&self, direction: i8, size: u64, opening: bool) -> bool {
+        let before = self.open_interest_long.abs_diff(self.open_interest_short);
+        let mut after_state = self.clone();
+        
+        if opening {
+            after_state.add_open_interest(direction, size);
+        } else {
+            after_state.remove_open_interest(direction, size);
+        }
+        
+        after_state.open_interest_long.abs_diff(after_state.open_interest_short) < before
+    }
+    
+    /// Half-spread applied around the oracle price, widening with utilization and skew up to `spread_max_bps`.
+    pub fn spread_bps(&self, liquidity: u64) -> u64 {
+        let utilization_component = self
+            .utilization_bps(liquidity)
+            .saturating_mul(self.spread_utilization_factor_bps as u64)
+            / BASIS_POINTS_DIVISOR;
+        let skew_component = self
+            .skew_bps()
+            .saturating_mul(self.spread_skew_factor_bps as u64)
+            / BASIS_POINTS_DIVISOR;
+        
+        (self.spread_base_bps as u64)
+            .saturating_add(utilization_component)
+            .saturating_add(skew_component)
+            .min(self.spread_max_bps as u64)
+    }
+    
+    /// Leverage cap for a position of `notional`; `None` when tiers exist but none covers that size.
+    pub fn max_leverage_for(&self, notional: u64) -> Option<u8> {
+        let mut tiers = self.leverage_tiers.iter().filter(|tier| tier.max_leverage != 0).peekable();
+        
+        if tiers.peek().is_none() {
+            return Some(MAXIMUM_LEVERAGE);
+        }
+        
+        tiers
+            .find(|tier| tier.max_notional == 0 || notional <= tier.max_notional)
+            .map(|tier| tier.max_leverage)
+    }
+    
+    /// Advances the borrow index to `slot` at the rate implied by the current utilization.
+    pub fn accrue_borrow(&mut self, liquidity: u64, slot: u64) {
+        if self.borrow_updated_slot != 0 && slot > self.borrow_updated_slot {
+            let elapsed = slot - self.borrow_updated_slot;
+            let increment = (self.borrow_rate_bps(liquidity) as i128)
+                .saturating_mul(elapsed as i128)
+                .saturating_mul(FUNDING_INDEX_PRECISION)
+                / (BASIS_POINTS_DIVISOR as i128 * SLOTS_PER_DAY as i128);
+            
+            self.cumulative_borrow_index = self.cumulative_borrow_index.saturating_add(increment);
+        }
+        
+        self.borrow_updated_slot = slot;
+    }
+    
+    /// Largest funding index move per accrual, from `max_funding_rate_bps`; `None` when uncapped.
+    pub fn max_funding_index_delta(&self) -> Option<i128> {
+        (self.max_funding_rate_bps != 0).then(|| {
+            (self.max_funding_rate_bps as i128).saturating_mul(FUNDING_INDEX_PRECISION) / BASIS_POINTS_DIVISOR as i128
+        })
+    }
+    
+    /// Cumulative funding paid per unit of notional by the given side, scaled by `FUNDING_INDEX_PRECISION`.
+    pub fn funding_index(&self, direction: i8) -> i128 {
+        if direction == POSITION_LONG {
+            self.cumulative_funding_long
+        } else {
+            self.cumulative_funding_short
+        }
+    }
+    
+    pub fn fee_schedule(&self) -> FeeSchedule {
+        let defaults = FeeSchedule::default();
+        
+        FeeSchedule {
+            base_fee_bps: if self.base_fee_bps != 0 { self.base_fee_bps as u64 } else { defaults.base_fee_bps },
+            leverage_fee_bps: if self.leverage_fee_bps != 0 { self.leverage_fee_bps as u64 } else { defaults.leverage_fee_bps },
+            maker_fee_bps: if self.maker_fee_bps != 0 { self.maker_fee_bps as u64 } else { defaults.maker_fee_bps },
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct SymbolRegistryAccount {
+    pub market_mint: Pubkey,
+    pub market_symbol: [u8; MAX_SYMBOL_LENGTH],
+    pub decimals: u8,
+}
+
+impl SymbolRegistryAccount {
+    pub const LEN: usize = 32 + MAX_SYMBOL_LENGTH + 1;
+}
+
+/// Proof that the config's attestor cleared `owner` to open positions while
+/// `FEATURE_PERMISSIONED_OPENS` is enabled. A zero `expiry_slot` never expires.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct AttestationAccount {
+    pub owner: Pubkey,
+    pub attestor: Pubkey,
+    pub issued_slot: u64,
+    pub expiry_slot: u64,
+    pub revoked: u8,
+}
+
+impl AttestationAccount {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 1;
+    
+    /// Issued by `attestor`, not revoked and not yet expired at `slot`.
+    pub fn is_valid(&self, attestor: &Pubkey, slot: u64) -> bool {
+        &self.attestor == attestor && self.revoked == 0 && (self.expiry_slot == 0 || slot < self.expiry_slot)
+    }
+}
+
+/// Incident-response hold on an owner or a single position. While `frozen` the target cannot
+/// open or withdraw, and its closes wait out the config's `freeze_close_timelock_slots`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct FreezeAccount {
+    pub target: Pubkey,
+    pub frozen: u8,
+    pub reason_code: u16,
+    pub frozen_slot: u64,
+}
+
+impl FreezeAccount {
+    pub const LEN: usize = 32 + 1 + 2 + 8;
+    
+    pub fn is_frozen(&self) -> bool {
+        self.frozen != 0
+    }
+}
+
+/// Operator keys of a deployment, set by `INITIALIZE_AUTHORITY` and rotated by `admin`.
+/// `operators` are extra keys managed with `SET_OPERATOR`, each holding the `OPERATOR_ROLE_*`
+/// bits in the matching `operator_roles` slot; unused slots are zero. The DEX authority holds
+/// every role.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuthorityAccount {
+    pub admin: Pubkey,
+    pub dex_authority: Pubkey,
+    pub fees_wallet: Pubkey,
+    pub operators: [Pubkey; MAX_OPERATORS],
+    pub operator_roles: [u8; MAX_OPERATORS],
+    pub last_heartbeat_slot: u64,
+}
+
+impl AuthorityAccount {
+    pub const LEN: usize = 32 + 32 + 32 + 32 * MAX_OPERATORS + MAX_OPERATORS + 8;
+    
+    /// Roles held by `key`. Operators added before roles existed have a zero roles byte and keep
+    /// the price-setter and settler powers they were added with.
+    pub fn roles(&self, key: &Pubkey) -> u8 {
+        if key == &self.dex_authority {
+            return OPERATOR_ROLES_ALL;
+        }
+        
+        if key == &Pubkey::default() {
+            return 0;
+        }
+        
+        match self.operators.iter().position(|operator| operator == key) {
+            Some(index) if self.operator_roles[index] == 0 => OPERATOR_ROLE_PRICE_SETTER | OPERATOR_ROLE_SETTLER,
+            Some(index) => self.operator_roles[index],
+            None => 0,
+        }
+    }
+    
+    pub fn has_role(&self, key: &Pubkey, role: u8) -> bool {
+        self.roles(key) & role != 0
+    }
+    
+    /// Requires `account` to hold `role` and to have signed.
+    pub fn check_role(&self, account: &AccountInfo, role: u8) -> ProgramResult {
+        if !account.is_signer || !self.has_role(account.key, role) {
+            msg!("{} lacks operator role {:#04b}", account.key, role);
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        
+        Ok(())
+    }
+    
+    /// Whether the operator has missed its heartbeat for more than `OPERATOR_LIVENESS_SLOTS`.
+    /// Deployments that never sent a heartbeat are never considered offline.
+    pub fn operator_offline(&self, slot: u64) -> bool {
+        self.last_heartbeat_slot != 0 && slot > self.last_heartbeat_slot.saturating_add(OPERATOR_LIVENESS_SLOTS)
+    }
+    
+    /// Requires `account` to be the DEX authority and to have signed.
+    pub fn check_dex(&self, account: &AccountInfo) -> ProgramResult {
+        if !account.is_signer || account.key != &self.dex_authority {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        
+        Ok(())
+    }
+}
+
+/// Written by `MARK_CORRUPTED` for a position failing its checksum; `FORCE_CLOSE` is allowed
+/// from `marked_slot + FORCE_CLOSE_DELAY_SLOTS` and pays the `owner` recorded here.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct CorruptionMarkAccount {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub marked_by: Pubkey,
+    pub marked_slot: u64,
+}
+
+impl CorruptionMarkAccount {
+    pub const LEN: usize = 32 + 32 + 32 + 8;
+    
+    pub fn close_slot(&self) -> u64 {
+        self.marked_slot.saturating_add(FORCE_CLOSE_DELAY_SLOTS)
+    }
+}
+
+/// One settled position in an owner's history; `timestamp` is the settlement's unix time.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryRecord {
+    pub market_mint: Pubkey,
+    pub direction: i8,
+    pub pnl: i64,
+    pub timestamp: i64,
+}
+
+impl HistoryRecord {
+    pub const LEN: usize = 32 + 1 + 8 + 8;
+}
+
+/// Opt-in ring buffer of an owner's last `POSITION_HISTORY_LEN` settlements. `head` is the slot
+/// the next record goes to; once `count` reaches the capacity each record overwrites the oldest.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct PositionHistoryAccount {
+    pub owner: Pubkey,
+    pub head: u16,
+    pub count: u16,
+    pub records: [HistoryRecord; POSITION_HISTORY_LEN],
+}
+
+impl PositionHistoryAccount {
+    pub const LEN: usize = 32 + 2 + 2 + HistoryRecord::LEN * POSITION_HISTORY_LEN;
+    
+    pub fn push(&mut self, record: HistoryRecord) {
+        self.records[self.head as usize % POSITION_HISTORY_LEN] = record;
+        self.head = ((self.head as usize + 1) % POSITION_HISTORY_LEN) as u16;
+        self.count = self.count.saturating_add(1).min(POSITION_HISTORY_LEN as u16);
+    }
+    
+    /// Stored records, most recent first.
+    pub fn recent(&self) -> impl Iterator<Item = &HistoryRecord> {
+        let head = self.head as usize;
+        
+        (1..=self.count as usize).map(move |back| &self.records[(head + POSITION_HISTORY_LEN - back) % POSITION_HISTORY_LEN])
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct BlacklistEntry {
+    pub address: Pubkey,
+    pub reason_code: u16,
+    pub added_slot: u64,
+}
+
+impl BlacklistEntry {
+    pub const LEN: usize = 32 + 2 + 8;
+}
+
+/// Addresses barred from opening positions and depositing liquidity.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct BlacklistAccount {
+    pub entries: Vec<BlacklistEntry>,
+}
+
+impl BlacklistAccount {
+    pub const HEADER_LEN: usize = 4;
+    pub const LEN: usize = Self::HEADER_LEN + BlacklistEntry::LEN * MAX_BLACKLIST_ENTRIES;
+    
+    pub fn is_blocked(&self, address: &Pubkey) -> bool {
+        self.entries.iter().any(|entry| &entry.address == address)
+    }
+}
+
+/// An approved frontend: the most of each open fee it may take, and the volume it has routed.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct IntegratorAccount {
+    pub integrator: Pubkey,
+    pub fee_share_bps: u16,
+    pub active: u8,
+    pub registered_slot: u64,
+    pub opens: u64,
+    pub total_volume: u64,
+    pub total_fees: u64,
+}
+
+impl IntegratorAccount {
+    pub const LEN: usize = 32 + 2 + 1 + 8 + 8 + 8 + 8;
+    
+    pub fn is_active(&self) -> bool {
+        self.active != 0
+    }
+    
+    /// Counts an open of `volume` notional that paid the integrator `fee`.
+    pub fn record_open(&mut self, volume: u64, fee: u64) {
+        self.opens = self.opens.saturating_add(1);
+        self.total_volume = self.total_volume.saturating_add(volume);
+        self.total_fees = self.total_fees.saturating_add(fee);
+    }
+}
+
+/// Where a market's primary price comes from. Operator-attested prices are published with
+/// `UPDATE_PRICE_FEED`, Pyth and Switchboard prices are copied from the named account by
+/// `SYNC_PRICE_FEED`, and a fixed price never goes stale.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PriceSource {
+    #[default]
+    OperatorAttested,
+    Pyth { feed: Pubkey },
+    Switchboard { aggregator: Pubkey },
+    Fixed { price: u64 },
+}
+
+impl PriceSource {
+    /// Largest variant: tag plus a pubkey.
+    pub const LEN: usize = 1 + 32;
+}
+
+/// A market's oracle price, plus a secondary price published by `fallback_authority` that
+/// `load_price_feed` switches to when the primary is stale or unset.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct PriceFeedAccount {
+    pub market_mint: Pubkey,
+    pub price: u64,
+    pub confidence: u64,
+    pub publish_slot: u64,
+    pub fallback_authority: Pubkey,
+    pub fallback_price: u64,
+    pub fallback_confidence: u64,
+    pub fallback_publish_slot: u64,
+    pub source: PriceSource,
+}
+
+impl PriceFeedAccount {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 32 + 8 + 8 + 8 + PriceSource::LEN;
+}
+
+/// Resting limit order; `paid_amount` is held in the book account until the order fills or is cancelled.
+/// Iceberg orders only expose `display_amount` at a time as `paid_amount`, keeping the rest in
+/// `hidden_amount` until the displayed part fills.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct Order {
+    pub order_id: u64,
+    pub owner: Pubkey,
+    pub price: u64,
+    pub paid_amount: u64,
+    pub leverage: u8,
+    pub position_nonce: u64,
+    pub placed_slot: u64,
+    pub display_amount: u64,
+    pub hidden_amount: u64,
+}
+
+impl Order {
+    pub const LEN: usize = 8 + 32 + 8 + 8 + 1 + 8 + 8 + 8 + 8;
+    
+    /// Collateral still escrowed for the order, displayed or not.
+    pub fn total_amount(&self) -> u64 {
+        self.paid_amount.saturating_add(self.hidden_amount)
+    }
+    
+    /// Shows the next `display_amount` of a filled iceberg order as of `slot`; false when nothing
+    /// is left hidden.
+    pub fn replenish(&mut self, slot: u64) -> bool {
+        if self.hidden_amount == 0 {
+            return false;
+        }
+        
+        let shown = self.display_amount.min(self.hidden_amount).max(1);
+        self.paid_amount = self.paid_amount.saturating_add(shown);
+        self.hidden_amount = self.hidden_amount.saturating_sub(shown);
+        self.placed_slot = slot;
+        
+        true
+    }
+}
+
+/// Take-profit `limit_price` and stop-loss `stop_price` at which a keeper may close the owner's
+/// position, either left at 0 when unused. With both set the pair is one-cancels-other: the first
+/// leg to execute closes the position and this account together.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct CloseOrderAccount {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub position_nonce: u64,
+    pub direction: i8,
+    pub limit_price: u64,
+    pub placed_slot: u64,
+    pub stop_price: u64,
+}
+
+impl CloseOrderAccount {
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 8 + 8 + 8;
+    
+    /// Longs take profit at or above `limit_price`, shorts at or below.
+    pub fn take_profit_triggered(&self, mark_price: u64) -> bool {
+        self.limit_price != 0
+            && if self.direction == POSITION_LONG {
+                mark_price >= self.limit_price
+            } else {
+                mark_price <= self.limit_price
+            }
+    }
+    
+    /// Longs stop out at or below `stop_price`, shorts at or above.
+    pub fn stop_loss_triggered(&self, mark_price: u64) -> bool {
+        self.stop_price != 0
+            && if self.direction == POSITION_LONG {
+                mark_price <= self.stop_price
+            } else {
+                mark_price >= self.stop_price
+            }
+    }
+    
+    pub fn is_triggered(&self, mark_price: u64) -> bool {
+        self.take_profit_triggered(mark_price) || self.stop_loss_triggered(mark_price)
+    }
+    
+    /// False when both legs are unset or the stop sits on the wrong side of the take-profit.
+    pub fn is_valid(&self) -> bool {
+        match (self.limit_price, self.stop_price) {
+            (0, 0) => false,
+            (0, _) | (_, 0) => true,
+            (limit, stop) if self.direction == POSITION_LONG => stop < limit,
+            (limit, stop) => stop > limit,
+        }
+    }
+}
+
+/// Open or close of one position that a keeper may execute at the oracle price once both
+/// `execute_after_slot` and `execute_after_timestamp` have passed (0 leaves either unbounded).
+/// A scheduled open escrows its collateral here until then.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct ScheduledOrderAccount {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub position_nonce: u64,
+    pub kind: u8,
+    pub direction: i8,
+    pub leverage: u8,
+    pub paid_amount: u64,
+    pub execute_after_slot: u64,
+    pub execute_after_timestamp: i64,
+    pub placed_slot: u64,
+}
+
+impl ScheduledOrderAccount {
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 1 + 1 + 8 + 8 + 8 + 8;
+    
+    pub fn is_ready(&self, slot: u64, unix_timestamp: i64) -> bool {
+        slot >= self.execute_after_slot && unix_timestamp >= self.execute_after_timestamp
+    }
+}
+
+/// Recurring open of a new position every `interval_slots`, funded from `remaining_budget`, which
+/// is escrowed in this account above its rent.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct DcaScheduleAccount {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub schedule_id: u64,
+    pub direction: i8,
+    pub leverage: u8,
+    pub amount_per_interval: u64,
+    pub interval_slots: u64,
+    pub remaining_budget: u64,
+    pub next_execution_slot: u64,
+    pub executions: u32,
+}
+
+impl DcaScheduleAccount {
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 1 + 8 + 8 + 8 + 8 + 4;
+}
+
+/// Open split into `slices` fills of `amount_per_slice` (the last taking what is left of
+/// `remaining_budget`) into one reserved position, one per `interval_slots`; the unfilled
+/// collateral is escrowed in this account above its rent.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct TwapScheduleAccount {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub position_nonce: u64,
+    pub direction: i8,
+    pub leverage: u8,
+    pub slices: u16,
+    pub slices_filled: u16,
+    pub amount_per_slice: u64,
+    pub interval_slots: u64,
+    pub remaining_budget: u64,
+    pub next_execution_slot: u64,
+    pub placed_slot: u64,
+}
+
+impl TwapScheduleAccount {
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 1 + 2 + 2 + 8 + 8 + 8 + 8 + 8;
+    
+    /// Collateral the next fill takes; the final slice sweeps any rounding remainder.
+    pub fn next_slice_amount(&self) -> u64 {
+        if self.slices_filled.saturating_add(1) >= self.slices {
+            self.remaining_budget
+        } else {
+            self.amount_per_slice.min(self.remaining_budget)
+        }
+    }
+}
+
+/// A market's statistics as of its rollover out of `epoch`, written once by `ROLLOVER_EPOCH`.
+/// Volume and fees are lifetime totals, so per-epoch volume is the difference between
+/// consecutive snapshots; the epoch's fees are also recorded split into protocol and LP legs.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EpochSnapshotAccount {
+    pub market_mint: Pubkey,
+    pub epoch: u64,
+    pub snapshot_slot: u64,
+    pub cumulative_volume: u64,
+    pub cumulative_fees: u64,
+    pub open_interest_long: u64,
+    pub open_interest_short: u64,
+    pub insurance_balance: u64,
+    pub epoch_protocol_fees: u64,
+    pub epoch_lp_fees: u64,
+}
+
+impl EpochSnapshotAccount {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8;
+}
+
+/// A small position stored as a leaf of its market's `PositionTreeAccount` instead of in its own
+/// PDA. Only the leaf hash is kept on chain, so every change supplies the leaf and its proof.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressedPosition {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub leaf_index: u64,
+    pub entry_price: u64,
+    pub liquidation_price: u64,
+    pub paid_amount: u64,
+    pub position_size: u64,
+    pub leverage: u8,
+    pub direction: i8,
+    pub contract_type: u8,
+    pub open_slot: u64,
+    pub funding_index_snapshot: i128,
+    pub borrow_index_snapshot: i128,
+}
+
+impl CompressedPosition {
+    pub fn leaf_hash(&self) -> Result<[u8; 32], ProgramError> {
+        let data = self.try_to_vec().map_err(|_| ProgramError::InvalidAccountData)?;
+        
+        Ok(keccak::hashv(&[b"uranus_leaf", &data]).to_bytes())
+    }
+    
+    /// The leaf as an isolated, confirmed `PositionAccount`, so PnL, funding and borrow fees
+    /// are computed exactly as for an uncompressed position.
+    pub fn to_position(&self) -> PositionAccount {
+        PositionAccount {
+            version: POSITION_ACCOUNT_VERSION,
+            owner: self.owner,
+            market_mint: self.market_mint,
+            entry_price: self.entry_price,
+            liquidation_price: self.liquidation_price,
+            paid_amount: self.paid_amount,
+            position_size: self.position_size,
+            leverage: self.leverage,
+            direction: self.direction,
+            margin_mode: MARGIN_MODE_ISOLATED,
+            confirmed: 1,
+            open_slot: self.open_slot,
+            funding_index_snapshot: self.funding_index_snapshot,
+            borrow_index_snapshot: self.borrow_index_snapshot,
+            contract_type: self.contract_type,
+            open_base_fee_bps: PositionAccount::NO_FEE_SNAPSHOT_BPS,
+            open_leverage_fee_bps: PositionAccount::NO_FEE_SNAPSHOT_BPS,
+            ..PositionAccount::default()
+        }
+    }
+}
+
+/// Merkle tree of a market's compressed positions, one PDA per market. Leaves start as
+/// `[0; 32]`; `collateral` is the total held in this account above rent for open leaves.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct PositionTreeAccount {
+    pub market_mint: Pubkey,
+    pub depth: u8,
+    pub root: [u8; 32],
+    pub next_index: u64,
+    pub open_positions: u64,
+    pub collateral: u64,
+}
+
+impl PositionTreeAccount {
+    pub const LEN: usize = 32 + 1 + 32 + 8 + 8 + 8;
+    
+    /// Root of a tree of `depth` whose leaves are all empty.
+    pub fn empty_root(depth: u8) -> [u8; 32] {
+        (0..depth).fold([0u8; 32], |node, _| keccak::hashv(&[&node, &node]).to_bytes())
+    }
+    
+    /// Root obtained by placing `leaf` at `index` and hashing up through `proof` (siblings from
+    /// the leaf level upwards).
+    pub fn compute_root(&self, leaf: [u8; 32], index: u64, proof: &[[u8; 32]]) -> Result<[u8; 32], ProgramError> {
+        if proof.len() != self.depth as usize || index >> self.depth != 0 {
+            msg!("Proof must have {} nodes", self.depth);
+            return Err(DexError::InvalidMerkleProof.into());
+        }
+        
+        Ok(proof.iter().enumerate().fold(leaf, |node, (level, sibling)| {
+            if (index >> level) & 1 == 0 {
+                keccak::hashv(&[&node, sibling]).to_bytes()
+            } else {
+                keccak::hashv(&[sibling, &node]).to_bytes()
+            }
+        }))
+    }
+    
+    /// Checks that `index` currently holds `current` and moves the root to hold `replacement`.
+    pub fn replace_leaf(
+        &mut self,
+        current: [u8; 32],
+        replacement: [u8; 32],
+        index: u64,
+        proof: &[[u8; 32]],
+    ) -> ProgramResult {
+        if self.compute_root(current, index, proof)? != self.root {
+            msg!("Proof does not match the current root");
+            return Err(DexError::InvalidMerkleProof.into());
+        }
+        
+        self.root = self.compute_root(replacement, index, proof)?;
+        
+        Ok(())
+    }
+}
+
+/// A trader others can follow; `profit_share_bps` of each mirrored position's profit goes to them.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct LeadTraderAccount {
+    pub lead: Pubkey,
+    pub profit_share_bps: u16,
+    pub followers: u32,
+    pub registered_slot: u64,
+}
+
+impl LeadTraderAccount {
+    pub const LEN: usize = 32 + 2 + 4 + 8;
+}
+
+/// A follower's subscription to one lead trader. The lamports above rent are the follower's
+/// copy margin: mirrored positions are funded from it and settle back into it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct FollowerAccount {
+    pub follower: Pubkey,
+    pub lead: Pubkey,
+    pub copy_ratio_bps: u16,
+    pub max_collateral: u64,
+    pub subscribed_slot: u64,
+    pub open_copies: u32,
+    pub realized_pnl: i64,
+}
+
+impl FollowerAccount {
+    pub const LEN: usize = 32 + 32 + 2 + 8 + 8 + 4 + 8;
+}
+
+/// Ties a lead trader's position to the follower position mirroring it, so each lead position
+/// is copied at most once per follower and its close can be mirrored.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct CopyLinkAccount {
+    pub follower: Pubkey,
+    pub lead: Pubkey,
+    pub lead_position_nonce: u64,
+    pub follower_position_nonce: u64,
+}
+
+impl CopyLinkAccount {
+    pub const LEN: usize = 32 + 32 + 8 + 8;
+}
+
+/// A vault whose pooled deposits a manager trades within fixed risk limits. Its positions are
+/// owned by the vault PDA; net asset value is the free lamports above rent plus the collateral
+/// of the positions it tracks, excluding lamports held for queued deposits and processed
+/// withdrawals. `high_water_mark` is the highest share price (scaled by
+/// `LP_FEE_PER_SHARE_PRECISION`) the manager has been paid a performance fee up to.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct ManagedVaultAccount {
+    pub manager: Pubkey,
+    pub vault_id: u64,
+    pub total_shares: u64,
+    pub max_leverage: u8,
+    pub max_position_collateral: u64,
+    pub max_deployed_bps: u16,
+    pub created_slot: u64,
+    pub performance_fee_bps: u16,
+    pub high_water_mark: u128,
+    pub fees_paid: u64,
+    pub epoch_slots: u64,
+    pub current_epoch: u64,
+    pub epoch_start_slot: u64,
+    pub pending_deposits: u64,
+    pub pending_withdrawal_shares: u64,
+    pub reserved_withdrawals: u64,
+    pub positions: Vec<ManagedVaultPosition>,
+}
+
+impl ManagedVaultAccount {
+    /// Bytes before the position vector's elements, including its length prefix.
+    pub const HEADER_LEN: usize = 32 + 8 + 8 + 1 + 8 + 2 + 8 + 2 + 16 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 4;
+    pub const LEN: usize = Self::HEADER_LEN + ManagedVaultPosition::LEN * MAX_MANAGED_VAULT_POSITIONS;
+    
+    pub fn deployed_collateral(&self) -> u64 {
+        self.positions
+            .iter()
+            .fold(0u64, |total, position| total.saturating_add(position.collateral))
+    }
+    
+    pub fn position_slot(&self, position_nonce: u64) -> Option<usize> {
+        self.positions
+            .iter()
+            .position(|position| position.position_nonce == position_nonce)
+    }
+}
+
+/// A live position opened by a managed vault and the collateral it committed.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct ManagedVaultPosition {
+    pub position_nonce: u64,
+    pub collateral: u64,
+}
+
+impl ManagedVaultPosition {
+    pub const LEN: usize = 8 + 8;
+}
+
+/// A depositor's shares in one managed vault.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct VaultDepositorAccount {
+    pub vault: Pubkey,
+    pub depositor: Pubkey,
+    pub shares: u64,
+    pub net_deposits: u64,
+}
+
+impl VaultDepositorAccount {
+    pub const LEN: usize = 32 + 32 + 8 + 8;
+}
+
+/// An owner's position-mode choice for one market: in hedge mode longs and shorts are tracked
+/// independently, so they are never netted, neither by `NET_POSITIONS` nor by the portfolio
+/// margin requirement.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct HedgeModeAccount {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub enabled: u8,
+    pub updated_slot: u64,
+}
+
+impl HedgeModeAccount {
+    pub const LEN: usize = 32 + 32 + 1 + 8;
+    
+    pub fn is_enabled(&self) -> bool {
+        self.enabled != 0
+    }
+}
+
+/// A depositor's queued deposit (`amount` in lamports) or withdrawal (`amount` in shares) for
+/// one vault epoch, claimable once that epoch is processed.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct VaultRequestAccount {
+    pub vault: Pubkey,
+    pub depositor: Pubkey,
+    pub epoch: u64,
+    pub kind: u8,
+    pub amount: u64,
+}
+
+impl VaultRequestAccount {
+    pub const LEN: usize = 32 + 32 + 8 + 1 + 8;
+}
+
+/// The share price a processed vault epoch settled its queued requests at, scaled by
+/// `LP_FEE_PER_SHARE_PRECISION`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct VaultEpochAccount {
+    pub vault: Pubkey,
+    pub epoch: u64,
+    pub share_price: u128,
+    pub settled_slot: u64,
+}
+
+impl VaultEpochAccount {
+    pub const LEN: usize = 32 + 8 + 16 + 8;
+}
+
+/// One side of a market's book, allocated up front for `MAX_ORDERS_PER_SIDE` orders and kept
+/// in price-time priority: best price first, then lowest `order_id`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct OrderBookSide {
+    pub market_mint: Pubkey,
+    pub side: i8,
+    pub next_order_id: u64,
+    pub orders: Vec<Order>,
+}
+
+impl OrderBookSide {
+    /// Bytes before the order vector's elements: mint, side, next id and the vector length prefix.
+    pub const HEADER_LEN: usize = 32 + 1 + 8 + 4;
+    
+    /// Account size for a book that can hold `max_orders` orders.
+    pub const fn space(max_orders: usize) -> usize {
+        Self::HEADER_LEN + Order::LEN * max_orders
+    }
+    
+    /// True if `price` has strictly better priority than `other` on this side.
+    pub fn price_improves(&self, price: u64, other: u64) -> bool {
+        if self.side == POSITION_LONG {
+            price > other
+        } else {
+            price < other
+        }
+    }
+    
+    pub fn insert(&mut self, order: Order) -> Result<(), ProgramError> {
+        if self.orders.len() >= MAX_ORDERS_PER_SIDE {
+            return Err(DexError::OrderBookFull.into());
+        }
+        
+        let index = self
+            .orders
+            .iter()
+            .position(|resting| self.price_improves(order.price, resting.price))
+            .unwrap_or(self.orders.len());
+        self.orders.insert(index, order);
+        
+        Ok(())
+    }
+    
+    pub fn remove(&mut self, order_id: u64) -> Result<Order, ProgramError> {
+        let index = self
+            .orders
+            .iter()
+            .position(|order| order.order_id == order_id)
+            .ok_or(DexError::OrderNotFound)?;
+        
+        Ok(self.orders.remove(index))
+    }
+}
+
+/// Shared collateral for cross-margin positions; the balance is the lamports above rent.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct MarginAccount {
+    pub owner: Pubkey,
+}
+
+impl MarginAccount {
+    pub const LEN: usize = 32;
+}
+
+/// One provider's shares in a market's LP pool and its checkpoint in the market's fee accumulator.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct LpPositionAccount {
+    pub owner: Pubkey,
+    pub market_mint: Pubkey,
+    pub shares: u64,
+    pub fee_per_share_paid: u128,
+    pub accrued_fees: u64,
+}
+
+impl LpPositionAccount {
+    pub const LEN: usize = 32 + 32 + 8 + 16 + 8;
+    
+    /// Moves fees earned since the last checkpoint into `accrued_fees`; call before `shares` changes.
+    pub fn checkpoint_fees(&mut self, fee_per_share: u128) {
+        self.accrued_fees = self
+            .accrued_fees
+            .saturating_add(lp_pending_fees(self.shares, fee_per_share, self.fee_per_share_paid));
+        self.fee_per_share_paid = fee_per_share;
+    }
+}
+
+/// Nonces of an owner's live positions, so clients can list them with a single fetch.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct PositionIndexAccount {
+    pub owner: Pubkey,
+    pub nonces: Vec<u64>,
+}
+
+impl PositionIndexAccount {
+    /// Bytes before the nonce vector's elements: owner and the vector length prefix.
+    pub const HEADER_LEN: usize = 32 + 4;
+    
+    /// Account size for an index listing `nonces` positions.
+    pub const fn space(nonces: usize) -> usize {
+        Self::HEADER_LEN + 8 * nonces
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct LiquidationQueueEntry {
+    pub owner: Pubkey,
+    pub position_nonce: u64,
+    pub health_bps: u64,
+    pub notional: u64,
+    pub queued_slot: u64,
+}
+
+impl LiquidationQueueEntry {
+    pub const LEN: usize = 32 + 8 + 8 + 8 + 8;
+}
+
+/// Underwater positions keepers have registered on one market, lowest health first.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct LiquidationQueueAccount {
+    pub market_mint: Pubkey,
+    pub entries: Vec<LiquidationQueueEntry>,
+}
+
+impl LiquidationQueueAccount {
+    pub const HEADER_LEN: usize = 32 + 4;
+    pub const LEN: usize = Self::HEADER_LEN + LiquidationQueueEntry::LEN * MAX_LIQUIDATION_QUEUE_ENTRIES;
+    
+    pub fn entry_index(&self, owner: &Pubkey, position_nonce: u64) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| &entry.owner == owner && entry.position_nonce == position_nonce)
+    }
+    
+    pub fn remove(&mut self, owner: &Pubkey, position_nonce: u64) -> bool {
+        match self.entry_index(owner, position_nonce) {
+            Some(index) => {
+                self.entries.remove(index);
+                true
+            },
+            None => false,
+        }
+    }
+    
+    /// Riskier first: lower health, then larger notional, so the worst exposures clear first.
+    pub fn is_riskier(entry: &LiquidationQueueEntry, than: &LiquidationQueueEntry) -> bool {
+        (entry.health_bps, std::cmp::Reverse(entry.notional)) < (than.health_bps, std::cmp::Reverse(than.notional))
+    }
+    
+    /// Inserts or refreshes `entry` in risk order. A full queue makes room by dropping its
+    /// least risky entry if `entry` is riskier; otherwise returns false.
+    pub fn upsert(&mut self, entry: LiquidationQueueEntry) -> bool {
+        self.remove(&entry.owner, entry.position_nonce);
+        
+        if self.entries.len() >= MAX_LIQUIDATION_QUEUE_ENTRIES {
+            match self.entries.last() {
+                Some(last) if Self::is_riskier(&entry, last) => {
+                    self.entries.pop();
+                },
+                _ => return false,
+            }
+        }
+        
+        let at = self.entries.partition_point(|queued| !Self::is_riskier(&entry, queued));
+        self.entries.insert(at, entry);
+        
+        true
+    }
+}
+
+/// Per-owner activity counters, written on every `INITIALIZE`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct UserStatsAccount {
+    pub owner: Pubkey,
+    pub last_open_slot: u64,
+    pub opens_in_slot: u16,
+    pub total_opens: u64,
+}
+
+impl UserStatsAccount {
+    pub const LEN: usize = 32 + 8 + 2 + 8;
+}
+
+/// Next position nonce for an owner; `INITIALIZE` only accepts this value and then increments it.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(borsh::BorshSchema))]
+pub struct NonceCounterAccount {
+    pub owner: Pubkey,
+    pub next_nonce: u64,
+}
+
+impl NonceCounterAccount {
+    pub const LEN: usize = 32 + 8;
+}
+
+pub fn fixed_array_to_string(array: &[u8; MAX_SYMBOL_LENGTH]) -> Result<String, ProgramError> {
+    let end = array.iter().position(|&x| x == 0).unwrap_or(MAX_SYMBOL_LENGTH);
+    
+    match std::str::from_utf8(&array[..end]) {
+        Ok(s) => Ok(s.to_string()),
+        Err(_) => {
+            msg!("Invalid UTF-8 in market symbol");
+            Err(ProgramError::InvalidAccountData)
+        }
+    }
+}
+
+pub fn validate_market_symbol(array: &[u8; MAX_SYMBOL_LENGTH]) -> Result<(), ProgramError> {
+    let end = array.iter().position(|&x| x == 0).unwrap_or(MAX_SYMBOL_LENGTH);
+    
+    if end == 0 {
+        msg!("Market symbol is empty");
+        return Err(DexError::InvalidSymbol.into());
+    }
+    
+    if !array[..end].iter().all(|&x| (0x20..=0x7e).contains(&x)) {
+        msg!("Market symbol must be printable ASCII");
+        return Err(DexError::InvalidSymbol.into());
+    }
+    
+    if array[end..].iter().any(|&x| x != 0) {
+        msg!("Market symbol must be zero padded");
+        return Err(DexError::InvalidSymbol.into());
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn borsh_len<T: BorshSerialize>(value: &T) -> usize {
+        value.try_to_vec().unwrap().len()
+    }
+    
+    #[test]
+    fn write_dex_fields_matches_full_serialize() {
+        let original = PositionAccount {
+            version: POSITION_ACCOUNT_VERSION,
+            owner: Pubkey::new_unique(),
+            market_mint: Pubkey::new_unique(),
+            paid_amount: 5_000,
+            position_size: 50_000,
+            leverage: 10,
+            position_nonce: 3,
+            direction: POSITION_SHORT,
+            open_fee: 17,
+            margin_call_slot: 99,
+            ..PositionAccount::default()
+        };
+        let mut data = original.try_to_vec().unwrap();
+        
+        let updated = PositionAccount {
+            entry_price: 123_456,
+            liquidation_price: 135_000,
+            closed: 1,
+            pnl: -4_321,
+            confirmed: 1,
+            ..original
+        };
+        updated.write_dex_fields(&mut data).unwrap();
+        
+        let mut expected = updated.try_to_vec().unwrap();
+        PositionAccount::stamp_checksum(&mut expected).unwrap();
+        
+        assert_eq!(data, expected);
+        assert!(!PositionAccount::is_corrupted(&data));
+        
+        data[PositionAccount::PNL_OFFSET] ^= 1;
+        
+        assert!(PositionAccount::is_corrupted(&data));
+        
+        data[0] = POSITION_ACCOUNT_VERSION - 1;
+        
+        assert!(updated.write_dex_fields(&mut data).is_err());
+    }
+    
+    #[test]
+    fn fee_snapshot_overrides_market_fees() {
+        let market = FeeSchedule { base_fee_bps: 300, leverage_fee_bps: 20, maker_fee_bps: 5 };
+        let current = |open_base_fee_bps, open_leverage_fee_bps| PositionAccount {
+            version: POSITION_ACCOUNT_VERSION,
+            open_base_fee_bps,
+            open_leverage_fee_bps,
+            ..PositionAccount::default()
+        };
+        let legacy = PositionAccount { version: PositionAccount::FIRST_FEE_SNAPSHOT_VERSION - 1, ..PositionAccount::default() };
+        let mut upgraded = legacy.clone();
+        upgraded.upgrade_version();
+        let mut resaved = current(200, 10);
+        resaved.upgrade_version();
+        
+        assert_eq!(legacy.fee_schedule(market), market);
+        assert_eq!(upgraded.version, POSITION_ACCOUNT_VERSION);
+        assert_eq!(upgraded.fee_schedule(market), market);
+        assert_eq!(CompressedPosition::default().to_position().fee_schedule(market), market);
+        assert_eq!(
+            current(200, 10).fee_schedule(market),
+            FeeSchedule { base_fee_bps: 200, leverage_fee_bps: 10, maker_fee_bps: 5 }
+        );
+        assert_eq!(resaved.fee_schedule(market), current(200, 10).fee_schedule(market));
+        // A market charging no fees at open is snapshotted as such, not mistaken for a legacy position.
+        assert_eq!(
+            current(0, 0).fee_schedule(market),
+            FeeSchedule { base_fee_bps: 0, leverage_fee_bps: 0, maker_fee_bps: 5 }
+        );
+    }
+    
+    #[test]
+    fn fixed_len_constants_match_borsh() {
+        assert_eq!(PositionAccount::LEN, borsh_len(&PositionAccount::default()));
+        assert_eq!(GlobalConfig::LEN, borsh_len(&GlobalConfig::default()));
+        assert_eq!(MarketState::LEN, borsh_len(&MarketState::default()));
+        assert_eq!(LeverageTier::LEN, borsh_len(&LeverageTier::default()));
+        assert_eq!(
+            PriceFeedAccount::LEN,
+            borsh_len(&PriceFeedAccount {
+                source: PriceSource::Pyth { feed: Pubkey::default() },
+                ..PriceFeedAccount::default()
+            })
+        );
+        assert_eq!(Order::LEN, borsh_len(&Order::default()));
+        assert_eq!(MarginAccount::LEN, borsh_len(&MarginAccount::default()));
+        assert_eq!(CloseOrderAccount::LEN, borsh_len(&CloseOrderAccount::default()));
+        assert_eq!(ScheduledOrderAccount::LEN, borsh_len(&ScheduledOrderAccount::default()));
+        assert_eq!(DcaScheduleAccount::LEN, borsh_len(&DcaScheduleAccount::default()));
+        assert_eq!(TwapScheduleAccount::LEN, borsh_len(&TwapScheduleAccount::default()));
+        assert_eq!(EpochSnapshotAccount::LEN, borsh_len(&EpochSnapshotAccount::default()));
+        assert_eq!(PositionTreeAccount::LEN, borsh_len(&PositionTreeAccount::default()));
+        assert_eq!(LeadTraderAccount::LEN, borsh_len(&LeadTraderAccount::default()));
+        assert_eq!(FollowerAccount::LEN, borsh_len(&FollowerAccount::default()));
+        assert_eq!(CopyLinkAccount::LEN, borsh_len(&CopyLinkAccount::default()));
+        assert_eq!(
+            ManagedVaultAccount::LEN,
+            borsh_len(&ManagedVaultAccount {
+                positions: vec![ManagedVaultPosition::default(); MAX_MANAGED_VAULT_POSITIONS],
+                ..ManagedVaultAccount::default()
+            })
+        );
+        assert_eq!(VaultDepositorAccount::LEN, borsh_len(&VaultDepositorAccount::default()));
+        assert_eq!(VaultRequestAccount::LEN, borsh_len(&VaultRequestAccount::default()));
+        assert_eq!(VaultEpochAccount::LEN, borsh_len(&VaultEpochAccount::default()));
+        assert_eq!(HedgeModeAccount::LEN, borsh_len(&HedgeModeAccount::default()));
+        assert_eq!(IntegratorAccount::LEN, borsh_len(&IntegratorAccount::default()));
+        assert_eq!(AttestationAccount::LEN, borsh_len(&AttestationAccount::default()));
+        assert_eq!(FreezeAccount::LEN, borsh_len(&FreezeAccount::default()));
+        assert_eq!(BlacklistEntry::LEN, borsh_len(&BlacklistEntry::default()));
+        assert_eq!(UserStatsAccount::LEN, borsh_len(&UserStatsAccount::default()));
+        assert_eq!(PositionHistoryAccount::LEN, borsh_len(&PositionHistoryAccount::default()));
+        assert_eq!(LiquidationQueueEntry::LEN, borsh_len(&LiquidationQueueEntry::default()));
+        assert_eq!(NonceCounterAccount::LEN, borsh_len(&NonceCounterAccount::default()));
+        assert_eq!(
+            SymbolRegistryAccount::LEN,
+            borsh_len(&SymbolRegistryAccount {
+                market_mint: Pubkey::default(),
+                market_symbol: [0; MAX_SYMBOL_LENGTH],
+                decimals: 0,
+            })
+        );
+    }
+    
+    #[test]
+    fn order_book_space_matches_borsh() {
+        for max_orders in [0, 1, MAX_ORDERS_PER_SIDE] {
+            let book = OrderBookSide {
+                orders: vec![Order::default(); max_orders],
+                ..OrderBookSide::default()
+            };
+            
+            assert_eq!(OrderBookSide::space(max_orders), borsh_len(&book));
+        }
+    }
+    
+    #[test]
+    fn position_index_space_matches_borsh() {
+        for nonces in [0, 1, 17] {
+            let index = PositionIndexAccount {
+                owner: Pubkey::default(),
+                nonces: vec![0; nonces],
+            };
+            
+            assert_eq!(PositionIndexAccount::space(nonces), borsh_len(&index));
+        }
+    }
+}